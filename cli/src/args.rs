@@ -0,0 +1,291 @@
+// cli/src/args.rs
+//! Command-line parsing. Global flags (`--json`, `-v`, `-h`) are accepted
+//! anywhere; everything else belongs to the subcommand.
+
+use lexopt::prelude::*;
+use lexopt::Parser;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: tissaia [--json] [-v] <command> [args]
+
+Commands:
+  detect <scan> [--retry] [-o boxes.json]     Detect photos on a scan
+  crop <scan> [--boxes FILE] [--out-dir DIR]  Cut photos out (detects unless --boxes)
+  restore <image> [--preset NAME] [-o FILE]   AI restoration (presets: archival-faithful,
+                                              gentle-cleanup, full-colorize, or your own)
+  filters <image> [--filter NAME]... [-o FILE]
+                                              Local filters: clahe, sharpen, denoise
+  upscale <image> [--scale N] [-o FILE]       Lanczos3 upscale (default 2x)
+  verify detection <scan> --boxes FILE        Check detected boxes
+  verify crop <photo> [--index N]             Check a cropped photo
+  verify restoration <original> <restored>    Compare a restoration with its original
+  metadata <image>                            EXIF and image properties
+
+Options:
+  --json          Machine-readable output on stdout (errors as JSON on stderr)
+  -v, --verbose   Log progress to stderr (-vv for debug); RUST_LOG overrides
+  -h, --help      Show this help
+  -V, --version   Show the version
+
+Box files are the `detect --json` output or a bare JSON array of boxes;
+`--boxes -` reads them from stdin. API keys come from the environment or .env.
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cli {
+    pub json: bool,
+    pub verbose: u8,
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Detect {
+        input: PathBuf,
+        /// Verify the detection and merge missed photos.
+        retry: bool,
+        output: Option<PathBuf>,
+    },
+    Crop {
+        input: PathBuf,
+        boxes: Option<PathBuf>,
+        out_dir: Option<PathBuf>,
+    },
+    Restore {
+        input: PathBuf,
+        /// Restoration preset; `None` = every step at full strength.
+        preset: Option<String>,
+        output: Option<PathBuf>,
+    },
+    Filters {
+        input: PathBuf,
+        /// Empty = the default chain.
+        filters: Vec<String>,
+        output: Option<PathBuf>,
+    },
+    Upscale {
+        input: PathBuf,
+        scale: Option<f64>,
+        output: Option<PathBuf>,
+    },
+    VerifyDetection {
+        input: PathBuf,
+        boxes: PathBuf,
+    },
+    VerifyCrop {
+        input: PathBuf,
+        index: usize,
+    },
+    VerifyRestoration {
+        original: PathBuf,
+        restored: PathBuf,
+    },
+    Metadata {
+        input: PathBuf,
+    },
+    Help,
+    Version,
+}
+
+/// Parse `args` (including the program name, as `std::env::args_os()`).
+pub fn parse(args: impl IntoIterator<Item = impl Into<OsString>>) -> Result<Cli, lexopt::Error> {
+    let mut parser = Parser::from_iter(args);
+    let mut globals = Globals::default();
+
+    let name = loop {
+        match parser.next()? {
+            Some(arg) if globals.accept(&arg) => {}
+            Some(Value(value)) => break value.string()?,
+            Some(arg) => return Err(arg.unexpected()),
+            None => break "help".to_string(),
+        }
+    };
+
+    let mut positionals = Vec::new();
+    let mut boxes = None;
+    let mut output = None;
+    let mut out_dir = None;
+    let mut filters = Vec::new();
+    let mut scale = None;
+    let mut index = None;
+    let mut retry = false;
+    let mut preset = None;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            arg if globals.accept(&arg) => {}
+            Value(value) => positionals.push(value),
+            Long("boxes") if matches!(name.as_str(), "crop" | "verify") => boxes = Some(parser.value()?.into()),
+            Short('o') | Long("output") if matches!(name.as_str(), "detect" | "restore" | "filters" | "upscale") => {
+                output = Some(parser.value()?.into())
+            }
+            Long("out-dir") if name == "crop" => out_dir = Some(parser.value()?.into()),
+            Long("filter") if name == "filters" => {
+                let value = parser.value()?.string()?;
+                filters.extend(value.split(',').map(|f| f.trim().to_string()).filter(|f| !f.is_empty()));
+            }
+            Long("scale") if name == "upscale" => scale = Some(parser.value()?.parse()?),
+            Long("index") if name == "verify" => index = Some(parser.value()?.parse()?),
+            Long("retry") if name == "detect" => retry = true,
+            Long("preset") if name == "restore" => preset = Some(parser.value()?.string()?),
+            arg => return Err(arg.unexpected()),
+        }
+    }
+
+    if globals.help || globals.version {
+        let command = if globals.help { Command::Help } else { Command::Version };
+        return Ok(globals.into_cli(command));
+    }
+    let mut positionals = Positionals { command: &name, values: positionals.into_iter() };
+    let command = match name.as_str() {
+        "detect" => Command::Detect { input: positionals.path("scan")?, retry, output },
+        "crop" => Command::Crop { input: positionals.path("scan")?, boxes, out_dir },
+        "restore" => Command::Restore { input: positionals.path("image")?, preset, output },
+        "filters" => Command::Filters { input: positionals.path("image")?, filters, output },
+        "upscale" => Command::Upscale { input: positionals.path("image")?, scale, output },
+        "verify" => match positionals.string("target")?.as_str() {
+            "detection" => Command::VerifyDetection {
+                input: positionals.path("scan")?,
+                boxes: boxes.ok_or("verify detection needs --boxes FILE")?,
+            },
+            "crop" => Command::VerifyCrop { input: positionals.path("photo")?, index: index.unwrap_or(0) },
+            "restoration" => Command::VerifyRestoration {
+                original: positionals.path("original")?,
+                restored: positionals.path("restored")?,
+            },
+            other => {
+                return Err(format!("unknown verify target '{}' (detection, crop or restoration)", other).into())
+            }
+        },
+        "metadata" => Command::Metadata { input: positionals.path("image")? },
+        "help" => Command::Help,
+        "version" => Command::Version,
+        other => return Err(format!("unknown command '{}'", other).into()),
+    };
+    positionals.finish()?;
+    Ok(globals.into_cli(command))
+}
+
+#[derive(Default)]
+struct Globals {
+    json: bool,
+    verbose: u8,
+    help: bool,
+    version: bool,
+}
+
+impl Globals {
+    /// Record `arg` if it is a global flag.
+    fn accept(&mut self, arg: &lexopt::Arg) -> bool {
+        match arg {
+            Long("json") => self.json = true,
+            Short('v') | Long("verbose") => self.verbose += 1,
+            Short('h') | Long("help") => self.help = true,
+            Short('V') | Long("version") => self.version = true,
+            _ => return false,
+        }
+        true
+    }
+
+    fn into_cli(self, command: Command) -> Cli {
+        Cli { json: self.json, verbose: self.verbose, command }
+    }
+}
+
+struct Positionals<'a> {
+    command: &'a str,
+    values: std::vec::IntoIter<OsString>,
+}
+
+impl Positionals<'_> {
+    fn string(&mut self, what: &str) -> Result<String, lexopt::Error> {
+        Ok(self.path(what)?.to_string_lossy().into_owned())
+    }
+
+    fn path(&mut self, what: &str) -> Result<PathBuf, lexopt::Error> {
+        self.values
+            .next()
+            .map(PathBuf::from)
+            .ok_or_else(|| format!("{} needs <{}>", self.command, what).into())
+    }
+
+    fn finish(mut self) -> Result<(), lexopt::Error> {
+        match self.values.next() {
+            Some(extra) => Err(lexopt::Error::UnexpectedArgument(extra)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Cli, lexopt::Error> {
+        parse(std::iter::once("tissaia").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn global_flags_go_anywhere() {
+        let cli = parse_args(&["--json", "crop", "scan.jpg", "--boxes", "-", "-v", "--out-dir", "out"]).unwrap();
+        assert!(cli.json);
+        assert_eq!(cli.verbose, 1);
+        assert_eq!(
+            cli.command,
+            Command::Crop {
+                input: "scan.jpg".into(),
+                boxes: Some("-".into()),
+                out_dir: Some("out".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_subcommand_options() {
+        assert_eq!(
+            parse_args(&["filters", "a.png", "--filter", "clahe,sharpen", "--filter", "denoise"]).unwrap().command,
+            Command::Filters {
+                input: "a.png".into(),
+                filters: vec!["clahe".into(), "sharpen".into(), "denoise".into()],
+                output: None,
+            }
+        );
+        assert_eq!(
+            parse_args(&["upscale", "a.png", "--scale=1.5", "-o", "b.png"]).unwrap().command,
+            Command::Upscale { input: "a.png".into(), scale: Some(1.5), output: Some("b.png".into()) }
+        );
+        assert_eq!(
+            parse_args(&["restore", "a.png", "--preset", "archival-faithful"]).unwrap().command,
+            Command::Restore { input: "a.png".into(), preset: Some("archival-faithful".into()), output: None }
+        );
+        assert_eq!(
+            parse_args(&["verify", "restoration", "a.png", "b.png"]).unwrap().command,
+            Command::VerifyRestoration { original: "a.png".into(), restored: "b.png".into() }
+        );
+        assert_eq!(
+            parse_args(&["verify", "crop", "a.png", "--index", "2"]).unwrap().command,
+            Command::VerifyCrop { input: "a.png".into(), index: 2 }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_invocations() {
+        assert!(parse_args(&["restore"]).is_err());
+        assert!(parse_args(&["restore", "a.png", "b.png"]).is_err());
+        assert!(parse_args(&["restore", "a.png", "--scale", "2"]).is_err());
+        assert!(parse_args(&["detect", "a.png", "--preset", "gentle-cleanup"]).is_err());
+        assert!(parse_args(&["upscale", "a.png", "--scale", "big"]).is_err());
+        assert!(parse_args(&["verify", "detection", "a.png"]).is_err());
+        assert!(parse_args(&["verify", "everything", "a.png"]).is_err());
+        assert!(parse_args(&["enhance", "a.png"]).is_err());
+    }
+
+    #[test]
+    fn help_and_version_win() {
+        assert_eq!(parse_args(&[]).unwrap().command, Command::Help);
+        assert_eq!(parse_args(&["restore", "--help"]).unwrap().command, Command::Help);
+        assert_eq!(parse_args(&["-V"]).unwrap().command, Command::Version);
+    }
+}
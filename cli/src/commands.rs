@@ -0,0 +1,364 @@
+// cli/src/commands.rs
+//! Subcommands — thin adapters over `tissaia_core::operations` that read
+//! and write files instead of base64 payloads. Every command returns a
+//! `Report`: JSON for `--json`, a few lines of text otherwise.
+
+use crate::args::Command;
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tissaia_core::models::{BoundingBox, VerificationResult};
+use tissaia_core::{imaging, operations, SharedState, TissaiaError};
+
+pub struct Report {
+    pub json: Value,
+    pub text: String,
+}
+
+pub async fn run(state: &SharedState, command: Command) -> Result<Report> {
+    match command {
+        Command::Detect { input, retry, output } => detect(state, &input, retry, output.as_deref()).await,
+        Command::Crop { input, boxes, out_dir } => crop(state, &input, boxes.as_deref(), out_dir.as_deref()).await,
+        Command::Restore { input, preset, output } => restore(state, &input, preset.as_deref(), output).await,
+        Command::Filters { input, filters, output } => filters_command(state, &input, filters, output).await,
+        Command::Upscale { input, scale, output } => upscale(state, &input, scale, output).await,
+        Command::VerifyDetection { input, boxes } => {
+            let (image, mime) = read_image(&input)?;
+            let boxes = load_boxes(&boxes)?;
+            verification(operations::verify_detection(state, &image, mime, &boxes).await?)
+        }
+        Command::VerifyCrop { input, index } => {
+            let (image, mime) = read_image(&input)?;
+            verification(operations::verify_crop(state, &image, mime, index).await?)
+        }
+        Command::VerifyRestoration { original, restored } => {
+            let (original, mime) = read_image(&original)?;
+            let (restored, _) = read_image(&restored)?;
+            verification(operations::verify_restoration(state, &original, &restored, mime).await?)
+        }
+        Command::Metadata { input } => {
+            let (image, mime) = read_image_bytes(&input)?;
+            let metadata = operations::extract_metadata(state, image.as_slice(), mime).await?;
+            let text = match &metadata {
+                Value::Object(fields) => fields
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}\n", key, plain(value)))
+                    .collect(),
+                other => format!("{}\n", other),
+            };
+            Ok(Report { json: metadata, text })
+        }
+        Command::Help | Command::Version => unreachable!("handled in main"),
+    }
+}
+
+// ============================================
+// DETECTION & CROP
+// ============================================
+
+async fn detect(state: &SharedState, input: &Path, retry: bool, output: Option<&Path>) -> Result<Report> {
+    let (image, mime) = read_image(input)?;
+    let result = if retry {
+        operations::detect_photos_with_retry(state, &image, mime).await?
+    } else {
+        operations::detect_photos(state, &image, mime).await?
+    };
+
+    let mut text = format!(
+        "{} photos detected by {} (scan {}x{})\n",
+        result.photo_count, result.provider_used, result.scan_width, result.scan_height
+    );
+    for (i, bbox) in result.bounding_boxes.iter().enumerate() {
+        text.push_str(&format_box(i, bbox));
+    }
+    if let Some(output) = output {
+        fs::write(output, serde_json::to_vec_pretty(&result)?).with_context(|| format!("write {:?}", output))?;
+        let _ = writeln!(text, "boxes written to {}", output.display());
+    }
+    Ok(Report { json: serde_json::to_value(&result)?, text })
+}
+
+async fn crop(state: &SharedState, input: &Path, boxes: Option<&Path>, out_dir: Option<&Path>) -> Result<Report> {
+    let (image, mime) = read_image(input)?;
+    let boxes = match boxes {
+        Some(path) => load_boxes(path)?,
+        None => operations::detect_photos(state, &image, mime).await?.bounding_boxes,
+    };
+    let file_name = input.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let result = operations::crop_photos(state, &image, mime, &boxes, file_name).await?;
+
+    let out_dir = out_dir.map(Path::to_path_buf).unwrap_or_else(|| parent(input));
+    fs::create_dir_all(&out_dir).with_context(|| format!("create {:?}", out_dir))?;
+    let stem = stem(input);
+    let mut text = format!("{} photos cropped\n", result.photos.len());
+    let mut photos = Vec::with_capacity(result.photos.len());
+    for photo in &result.photos {
+        let path = out_dir.join(format!(
+            "{}_{}.{}",
+            stem,
+            photo.index + 1,
+            imaging::extension_for_mime(&photo.mime_type)
+        ));
+        write_image(&path, &photo.image_base64)?;
+        let _ = writeln!(text, "  {} ({}x{})", path.display(), photo.width, photo.height);
+        photos.push(json!({
+            "index": photo.index,
+            "path": path,
+            "width": photo.width,
+            "height": photo.height,
+            "source_box": photo.source_box,
+        }));
+    }
+    Ok(Report {
+        json: json!({ "original_filename": result.original_filename, "photos": photos }),
+        text,
+    })
+}
+
+// ============================================
+// RESTORATION & LOCAL PROCESSING
+// ============================================
+
+async fn restore(state: &SharedState, input: &Path, preset: Option<&str>, output: Option<PathBuf>) -> Result<Report> {
+    let options = operations::restoration_options(state, preset, None).await?;
+    let (image, mime) = read_image(input)?;
+    let result = operations::restore_image(state, image, mime.to_string(), &options).await?;
+    let output = output.unwrap_or_else(|| sibling(input, "restored", mime));
+    write_image(&output, &result.restored_image)?;
+
+    let mut text = format!("restored by {} → {}\n", result.provider_used, output.display());
+    for improvement in &result.improvements {
+        let _ = writeln!(text, "  - {}", improvement);
+    }
+    let mut json = serde_json::to_value(&result)?;
+    if let Value::Object(fields) = &mut json {
+        fields.remove("original_image");
+        fields.remove("restored_image");
+        fields.insert("output".to_string(), json!(output));
+    }
+    Ok(Report { json, text })
+}
+
+async fn filters_command(
+    state: &SharedState,
+    input: &Path,
+    filters: Vec<String>,
+    output: Option<PathBuf>,
+) -> Result<Report> {
+    let (image, mime) = read_image_bytes(input)?;
+    let applied = (!filters.is_empty()).then_some(filters);
+    let filtered = operations::apply_local_filters(state, image.as_slice(), mime, applied.clone()).await?;
+    let output = output.unwrap_or_else(|| sibling(input, "filtered", mime));
+    write_image(&output, &filtered)?;
+
+    let names = applied.as_ref().map_or("default".to_string(), |f| f.join(", "));
+    Ok(Report {
+        json: json!({ "input": input, "output": output, "filters": applied }),
+        text: format!("filters {} → {}\n", names, output.display()),
+    })
+}
+
+async fn upscale(state: &SharedState, input: &Path, scale: Option<f64>, output: Option<PathBuf>) -> Result<Report> {
+    let (image, mime) = read_image_bytes(input)?;
+    let upscaled = operations::upscale_image(state, image.as_slice(), mime, scale).await?;
+    let factor = scale.unwrap_or(2.0);
+    let output = output.unwrap_or_else(|| sibling(input, &format!("x{}", factor), mime));
+    write_image(&output, &upscaled)?;
+    Ok(Report {
+        json: json!({ "input": input, "output": output, "scale_factor": factor }),
+        text: format!("upscaled {}x → {}\n", factor, output.display()),
+    })
+}
+
+// ============================================
+// VERIFICATION
+// ============================================
+
+fn verification(result: VerificationResult) -> Result<Report> {
+    let json = serde_json::to_value(&result)?;
+    let mut text = format!(
+        "{} verification: {} (confidence {}%, {})\n",
+        plain(&json["stage"]),
+        plain(&json["status"]),
+        result.confidence,
+        result.model_used
+    );
+    for check in &result.checks {
+        let mark = if check.passed { "ok" } else { "!!" };
+        let detail = check.detail.as_deref().map(|d| format!(": {}", d)).unwrap_or_default();
+        let _ = writeln!(text, "  [{}] {}{}", mark, check.name, detail);
+    }
+    for issue in &result.issues {
+        let _ = writeln!(text, "  issue ({}): {}", issue.severity, issue.description);
+    }
+    for recommendation in &result.recommendations {
+        let _ = writeln!(text, "  → {}", recommendation);
+    }
+    if !result.missing_boxes.is_empty() {
+        let _ = writeln!(text, "  {} missing photos:", result.missing_boxes.len());
+        for (i, bbox) in result.missing_boxes.iter().enumerate() {
+            text.push_str(&format_box(i, bbox));
+        }
+    }
+    Ok(Report { json, text })
+}
+
+// ============================================
+// FILES
+// ============================================
+
+/// Read an image file as base64 plus its MIME type (from the extension).
+pub fn read_image(path: &Path) -> Result<(String, &'static str)> {
+    let (bytes, mime) = read_image_bytes(path)?;
+    Ok((STANDARD.encode(bytes), mime))
+}
+
+/// `read_image` without the base64 step, for the local operations.
+fn read_image_bytes(path: &Path) -> Result<(Vec<u8>, &'static str)> {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let mime = imaging::mime_from_filename(&name).ok_or_else(|| {
+        TissaiaError::UnsupportedFormat(format!("Cannot tell the image type of {:?} from its extension", path))
+    })?;
+    let bytes = fs::read(path).with_context(|| format!("read {:?}", path))?;
+    Ok((bytes, mime))
+}
+
+fn write_image(path: &Path, image_base64: &str) -> Result<()> {
+    let bytes = imaging::decode_base64(image_base64)?;
+    fs::write(path, bytes).with_context(|| format!("write {:?}", path))
+}
+
+/// Boxes from `detect --json` output (or any object with `bounding_boxes`)
+/// or a bare array; `-` reads stdin.
+pub fn load_boxes(path: &Path) -> Result<Vec<BoundingBox>> {
+    let raw = if path == Path::new("-") {
+        let mut raw = String::new();
+        std::io::stdin().read_to_string(&mut raw).context("read boxes from stdin")?;
+        raw
+    } else {
+        fs::read_to_string(path).with_context(|| format!("read {:?}", path))?
+    };
+    let value: Value = serde_json::from_str(&raw)
+        .map_err(|e| TissaiaError::Validation(format!("Box file {:?} is not JSON: {}", path, e)))?;
+    let boxes = match value {
+        Value::Object(mut fields) => fields.remove("bounding_boxes").unwrap_or(Value::Null),
+        array => array,
+    };
+    serde_json::from_value(boxes).map_err(|e| {
+        TissaiaError::Validation(format!("Box file {:?} holds no bounding boxes: {}", path, e)).into()
+    })
+}
+
+/// `{stem}_{suffix}.{ext}` next to `input`.
+fn sibling(input: &Path, suffix: &str, mime_type: &str) -> PathBuf {
+    parent(input).join(format!("{}_{}.{}", stem(input), suffix, imaging::extension_for_mime(mime_type)))
+}
+
+fn parent(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "image".to_string())
+}
+
+fn format_box(i: usize, bbox: &BoundingBox) -> String {
+    let mut line = format!(
+        "  #{}  x={} y={} {}x{}  confidence {:.2}",
+        i + 1,
+        bbox.x,
+        bbox.y,
+        bbox.width,
+        bbox.height,
+        bbox.confidence
+    );
+    if bbox.rotation_angle != 0.0 {
+        let _ = write!(line, "  rotated {}°", bbox.rotation_angle);
+    }
+    if bbox.needs_outpaint {
+        line.push_str("  needs outpaint");
+    }
+    line.push('\n');
+    line
+}
+
+/// JSON value without string quotes.
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_files_accept_detection_results_and_arrays() {
+        let dir = tempfile::tempdir().unwrap();
+        let bbox = json!({"x": 10, "y": 20, "width": 300, "height": 200, "confidence": 0.9, "label": null});
+
+        let detection = dir.path().join("detection.json");
+        fs::write(&detection, json!({"photo_count": 1, "bounding_boxes": [bbox]}).to_string()).unwrap();
+        let array = dir.path().join("boxes.json");
+        fs::write(&array, json!([bbox, bbox]).to_string()).unwrap();
+        let empty = dir.path().join("empty.json");
+        fs::write(&empty, "{}").unwrap();
+
+        assert_eq!(load_boxes(&detection).unwrap()[0].width, 300);
+        assert_eq!(load_boxes(&array).unwrap().len(), 2);
+        let error = tissaia_core::ApiError::from(&load_boxes(&empty).unwrap_err());
+        assert_eq!(error.code, "validation_error");
+    }
+
+    #[test]
+    fn outputs_are_named_after_the_input() {
+        assert_eq!(
+            sibling(Path::new("scans/album 1.png"), "restored", "image/png"),
+            PathBuf::from("scans/album 1_restored.png")
+        );
+        assert_eq!(sibling(Path::new("photo.jpeg"), "x1.5", "image/jpeg"), PathBuf::from("photo_x1.5.jpg"));
+        assert!(read_image(Path::new("notes.txt")).is_err());
+    }
+
+    #[cfg(feature = "image-processing")]
+    #[tokio::test]
+    async fn crop_from_a_box_file_needs_no_provider() {
+        let dir = tempfile::tempdir().unwrap();
+        let scan = dir.path().join("album.png");
+        image::RgbImage::from_pixel(400, 200, image::Rgb([120, 90, 60])).save(&scan).unwrap();
+        let boxes = dir.path().join("boxes.json");
+        fs::write(
+            &boxes,
+            json!([
+                {"x": 0, "y": 0, "width": 500, "height": 1000, "confidence": 0.9, "label": null},
+                {"x": 500, "y": 0, "width": 500, "height": 1000, "confidence": 0.9, "label": null}
+            ])
+            .to_string(),
+        )
+        .unwrap();
+        // No provider has a key: any AI call would fail
+        let state = std::sync::Arc::new(tokio::sync::Mutex::new(tissaia_core::AppState::new()));
+        for provider in &mut state.lock().await.providers {
+            provider.enabled = false;
+        }
+
+        let out_dir = dir.path().join("photos");
+        let report = run(
+            &state,
+            Command::Crop { input: scan, boxes: Some(boxes), out_dir: Some(out_dir.clone()) },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.json["photos"].as_array().unwrap().len(), 2);
+        assert!(out_dir.join("album_1.png").is_file());
+        assert!(out_dir.join("album_2.png").is_file());
+        assert!(report.text.starts_with("2 photos cropped"));
+    }
+}
@@ -0,0 +1,75 @@
+// cli/src/main.rs
+//! Tissaia AI Studio — headless CLI
+//! ================================
+//! `tissaia detect | crop | restore | filters | upscale | verify | metadata`
+//! over image files, for shell pipelines and cron jobs. Same providers and
+//! image pipeline as the server and desktop app (`tissaia_core`).
+//!
+//! Exit codes: 0 success, 1 operation failed, 2 invalid arguments.
+
+mod args;
+mod commands;
+
+use args::Command;
+use std::io::Write;
+use std::process::ExitCode;
+use std::sync::Arc;
+use tissaia_core::{ApiError, AppState, PresetStore, SharedState};
+use tokio::sync::Mutex;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = match args::parse(std::env::args_os()) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("tissaia: {}\nRun `tissaia --help` for usage.", e);
+            return ExitCode::from(2);
+        }
+    };
+    match cli.command {
+        Command::Help => {
+            emit(args::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Command::Version => {
+            emit(&format!("tissaia {}\n", env!("CARGO_PKG_VERSION")));
+            return ExitCode::SUCCESS;
+        }
+        _ => {}
+    }
+
+    // Core logs every stage at info; keep stderr quiet unless asked
+    let level = match cli.verbose {
+        0 => "warn",
+        1 => "info",
+        _ => "debug",
+    };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level)).init();
+    let _ = dotenvy::dotenv();
+
+    // User presets are shared with the server through TISSAIA_PRESETS_FILE
+    let state: SharedState = Arc::new(Mutex::new(AppState::new().with_presets(PresetStore::from_env())));
+    match commands::run(&state, cli.command).await {
+        Ok(report) if cli.json => {
+            emit(&format!("{}\n", serde_json::to_string_pretty(&report.json).unwrap_or_default()));
+            ExitCode::SUCCESS
+        }
+        Ok(report) => {
+            emit(&report.text);
+            ExitCode::SUCCESS
+        }
+        Err(e) if cli.json => {
+            eprintln!("{}", serde_json::to_string(&ApiError::from(&e)).unwrap_or_default());
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("tissaia: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Write to stdout; a closed pipe (`tissaia ... | head`) is not an error.
+fn emit(text: &str) {
+    let _ = std::io::stdout().lock().write_all(text.as_bytes());
+}
@@ -0,0 +1,1115 @@
+// core/src/ai.rs
+//! AI provider layer — Gemini, Claude, GPT-4o, Ollama and OpenAI-compatible
+//! (Mistral, Groq) HTTP clients. Detection, verification and restoration
+//! plan replies are requested with a JSON schema where the provider supports
+//! one and validated (`responses`), re-asking on an invalid reply.
+
+use crate::config::{default_provider_configs, Endpoint, ProviderConfigs, ProviderOperation};
+use crate::error::TissaiaError;
+use crate::executor::{self, RequestExecutor};
+use crate::local_restoration;
+use crate::prompts::{Prompt, PromptKind, PromptLibrary};
+use crate::models::{
+    AiModel, BoundingBox, DetectionResult, RestorationOptions, RestorationResult, StepOption,
+    VerificationCheck, VerificationIssue, VerificationResult, VerificationStage,
+};
+use crate::responses::{gemini_schema, parse_reply, AiReply, DetectionReply, PlanReply, VerificationReply};
+use anyhow::{anyhow, Result};
+use log::{debug, error, info, warn};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde_json::{json, Value};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Gemini Pro wymaga temperature=1.0 dla generowania obrazów i stabilnych wyników.
+/// NIE ZMIENIAJ — wartość wymagana przez API Gemini dla response_modalities z IMAGE.
+const GEMINI_TEMPERATURE: f64 = 1.0;
+
+/// Health probes are cheap calls; a provider slower than this counts as down.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Provider-side failure that callers may want to classify
+/// (e.g. failover treats 429/5xx, missing images and invalid replies as retryable).
+#[derive(Debug)]
+pub enum ProviderFailure {
+    /// Non-2xx HTTP response.
+    Http {
+        context: &'static str,
+        status: StatusCode,
+        body: String,
+        /// `Retry-After` header (delta seconds), when the provider sent one.
+        retry_after_secs: Option<u64>,
+    },
+    /// Image generation succeeded at HTTP level but returned no image.
+    NoImage { provider: &'static str },
+    /// JSON reply still invalid after the re-asks (`responses`).
+    InvalidResponse { provider: &'static str, error: String },
+}
+
+impl ProviderFailure {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ProviderFailure::Http { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            ProviderFailure::NoImage { .. } | ProviderFailure::InvalidResponse { .. } => true,
+        }
+    }
+}
+
+impl fmt::Display for ProviderFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderFailure::Http { context, status, body, .. } => {
+                write!(f, "{} ({}): {}", context, status.as_u16(), body)
+            }
+            ProviderFailure::NoImage { provider } => {
+                write!(f, "{} returned no image in response", provider)
+            }
+            ProviderFailure::InvalidResponse { provider, error } => {
+                write!(f, "{} returned an invalid reply: {}", provider, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProviderFailure {}
+
+/// Turn a non-2xx response into a `ProviderFailure::Http`.
+async fn http_failure(context: &'static str, response: Response) -> anyhow::Error {
+    let status = response.status();
+    let retry_after_secs = executor::retry_after(response.headers()).map(|wait| wait.as_secs_f64().ceil() as u64);
+    let body = response.text().await.unwrap_or_default();
+    error!("{} ({}): {}", context, status, body);
+    ProviderFailure::Http { context, status, body, retry_after_secs }.into()
+}
+
+/// Provider served through an OpenAI-compatible `/chat/completions` endpoint.
+/// URL, model and limits come from the provider config.
+#[derive(Debug, Clone, Copy)]
+pub struct ChatProvider {
+    /// Provider name recorded in results and used for config lookup.
+    pub name: &'static str,
+    /// Prefix for HTTP error messages.
+    pub error_context: &'static str,
+}
+
+impl ChatProvider {
+    pub const MISTRAL: ChatProvider = ChatProvider { name: "mistral", error_context: "Mistral API error" };
+    pub const GROQ: ChatProvider = ChatProvider { name: "groq", error_context: "Groq API error" };
+}
+
+/// Model that answers the verification agent prompts.
+#[derive(Debug, Clone, Copy)]
+pub enum Verifier {
+    GeminiFlash,
+    Chat(ChatProvider),
+}
+
+impl Verifier {
+    fn provider(&self) -> &'static str {
+        match self {
+            Verifier::GeminiFlash => "google",
+            Verifier::Chat(provider) => provider.name,
+        }
+    }
+}
+
+/// Times a structured reply that fails validation is asked for again.
+const MAX_REASKS: usize = 2;
+
+/// Ask with `prompt` until the reply parses as `T`. Each re-ask repeats the
+/// prompt with the validation error appended; after `MAX_REASKS` the
+/// failure is a retryable `ProviderFailure::InvalidResponse`.
+async fn ask_structured<T, F, Fut>(provider: &'static str, prompt: &str, ask: F) -> Result<T>
+where
+    T: AiReply,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let mut text = prompt.to_string();
+    let mut reasks = 0;
+    loop {
+        let reply = ask(text).await?;
+        debug!("{} reply: {}", T::NAME, reply);
+        match parse_reply::<T>(&reply) {
+            Ok(parsed) => return Ok(parsed),
+            Err(error) if reasks < MAX_REASKS => {
+                reasks += 1;
+                warn!("{} {} reply invalid ({}); re-asking ({}/{})", provider, T::NAME, error, reasks, MAX_REASKS);
+                text = format!(
+                    "{}\n\nYour previous reply was invalid: {}. Reply with ONLY a JSON object that matches the requested format.",
+                    prompt, error
+                );
+            }
+            Err(error) => return Err(ProviderFailure::InvalidResponse { provider, error }.into()),
+        }
+    }
+}
+
+pub struct AiProvider {
+    client: Client,
+    config: ProviderConfigs,
+    prompts: Arc<PromptLibrary>,
+    executor: Arc<RequestExecutor>,
+}
+
+impl AiProvider {
+    pub fn new() -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(Duration::from_secs(120))
+                .connect_timeout(Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
+            config: default_provider_configs(),
+            prompts: Arc::new(PromptLibrary::embedded()),
+            executor: Arc::new(RequestExecutor::new()),
+        }
+    }
+
+    pub fn with_client(client: Client) -> Self {
+        Self::with_config(client, default_provider_configs())
+    }
+
+    pub fn with_config(client: Client, config: ProviderConfigs) -> Self {
+        Self {
+            client,
+            config,
+            prompts: Arc::new(PromptLibrary::embedded()),
+            executor: Arc::new(RequestExecutor::new()),
+        }
+    }
+
+    /// Render prompts from `prompts` instead of the embedded templates.
+    pub fn with_prompts(mut self, prompts: Arc<PromptLibrary>) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Share retry state, concurrency and rate limits with other providers
+    /// built from the same `AppState`.
+    pub fn with_executor(mut self, executor: Arc<RequestExecutor>) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Send a request to `provider` through the executor, with its
+    /// configured `RequestLimits`.
+    async fn send(&self, provider: &str, request: impl Fn() -> RequestBuilder) -> Result<Response> {
+        let limits = self.config.get(provider).map(|c| c.limits.clone()).unwrap_or_default();
+        self.executor.send(provider, &limits, request).await
+    }
+
+    /// Resolve base URL, model, max tokens and timeout for a provider operation.
+    fn endpoint(&self, provider: &str, operation: ProviderOperation) -> Result<Endpoint> {
+        self.config
+            .get(provider)
+            .and_then(|config| config.endpoint(operation))
+            .ok_or_else(|| anyhow!("Provider '{}' has no {:?} endpoint configured", provider, operation))
+    }
+
+    fn gemini_url(endpoint: &Endpoint) -> String {
+        format!("{}/models/{}:generateContent", endpoint.base_url, endpoint.model)
+    }
+
+    /// `RestoreImage` or `RestorePlan` prompt for `options`. Plans may only
+    /// use the local steps the options allow.
+    fn restoration_prompt(&self, kind: PromptKind, options: &RestorationOptions) -> Prompt {
+        let allowed: Vec<String> = local_restoration::allowed_steps(options)
+            .iter()
+            .map(|step| serde_json::to_value(step).ok().and_then(|v| v.as_str().map(String::from)).unwrap_or_default())
+            .collect();
+        self.prompts.render(kind, &[
+            ("instructions", &restoration_instructions(options)),
+            ("options_json", &serde_json::to_string(options).unwrap_or_default()),
+            ("allowed_steps", &allowed.join(", ")),
+        ])
+    }
+
+    // ========== Google Gemini ==========
+
+    pub async fn restore_with_google(
+        &self,
+        api_key: &str,
+        image_base64: &str,
+        mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        info!("=== GOOGLE GEMINI RESTORATION ===");
+
+        let endpoint = self.endpoint("google", ProviderOperation::Restore)?;
+        let url = Self::gemini_url(&endpoint);
+
+        let prompt = self.restoration_prompt(PromptKind::RestoreImage, options);
+
+        let body = json!({
+            "contents": [{
+                "parts": [
+                    {"text": prompt.text},
+                    {
+                        "inline_data": {
+                            "mime_type": mime_type,
+                            "data": image_base64
+                        }
+                    }
+                ]
+            }],
+            "generationConfig": {
+                "temperature": GEMINI_TEMPERATURE,
+                "maxOutputTokens": endpoint.max_tokens,
+                "response_modalities": ["TEXT", "IMAGE"]
+            }
+        });
+
+        let start = std::time::Instant::now();
+        info!("Sending restoration request to Google Gemini ({})...", endpoint.model);
+        let response = self.send("google", || {
+            self.client.post(&url)
+                .timeout(endpoint.timeout)
+                .header("x-goog-api-key", api_key)
+                .json(&body)
+        }).await?;
+
+        let status = response.status();
+        info!("Response status: {}", status);
+
+        if !status.is_success() {
+            return Err(http_failure("Google API error", response).await);
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        debug!("Restoration response keys: {:?}", data);
+
+        let mut result = RestorationResult::new("google", image_base64.to_string());
+        result.processing_time_ms = start.elapsed().as_millis() as u64;
+        result.prompt_version = Some(prompt.version);
+
+        // Try to extract generated image from response
+        let mut found_image = false;
+        if let Some(candidates) = data["candidates"].as_array() {
+            for candidate in candidates {
+                if let Some(parts) = candidate["content"]["parts"].as_array() {
+                    for part in parts {
+                        // Check for inline image data (Gemini image generation)
+                        if let Some(inline_data) = part.get("inlineData").or_else(|| part.get("inline_data")) {
+                            if let Some(img_data) = inline_data["data"].as_str() {
+                                info!("Found generated image in response ({} bytes)", img_data.len());
+                                result.restored_image = img_data.to_string();
+                                found_image = true;
+                            }
+                        }
+                        // Check for text response with improvements info
+                        if let Some(text) = part["text"].as_str() {
+                            info!("Found text in response: {} chars", text.len());
+                            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(text) {
+                                if let Some(improvements) = parsed["improvements"].as_array() {
+                                    result.improvements = improvements
+                                        .iter()
+                                        .filter_map(|v| v.as_str())
+                                        .map(|s| s.to_string())
+                                        .collect();
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if !found_image {
+            info!("No generated image found in Gemini response");
+            return Err(ProviderFailure::NoImage { provider: "google" }.into());
+        }
+
+        if result.improvements.is_empty() {
+            result.improvements = requested_improvements(options);
+        }
+
+        Ok(result)
+    }
+
+    // ========== Anthropic Claude ==========
+
+    pub async fn restore_with_anthropic(
+        &self,
+        api_key: &str,
+        image_base64: &str,
+        mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        info!("=== ANTHROPIC CLAUDE RESTORATION ===");
+        let endpoint = self.endpoint("anthropic", ProviderOperation::Restore)?;
+        let url = format!("{}/messages", endpoint.base_url);
+
+        let prompt = self.restoration_prompt(PromptKind::RestorePlan, options);
+
+        let start = std::time::Instant::now();
+        let (url, endpoint) = (&url, &endpoint);
+        let reply: PlanReply = ask_structured("anthropic", &prompt.text, move |text| async move {
+            let body = json!({
+                "model": endpoint.model,
+                "max_tokens": endpoint.max_tokens,
+                "messages": [{
+                    "role": "user",
+                    "content": [
+                        {
+                            "type": "image",
+                            "source": {
+                                "type": "base64",
+                                "media_type": mime_type,
+                                "data": image_base64
+                            }
+                        },
+                        {
+                            "type": "text",
+                            "text": text
+                        }
+                    ]
+                }]
+            });
+
+            let response = self.send("anthropic", || {
+                self.client.post(url)
+                    .timeout(endpoint.timeout)
+                    .header("x-api-key", api_key)
+                    .header("anthropic-version", "2023-06-01")
+                    .header("content-type", "application/json")
+                    .json(&body)
+            }).await?;
+
+            if !response.status().is_success() {
+                return Err(http_failure("Anthropic API error", response).await);
+            }
+
+            let data: serde_json::Value = response.json().await?;
+            data["content"][0]["text"].as_str().map(|text| text.to_string()).ok_or_else(|| anyhow!("Invalid response"))
+        }).await?;
+
+        let mut result = Self::plan_result("anthropic", image_base64, reply, options);
+        result.processing_time_ms = start.elapsed().as_millis() as u64;
+        result.prompt_version = Some(prompt.version);
+        Ok(result)
+    }
+
+    // ========== OpenAI GPT-4 Vision ==========
+
+    pub async fn restore_with_openai(
+        &self,
+        api_key: &str,
+        image_base64: &str,
+        mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        info!("=== OPENAI GPT-4 RESTORATION ===");
+        let endpoint = self.endpoint("openai", ProviderOperation::Restore)?;
+        let url = format!("{}/chat/completions", endpoint.base_url);
+
+        let prompt = self.restoration_prompt(PromptKind::RestorePlan, options);
+
+        let image_url = &format!("data:{};base64,{}", mime_type, image_base64);
+        let response_format = &json!({
+            "type": "json_schema",
+            "json_schema": {"name": PlanReply::NAME, "schema": PlanReply::schema(), "strict": false}
+        });
+
+        let start = std::time::Instant::now();
+        let (url, endpoint) = (&url, &endpoint);
+        let reply: PlanReply = ask_structured("openai", &prompt.text, move |text| async move {
+            let body = json!({
+                "model": endpoint.model,
+                "messages": [{
+                    "role": "user",
+                    "content": [
+                        {"type": "text", "text": text},
+                        {"type": "image_url", "image_url": {"url": image_url, "detail": "high"}}
+                    ]
+                }],
+                "max_tokens": endpoint.max_tokens,
+                "response_format": response_format
+            });
+
+            let response = self.send("openai", || {
+                self.client.post(url)
+                    .timeout(endpoint.timeout)
+                    .header("Authorization", format!("Bearer {}", api_key))
+                    .json(&body)
+            }).await?;
+
+            if !response.status().is_success() {
+                return Err(http_failure("OpenAI API error", response).await);
+            }
+
+            let data: serde_json::Value = response.json().await?;
+            data["choices"][0]["message"]["content"]
+                .as_str()
+                .map(|text| text.to_string())
+                .ok_or_else(|| anyhow!("Invalid response"))
+        }).await?;
+
+        let mut result = Self::plan_result("openai", image_base64, reply, options);
+        result.processing_time_ms = start.elapsed().as_millis() as u64;
+        result.prompt_version = Some(prompt.version);
+        Ok(result)
+    }
+
+    // ========== Ollama ==========
+    pub async fn get_ollama_models(&self) -> Result<Vec<AiModel>> {
+        let ollama_host = self.config
+            .get("ollama")
+            .map(|config| config.base_url.trim_end_matches('/').to_string())
+            .ok_or_else(|| TissaiaError::NoProvider("Ollama is not configured".to_string()))?;
+        let url = format!("{}/api/tags", ollama_host);
+
+        info!("Fetching Ollama models from {}", url);
+
+        let response = self.client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to fetch Ollama models"));
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        let mut models = Vec::new();
+
+        if let Some(model_list) = data["models"].as_array() {
+            for m in model_list {
+                if let Some(name) = m["name"].as_str() {
+                    models.push(AiModel {
+                        id: name.to_string(),
+                        name: name.to_string(),
+                        provider: "ollama".to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(models)
+    }
+
+    pub async fn restore_with_ollama(
+        &self,
+        model: &str,
+        image_base64: &str,
+        _mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        info!("=== OLLAMA RESTORATION ({}) ===", model);
+        let endpoint = self.endpoint("ollama", ProviderOperation::Restore)?;
+        let url = format!("{}/api/generate", endpoint.base_url);
+
+        let prompt = self.restoration_prompt(PromptKind::RestorePlan, options);
+
+        let start = std::time::Instant::now();
+        let (url, endpoint) = (&url, &endpoint);
+        let reply: PlanReply = ask_structured("ollama", &prompt.text, move |text| async move {
+            let body = json!({
+                "model": model,
+                "prompt": text,
+                "images": [image_base64],
+                "stream": false,
+                "format": "json",
+                "options": {"num_predict": endpoint.max_tokens}
+            });
+
+            let response = self.send("ollama", || self.client.post(url).timeout(endpoint.timeout).json(&body)).await?;
+
+            if !response.status().is_success() {
+                return Err(http_failure("Ollama API error", response).await);
+            }
+
+            let data: serde_json::Value = response.json().await?;
+            data["response"].as_str().map(|text| text.to_string()).ok_or_else(|| anyhow!("Invalid Ollama response"))
+        }).await?;
+
+        let mut result = Self::plan_result("ollama", image_base64, reply, options);
+        result.processing_time_ms = start.elapsed().as_millis() as u64;
+        result.prompt_version = Some(prompt.version);
+        Ok(result)
+    }
+
+    // ========== Health probes ==========
+
+    /// Cheap reachability and credential check for `provider` (`health`):
+    /// lists models, or Ollama's `/api/tags`. Sent once, without the executor's
+    /// retries, so the result reflects the provider right now.
+    pub async fn probe(&self, provider: &str, api_key: Option<&str>) -> Result<()> {
+        let base_url = self.config
+            .get(provider)
+            .map(|config| config.base_url.trim_end_matches('/').to_string())
+            .ok_or_else(|| anyhow!("Provider '{}' is not configured", provider))?;
+        let key = || api_key.ok_or_else(|| anyhow!("No API key for {}", provider));
+
+        let request = match provider {
+            "ollama" => self.client.get(format!("{}/api/tags", base_url)),
+            "google" => self.client.get(format!("{}/models", base_url)).header("x-goog-api-key", key()?),
+            "anthropic" => self.client.get(format!("{}/models", base_url))
+                .header("x-api-key", key()?)
+                .header("anthropic-version", "2023-06-01"),
+            _ => self.client.get(format!("{}/models", base_url)).bearer_auth(key()?),
+        };
+        let response = request.timeout(PROBE_TIMEOUT).send().await?;
+        if !response.status().is_success() {
+            return Err(http_failure("Health probe failed", response).await);
+        }
+        Ok(())
+    }
+
+    // ========== OpenAI-compatible chat (Mistral, Groq) ==========
+
+    /// POST a vision prompt to an OpenAI-compatible `/chat/completions`
+    /// endpoint and return the assistant message text. The reply is
+    /// requested as structured output matching `schema` (name, JSON Schema).
+    #[allow(clippy::too_many_arguments)]
+    async fn chat_completion(
+        &self,
+        provider: ChatProvider,
+        operation: ProviderOperation,
+        api_key: &str,
+        prompt: &str,
+        images: &[&str],
+        mime_type: &str,
+        (schema_name, schema): (&str, &Value),
+    ) -> Result<String> {
+        let endpoint = self.endpoint(provider.name, operation)?;
+        let url = format!("{}/chat/completions", endpoint.base_url);
+
+        let mut content = vec![json!({"type": "text", "text": prompt})];
+        content.extend(images.iter().map(|image| {
+            json!({
+                "type": "image_url",
+                "image_url": {"url": format!("data:{};base64,{}", mime_type, image)}
+            })
+        }));
+
+        let response_format = json!({
+            "type": "json_schema",
+            "json_schema": {"name": schema_name, "schema": schema, "strict": false}
+        });
+        let body = json!({
+            "model": endpoint.model,
+            "messages": [{"role": "user", "content": content}],
+            "max_tokens": endpoint.max_tokens,
+            "response_format": response_format
+        });
+
+        let response = self.send(provider.name, || {
+            self.client.post(&url)
+                .timeout(endpoint.timeout)
+                .header("Authorization", format!("Bearer {}", api_key))
+                .json(&body)
+        }).await?;
+
+        if !response.status().is_success() {
+            return Err(http_failure(provider.error_context, response).await);
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        data["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|text| text.to_string())
+            .ok_or_else(|| anyhow!("Invalid {} response", provider.name))
+    }
+
+    /// Restoration plan from a chat model; the original image is returned
+    /// unchanged and the backend executes `plan` locally.
+    pub async fn restore_with_chat(
+        &self,
+        provider: ChatProvider,
+        api_key: &str,
+        image_base64: &str,
+        mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        info!("=== {} RESTORATION ===", provider.name.to_uppercase());
+
+        let start = std::time::Instant::now();
+        let prompt = self.restoration_prompt(PromptKind::RestorePlan, options);
+        let schema = &PlanReply::schema();
+        let reply: PlanReply = ask_structured(provider.name, &prompt.text, move |text| async move {
+            self.chat_completion(
+                provider, ProviderOperation::Restore, api_key, &text, &[image_base64], mime_type,
+                (PlanReply::NAME, schema),
+            ).await
+        }).await?;
+
+        let mut result = Self::plan_result(provider.name, image_base64, reply, options);
+        result.processing_time_ms = start.elapsed().as_millis() as u64;
+        result.prompt_version = Some(prompt.version);
+        Ok(result)
+    }
+
+    pub async fn detect_with_chat(
+        &self,
+        provider: ChatProvider,
+        api_key: &str,
+        image_base64: &str,
+        mime_type: &str,
+    ) -> Result<DetectionResult> {
+        info!("=== DETECT PHOTO BOUNDARIES ({}) ===", provider.name);
+
+        let prompt = self.prompts.render(PromptKind::Detect, &[]);
+        let schema = &DetectionReply::schema();
+        let reply: DetectionReply = ask_structured(provider.name, &prompt.text, move |text| async move {
+            self.chat_completion(
+                provider, ProviderOperation::Detect, api_key, &text, &[image_base64], mime_type,
+                (DetectionReply::NAME, schema),
+            ).await
+        }).await?;
+
+        let mut result = Self::detection_result(reply, provider.name);
+        result.prompt_version = Some(prompt.version);
+        Ok(result)
+    }
+
+    // ========== Photo Boundary Detection (Google Gemini) ==========
+    pub async fn detect_photo_boundaries(
+        &self,
+        api_key: &str,
+        image_base64: &str,
+        mime_type: &str,
+    ) -> Result<DetectionResult> {
+        info!("=== DETECT PHOTO BOUNDARIES ===");
+        info!("Image base64 length: {} bytes", image_base64.len());
+
+        let prompt = self.prompts.render(PromptKind::Detect, &[]);
+        let schema = gemini_schema(&DetectionReply::schema());
+
+        info!("Sending detection request to Google Gemini...");
+        let reply: DetectionReply = ask_structured("google", &prompt.text, |text| {
+            let parts = vec![
+                json!({"inline_data": {"mime_type": mime_type, "data": image_base64}}),
+                json!({"text": text}),
+            ];
+            self.gemini_json(ProviderOperation::Detect, "Google API error", api_key, parts, &schema)
+        }).await?;
+
+        let mut result = Self::detection_result(reply, "google");
+        result.prompt_version = Some(prompt.version);
+        Ok(result)
+    }
+
+    /// POST `parts` to Gemini with a JSON `responseSchema` and return the
+    /// reply text.
+    async fn gemini_json(
+        &self,
+        operation: ProviderOperation,
+        error_context: &'static str,
+        api_key: &str,
+        parts: Vec<Value>,
+        schema: &Value,
+    ) -> Result<String> {
+        let endpoint = self.endpoint("google", operation)?;
+        let url = Self::gemini_url(&endpoint);
+
+        let body = json!({
+            "contents": [{"parts": parts}],
+            "generationConfig": {
+                "temperature": GEMINI_TEMPERATURE,
+                "maxOutputTokens": endpoint.max_tokens,
+                "responseMimeType": "application/json",
+                "responseSchema": schema
+            }
+        });
+
+        let response = self.send("google", || {
+            self.client.post(&url)
+                .timeout(endpoint.timeout)
+                .header("x-goog-api-key", api_key)
+                .json(&body)
+        }).await?;
+        info!("Response status: {}", response.status());
+
+        if !response.status().is_success() {
+            return Err(http_failure(error_context, response).await);
+        }
+
+        let data: Value = response.json().await?;
+        data["candidates"][0]["content"]["parts"][0]["text"]
+            .as_str()
+            .map(|text| text.to_string())
+            .ok_or_else(|| anyhow!("Invalid Gemini response format"))
+    }
+
+    // ========== Outpainting (Gemini 3 Pro) ==========
+
+    /// Fill non-rectangular edges of a cropped photo to produce a clean rectangle.
+    /// Takes the cropped image (rectangular bbox region) and the polygon contour
+    /// describing the actual photo shape within that region.
+    /// Gemini generates content for the areas outside the polygon but inside the rectangle.
+    pub async fn outpaint_to_rectangle(
+        &self,
+        api_key: &str,
+        cropped_base64: &str,
+        mime_type: &str,
+        contour_points: &[crate::models::Point2D],
+        bbox_width: u32,
+        bbox_height: u32,
+    ) -> Result<String> {
+        info!("=== OUTPAINT TO RECTANGLE ===");
+        info!("BBox size: {}x{}, contour points: {}", bbox_width, bbox_height, contour_points.len());
+
+        let endpoint = self.endpoint("google", ProviderOperation::Outpaint)?;
+        let url = Self::gemini_url(&endpoint);
+
+        // Convert contour points from global 0-1000 space to local bbox-relative percentages
+        // for the prompt description (Gemini works with the image it sees)
+        let contour_desc: Vec<String> = contour_points.iter().map(|p| {
+            format!("[{:.0}, {:.0}]", p.x, p.y)
+        }).collect();
+        let contour_json = contour_desc.join(", ");
+
+        let prompt = self.prompts.render(PromptKind::Outpaint, &[
+            ("contour", &contour_json),
+            ("bbox_width", &bbox_width.to_string()),
+            ("bbox_height", &bbox_height.to_string()),
+        ]);
+        info!("Outpaint prompt {}", prompt.version);
+
+        let body = json!({
+            "contents": [{
+                "parts": [
+                    {
+                        "inline_data": {
+                            "mime_type": mime_type,
+                            "data": cropped_base64
+                        }
+                    },
+                    {"text": prompt.text}
+                ]
+            }],
+            "generationConfig": {
+                "temperature": GEMINI_TEMPERATURE,
+                "maxOutputTokens": endpoint.max_tokens,
+                "response_modalities": ["TEXT", "IMAGE"]
+            }
+        });
+
+        info!("Sending outpainting request to Google Gemini...");
+        let response = self.send("google", || {
+            self.client.post(&url)
+                .timeout(endpoint.timeout)
+                .header("x-goog-api-key", api_key)
+                .json(&body)
+        }).await?;
+        let status = response.status();
+        info!("Outpainting response status: {}", status);
+
+        if !status.is_success() {
+            return Err(http_failure("Google API outpainting error", response).await);
+        }
+
+        let data: serde_json::Value = response.json().await?;
+
+        // Extract the generated image from the response
+        // Gemini returns images as inline_data in parts
+        if let Some(parts) = data["candidates"][0]["content"]["parts"].as_array() {
+            for part in parts {
+                if let Some(inline) = part.get("inline_data") {
+                    if let Some(img_data) = inline["data"].as_str() {
+                        info!("Outpainting successful, output size: {} bytes", img_data.len());
+                        return Ok(img_data.to_string());
+                    }
+                }
+            }
+        }
+
+        // Fallback: if no image generated, return original
+        info!("Outpainting: no image in response, returning original");
+        Ok(cropped_base64.to_string())
+    }
+
+    // ========== Verification Agent (Gemini 3 Flash) ==========
+
+    /// Send a verification prompt with one or two images to `verifier`
+    /// and return the validated reply.
+    async fn ask_verifier(
+        &self,
+        verifier: Verifier,
+        api_key: &str,
+        prompt: &str,
+        images: &[&str],
+        mime_type: &str,
+    ) -> Result<VerificationReply> {
+        match verifier {
+            Verifier::GeminiFlash => {
+                if !(1..=2).contains(&images.len()) {
+                    return Err(anyhow!("Gemini Flash verification takes one or two images"));
+                }
+                let schema = gemini_schema(&VerificationReply::schema());
+                ask_structured("google", prompt, |text| {
+                    let mut parts = vec![json!({"text": text})];
+                    parts.extend(images.iter().map(|image| {
+                        json!({"inline_data": {"mime_type": mime_type, "data": image}})
+                    }));
+                    self.gemini_json(ProviderOperation::Verify, "Gemini Flash verification error", api_key, parts, &schema)
+                }).await
+            }
+            Verifier::Chat(provider) => {
+                let schema = &VerificationReply::schema();
+                ask_structured(provider.name, prompt, move |text| async move {
+                    self.chat_completion(
+                        provider, ProviderOperation::Verify, api_key, &text, images, mime_type,
+                        (VerificationReply::NAME, schema),
+                    ).await
+                }).await
+            }
+        }
+    }
+
+    pub async fn verify_restoration(
+        &self,
+        verifier: Verifier,
+        api_key: &str,
+        original_base64: &str,
+        restored_base64: &str,
+        mime_type: &str,
+    ) -> Result<VerificationResult> {
+        let model = self.endpoint(verifier.provider(), ProviderOperation::Verify)?.model;
+        info!("=== VERIFY RESTORATION ({}) ===", model);
+        let start = std::time::Instant::now();
+
+        let prompt = self.prompts.render(PromptKind::VerifyRestoration, &[]);
+
+        let reply = self.ask_verifier(
+            verifier, api_key, &prompt.text, &[original_base64, restored_base64], mime_type,
+        ).await?;
+
+        let mut result = VerificationResult::new(VerificationStage::Restoration);
+        result.processing_time_ms = start.elapsed().as_millis() as u64;
+        result.model_used = model;
+        result.prompt_version = Some(prompt.version);
+        Self::apply_verification(&mut result, reply);
+        Ok(result)
+    }
+
+    pub async fn verify_detection(
+        &self,
+        verifier: Verifier,
+        api_key: &str,
+        image_base64: &str,
+        mime_type: &str,
+        bounding_boxes: &[BoundingBox],
+    ) -> Result<VerificationResult> {
+        let model = self.endpoint(verifier.provider(), ProviderOperation::Verify)?.model;
+        info!("=== VERIFY DETECTION ({}) ===", model);
+        let start = std::time::Instant::now();
+
+        let boxes_json = serde_json::to_string(bounding_boxes)
+            .unwrap_or_else(|_| "[]".to_string());
+
+        let prompt = self.prompts.render(PromptKind::VerifyDetection, &[("boxes_json", &boxes_json)]);
+
+        let reply = self.ask_verifier(
+            verifier, api_key, &prompt.text, &[image_base64], mime_type,
+        ).await?;
+
+        let mut result = VerificationResult::new(VerificationStage::Detection);
+        result.processing_time_ms = start.elapsed().as_millis() as u64;
+        result.model_used = model;
+        result.prompt_version = Some(prompt.version);
+        Self::apply_verification(&mut result, reply);
+        Ok(result)
+    }
+
+    pub async fn verify_crop(
+        &self,
+        verifier: Verifier,
+        api_key: &str,
+        cropped_base64: &str,
+        mime_type: &str,
+        crop_index: usize,
+    ) -> Result<VerificationResult> {
+        let model = self.endpoint(verifier.provider(), ProviderOperation::Verify)?.model;
+        info!("=== VERIFY CROP {} ({}) ===", crop_index, model);
+        let start = std::time::Instant::now();
+
+        let prompt = self.prompts.render(PromptKind::VerifyCrop, &[("crop_number", &(crop_index + 1).to_string())]);
+
+        let reply = self.ask_verifier(
+            verifier, api_key, &prompt.text, &[cropped_base64], mime_type,
+        ).await?;
+
+        let mut result = VerificationResult::new(VerificationStage::Crop);
+        result.processing_time_ms = start.elapsed().as_millis() as u64;
+        result.model_used = model;
+        result.prompt_version = Some(prompt.version);
+        Self::apply_verification(&mut result, reply);
+        Ok(result)
+    }
+
+    /// Result of a text-only provider: the original image, to be restored
+    /// locally by following `reply`.
+    fn plan_result(provider: &str, image_base64: &str, reply: PlanReply, options: &RestorationOptions) -> RestorationResult {
+        let mut result = RestorationResult::new(provider, image_base64.to_string());
+        result.restored_image = image_base64.to_string();
+        result.improvements = reply.improvements.clone();
+        result.plan = Some(reply.into_plan());
+
+        if result.improvements.is_empty() {
+            result.improvements = requested_improvements(options);
+        }
+        result
+    }
+
+    fn apply_verification(result: &mut VerificationResult, reply: VerificationReply) {
+        result.status = reply.status;
+        result.confidence = reply.confidence.round() as u8;
+        result.checks = reply
+            .checks
+            .into_iter()
+            .map(|c| VerificationCheck { name: c.name, passed: c.passed, detail: c.detail })
+            .collect();
+        result.issues = reply
+            .issues
+            .into_iter()
+            .map(|i| VerificationIssue { severity: i.severity, description: i.description, suggestion: i.suggestion })
+            .collect();
+        result.recommendations = reply.recommendations;
+        result.missing_boxes = reply.missing_boxes.into_iter().map(|b| b.into_bounding_box()).collect();
+        if !result.missing_boxes.is_empty() {
+            info!("Verifier found {} missing photo(s)", result.missing_boxes.len());
+        }
+    }
+
+    fn detection_result(reply: DetectionReply, provider: &str) -> DetectionResult {
+        for b in &reply.bounding_boxes {
+            // Log rotation reasoning from AI for debugging
+            if let Some(reasoning) = &b.rotation_reasoning {
+                info!("Photo '{}' rotation reasoning: {} → angle={}",
+                    b.label.as_deref().unwrap_or("?"), reasoning, b.rotation_angle);
+            }
+        }
+        let photo_count = reply.photo_count.round() as usize;
+        let bounding_boxes: Vec<BoundingBox> =
+            reply.bounding_boxes.into_iter().map(|b| b.into_bounding_box()).collect();
+
+        info!("Detected {} photos with {} bounding boxes ({} need outpainting)",
+            photo_count, bounding_boxes.len(),
+            bounding_boxes.iter().filter(|b| b.needs_outpaint).count()
+        );
+
+        DetectionResult {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            photo_count,
+            bounding_boxes,
+            provider_used: provider.to_string(),
+            scan_width: 0,
+            scan_height: 0,
+            attempts: Vec::new(),
+            adjustments: Vec::new(),
+            prompt_version: None,
+        }
+    }
+}
+
+impl Default for AiProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ========== Prompt variables ==========
+// Templates live in `prompts`; shared by every provider that speaks the
+// operation, so the Gemini and OpenAI-compatible paths ask the same question.
+
+/// How hard a step should be applied, in prompt words.
+fn intensity(strength: f32) -> &'static str {
+    match strength {
+        s if s < 0.34 => "GENTLE",
+        s if s < 0.67 => "MODERATE",
+        s if s < 1.0 => "STRONG",
+        _ => "MAXIMUM",
+    }
+}
+
+/// Numbered restoration instructions: what each enabled step should do and
+/// how strongly, then what disabled steps must leave alone.
+fn restoration_instructions(options: &RestorationOptions) -> String {
+    let steps: [(StepOption, &str, &str, &str); 7] = [
+        (
+            options.geometry,
+            "GEOMETRY",
+            "Straighten the photo. Inpaint missing corners, torn or missing areas seamlessly from the inner context (walls, floor, background).",
+            "Keep the geometry exactly as scanned: do not straighten, crop or inpaint edges.",
+        ),
+        (
+            options.flash,
+            "FLASH REMOVAL",
+            "Neutralize flash glare hotspots on faces and reflective surfaces; recover detail under blown-out highlights.",
+            "Leave highlights and flash reflections as they are.",
+        ),
+        (
+            options.denoise,
+            "DENOISING",
+            "Remove grain, film grain, digital and chroma noise, dust specks, scratches and stains while preserving edges and fine detail.",
+            "Do not denoise: keep the original grain and texture.",
+        ),
+        (
+            options.faces,
+            "FACES",
+            "Lock facial features strictly (shape, expression, identity). Restore natural skin tone, not plastic or airbrushed; enhance eye detail.",
+            "Do not retouch faces in any way.",
+        ),
+        (
+            options.colorize,
+            "COLOR",
+            "If the photo is black & white, colorize it naturally. If it is in colour, restore faded colours to vibrant, accurate tones.",
+            "Do NOT colorize: black & white photos stay black & white and the original palette is kept.",
+        ),
+        (
+            options.upscale,
+            "RESOLUTION",
+            "Super-resolution upscale: enhance fine detail, texture and micro-contrast; the result must be sharp and crisp.",
+            "Keep the original resolution; do not invent detail.",
+        ),
+        (
+            options.studio_finish,
+            "STUDIO QUALITY",
+            "Professional studio finish: soft diffused lighting, subtle vignette, professional colour grading.",
+            "No stylistic finish: no relighting, vignette or colour grading.",
+        ),
+    ];
+
+    let mut text = String::new();
+    let mut number = 0;
+    for (option, name, apply, _) in &steps {
+        if option.enabled {
+            number += 1;
+            text.push_str(&format!("{}. {} ({}): {}\n", number, name, intensity(option.strength), apply));
+        }
+    }
+    if number == 0 {
+        text.push_str("No restoration steps are requested: keep the photo as it is.\n");
+    }
+    let disabled: Vec<String> = steps.iter()
+        .filter(|(option, ..)| !option.enabled)
+        .map(|(_, name, _, keep)| format!("- {}: {}", name, keep))
+        .collect();
+    if !disabled.is_empty() {
+        text.push_str("\nDO NOT CHANGE:\n");
+        text.push_str(&disabled.join("\n"));
+        text.push('\n');
+    }
+    text
+}
+
+/// Improvements the options asked for, when the model did not list any.
+fn requested_improvements(options: &RestorationOptions) -> Vec<String> {
+    [
+        (options.geometry, "Geometry corrected"),
+        (options.flash, "Flash glare removed"),
+        (options.denoise, "Noise and grain removed"),
+        (options.faces, "Face enhancement"),
+        (options.colorize, "Color restoration"),
+        (options.upscale, "Super-resolution upscale"),
+        (options.studio_finish, "Studio-quality finish"),
+    ]
+    .iter()
+    .filter(|(option, _)| option.enabled)
+    .map(|(_, text)| text.to_string())
+    .collect()
+}
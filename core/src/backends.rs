@@ -0,0 +1,469 @@
+// core/src/backends.rs
+//! Pluggable AI backends.
+//! Every provider implements `RestorationBackend` once and is registered in
+//! the `BackendRegistry` held by `AppState`. Operations pick a backend by
+//! capability instead of matching on provider names.
+
+use crate::ai::{AiProvider, ChatProvider, Verifier};
+use crate::config::{ProviderConfigs, ProviderOperation};
+use crate::error::TissaiaError;
+use crate::executor::RequestExecutor;
+use crate::local_restoration;
+use crate::models::{
+    BackendCapabilities, BoundingBox, DetectionResult, RestorationOptions, RestorationResult,
+    VerificationResult,
+};
+use crate::prompts::PromptLibrary;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::info;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// What an operation needs from a backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    Restore,
+    Detection,
+    Verification,
+}
+
+impl Capability {
+    /// Human-readable operation name for logs and errors.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Capability::Restore => "restoration",
+            Capability::Detection => "photo detection",
+            Capability::Verification => "verification",
+        }
+    }
+}
+
+impl BackendCapabilities {
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Restore => self.restore,
+            Capability::Detection => self.detection,
+            Capability::Verification => self.verification,
+        }
+    }
+}
+
+/// Per-call inputs resolved from `AppState` (shared HTTP client and request
+/// executor, API key, provider endpoint config and prompt templates).
+#[derive(Clone)]
+pub struct BackendContext {
+    pub client: Client,
+    pub executor: Arc<RequestExecutor>,
+    pub api_key: Option<String>,
+    pub config: ProviderConfigs,
+    pub prompts: Arc<PromptLibrary>,
+}
+
+impl BackendContext {
+    pub fn ai(&self) -> AiProvider {
+        AiProvider::with_config(self.client.clone(), self.config.clone())
+            .with_prompts(self.prompts.clone())
+            .with_executor(self.executor.clone())
+    }
+
+    pub fn require_key(&self) -> Result<&str> {
+        self.api_key
+            .as_deref()
+            .ok_or_else(|| TissaiaError::NoProvider("API key not found".to_string()).into())
+    }
+}
+
+/// The three verification agent checks.
+#[derive(Clone, Copy)]
+pub enum VerificationRequest<'a> {
+    Restoration {
+        original_base64: &'a str,
+        restored_base64: &'a str,
+        mime_type: &'a str,
+    },
+    Detection {
+        image_base64: &'a str,
+        mime_type: &'a str,
+        bounding_boxes: &'a [BoundingBox],
+    },
+    Crop {
+        cropped_base64: &'a str,
+        mime_type: &'a str,
+        crop_index: usize,
+    },
+}
+
+#[async_trait]
+pub trait RestorationBackend: Send + Sync {
+    /// Provider name as used in `ProviderStatus` and API keys.
+    fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> BackendCapabilities;
+
+    async fn restore(
+        &self,
+        ctx: &BackendContext,
+        image_base64: &str,
+        mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult>;
+
+    async fn detect(
+        &self,
+        _ctx: &BackendContext,
+        _image_base64: &str,
+        _mime_type: &str,
+    ) -> Result<DetectionResult> {
+        Err(anyhow!("Photo detection not supported by provider '{}'", self.name()))
+    }
+
+    async fn verify(
+        &self,
+        _ctx: &BackendContext,
+        _request: VerificationRequest<'_>,
+    ) -> Result<VerificationResult> {
+        Err(anyhow!("Verification not supported by provider '{}'", self.name()))
+    }
+}
+
+// ============================================
+// REGISTRY
+// ============================================
+
+#[derive(Clone, Default)]
+pub struct BackendRegistry {
+    backends: HashMap<String, Arc<dyn RestorationBackend>>,
+}
+
+impl BackendRegistry {
+    /// Registry with all built-in providers.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::default();
+        registry.register(Arc::new(GoogleBackend));
+        registry.register(Arc::new(AnthropicBackend));
+        registry.register(Arc::new(OpenAiBackend));
+        registry.register(Arc::new(ChatBackend::new(ChatProvider::MISTRAL)));
+        registry.register(Arc::new(ChatBackend::new(ChatProvider::GROQ)));
+        registry.register(Arc::new(OllamaBackend));
+        registry.register(Arc::new(LocalBackend));
+        registry
+    }
+
+    pub fn register(&mut self, backend: Arc<dyn RestorationBackend>) {
+        self.backends.insert(backend.name().to_string(), backend);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn RestorationBackend>> {
+        self.backends.get(name).cloned()
+    }
+
+    pub fn capabilities(&self, name: &str) -> BackendCapabilities {
+        self.backends
+            .get(name)
+            .map(|b| b.capabilities())
+            .unwrap_or_default()
+    }
+}
+
+// ============================================
+// GOOGLE GEMINI
+// ============================================
+
+pub struct GoogleBackend;
+
+#[async_trait]
+impl RestorationBackend for GoogleBackend {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            restore: true,
+            image_output: true,
+            detection: true,
+            verification: true,
+            requires_api_key: true,
+        }
+    }
+
+    async fn restore(
+        &self,
+        ctx: &BackendContext,
+        image_base64: &str,
+        mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        ctx.ai().restore_with_google(ctx.require_key()?, image_base64, mime_type, options).await
+    }
+
+    async fn detect(
+        &self,
+        ctx: &BackendContext,
+        image_base64: &str,
+        mime_type: &str,
+    ) -> Result<DetectionResult> {
+        ctx.ai().detect_photo_boundaries(ctx.require_key()?, image_base64, mime_type).await
+    }
+
+    async fn verify(
+        &self,
+        ctx: &BackendContext,
+        request: VerificationRequest<'_>,
+    ) -> Result<VerificationResult> {
+        verify_with(ctx, Verifier::GeminiFlash, request).await
+    }
+}
+
+/// Dispatch a verification request to the `AiProvider` method for its stage.
+async fn verify_with(
+    ctx: &BackendContext,
+    verifier: Verifier,
+    request: VerificationRequest<'_>,
+) -> Result<VerificationResult> {
+    let ai = ctx.ai();
+    let api_key = ctx.require_key()?;
+    match request {
+        VerificationRequest::Restoration { original_base64, restored_base64, mime_type } => {
+            ai.verify_restoration(verifier, api_key, original_base64, restored_base64, mime_type).await
+        }
+        VerificationRequest::Detection { image_base64, mime_type, bounding_boxes } => {
+            ai.verify_detection(verifier, api_key, image_base64, mime_type, bounding_boxes).await
+        }
+        VerificationRequest::Crop { cropped_base64, mime_type, crop_index } => {
+            ai.verify_crop(verifier, api_key, cropped_base64, mime_type, crop_index).await
+        }
+    }
+}
+
+// ============================================
+// TEXT-ONLY ANALYSIS PROVIDERS
+// ============================================
+
+/// Restoration plan from the model, executed by the local engine
+/// (`local_restoration`); pixels only with the `image-processing` feature.
+const TEXT_ANALYSIS: BackendCapabilities = BackendCapabilities {
+    restore: true,
+    image_output: cfg!(feature = "image-processing"),
+    detection: false,
+    verification: false,
+    requires_api_key: true,
+};
+
+/// Turn a text-only provider's plan into a restored image.
+async fn run_plan_locally(
+    mut result: RestorationResult,
+    mime_type: &str,
+    options: &RestorationOptions,
+) -> Result<RestorationResult> {
+    // CPU-bound on full-size scans; keep it off the async workers
+    let (mime_type, options) = (mime_type.to_string(), *options);
+    tokio::task::spawn_blocking(move || {
+        local_restoration::apply_plan(&mut result, &mime_type, &options)?;
+        Ok(result)
+    })
+    .await
+    .map_err(|e| TissaiaError::Internal(format!("Local restoration task failed: {}", e)))?
+}
+
+pub struct AnthropicBackend;
+
+#[async_trait]
+impl RestorationBackend for AnthropicBackend {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        TEXT_ANALYSIS
+    }
+
+    async fn restore(
+        &self,
+        ctx: &BackendContext,
+        image_base64: &str,
+        mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        let result = ctx.ai().restore_with_anthropic(ctx.require_key()?, image_base64, mime_type, options).await?;
+        run_plan_locally(result, mime_type, options).await
+    }
+}
+
+pub struct OpenAiBackend;
+
+#[async_trait]
+impl RestorationBackend for OpenAiBackend {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        TEXT_ANALYSIS
+    }
+
+    async fn restore(
+        &self,
+        ctx: &BackendContext,
+        image_base64: &str,
+        mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        let result = ctx.ai().restore_with_openai(ctx.require_key()?, image_base64, mime_type, options).await?;
+        run_plan_locally(result, mime_type, options).await
+    }
+}
+
+// ============================================
+// OPENAI-COMPATIBLE VISION PROVIDERS (MISTRAL, GROQ)
+// ============================================
+
+/// Vision chat model behind an OpenAI-compatible endpoint: restoration
+/// plans (executed locally), detection and verification.
+pub struct ChatBackend {
+    provider: ChatProvider,
+}
+
+impl ChatBackend {
+    pub fn new(provider: ChatProvider) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl RestorationBackend for ChatBackend {
+    fn name(&self) -> &'static str {
+        self.provider.name
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            detection: true,
+            verification: true,
+            ..TEXT_ANALYSIS
+        }
+    }
+
+    async fn restore(
+        &self,
+        ctx: &BackendContext,
+        image_base64: &str,
+        mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        let result = ctx.ai().restore_with_chat(self.provider, ctx.require_key()?, image_base64, mime_type, options).await?;
+        run_plan_locally(result, mime_type, options).await
+    }
+
+    async fn detect(
+        &self,
+        ctx: &BackendContext,
+        image_base64: &str,
+        mime_type: &str,
+    ) -> Result<DetectionResult> {
+        ctx.ai().detect_with_chat(self.provider, ctx.require_key()?, image_base64, mime_type).await
+    }
+
+    async fn verify(
+        &self,
+        ctx: &BackendContext,
+        request: VerificationRequest<'_>,
+    ) -> Result<VerificationResult> {
+        verify_with(ctx, Verifier::Chat(self.provider), request).await
+    }
+}
+
+// ============================================
+// OLLAMA (LOCAL)
+// ============================================
+
+pub struct OllamaBackend;
+
+#[async_trait]
+impl RestorationBackend for OllamaBackend {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            requires_api_key: false,
+            ..TEXT_ANALYSIS
+        }
+    }
+
+    async fn restore(
+        &self,
+        ctx: &BackendContext,
+        image_base64: &str,
+        mime_type: &str,
+        options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        let ai = ctx.ai();
+        // Configured model wins; empty means "first installed model"
+        let configured = ctx
+            .config
+            .get("ollama")
+            .and_then(|config| config.operation(ProviderOperation::Restore))
+            .map(|op| op.model.clone())
+            .filter(|model| !model.is_empty());
+        let model = match configured {
+            Some(model) => model,
+            None => {
+                let models = ai.get_ollama_models().await.unwrap_or_default();
+                models.first().map(|m| m.name.clone()).unwrap_or("llama3.2:vision".to_string())
+            }
+        };
+        info!("Ollama restoration using model {}", model);
+        let result = ai.restore_with_ollama(&model, image_base64, mime_type, options).await?;
+        run_plan_locally(result, mime_type, options).await
+    }
+}
+
+// ============================================
+// LOCAL (OFFLINE DETECTION)
+// ============================================
+
+/// Pixel-based photo detection (`local_detection`); no network, no key.
+/// Last in the failover order, so it takes over when no AI provider is
+/// reachable, or runs first when set as the preferred provider.
+pub struct LocalBackend;
+
+#[async_trait]
+impl RestorationBackend for LocalBackend {
+    fn name(&self) -> &'static str {
+        crate::local_detection::LOCAL_PROVIDER
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            detection: cfg!(feature = "image-processing"),
+            ..BackendCapabilities::default()
+        }
+    }
+
+    async fn restore(
+        &self,
+        _ctx: &BackendContext,
+        _image_base64: &str,
+        _mime_type: &str,
+        _options: &RestorationOptions,
+    ) -> Result<RestorationResult> {
+        Err(anyhow!("Restoration not supported by provider '{}'", self.name()))
+    }
+
+    async fn detect(
+        &self,
+        _ctx: &BackendContext,
+        image_base64: &str,
+        _mime_type: &str,
+    ) -> Result<DetectionResult> {
+        // CPU-bound on full-size scans; keep it off the async workers
+        let image_base64 = image_base64.to_string();
+        tokio::task::spawn_blocking(move || crate::local_detection::detect_photos(&image_base64, &Default::default()))
+            .await
+            .map_err(|e| TissaiaError::Internal(format!("Local detection task failed: {}", e)))?
+    }
+}
@@ -0,0 +1,551 @@
+// core/src/batch.rs
+//! Batch processing of a folder of scans.
+//! Every image under `input_dir` goes through the pipeline (detect → crop →
+//! optional restore, see `pipeline`) on a bounded number of concurrent
+//! scans. Photos land in `output_dir` as `{scan stem}_{n}.{ext}`, mirroring
+//! the input's sub-folders. `manifest.json` and `manifest.csv` list every
+//! scan and are rewritten after each one, so an interrupted run resumes where
+//! it stopped: completed scans (same size, mtime and options, outputs still
+//! on disk) are skipped, failed and partial ones run again. A scan that runs
+//! again replaces its earlier photos; ones it no longer produces are deleted.
+
+use crate::error::TissaiaError;
+use crate::imaging;
+use crate::pipeline::{self, PipelineOptions, PipelineRequest, PipelineResult};
+use crate::progress::{Stage, StageEvent, StageObserver, StageStatus};
+use crate::state::SharedState;
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+pub const MANIFEST_JSON: &str = "manifest.json";
+pub const MANIFEST_CSV: &str = "manifest.csv";
+/// Scans processed at once unless the request says otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 2;
+/// Upper bound on `BatchRequest::concurrency`; every scan in flight keeps
+/// all of its photos in memory.
+pub const MAX_CONCURRENCY: usize = 8;
+
+// ============================================
+// REQUEST, MANIFEST & SUMMARY
+// ============================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequest {
+    pub input_dir: PathBuf,
+    pub output_dir: PathBuf,
+    /// Descend into sub-folders (mirrored under `output_dir`).
+    #[serde(default)]
+    pub recursive: bool,
+    /// Scans processed at once (`DEFAULT_CONCURRENCY`, at most `MAX_CONCURRENCY`).
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    /// Process scans the manifest already lists as completed.
+    #[serde(default)]
+    pub force: bool,
+    /// Stages run for every scan.
+    #[serde(default)]
+    pub options: PipelineOptions,
+}
+
+impl BatchRequest {
+    /// Resolve both directories against `root`, rejecting paths that leave it.
+    /// Used where the caller must not reach arbitrary paths (the HTTP API).
+    pub fn confined_to(mut self, root: &Path) -> Result<Self> {
+        self.input_dir = confine(root, &self.input_dir)?;
+        self.output_dir = confine(root, &self.output_dir)?;
+        Ok(self)
+    }
+}
+
+fn confine(root: &Path, path: &Path) -> Result<PathBuf> {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    if relative.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(TissaiaError::Validation(format!(
+            "Batch path {:?} must stay inside {:?}",
+            path, root
+        ))
+        .into());
+    }
+    Ok(root.join(relative))
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanStatus {
+    /// Every photo went through every requested stage.
+    Completed,
+    /// Photos were written, but a per-photo stage failed (e.g. restoration).
+    Partial,
+    /// Nothing written: unreadable scan, failed detection or crop.
+    Failed,
+}
+
+impl ScanStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            ScanStatus::Completed => "completed",
+            ScanStatus::Partial => "partial",
+            ScanStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestPhoto {
+    pub index: usize,
+    /// Path relative to `output_dir`, `/`-separated.
+    pub output: String,
+    pub restored: bool,
+    pub provider: Option<String>,
+    /// Whether every stage that ran for this photo succeeded.
+    pub succeeded: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestEntry {
+    /// Path relative to `input_dir`, `/`-separated.
+    pub source: String,
+    pub status: ScanStatus,
+    pub size: u64,
+    /// Source mtime (Unix seconds); a scan changed since is processed again.
+    pub modified: Option<u64>,
+    pub photos: Vec<ManifestPhoto>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Options the scan was processed with; a run with other options
+    /// processes it again. `None` in manifests from older versions.
+    #[serde(default)]
+    pub options: Option<PipelineOptions>,
+    pub processing_time_ms: u64,
+    pub processed_at: DateTime<Utc>,
+}
+
+/// Contents of `manifest.json`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BatchManifest {
+    /// Entries from a manifest written for another input folder are dropped.
+    pub input_dir: PathBuf,
+    /// Options of the latest run; each entry records its own.
+    pub options: PipelineOptions,
+    pub updated_at: DateTime<Utc>,
+    /// Sorted by `source`.
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BatchSummary {
+    pub input_dir: PathBuf,
+    pub output_dir: PathBuf,
+    /// Images found under `input_dir`.
+    pub total: usize,
+    /// Scans run this time (completed, partial or failed).
+    pub processed: usize,
+    /// Scans already completed by an earlier run.
+    pub skipped: usize,
+    pub partial: usize,
+    pub failed: usize,
+    pub photos_written: usize,
+    pub manifest: PathBuf,
+    pub processing_time_ms: u64,
+}
+
+// ============================================
+// BATCH RUN
+// ============================================
+
+pub async fn run_batch(state: &SharedState, request: BatchRequest) -> Result<BatchSummary> {
+    run_batch_observed(state, request, &()).await
+}
+
+/// `run_batch`, reporting progress as `Stage::Batch` events: one `running`
+/// event per finished scan (message "N/M scans", payload the scan's
+/// `ManifestEntry`), then `completed` with the `BatchSummary`.
+pub async fn run_batch_observed(
+    state: &SharedState,
+    request: BatchRequest,
+    observer: &dyn StageObserver,
+) -> Result<BatchSummary> {
+    let BatchRequest { input_dir, output_dir, recursive, concurrency, force, options } = request;
+    options.validate()?;
+    if !input_dir.is_dir() {
+        return Err(TissaiaError::Validation(format!("Input folder {:?} does not exist", input_dir)).into());
+    }
+    fs::create_dir_all(&output_dir).with_context(|| format!("create output folder {:?}", output_dir))?;
+    let concurrency = concurrency.unwrap_or(DEFAULT_CONCURRENCY).clamp(1, MAX_CONCURRENCY);
+
+    info!("=== BATCH START === ({:?} → {:?}, {} at once)", input_dir, output_dir, concurrency);
+    let start = Instant::now();
+    observer.running(Stage::Batch, None);
+
+    let sources = collect_sources(&input_dir, &output_dir, recursive)?;
+    let stems = output_stems(&sources);
+    let mut manifest = load_manifest(&output_dir, &input_dir, &options);
+
+    // Plan: skip what an earlier run already completed
+    let mut pending = Vec::new();
+    let mut skipped = 0;
+    for source in &sources {
+        let (size, modified) = file_stamp(&input_dir.join(source));
+        let key = source_key(source);
+        let done = manifest.entries.iter().find(|e| e.source == key).is_some_and(|entry| {
+            entry.status == ScanStatus::Completed
+                && entry.size == size
+                && entry.modified == modified
+                && entry.options.as_ref() == Some(&options)
+                && entry.photos.iter().all(|p| output_dir.join(&p.output).is_file())
+        });
+        if done && !force {
+            skipped += 1;
+        } else {
+            pending.push(source.clone());
+        }
+    }
+    info!("Batch: {} scans, {} already done, {} to process", sources.len(), skipped, pending.len());
+
+    let queued = pending.len();
+    let permits = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = JoinSet::new();
+    for source in pending {
+        let state = state.clone();
+        let permits = permits.clone();
+        let options = options.clone();
+        let input = input_dir.join(&source);
+        let stem = stems[&source].clone();
+        let output_dir = output_dir.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            process_scan(&state, &input, &source, &stem, &output_dir, options).await
+        });
+    }
+
+    let (mut partial, mut failed, mut photos_written) = (0, 0, 0);
+    let mut done = 0;
+    while let Some(joined) = tasks.join_next().await {
+        let entry = match joined {
+            Ok(entry) => entry,
+            Err(e) => {
+                // Not recorded: the scan runs again next time
+                error!("Batch scan task failed: {}", e);
+                continue;
+            }
+        };
+        done += 1;
+        match entry.status {
+            ScanStatus::Completed => {}
+            ScanStatus::Partial => partial += 1,
+            ScanStatus::Failed => failed += 1,
+        }
+        photos_written += entry.photos.len();
+
+        observer.on_stage(StageEvent {
+            stage: Stage::Batch,
+            status: StageStatus::Running,
+            message: Some(format!("{}/{} scans", done, queued)),
+            payload: serde_json::to_value(&entry).ok(),
+        });
+        if let Some(previous) = manifest.entries.iter().find(|e| e.source == entry.source) {
+            remove_stale_outputs(&output_dir, previous, &entry);
+        }
+        manifest.entries.retain(|e| e.source != entry.source);
+        manifest.entries.push(entry);
+        if let Err(e) = save_manifest(&output_dir, &mut manifest) {
+            warn!("Failed to write batch manifest: {:#}", e);
+        }
+    }
+    // A run with nothing to do still leaves a manifest behind
+    if queued == 0 {
+        save_manifest(&output_dir, &mut manifest)?;
+    }
+
+    let summary = BatchSummary {
+        input_dir,
+        output_dir: output_dir.clone(),
+        total: sources.len(),
+        processed: done,
+        skipped,
+        partial,
+        failed,
+        photos_written,
+        manifest: output_dir.join(MANIFEST_JSON),
+        processing_time_ms: start.elapsed().as_millis() as u64,
+    };
+    info!(
+        "=== BATCH END === ({} processed, {} skipped, {} partial, {} failed, {} photos, {} ms)",
+        summary.processed, summary.skipped, summary.partial, summary.failed, summary.photos_written,
+        summary.processing_time_ms
+    );
+    observer.completed_with(
+        Stage::Batch,
+        Some(format!("{} scans, {} failed", summary.total, summary.failed)),
+        serde_json::to_value(&summary).ok(),
+    );
+    Ok(summary)
+}
+
+/// Run the pipeline on one scan and write its photos.
+async fn process_scan(
+    state: &SharedState,
+    input: &Path,
+    source: &Path,
+    stem: &str,
+    output_dir: &Path,
+    options: PipelineOptions,
+) -> ManifestEntry {
+    let start = Instant::now();
+    let (size, modified) = file_stamp(input);
+    let mut entry = ManifestEntry {
+        source: source_key(source),
+        status: ScanStatus::Failed,
+        size,
+        modified,
+        photos: Vec::new(),
+        error: None,
+        options: Some(options.clone()),
+        processing_time_ms: 0,
+        processed_at: Utc::now(),
+    };
+
+    let outcome = async {
+        let bytes = fs::read(input).with_context(|| format!("read {:?}", input))?;
+        let file_name = source.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let mime_type = imaging::mime_from_filename(&file_name).unwrap_or("image/jpeg").to_string();
+        let result = pipeline::run_pipeline(
+            state,
+            PipelineRequest {
+                image_base64: STANDARD.encode(bytes),
+                mime_type,
+                original_filename: Some(file_name),
+                options,
+            },
+        )
+        .await?;
+        write_photos(&result, source, stem, output_dir)
+    }
+    .await;
+
+    match outcome {
+        Ok(photos) => {
+            entry.status = if photos.iter().all(|p| p.succeeded) { ScanStatus::Completed } else { ScanStatus::Partial };
+            entry.photos = photos;
+        }
+        Err(e) => {
+            warn!("Batch: {:?} failed: {:#}", source, e);
+            entry.error = Some(format!("{:#}", e));
+        }
+    }
+    entry.processing_time_ms = start.elapsed().as_millis() as u64;
+    entry
+}
+
+fn write_photos(result: &PipelineResult, source: &Path, stem: &str, output_dir: &Path) -> Result<Vec<ManifestPhoto>> {
+    let folder = source.parent().unwrap_or(Path::new(""));
+    fs::create_dir_all(output_dir.join(folder))?;
+
+    let mut written = Vec::with_capacity(result.photos.len());
+    for photo in &result.photos {
+        let name = format!("{}_{}.{}", stem, photo.index + 1, imaging::extension_for_mime(&photo.mime_type));
+        let relative = folder.join(name);
+        let bytes = imaging::decode_base64(&photo.image_base64)?;
+        fs::write(output_dir.join(&relative), bytes).with_context(|| format!("write {:?}", relative))?;
+        written.push(ManifestPhoto {
+            index: photo.index,
+            output: source_key(&relative),
+            restored: photo.restored,
+            provider: photo.provider.clone(),
+            succeeded: photo.succeeded(),
+        });
+    }
+    Ok(written)
+}
+
+/// Delete photos an earlier run wrote for a scan that its new run did not
+/// write again (fewer photos, another output format, or a failure), so the
+/// output folder matches the manifest.
+fn remove_stale_outputs(output_dir: &Path, previous: &ManifestEntry, current: &ManifestEntry) {
+    for photo in &previous.photos {
+        let relative = Path::new(&photo.output);
+        // Never follow a manifest entry out of the output folder
+        if current.photos.iter().any(|p| p.output == photo.output)
+            || !relative.components().all(|c| matches!(c, Component::Normal(_)))
+        {
+            continue;
+        }
+        match fs::remove_file(output_dir.join(relative)) {
+            Ok(()) => info!("Batch: removed stale output {}", photo.output),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Batch: failed to remove stale output {}: {}", photo.output, e),
+        }
+    }
+}
+
+// ============================================
+// SOURCES & OUTPUT NAMES
+// ============================================
+
+/// Image files under `input_dir` (relative paths, sorted), leaving out
+/// hidden files and the output folder when it sits inside the input.
+fn collect_sources(input_dir: &Path, output_dir: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    let output_dir = fs::canonicalize(output_dir).unwrap_or_else(|_| output_dir.to_path_buf());
+    let mut sources = Vec::new();
+    let mut folders = vec![PathBuf::new()];
+    while let Some(folder) = folders.pop() {
+        let dir = input_dir.join(&folder);
+        for item in fs::read_dir(&dir).with_context(|| format!("read folder {:?}", dir))? {
+            let item = item?;
+            let name = item.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            let path = item.path();
+            let relative = folder.join(&name);
+            if path.is_dir() {
+                if recursive && fs::canonicalize(&path).ok().as_deref() != Some(output_dir.as_path()) {
+                    folders.push(relative);
+                }
+            } else if imaging::mime_from_filename(&name).is_some() {
+                sources.push(relative);
+            }
+        }
+    }
+    sources.sort();
+    Ok(sources)
+}
+
+/// Output stem per scan: its file stem, or the whole file name when two
+/// scans in one folder share a stem (`a.jpg` and `a.png` → `a_jpg`, `a_png`).
+fn output_stems(sources: &[PathBuf]) -> HashMap<PathBuf, String> {
+    let stem = |source: &PathBuf| source.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut seen = HashSet::new();
+    let mut clashing = HashSet::new();
+    for source in sources {
+        let key = (source.parent().map(Path::to_path_buf), stem(source));
+        if !seen.insert(key.clone()) {
+            clashing.insert(key);
+        }
+    }
+    sources
+        .iter()
+        .map(|source| {
+            let key = (source.parent().map(Path::to_path_buf), stem(source));
+            let name = if clashing.contains(&key) {
+                source.file_name().map(|n| n.to_string_lossy().replace('.', "_")).unwrap_or_default()
+            } else {
+                key.1
+            };
+            (source.clone(), name)
+        })
+        .collect()
+}
+
+fn source_key(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn file_stamp(path: &Path) -> (u64, Option<u64>) {
+    let Ok(meta) = fs::metadata(path) else {
+        return (0, None);
+    };
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    (meta.len(), modified)
+}
+
+// ============================================
+// MANIFEST FILES
+// ============================================
+
+/// The manifest of an earlier run, or an empty one. An unreadable manifest
+/// only costs a reprocess, so it is replaced rather than fatal.
+fn load_manifest(output_dir: &Path, input_dir: &Path, options: &PipelineOptions) -> BatchManifest {
+    let path = output_dir.join(MANIFEST_JSON);
+    let previous = match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice::<BatchManifest>(&bytes)
+            .inspect_err(|e| warn!("Ignoring unreadable batch manifest {:?}: {}", path, e))
+            .ok(),
+        Err(_) => None,
+    };
+    let entries = match previous {
+        Some(previous) if previous.input_dir == input_dir => previous.entries,
+        Some(previous) => {
+            info!("Batch manifest {:?} was written for {:?}; starting a new one", path, previous.input_dir);
+            Vec::new()
+        }
+        None => Vec::new(),
+    };
+    BatchManifest {
+        input_dir: input_dir.to_path_buf(),
+        options: options.clone(),
+        updated_at: Utc::now(),
+        entries,
+    }
+}
+
+/// Write `manifest.json` and `manifest.csv` (via temp files, so a crash
+/// mid-write never leaves a truncated manifest).
+fn save_manifest(output_dir: &Path, manifest: &mut BatchManifest) -> Result<()> {
+    manifest.entries.sort_by(|a, b| a.source.cmp(&b.source));
+    manifest.updated_at = Utc::now();
+    write_atomic(&output_dir.join(MANIFEST_JSON), &serde_json::to_vec_pretty(manifest)?)?;
+    write_atomic(&output_dir.join(MANIFEST_CSV), manifest_csv(manifest).as_bytes())
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, contents).with_context(|| format!("write {:?}", tmp))?;
+    fs::rename(&tmp, path).with_context(|| format!("replace {:?}", path))?;
+    Ok(())
+}
+
+/// One row per written photo; scans without photos get a single row.
+pub fn manifest_csv(manifest: &BatchManifest) -> String {
+    let mut csv = String::from("source,status,photo,output,restored,provider,succeeded,error\n");
+    for entry in &manifest.entries {
+        let error = entry.error.as_deref().unwrap_or("");
+        if entry.photos.is_empty() {
+            csv.push_str(&csv_row(&[&entry.source, entry.status.as_str(), "", "", "", "", "", error]));
+        }
+        for photo in &entry.photos {
+            csv.push_str(&csv_row(&[
+                &entry.source,
+                entry.status.as_str(),
+                &(photo.index + 1).to_string(),
+                &photo.output,
+                &photo.restored.to_string(),
+                photo.provider.as_deref().unwrap_or(""),
+                &photo.succeeded.to_string(),
+                error,
+            ]));
+        }
+    }
+    csv
+}
+
+fn csv_row(fields: &[&str]) -> String {
+    let quoted: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    format!("{}\n", quoted.join(","))
+}
@@ -0,0 +1,322 @@
+// core/src/boxes.rs
+//! Box post-processing: conflicts between detected boxes.
+//! Detection (AI or local) and the verifier's `missing_boxes` can report a
+//! photo twice, box a whole group of photos next to the single ones, box a
+//! detail inside a photo, or let neighbours overlap. `resolve` cleans a box
+//! list in four steps, in this order:
+//! 1. group boxes enclosing two or more other boxes are dropped;
+//! 2. near-duplicates (IoU ≥ `DUPLICATE_IOU`) keep the most confident box;
+//! 3. boxes lying inside another box (`CONTAINED_FRACTION`) are dropped;
+//! 4. remaining overlaps are split at their middle, pass after pass, until
+//!    no two boxes overlap. A box that would end up thinner than
+//!    `MIN_SIZE` is dropped instead.
+//!
+//! Every change is logged and returned as a `BoxChange`. Boxes only ever
+//! shrink or disappear, so resolving one overlap never creates another.
+
+use crate::models::BoundingBox;
+use log::info;
+
+/// IoU above which two boxes are the same photo detected twice.
+pub const DUPLICATE_IOU: f64 = 0.6;
+/// Share of a box's area inside another box for it to count as contained.
+pub const CONTAINED_FRACTION: f64 = 0.9;
+/// Thinnest side (0-1000 units) a split may leave; below it the box goes.
+pub const MIN_SIZE: u32 = 10;
+
+/// One change made by `resolve`. Indices refer to the input list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoxChange {
+    /// `dropped` enclosed `members` other boxes: a group, not a photo.
+    Group { dropped: usize, members: usize },
+    /// `dropped` was the same photo as the more confident `kept`.
+    Duplicate { dropped: usize, kept: usize, iou: f64 },
+    /// `dropped` lay inside `container` (a detail of that photo).
+    Contained { dropped: usize, container: usize },
+    /// `a` and `b` overlapped; their shared edge moved to the middle.
+    Split { a: usize, b: usize },
+    /// `trimmed` crossed `other` and keeps its larger part outside it.
+    Trimmed { trimmed: usize, other: usize },
+    /// Separating `dropped` from `other` would have left a sliver.
+    Squeezed { dropped: usize, other: usize },
+}
+
+impl BoxChange {
+    /// Input index of the box this change removed, if any.
+    pub fn dropped(&self) -> Option<usize> {
+        match *self {
+            BoxChange::Group { dropped, .. }
+            | BoxChange::Duplicate { dropped, .. }
+            | BoxChange::Contained { dropped, .. }
+            | BoxChange::Squeezed { dropped, .. } => Some(dropped),
+            BoxChange::Split { .. } | BoxChange::Trimmed { .. } => None,
+        }
+    }
+}
+
+/// All four steps, for freshly detected or merged boxes.
+pub fn resolve(boxes: &mut Vec<BoundingBox>) -> Vec<BoxChange> {
+    let mut entries = Entries::new(boxes);
+    let mut changes = Vec::new();
+    drop_groups(&mut entries, &mut changes);
+    drop_duplicates(&mut entries, &mut changes);
+    drop_contained(&mut entries, &mut changes);
+    separate(&mut entries, &mut changes);
+    *boxes = entries.into_boxes();
+    changes
+}
+
+/// Only step 4, for boxes a user already curated (cropping).
+pub fn separate_overlaps(boxes: &mut Vec<BoundingBox>) -> Vec<BoxChange> {
+    let mut entries = Entries::new(boxes);
+    let mut changes = Vec::new();
+    separate(&mut entries, &mut changes);
+    *boxes = entries.into_boxes();
+    changes
+}
+
+// ============================================
+// GEOMETRY
+// ============================================
+
+fn right(b: &BoundingBox) -> u32 {
+    b.x + b.width
+}
+
+fn bottom(b: &BoundingBox) -> u32 {
+    b.y + b.height
+}
+
+fn area(b: &BoundingBox) -> u64 {
+    b.width as u64 * b.height as u64
+}
+
+/// Overlap of two boxes along x and y (0 when they only touch).
+fn overlap(a: &BoundingBox, b: &BoundingBox) -> (u32, u32) {
+    let x = right(a).min(right(b)).saturating_sub(a.x.max(b.x));
+    let y = bottom(a).min(bottom(b)).saturating_sub(a.y.max(b.y));
+    (x, y)
+}
+
+fn intersection(a: &BoundingBox, b: &BoundingBox) -> u64 {
+    let (x, y) = overlap(a, b);
+    x as u64 * y as u64
+}
+
+pub fn iou(a: &BoundingBox, b: &BoundingBox) -> f64 {
+    let inter = intersection(a, b);
+    let union = area(a) + area(b) - inter;
+    if union == 0 { 0.0 } else { inter as f64 / union as f64 }
+}
+
+/// Share of `inner`'s area that lies inside `outer`.
+fn inside_fraction(inner: &BoundingBox, outer: &BoundingBox) -> f64 {
+    match area(inner) {
+        0 => 0.0,
+        inner_area => intersection(inner, outer) as f64 / inner_area as f64,
+    }
+}
+
+// ============================================
+// STEPS
+// ============================================
+
+/// Boxes with their input index, so changes can name them.
+struct Entries(Vec<(usize, BoundingBox)>);
+
+impl Entries {
+    fn new(boxes: &[BoundingBox]) -> Self {
+        Entries(boxes.iter().cloned().enumerate().collect())
+    }
+
+    fn into_boxes(self) -> Vec<BoundingBox> {
+        self.0.into_iter().map(|(_, b)| b).collect()
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.0.retain(|(i, _)| *i != index);
+    }
+}
+
+fn drop_groups(entries: &mut Entries, changes: &mut Vec<BoxChange>) {
+    let groups: Vec<(usize, usize)> = entries
+        .0
+        .iter()
+        .filter_map(|(i, outer)| {
+            let members = entries
+                .0
+                .iter()
+                .filter(|(j, inner)| j != i && area(inner) < area(outer))
+                .filter(|(_, inner)| inside_fraction(inner, outer) >= CONTAINED_FRACTION)
+                .count();
+            (members >= 2).then_some((*i, members))
+        })
+        .collect();
+    for (dropped, members) in groups {
+        info!("Box {} encloses {} other boxes — dropping the group box", dropped, members);
+        entries.remove(dropped);
+        changes.push(BoxChange::Group { dropped, members });
+    }
+}
+
+fn drop_duplicates(entries: &mut Entries, changes: &mut Vec<BoxChange>) {
+    // Most confident first; ties keep the earlier box
+    let mut order = entries.0.clone();
+    order.sort_by(|a, b| b.1.confidence.partial_cmp(&a.1.confidence).unwrap_or(std::cmp::Ordering::Equal));
+    let mut kept: Vec<(usize, BoundingBox)> = Vec::new();
+    for (i, candidate) in order {
+        let duplicate = kept
+            .iter()
+            .map(|(k, b)| (*k, iou(&candidate, b)))
+            .find(|(_, overlap)| *overlap >= DUPLICATE_IOU);
+        match duplicate {
+            Some((k, overlap)) => {
+                info!("Box {} duplicates box {} (IoU {:.2}) — dropping it", i, k, overlap);
+                changes.push(BoxChange::Duplicate { dropped: i, kept: k, iou: overlap });
+            }
+            None => kept.push((i, candidate)),
+        }
+    }
+    kept.sort_by_key(|(i, _)| *i);
+    entries.0 = kept;
+}
+
+fn drop_contained(entries: &mut Entries, changes: &mut Vec<BoxChange>) {
+    let mut by_area: Vec<usize> = (0..entries.0.len()).collect();
+    by_area.sort_by_key(|&n| area(&entries.0[n].1));
+    let mut dropped = Vec::new();
+    for n in by_area {
+        let (i, inner) = &entries.0[n];
+        let container = entries.0.iter().find(|(j, outer)| {
+            j != i && !dropped.contains(j) && area(outer) >= area(inner) && inside_fraction(inner, outer) >= CONTAINED_FRACTION
+        });
+        if let Some((container, _)) = container {
+            info!("Box {} lies inside box {} — dropping it", i, container);
+            changes.push(BoxChange::Contained { dropped: *i, container: *container });
+            dropped.push(*i);
+        }
+    }
+    entries.0.retain(|(i, _)| !dropped.contains(i));
+}
+
+/// Split overlaps until none are left. Boxes only shrink, so resolving a
+/// pair never creates a new overlap; the pass limit is a safety net.
+fn separate(entries: &mut Entries, changes: &mut Vec<BoxChange>) {
+    let limit = entries.0.len() * entries.0.len() + 1;
+    for _ in 0..limit {
+        let Some((m, n)) = first_overlap(&entries.0) else {
+            return;
+        };
+        resolve_pair(entries, m, n, changes);
+    }
+    info!("Overlap resolution stopped after {} passes", limit);
+}
+
+fn first_overlap(entries: &[(usize, BoundingBox)]) -> Option<(usize, usize)> {
+    (0..entries.len())
+        .flat_map(|m| (m + 1..entries.len()).map(move |n| (m, n)))
+        .find(|&(m, n)| {
+            let (a, b) = (&entries[m].1, &entries[n].1);
+            let (x, y) = overlap(a, b);
+            // Skewed photos with measured outlines overlap only as boxes
+            x > 0 && y > 0 && !(snapped_outline(a) && snapped_outline(b))
+        })
+}
+
+fn snapped_outline(b: &BoundingBox) -> bool {
+    b.edges_snapped && b.contour.len() == 4
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+}
+
+/// Start and end of a box along `axis`.
+fn span(b: &BoundingBox, axis: Axis) -> (u32, u32) {
+    match axis {
+        Axis::X => (b.x, right(b)),
+        Axis::Y => (b.y, bottom(b)),
+    }
+}
+
+fn set_span(b: &mut BoundingBox, axis: Axis, (start, end): (u32, u32)) {
+    match axis {
+        Axis::X => {
+            b.x = start;
+            b.width = end - start;
+        }
+        Axis::Y => {
+            b.y = start;
+            b.height = end - start;
+        }
+    }
+    // The outline no longer matches the box
+    if !b.needs_outpaint {
+        b.contour.clear();
+    }
+    b.edges_snapped = false;
+}
+
+/// One span lies within the other along `axis`.
+fn nested(a: &BoundingBox, b: &BoundingBox, axis: Axis) -> bool {
+    let ((a0, a1), (b0, b1)) = (span(a, axis), span(b, axis));
+    (a0 <= b0 && a1 >= b1) || (b0 <= a0 && b1 >= a1)
+}
+
+fn resolve_pair(entries: &mut Entries, m: usize, n: usize, changes: &mut Vec<BoxChange>) {
+    let (a, b) = (entries.0[m].1.clone(), entries.0[n].1.clone());
+    let (ia, ib) = (entries.0[m].0, entries.0[n].0);
+    let (ox, oy) = overlap(&a, &b);
+    let weaker = if b.confidence < a.confidence { n } else { m };
+
+    // Split along the axis where the boxes are staggered, smaller overlap first
+    let mut axes: Vec<(Axis, u32)> = [(Axis::X, ox), (Axis::Y, oy)]
+        .into_iter()
+        .filter(|&(axis, _)| !nested(&a, &b, axis))
+        .collect();
+    axes.sort_by_key(|&(_, o)| o);
+
+    if let Some(&(axis, _)) = axes.first() {
+        let ((a0, a1), (b0, b1)) = (span(&a, axis), span(&b, axis));
+        // `first` starts before `second`; the overlap is [second start, first end)
+        let (first, second, (f0, f1), (s0, s1)) =
+            if a0 < b0 { (m, n, (a0, a1), (b0, b1)) } else { (n, m, (b0, b1), (a0, a1)) };
+        let mid = (s0 + f1) / 2;
+        if mid - f0 < MIN_SIZE.min(f1 - f0) || s1 - mid < MIN_SIZE.min(s1 - s0) {
+            return squeeze(entries, weaker, if weaker == m { ib } else { ia }, changes);
+        }
+        set_span(&mut entries.0[first].1, axis, (f0, mid));
+        set_span(&mut entries.0[second].1, axis, (mid, s1));
+        info!("Boxes {} and {} overlapped — split at {} on {}", ia, ib, mid, if matches!(axis, Axis::X) { "x" } else { "y" });
+        changes.push(BoxChange::Split { a: ia, b: ib });
+        return;
+    }
+
+    // Crossed boxes: the weaker one keeps its larger part outside the other
+    let other = if weaker == m { &b } else { &a };
+    let (trimmed_index, other_index) = if weaker == m { (ia, ib) } else { (ib, ia) };
+    let weak = entries.0[weaker].1.clone();
+    let axis = if span(&weak, Axis::X).0 < span(other, Axis::X).0 || span(&weak, Axis::X).1 > span(other, Axis::X).1 {
+        Axis::X
+    } else {
+        Axis::Y
+    };
+    let ((w0, w1), (o0, o1)) = (span(&weak, axis), span(other, axis));
+    let (before, after) = (o0.saturating_sub(w0), w1.saturating_sub(o1));
+    let part = if before >= after { (w0, o0) } else { (o1, w1) };
+    if part.1.saturating_sub(part.0) < MIN_SIZE.min(w1 - w0) {
+        return squeeze(entries, weaker, other_index, changes);
+    }
+    set_span(&mut entries.0[weaker].1, axis, part);
+    info!("Box {} crossed box {} — trimmed to its larger part", trimmed_index, other_index);
+    changes.push(BoxChange::Trimmed { trimmed: trimmed_index, other: other_index });
+}
+
+fn squeeze(entries: &mut Entries, position: usize, other: usize, changes: &mut Vec<BoxChange>) {
+    let dropped = entries.0[position].0;
+    info!("Box {} cannot be separated from box {} without becoming a sliver — dropping it", dropped, other);
+    entries.0.remove(position);
+    changes.push(BoxChange::Squeezed { dropped, other });
+}
@@ -0,0 +1,403 @@
+// core/src/config.rs
+//! Provider endpoint configuration.
+//! Base URL, model id, max tokens and timeout per provider and per operation
+//! (restore, detect, outpaint, verify), plus per-provider retry and rate
+//! limits (`RequestLimits`, used by `executor`). Built-in defaults are overlaid with a
+//! JSON file and then with environment variables, and the result is exposed
+//! through `AppSettings.providers` (`/api/settings`). Updates are merged
+//! field by field. Base URLs are read-only through the settings API: they
+//! decide which host receives the stored API keys, so only the file and env
+//! may set them. Timeouts set there are clamped to `MAX_SETTINGS_TIMEOUT_SECS`.
+//!
+//! File: `TISSAIA_PROVIDERS_FILE`, or `providers.json` in the working directory.
+//! Env:  `TISSAIA_<PROVIDER>_BASE_URL`, `TISSAIA_<PROVIDER>_TIMEOUT_SECS`,
+//!       `TISSAIA_<PROVIDER>_<OP>_MODEL`, `TISSAIA_<PROVIDER>_<OP>_MAX_TOKENS`,
+//!       `TISSAIA_<PROVIDER>_<OP>_TIMEOUT_SECS` (e.g. `TISSAIA_GOOGLE_VERIFY_MODEL`),
+//!       `TISSAIA_<PROVIDER>_MAX_RETRIES`, `TISSAIA_<PROVIDER>_MAX_CONCURRENT`,
+//!       `TISSAIA_<PROVIDER>_REQUESTS_PER_MINUTE`.
+//!       `OLLAMA_HOST` still sets the Ollama base URL.
+
+use crate::error::TissaiaError;
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Provider name → endpoint configuration.
+pub type ProviderConfigs = BTreeMap<String, ProviderConfig>;
+
+/// Partial provider configs: provider name → JSON object with only the
+/// fields to change (`null` clears an optional field).
+pub type ProviderConfigUpdates = BTreeMap<String, Value>;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+/// Upper bound for timeouts changed through the settings API.
+pub const MAX_SETTINGS_TIMEOUT_SECS: u64 = 1800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderOperation {
+    Restore,
+    Detect,
+    Outpaint,
+    Verify,
+}
+
+impl ProviderOperation {
+    pub const ALL: [ProviderOperation; 4] = [
+        ProviderOperation::Restore,
+        ProviderOperation::Detect,
+        ProviderOperation::Outpaint,
+        ProviderOperation::Verify,
+    ];
+
+    fn env_name(&self) -> &'static str {
+        match self {
+            ProviderOperation::Restore => "RESTORE",
+            ProviderOperation::Detect => "DETECT",
+            ProviderOperation::Outpaint => "OUTPAINT",
+            ProviderOperation::Verify => "VERIFY",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    pub base_url: String,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// `None` = the provider does not offer this operation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restore: Option<OperationConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detect: Option<OperationConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outpaint: Option<OperationConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify: Option<OperationConfig>,
+    #[serde(default)]
+    pub limits: RequestLimits,
+}
+
+/// Retry and rate limits for every HTTP call to one provider.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestLimits {
+    /// Retries after a 429, 5xx or connection failure (0 = fire once).
+    pub max_retries: u32,
+    /// First backoff delay; doubled per retry, with equal jitter (each wait
+    /// is between half and all of the doubled delay).
+    pub backoff_base_ms: u64,
+    /// Longest backoff. A `Retry-After` above this is not waited for:
+    /// the call fails so failover can move on.
+    pub backoff_max_ms: u64,
+    /// Requests in flight at once.
+    pub max_concurrent: usize,
+    /// Token-bucket rate; `None` = unlimited.
+    pub requests_per_minute: Option<u32>,
+    /// Requests that may start back to back before the rate applies.
+    pub burst: u32,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff_base_ms: 500,
+            backoff_max_ms: 30_000,
+            max_concurrent: 4,
+            requests_per_minute: None,
+            burst: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationConfig {
+    /// Model id. For Ollama an empty string means "first installed model".
+    pub model: String,
+    pub max_tokens: u32,
+    /// Overrides the provider `base_url` for this operation only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Overrides the provider `timeout_secs` for this operation only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Fully resolved settings for one provider call.
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    /// Base URL without trailing slash.
+    pub base_url: String,
+    pub model: String,
+    pub max_tokens: u32,
+    pub timeout: Duration,
+}
+
+/// Provider base URL, then the per-operation overrides.
+type BaseUrls<'a> = (&'a str, [Option<&'a str>; 4]);
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+impl ProviderConfig {
+    fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            restore: None,
+            detect: None,
+            outpaint: None,
+            verify: None,
+            limits: RequestLimits::default(),
+        }
+    }
+
+    fn with(mut self, operation: ProviderOperation, model: &str, max_tokens: u32) -> Self {
+        *self.operation_mut(operation) = Some(OperationConfig {
+            model: model.to_string(),
+            max_tokens,
+            base_url: None,
+            timeout_secs: None,
+        });
+        self
+    }
+
+    pub fn operation(&self, operation: ProviderOperation) -> Option<&OperationConfig> {
+        match operation {
+            ProviderOperation::Restore => self.restore.as_ref(),
+            ProviderOperation::Detect => self.detect.as_ref(),
+            ProviderOperation::Outpaint => self.outpaint.as_ref(),
+            ProviderOperation::Verify => self.verify.as_ref(),
+        }
+    }
+
+    fn operation_mut(&mut self, operation: ProviderOperation) -> &mut Option<OperationConfig> {
+        match operation {
+            ProviderOperation::Restore => &mut self.restore,
+            ProviderOperation::Detect => &mut self.detect,
+            ProviderOperation::Outpaint => &mut self.outpaint,
+            ProviderOperation::Verify => &mut self.verify,
+        }
+    }
+
+    /// Base URLs, provider-wide and per operation.
+    fn base_urls(&self) -> BaseUrls<'_> {
+        let per_operation =
+            ProviderOperation::ALL.map(|operation| self.operation(operation).and_then(|op| op.base_url.as_deref()));
+        (&self.base_url, per_operation)
+    }
+
+    /// Clamp timeouts that differ from `current` to 1..=`MAX_SETTINGS_TIMEOUT_SECS`.
+    fn clamp_changed_timeouts(&mut self, current: &ProviderConfig) {
+        let clamp = |secs: u64| secs.clamp(1, MAX_SETTINGS_TIMEOUT_SECS);
+        if self.timeout_secs != current.timeout_secs {
+            self.timeout_secs = clamp(self.timeout_secs);
+        }
+        for operation in ProviderOperation::ALL {
+            let before = current.operation(operation).and_then(|op| op.timeout_secs);
+            if let Some(op) = self.operation_mut(operation) {
+                if op.timeout_secs != before {
+                    op.timeout_secs = op.timeout_secs.map(clamp);
+                }
+            }
+        }
+    }
+
+    pub fn endpoint(&self, operation: ProviderOperation) -> Option<Endpoint> {
+        let op = self.operation(operation)?;
+        let base_url = op.base_url.as_deref().unwrap_or(&self.base_url);
+        Some(Endpoint {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: op.model.clone(),
+            max_tokens: op.max_tokens,
+            timeout: Duration::from_secs(op.timeout_secs.unwrap_or(self.timeout_secs)),
+        })
+    }
+}
+
+/// Built-in endpoints (the values previously hard-coded in `ai.rs`).
+pub fn default_provider_configs() -> ProviderConfigs {
+    use ProviderOperation::*;
+
+    let ollama_host = std::env::var("OLLAMA_HOST").unwrap_or_else(|_| "http://127.0.0.1:11434".to_string());
+
+    [
+        (
+            "google",
+            ProviderConfig::new("https://generativelanguage.googleapis.com/v1beta")
+                .with(Restore, "gemini-3-pro-image-preview", 16384)
+                .with(Detect, "gemini-3-pro-image-preview", 4096)
+                .with(Outpaint, "gemini-3-pro-image-preview", 8192)
+                .with(Verify, "gemini-3-flash-preview", 4096),
+        ),
+        (
+            "anthropic",
+            ProviderConfig::new("https://api.anthropic.com/v1")
+                .with(Restore, "claude-sonnet-4-5-20250929", 4096),
+        ),
+        (
+            "openai",
+            ProviderConfig::new("https://api.openai.com/v1").with(Restore, "gpt-4o", 4096),
+        ),
+        (
+            "mistral",
+            ProviderConfig::new("https://api.mistral.ai/v1")
+                .with(Restore, "pixtral-large-latest", 4096)
+                .with(Detect, "pixtral-large-latest", 4096)
+                .with(Verify, "pixtral-large-latest", 4096),
+        ),
+        (
+            "groq",
+            ProviderConfig::new("https://api.groq.com/openai/v1")
+                .with(Restore, "meta-llama/llama-4-scout-17b-16e-instruct", 4096)
+                .with(Detect, "meta-llama/llama-4-scout-17b-16e-instruct", 4096)
+                .with(Verify, "meta-llama/llama-4-scout-17b-16e-instruct", 4096),
+        ),
+        (
+            "ollama",
+            ProviderConfig::new(&ollama_host).with(Restore, "", 4096),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, config)| (name.to_string(), config))
+    .collect()
+}
+
+/// Defaults, overlaid with the config file (if any) and then env overrides.
+pub fn load_provider_configs() -> ProviderConfigs {
+    let mut configs = default_provider_configs();
+
+    let path = std::env::var("TISSAIA_PROVIDERS_FILE").ok();
+    let path = path.as_deref().unwrap_or("providers.json");
+    if Path::new(path).exists() {
+        match read_config_file(Path::new(path)) {
+            Ok(from_file) => match merge_provider_configs(&mut configs, from_file) {
+                Ok(()) => info!("Loaded provider config from {}", path),
+                Err(e) => warn!("Ignoring provider config {}: {:#}", path, e),
+            },
+            Err(e) => warn!("Ignoring provider config {}: {:#}", path, e),
+        }
+    }
+
+    apply_env_overrides(&mut configs, |key| std::env::var(key).ok());
+    configs
+}
+
+fn read_config_file(path: &Path) -> Result<ProviderConfigUpdates> {
+    let text = std::fs::read_to_string(path).context("read failed")?;
+    serde_json::from_str(&text).map_err(|e| anyhow!("invalid JSON: {}", e))
+}
+
+/// Merge `updates` field by field (JSON merge patch): omitted fields keep
+/// their value, omitted providers are untouched. Nothing changes unless
+/// every merged entry is a valid config.
+pub fn merge_provider_configs(configs: &mut ProviderConfigs, updates: ProviderConfigUpdates) -> Result<()> {
+    let mut merged = configs.clone();
+    for (name, update) in updates {
+        let mut value = match merged.get(&name) {
+            Some(config) => serde_json::to_value(config)?,
+            None => Value::Null,
+        };
+        merge_json(&mut value, update);
+        let config = serde_json::from_value(value).with_context(|| format!("invalid config for provider '{}'", name))?;
+        merged.insert(name, config);
+    }
+    *configs = merged;
+    Ok(())
+}
+
+/// `merge_provider_configs` for updates from the settings API, which must
+/// not add providers or change base URLs; changed timeouts are clamped.
+pub fn merge_provider_settings(configs: &mut ProviderConfigs, updates: ProviderConfigUpdates) -> Result<()> {
+    let mut merged = configs.clone();
+    merge_provider_configs(&mut merged, updates).map_err(|e| TissaiaError::Validation(format!("{:#}", e)))?;
+    for (name, config) in &mut merged {
+        let Some(current) = configs.get(name).filter(|current| current.base_urls() == config.base_urls()) else {
+            return Err(TissaiaError::Validation(format!(
+                "Base URLs of provider '{}' can only be set in the providers file or env",
+                name
+            ))
+            .into());
+        };
+        config.clamp_changed_timeouts(current);
+    }
+    *configs = merged;
+    Ok(())
+}
+
+fn merge_json(target: &mut Value, update: Value) {
+    match (target, update) {
+        (Value::Object(target), Value::Object(update)) => {
+            for (key, value) in update {
+                if value.is_null() {
+                    target.remove(&key);
+                } else if let Some(existing) = target.get_mut(&key) {
+                    merge_json(existing, value);
+                } else {
+                    target.insert(key, value);
+                }
+            }
+        }
+        (target, update) => *target = update,
+    }
+}
+
+fn apply_env_overrides(configs: &mut ProviderConfigs, var: impl Fn(&str) -> Option<String>) {
+    for (name, config) in configs.iter_mut() {
+        let prefix = format!("TISSAIA_{}", name.to_uppercase());
+
+        if let Some(url) = var(&format!("{}_BASE_URL", prefix)) {
+            config.base_url = url;
+        }
+        if let Some(secs) = var(&format!("{}_TIMEOUT_SECS", prefix)).and_then(|v| v.parse().ok()) {
+            config.timeout_secs = secs;
+        }
+        if let Some(retries) = var(&format!("{}_MAX_RETRIES", prefix)).and_then(|v| v.parse().ok()) {
+            config.limits.max_retries = retries;
+        }
+        if let Some(concurrent) = var(&format!("{}_MAX_CONCURRENT", prefix)).and_then(|v| v.parse().ok()) {
+            config.limits.max_concurrent = concurrent;
+        }
+        if let Some(rpm) = var(&format!("{}_REQUESTS_PER_MINUTE", prefix)).and_then(|v| v.parse().ok()) {
+            config.limits.requests_per_minute = Some(rpm).filter(|&rpm| rpm > 0);
+        }
+
+        for operation in ProviderOperation::ALL {
+            let op_prefix = format!("{}_{}", prefix, operation.env_name());
+            let model = var(&format!("{}_MODEL", op_prefix));
+            let max_tokens = var(&format!("{}_MAX_TOKENS", op_prefix)).and_then(|v| v.parse().ok());
+            let timeout = var(&format!("{}_TIMEOUT_SECS", op_prefix)).and_then(|v| v.parse().ok());
+            if model.is_none() && max_tokens.is_none() && timeout.is_none() {
+                continue;
+            }
+
+            // A model override enables an operation the defaults leave out
+            let slot = config.operation_mut(operation);
+            let op = match (slot.as_mut(), model.as_ref()) {
+                (Some(op), _) => op,
+                (None, Some(_)) => slot.insert(OperationConfig {
+                    model: String::new(),
+                    max_tokens: 4096,
+                    base_url: None,
+                    timeout_secs: None,
+                }),
+                (None, None) => continue,
+            };
+            if let Some(model) = model {
+                op.model = model;
+            }
+            if let Some(max_tokens) = max_tokens {
+                op.max_tokens = max_tokens;
+            }
+            if timeout.is_some() {
+                op.timeout_secs = timeout;
+            }
+        }
+    }
+}
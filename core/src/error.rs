@@ -0,0 +1,166 @@
+// core/src/error.rs
+//! Typed errors with stable machine-readable codes.
+//! Core functions return `anyhow::Result`; the typed `TissaiaError` is either
+//! raised directly (bad input, missing provider, disabled feature) or derived
+//! from the error chain by `TissaiaError::classify` (provider HTTP failures,
+//! failover exhaustion). `ApiError` is the serialized form shared by the
+//! Axum handlers and the Tauri commands.
+
+use crate::ai::ProviderFailure;
+use crate::failover::failed_attempts;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TissaiaError {
+    /// Payload is not valid base64 or not a decodable image.
+    Decode(String),
+    /// Image format / MIME type the pipeline cannot handle.
+    UnsupportedFormat(String),
+    /// Well-formed request with unacceptable values.
+    Validation(String),
+    NotFound(String),
+    /// No enabled provider with an API key offers the operation.
+    NoProvider(String),
+    /// Provider answered 429.
+    RateLimited {
+        provider: Option<String>,
+        retry_after_secs: Option<u64>,
+        message: String,
+    },
+    /// Provider failed: 5xx, timeout, rejected request or malformed response.
+    Provider { provider: Option<String>, message: String },
+    /// Feature compiled out or switched off in settings.
+    FeatureDisabled(String),
+    Internal(String),
+}
+
+impl TissaiaError {
+    /// Stable identifier clients can match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TissaiaError::Decode(_) => "decode_error",
+            TissaiaError::UnsupportedFormat(_) => "unsupported_format",
+            TissaiaError::Validation(_) => "validation_error",
+            TissaiaError::NotFound(_) => "not_found",
+            TissaiaError::NoProvider(_) => "no_provider",
+            TissaiaError::RateLimited { .. } => "rate_limited",
+            TissaiaError::Provider { .. } => "provider_error",
+            TissaiaError::FeatureDisabled(_) => "feature_disabled",
+            TissaiaError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn http_status(&self) -> u16 {
+        match self {
+            TissaiaError::Decode(_) => 400,
+            TissaiaError::NotFound(_) => 404,
+            TissaiaError::UnsupportedFormat(_) | TissaiaError::Validation(_) => 422,
+            TissaiaError::RateLimited { .. } => 429,
+            TissaiaError::Provider { .. } => 502,
+            TissaiaError::NoProvider(_) | TissaiaError::FeatureDisabled(_) => 503,
+            TissaiaError::Internal(_) => 500,
+        }
+    }
+
+    pub fn provider(&self) -> Option<&str> {
+        match self {
+            TissaiaError::RateLimited { provider, .. } | TissaiaError::Provider { provider, .. } => {
+                provider.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            TissaiaError::RateLimited { retry_after_secs, .. } => *retry_after_secs,
+            _ => None,
+        }
+    }
+
+    /// Typed view of any error returned by the core.
+    pub fn classify(err: &anyhow::Error) -> TissaiaError {
+        if let Some(typed) = err.chain().find_map(|cause| cause.downcast_ref::<TissaiaError>()) {
+            return typed.clone();
+        }
+
+        let message = err.to_string();
+        // Failover errors name the providers tried; the last one decided the outcome
+        let provider = failed_attempts(err).last().map(|a| a.provider.clone());
+
+        for cause in err.chain() {
+            if let Some(ProviderFailure::Http { status, retry_after_secs, .. }) = cause.downcast_ref::<ProviderFailure>() {
+                if status.as_u16() == 429 {
+                    return TissaiaError::RateLimited { provider, retry_after_secs: *retry_after_secs, message };
+                }
+                return TissaiaError::Provider { provider, message };
+            }
+            if cause.is::<ProviderFailure>() || cause.is::<reqwest::Error>() {
+                return TissaiaError::Provider { provider, message };
+            }
+        }
+
+        match provider {
+            Some(provider) => TissaiaError::Provider { provider: Some(provider), message },
+            None => TissaiaError::Internal(message),
+        }
+    }
+}
+
+impl fmt::Display for TissaiaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TissaiaError::Decode(message)
+            | TissaiaError::UnsupportedFormat(message)
+            | TissaiaError::Validation(message)
+            | TissaiaError::NotFound(message)
+            | TissaiaError::NoProvider(message)
+            | TissaiaError::RateLimited { message, .. }
+            | TissaiaError::Provider { message, .. }
+            | TissaiaError::FeatureDisabled(message)
+            | TissaiaError::Internal(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for TissaiaError {}
+
+/// Error body returned by the HTTP API and Tauri commands.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiError {
+    /// Machine-readable code, see `TissaiaError::code`.
+    pub code: String,
+    /// Human-readable message.
+    pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+    /// HTTP status the code maps to.
+    pub status: u16,
+}
+
+impl From<TissaiaError> for ApiError {
+    fn from(err: TissaiaError) -> Self {
+        Self {
+            code: err.code().to_string(),
+            provider: err.provider().map(str::to_string),
+            retry_after_secs: err.retry_after_secs(),
+            status: err.http_status(),
+            error: err.to_string(),
+        }
+    }
+}
+
+impl From<&anyhow::Error> for ApiError {
+    fn from(err: &anyhow::Error) -> Self {
+        TissaiaError::classify(err).into()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        (&err).into()
+    }
+}
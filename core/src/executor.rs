@@ -0,0 +1,179 @@
+// core/src/executor.rs
+//! Shared HTTP request executor for provider calls.
+//! Every call `AiProvider` makes goes through `RequestExecutor::send`, which
+//! applies the provider's `RequestLimits`: at most `max_concurrent` requests
+//! in flight, a token bucket of `requests_per_minute` (with `burst`), and
+//! retries on 429, 5xx and connection failures with jittered exponential
+//! backoff. A `Retry-After` header (seconds or HTTP date) replaces the
+//! backoff delay, and a 429 with one pauses every request to that provider
+//! until it has passed. A `Retry-After` longer than `backoff_max_ms` is not
+//! waited for; the response is returned so failover can move on.
+//!
+//! The gates (semaphore, bucket, pause) are kept per provider name for the
+//! life of the executor; `AppState` shares one executor between all calls.
+
+use crate::config::RequestLimits;
+use anyhow::Result;
+use log::warn;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+#[derive(Default)]
+pub struct RequestExecutor {
+    gates: Mutex<HashMap<String, Arc<ProviderGate>>>,
+}
+
+struct ProviderGate {
+    limits: RequestLimits,
+    permits: Semaphore,
+    bucket: Mutex<TokenBucket>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+    /// Set by a 429 with `Retry-After`.
+    paused_until: Option<Instant>,
+}
+
+impl RequestExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send the request built by `request` (called again for every retry)
+    /// under `provider`'s limits. The final response is returned whatever
+    /// its status; only transport errors are `Err`.
+    pub async fn send<F>(&self, provider: &str, limits: &RequestLimits, request: F) -> Result<Response>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let gate = self.gate(provider, limits);
+        let mut attempt = 0;
+        loop {
+            let sent = {
+                let _permit = gate.permits.acquire().await?;
+                gate.take_token().await;
+                request().send().await
+            };
+
+            let (delay, reason) = match &sent {
+                Ok(response) if is_retryable_status(response.status()) => {
+                    let retry_after = retry_after(response.headers());
+                    if retry_after.is_some_and(|wait| wait > Duration::from_millis(limits.backoff_max_ms)) {
+                        warn!("{}: {} asks to retry after {:?}; not waiting", provider, response.status(), retry_after);
+                        return Ok(sent?);
+                    }
+                    if let (StatusCode::TOO_MANY_REQUESTS, Some(wait)) = (response.status(), retry_after) {
+                        gate.pause(wait);
+                    }
+                    (retry_after.unwrap_or_else(|| backoff(limits, attempt)), response.status().to_string())
+                }
+                Err(e) if e.is_connect() => (backoff(limits, attempt), e.to_string()),
+                _ => return Ok(sent?),
+            };
+
+            if attempt >= limits.max_retries {
+                return Ok(sent?);
+            }
+            attempt += 1;
+            warn!("{}: {}; retry {}/{} in {:?}", provider, reason, attempt, limits.max_retries, delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// The gate for `provider`, rebuilt when its limits changed.
+    fn gate(&self, provider: &str, limits: &RequestLimits) -> Arc<ProviderGate> {
+        let mut gates = self.gates.lock().unwrap_or_else(|e| e.into_inner());
+        match gates.get(provider) {
+            Some(gate) if gate.limits == *limits => gate.clone(),
+            _ => {
+                let gate = Arc::new(ProviderGate::new(limits));
+                gates.insert(provider.to_string(), gate.clone());
+                gate
+            }
+        }
+    }
+}
+
+impl ProviderGate {
+    fn new(limits: &RequestLimits) -> Self {
+        Self {
+            limits: limits.clone(),
+            permits: Semaphore::new(limits.max_concurrent.max(1)),
+            bucket: Mutex::new(TokenBucket {
+                tokens: limits.burst.max(1) as f64,
+                updated: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Wait until the bucket has a token and no pause is active.
+    async fn take_token(&self) {
+        loop {
+            let wait = self.bucket.lock().unwrap_or_else(|e| e.into_inner()).take(&self.limits, Instant::now());
+            match wait {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => return,
+            }
+        }
+    }
+
+    fn pause(&self, wait: Duration) {
+        let until = Instant::now() + wait;
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        bucket.paused_until = bucket.paused_until.max(Some(until));
+    }
+}
+
+impl TokenBucket {
+    /// Take a token, or return how long to wait for one.
+    fn take(&mut self, limits: &RequestLimits, now: Instant) -> Option<Duration> {
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return Some(until - now);
+            }
+            self.paused_until = None;
+        }
+        let rate = limits.requests_per_minute.filter(|&rpm| rpm > 0)? as f64 / 60.0;
+        let capacity = limits.burst.max(1) as f64;
+        self.tokens = (self.tokens + now.duration_since(self.updated).as_secs_f64() * rate).min(capacity);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// `backoff_base_ms · 2^attempt`, capped at `backoff_max_ms`, with equal
+/// jitter (half fixed, half random) so parallel callers spread out.
+fn backoff(limits: &RequestLimits, attempt: u32) -> Duration {
+    let ceiling = limits.backoff_base_ms.saturating_mul(1 << attempt.min(20)).min(limits.backoff_max_ms);
+    Duration::from_millis(ceiling / 2 + fastrand::u64(0..=ceiling / 2))
+}
+
+/// `Retry-After` as delta seconds or an HTTP date; a past date is zero.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers.get(RETRY_AFTER).and_then(|v| v.to_str().ok()).and_then(parse_retry_after)
+}
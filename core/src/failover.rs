@@ -0,0 +1,146 @@
+// core/src/failover.rs
+//! Provider failover chain.
+//! Walks `AppState::backend_chain` in order, moving to the next provider on
+//! retryable failures (timeouts, 429, 5xx, no image in response) and updating
+//! `ProviderStatus` so `/api/providers` reflects which provider is healthy.
+//! Each call is claimed from the provider's circuit breaker first, so a
+//! half-open provider gets a single trial call.
+
+use crate::ai::ProviderFailure;
+use crate::backends::{BackendContext, Capability, RestorationBackend};
+use crate::error::TissaiaError;
+use crate::models::ProviderAttempt;
+use crate::state::SharedState;
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Value produced by the first provider that succeeded.
+pub struct FailoverOutcome<T> {
+    pub value: T,
+    pub provider: &'static str,
+    /// Every attempt in order, the successful one last.
+    pub attempts: Vec<ProviderAttempt>,
+}
+
+/// Context attached to every error returned by `run_with_failover`, so
+/// callers can still see which providers were tried.
+#[derive(Debug)]
+pub struct FailoverFailure {
+    message: String,
+    pub attempts: Vec<ProviderAttempt>,
+}
+
+impl fmt::Display for FailoverFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Attempts recorded on a failover error (empty for other errors).
+pub fn failed_attempts(err: &anyhow::Error) -> &[ProviderAttempt] {
+    err.downcast_ref::<FailoverFailure>()
+        .map(|f| f.attempts.as_slice())
+        .unwrap_or(&[])
+}
+
+/// Whether an error should move the chain on to the next provider.
+pub fn is_retryable(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(failure) = cause.downcast_ref::<ProviderFailure>() {
+            return failure.is_retryable();
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return e.is_timeout()
+                || e.is_connect()
+                || e.status().is_some_and(|s| s.as_u16() == 429 || s.is_server_error());
+        }
+        false
+    })
+}
+
+/// Run `op` against each backend capable of `capability` until one succeeds
+/// or a non-retryable error stops the chain.
+pub async fn run_with_failover<T, F, Fut>(
+    state: &SharedState,
+    capability: Capability,
+    op: F,
+) -> Result<FailoverOutcome<T>>
+where
+    F: Fn(Arc<dyn RestorationBackend>, BackendContext) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let no_provider = || {
+        TissaiaError::NoProvider(format!(
+            "No AI provider available for {}. Please configure an API key.",
+            capability.label()
+        ))
+    };
+    let chain = state.lock().await.backend_chain(capability);
+    if chain.is_empty() {
+        return Err(no_provider().into());
+    }
+
+    let mut attempts = Vec::new();
+    let mut last_error = None;
+
+    for (backend, ctx) in chain {
+        let provider = backend.name();
+        if !state.lock().await.claim_provider(provider) {
+            info!("Skipping {}: its half-open trial is already running", provider);
+            continue;
+        }
+        let start = Instant::now();
+        let outcome = op(backend, ctx).await;
+        let elapsed = start.elapsed();
+        let duration_ms = elapsed.as_millis() as u64;
+
+        match outcome {
+            Ok(value) => {
+                state.lock().await.record_provider_success(provider, elapsed);
+                attempts.push(ProviderAttempt {
+                    provider: provider.to_string(),
+                    success: true,
+                    error: None,
+                    retryable: false,
+                    duration_ms,
+                });
+                return Ok(FailoverOutcome { value, provider, attempts });
+            }
+            Err(e) => {
+                let retryable = is_retryable(&e);
+                let message = e.to_string();
+                warn!("{} via {} failed (retryable: {}): {}", capability.label(), provider, retryable, message);
+                state.lock().await.record_provider_failure(provider, message.clone(), retryable);
+                attempts.push(ProviderAttempt {
+                    provider: provider.to_string(),
+                    success: false,
+                    error: Some(message.clone()),
+                    retryable,
+                    duration_ms,
+                });
+                if !retryable {
+                    return Err(e.context(FailoverFailure { message, attempts }));
+                }
+                info!("Failing over to next provider for {}", capability.label());
+                last_error = Some(e);
+            }
+        }
+    }
+
+    if attempts.is_empty() {
+        return Err(no_provider().into());
+    }
+    let summary = attempts
+        .iter()
+        .map(|a| format!("{}: {}", a.provider, a.error.as_deref().unwrap_or("unknown error")))
+        .collect::<Vec<_>>()
+        .join("; ");
+    let message = format!("All providers failed for {} ({})", capability.label(), summary);
+    Err(last_error
+        .unwrap_or_else(|| anyhow!("No provider attempted"))
+        .context(FailoverFailure { message, attempts }))
+}
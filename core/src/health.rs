@@ -0,0 +1,249 @@
+// core/src/health.rs
+//! Provider health: circuit breakers and background probes.
+//! Each `ProviderStatus` carries a `ProviderHealth`. Retryable failures of
+//! real calls (via `failover`) and failed probes count towards the breaker;
+//! after `failure_threshold` in a row the circuit opens and `backend_chain`
+//! leaves the provider out for `open_secs`. After that the circuit is
+//! half-open: the first caller to `claim` it makes a single trial call that
+//! closes it on success and reopens it on failure; other calls skip the
+//! provider until the trial reports.
+//!
+//! `run_monitor` probes every configured provider with a cheap call (model
+//! list; Ollama `/api/tags`) and records `last_error` and availability —
+//! Ollama becomes available once a probe reaches it. Auth errors (401/403)
+//! demote the provider but do not count towards the breaker. Successful
+//! calls and probes both feed the latency percentiles.
+//!
+//! Env:  `TISSAIA_HEALTH_INTERVAL_SECS` (default 60, 0 = no probing),
+//!       `TISSAIA_CIRCUIT_FAILURES` (default 3), `TISSAIA_CIRCUIT_OPEN_SECS` (default 60).
+
+use crate::failover::is_retryable;
+use crate::state::SharedState;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+/// Latencies kept for the percentiles.
+const LATENCY_WINDOW: usize = 50;
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+/// A half-open trial that has not reported by then (its caller gave up) is
+/// abandoned, so the next call can claim a new one.
+const TRIAL_TIMEOUT_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the circuit.
+    pub failure_threshold: u32,
+    /// How long an open circuit keeps the provider out of the chain.
+    pub open_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self { failure_threshold: 3, open_secs: 60 }
+    }
+}
+
+impl CircuitBreakerConfig {
+    pub fn from_env() -> Self {
+        let var = |key: &str| std::env::var(key).ok().and_then(|v| v.parse::<u64>().ok());
+        let defaults = Self::default();
+        Self {
+            failure_threshold: var("TISSAIA_CIRCUIT_FAILURES")
+                .map(|n| n.clamp(1, u32::MAX as u64) as u32)
+                .unwrap_or(defaults.failure_threshold),
+            open_secs: var("TISSAIA_CIRCUIT_OPEN_SECS").unwrap_or(defaults.open_secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    #[default]
+    Closed,
+    Open,
+    /// Open period over; the next call is a trial.
+    HalfOpen,
+}
+
+/// Latency percentiles over the last `LATENCY_WINDOW` successful calls and probes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub p50_ms: Option<u64>,
+    pub p90_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+    #[serde(skip)]
+    window: VecDeque<u64>,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, latency: Duration) {
+        if self.window.len() == LATENCY_WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back(latency.as_millis() as u64);
+
+        let mut sorted: Vec<u64> = self.window.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest-rank percentile
+        let rank = |p: f64| sorted[((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1];
+        self.samples = sorted.len();
+        self.p50_ms = Some(rank(0.50));
+        self.p90_ms = Some(rank(0.90));
+        self.p99_ms = Some(rank(0.99));
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProviderHealth {
+    pub circuit: CircuitState,
+    pub consecutive_failures: u32,
+    /// When an open circuit turns half-open.
+    pub open_until: Option<DateTime<Utc>>,
+    pub last_checked: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub latency: LatencyStats,
+    /// When the running half-open trial was claimed.
+    #[serde(skip)]
+    pub trial_started: Option<DateTime<Utc>>,
+}
+
+impl ProviderHealth {
+    /// Whether a call may go to the provider: closed, or open period over
+    /// with no trial running.
+    pub fn allows_requests(&self, now: DateTime<Utc>) -> bool {
+        match self.circuit {
+            CircuitState::Closed => true,
+            CircuitState::Open => self.open_until.is_some_and(|until| now >= until) && !self.trial_running(now),
+            CircuitState::HalfOpen => !self.trial_running(now),
+        }
+    }
+
+    /// Turn an open circuit whose period is over half-open.
+    pub fn refresh(&mut self, now: DateTime<Utc>) {
+        if self.circuit == CircuitState::Open && self.open_until.is_some_and(|until| now >= until) {
+            self.circuit = CircuitState::HalfOpen;
+        }
+    }
+
+    /// Claim a call: always granted while closed; when half-open only the
+    /// first caller gets the trial, until `record_success`/`record_failure`
+    /// or `release_trial`.
+    pub fn claim(&mut self, now: DateTime<Utc>) -> bool {
+        self.refresh(now);
+        if !self.allows_requests(now) {
+            return false;
+        }
+        if self.circuit == CircuitState::HalfOpen {
+            self.trial_started = Some(now);
+        }
+        true
+    }
+
+    /// End a trial whose outcome says nothing about the provider's health
+    /// (e.g. a rejected request).
+    pub fn release_trial(&mut self) {
+        self.trial_started = None;
+    }
+
+    fn trial_running(&self, now: DateTime<Utc>) -> bool {
+        self.trial_started
+            .is_some_and(|started| now < started + chrono::Duration::seconds(TRIAL_TIMEOUT_SECS))
+    }
+
+    pub fn record_success(&mut self, now: DateTime<Utc>) {
+        if self.circuit != CircuitState::Closed {
+            info!("Circuit closed after a successful call");
+        }
+        self.circuit = CircuitState::Closed;
+        self.consecutive_failures = 0;
+        self.open_until = None;
+        self.last_success = Some(now);
+        self.trial_started = None;
+    }
+
+    pub fn record_failure(&mut self, config: &CircuitBreakerConfig, now: DateTime<Utc>) {
+        self.refresh(now);
+        self.consecutive_failures += 1;
+        // A failed trial reopens at once
+        if self.circuit == CircuitState::HalfOpen || self.consecutive_failures >= config.failure_threshold {
+            self.circuit = CircuitState::Open;
+            self.open_until = Some(now + chrono::Duration::seconds(config.open_secs as i64));
+        }
+        self.trial_started = None;
+    }
+}
+
+/// Probe interval from `TISSAIA_HEALTH_INTERVAL_SECS`; `None` disables probing.
+pub fn monitor_interval_from_env() -> Option<Duration> {
+    let secs = std::env::var("TISSAIA_HEALTH_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// A failed probe. `retryable` as for real calls (`failover::is_retryable`):
+/// auth errors are not, so a bad key does not trip the breaker.
+#[derive(Debug, Clone)]
+pub struct ProbeFailure {
+    pub error: String,
+    pub retryable: bool,
+}
+
+/// Probe every enabled provider that has an API key (Ollama needs none)
+/// concurrently and record the results.
+pub async fn probe_providers(state: &SharedState) {
+    let (ai, targets) = {
+        let state = state.lock().await;
+        let targets: Vec<(String, Option<String>)> = state
+            .providers
+            .iter()
+            .filter(|p| p.enabled && state.settings.providers.contains_key(&p.name))
+            .filter(|p| p.name == "ollama" || state.get_api_key(&p.name).is_some())
+            .map(|p| (p.name.clone(), state.get_api_key(&p.name).cloned()))
+            .collect();
+        (Arc::new(state.ai_provider()), targets)
+    };
+
+    let mut probes = JoinSet::new();
+    for (provider, api_key) in targets {
+        let ai = ai.clone();
+        probes.spawn(async move {
+            let start = Instant::now();
+            let result = ai.probe(&provider, api_key.as_deref()).await;
+            let result = result.map(|()| start.elapsed()).map_err(|e| ProbeFailure {
+                error: format!("{:#}", e),
+                retryable: is_retryable(&e),
+            });
+            (provider, result)
+        });
+    }
+    while let Some(joined) = probes.join_next().await {
+        match joined {
+            Ok((provider, result)) => {
+                if let Err(e) = &result {
+                    warn!("Health probe for {} failed: {}", provider, e.error);
+                }
+                state.lock().await.record_probe(&provider, result);
+            }
+            Err(e) => warn!("Health probe task failed: {}", e),
+        }
+    }
+}
+
+/// Probe all providers now and then every `every`, forever. Spawn it on the
+/// runtime that serves requests.
+pub async fn run_monitor(state: SharedState, every: Duration) {
+    info!("Probing provider health every {:?}", every);
+    loop {
+        probe_providers(&state).await;
+        tokio::time::sleep(every).await;
+    }
+}
@@ -0,0 +1,417 @@
+// core/src/history.rs
+//! Durable operation history, kept in SQLite.
+//! Each `HistoryEntry` is a row of `entries` (filter columns plus the entry as
+//! JSON), and full input/output images are stored once in `blobs`, keyed by
+//! their SHA-256 and referenced from entries by `BlobRef`. Listing, filtering
+//! and pagination are SQL queries. Without a root directory the database is
+//! in memory (tests, read-only deployments, the CLI); blob bytes are then
+//! capped, dropping the oldest entries' images first.
+//!
+//! `HistoryStore` is a cheap handle: clones share one connection, so
+//! operations can decode and hash images (`PreparedBlob`) and write them on
+//! the blocking pool without holding the state lock.
+//!
+//! Root: `TISSAIA_HISTORY_DIR`, default `data/history` in the working directory;
+//! the database is `<root>/history.db`.
+
+use crate::models::{BlobRef, HistoryEntry, OperationType};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::{info, warn};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Oldest entries beyond this are pruned (with their unreferenced blobs).
+pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+/// Blob bytes an in-memory store keeps.
+pub const DEFAULT_MEMORY_BLOB_BYTES: usize = 256 * 1024 * 1024;
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS entries (
+    id        TEXT PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    operation TEXT NOT NULL,
+    provider  TEXT NOT NULL,
+    success   INTEGER NOT NULL,
+    entry     TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS entries_by_time ON entries (timestamp);
+CREATE TABLE IF NOT EXISTS blobs (
+    hash      TEXT PRIMARY KEY,
+    mime_type TEXT NOT NULL,
+    size      INTEGER NOT NULL,
+    data      BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS entry_blobs (
+    entry_id  TEXT NOT NULL REFERENCES entries (id) ON DELETE CASCADE,
+    hash      TEXT NOT NULL,
+    PRIMARY KEY (entry_id, hash)
+);
+CREATE INDEX IF NOT EXISTS entry_blobs_by_hash ON entry_blobs (hash);
+";
+
+/// Filter + pagination for listing history.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryQuery {
+    #[serde(default)]
+    pub offset: Option<usize>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub operation: Option<OperationType>,
+    #[serde(default)]
+    pub success: Option<bool>,
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// Matching entries before pagination.
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Clone)]
+pub struct HistoryStore {
+    db: Arc<Mutex<Connection>>,
+    root: Option<PathBuf>,
+    /// Blob bytes kept by an in-memory store.
+    memory_limit: usize,
+    max_entries: usize,
+}
+
+/// An image ready to be stored: its bytes and content address, computed
+/// without touching the store.
+pub struct PreparedBlob {
+    pub blob: BlobRef,
+    bytes: Vec<u8>,
+}
+
+impl PreparedBlob {
+    pub fn new(bytes: Vec<u8>, mime_type: &str) -> Self {
+        let hash = format!("{:x}", Sha256::digest(&bytes));
+        Self { blob: BlobRef { hash, mime_type: mime_type.to_string(), size: bytes.len() }, bytes }
+    }
+
+    /// A base64 image, decoded when valid.
+    pub fn from_base64(data_base64: &str, mime_type: &str) -> Self {
+        let bytes = STANDARD.decode(data_base64).unwrap_or_else(|_| data_base64.as_bytes().to_vec());
+        Self::new(bytes, mime_type)
+    }
+}
+
+impl HistoryStore {
+    pub fn in_memory() -> Self {
+        let db = Connection::open_in_memory()
+            .map_err(anyhow::Error::from)
+            .and_then(|db| init(&db).map(|()| db))
+            .expect("in-memory SQLite database");
+        Self::with_connection(db, None)
+    }
+
+    /// Open (or create) the store rooted at `root`.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root).context("create history dir")?;
+        let path = root.join("history.db");
+        let db = Connection::open(&path).with_context(|| format!("open {:?}", path))?;
+        db.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        db.pragma_update(None, "synchronous", "NORMAL")?;
+        db.busy_timeout(Duration::from_secs(5))?;
+        init(&db).with_context(|| format!("initialize {:?}", path))?;
+
+        let store = Self::with_connection(db, Some(root));
+        info!("History store at {:?} ({} entries)", path, store.len());
+        Ok(store)
+    }
+
+    fn with_connection(db: Connection, root: Option<PathBuf>) -> Self {
+        Self {
+            db: Arc::new(Mutex::new(db)),
+            root,
+            memory_limit: DEFAULT_MEMORY_BLOB_BYTES,
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    /// Store at `TISSAIA_HISTORY_DIR` (default `data/history`); falls back to
+    /// memory when the directory cannot be used.
+    pub fn from_env() -> Self {
+        let root = std::env::var("TISSAIA_HISTORY_DIR").unwrap_or_else(|_| "data/history".to_string());
+        Self::open(&root).unwrap_or_else(|e| {
+            warn!("History dir {} unavailable ({:#}); keeping history in memory", root, e);
+            Self::in_memory()
+        })
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Cap on blob bytes kept by an in-memory store.
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = bytes;
+        self
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.root.is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.db()
+            .query_row("SELECT COUNT(*) FROM entries", [], |row| row.get::<_, i64>(0))
+            .map(|count| count as usize)
+            .unwrap_or_else(|e| {
+                warn!("Failed to count history entries: {}", e);
+                0
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn db(&self) -> MutexGuard<'_, Connection> {
+        self.db.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // ============================================
+    // BLOBS
+    // ============================================
+
+    /// Store a base64 image (decoded when valid) and return its content address.
+    pub fn put_blob(&self, data_base64: &str, mime_type: &str) -> Result<BlobRef> {
+        let prepared = PreparedBlob::from_base64(data_base64, mime_type);
+        let blob = prepared.blob.clone();
+        self.keep_blob(&self.db(), &prepared)?;
+        Ok(blob)
+    }
+
+    pub fn put_blob_bytes(&self, bytes: Vec<u8>, mime_type: &str) -> Result<BlobRef> {
+        let prepared = PreparedBlob::new(bytes, mime_type);
+        let blob = prepared.blob.clone();
+        self.keep_blob(&self.db(), &prepared)?;
+        Ok(blob)
+    }
+
+    /// Store a prepared blob unless it is already stored (or, in memory,
+    /// larger than the whole cap).
+    fn keep_blob(&self, db: &Connection, prepared: &PreparedBlob) -> Result<()> {
+        if !self.is_persistent() && prepared.bytes.len() > self.memory_limit {
+            return Ok(());
+        }
+        db.execute(
+            "INSERT OR IGNORE INTO blobs (hash, mime_type, size, data) VALUES (?1, ?2, ?3, ?4)",
+            params![prepared.blob.hash, prepared.blob.mime_type, prepared.blob.size as i64, prepared.bytes],
+        )?;
+        Ok(())
+    }
+
+    /// Drop the oldest entries' blobs until an in-memory store fits its limit.
+    fn evict_memory_blobs(&self, db: &Connection) -> Result<()> {
+        if self.is_persistent() {
+            return Ok(());
+        }
+        loop {
+            let bytes: i64 = db.query_row("SELECT COALESCE(SUM(size), 0) FROM blobs", [], |row| row.get(0))?;
+            if bytes as usize <= self.memory_limit {
+                return Ok(());
+            }
+            let oldest: Option<String> = db
+                .query_row(
+                    "SELECT b.hash FROM blobs b
+                     JOIN entry_blobs eb ON eb.hash = b.hash
+                     JOIN entries e ON e.id = eb.entry_id
+                     ORDER BY e.timestamp, e.rowid LIMIT 1",
+                    [],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let Some(hash) = oldest else { return Ok(()) };
+            db.execute("DELETE FROM blobs WHERE hash = ?1", [hash])?;
+        }
+    }
+
+    /// Blob bytes, or `None` for unknown (or malformed) hashes.
+    pub fn get_blob(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        if !is_valid_hash(hash) {
+            return Ok(None);
+        }
+        Ok(self.db().query_row("SELECT data FROM blobs WHERE hash = ?1", [hash], |row| row.get(0)).optional()?)
+    }
+
+    /// Blob as base64, for transports that only carry strings (Tauri IPC).
+    pub fn get_blob_base64(&self, hash: &str) -> Result<Option<String>> {
+        Ok(self.get_blob(hash)?.map(|bytes| STANDARD.encode(bytes)))
+    }
+
+    /// Reference (with MIME type) of a stored blob.
+    pub fn blob_ref(&self, hash: &str) -> Result<Option<BlobRef>> {
+        Ok(self
+            .db()
+            .query_row("SELECT mime_type, size FROM blobs WHERE hash = ?1", [hash], |row| {
+                Ok(BlobRef { hash: hash.to_string(), mime_type: row.get(0)?, size: row.get::<_, i64>(1)? as usize })
+            })
+            .optional()?)
+    }
+
+    // ============================================
+    // ENTRIES
+    // ============================================
+
+    pub fn add(&self, entry: HistoryEntry) -> Result<()> {
+        self.add_prepared(entry, Vec::new())
+    }
+
+    /// Add an entry together with its blobs in one transaction, then prune
+    /// past `max_entries`.
+    pub fn add_prepared(&self, entry: HistoryEntry, blobs: Vec<PreparedBlob>) -> Result<()> {
+        let json = serde_json::to_string(&entry)?;
+        let mut db = self.db();
+        let tx = db.transaction()?;
+        for blob in &blobs {
+            self.keep_blob(&tx, blob)?;
+        }
+        tx.execute("DELETE FROM entries WHERE id = ?1", [&entry.id])?;
+        tx.execute(
+            "INSERT INTO entries (id, timestamp, operation, provider, success, entry)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                entry.id,
+                entry.timestamp.timestamp_micros(),
+                operation_key(&entry.operation),
+                entry.provider,
+                entry.success,
+                json
+            ],
+        )?;
+        for blob in entry.blobs() {
+            tx.execute(
+                "INSERT OR IGNORE INTO entry_blobs (entry_id, hash) VALUES (?1, ?2)",
+                params![entry.id, blob.hash],
+            )?;
+        }
+
+        let excess: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "SELECT id FROM entries ORDER BY timestamp DESC, rowid DESC LIMIT -1 OFFSET ?1",
+            )?;
+            let ids = stmt.query_map([self.max_entries as i64], |row| row.get(0))?;
+            ids.collect::<rusqlite::Result<_>>()?
+        };
+        for id in excess {
+            delete_entry(&tx, &id)?;
+        }
+        self.evict_memory_blobs(&tx)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<HistoryEntry>> {
+        let json: Option<String> =
+            self.db().query_row("SELECT entry FROM entries WHERE id = ?1", [id], |row| row.get(0)).optional()?;
+        json.map(|json| serde_json::from_str(&json).context("decode history entry")).transpose()
+    }
+
+    /// Remove one entry and any blobs no other entry references.
+    pub fn delete(&self, id: &str) -> Result<bool> {
+        let mut db = self.db();
+        let tx = db.transaction()?;
+        let removed = delete_entry(&tx, id)?;
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        self.db().execute_batch("DELETE FROM entries; DELETE FROM blobs;")?;
+        Ok(())
+    }
+
+    /// Newest-first page of entries matching `query`.
+    pub fn list(&self, query: &HistoryQuery) -> Result<HistoryPage> {
+        let mut filters = Vec::new();
+        let mut args = Vec::new();
+        if let Some(operation) = &query.operation {
+            filters.push("operation = ?");
+            args.push(Value::Text(operation_key(operation)));
+        }
+        if let Some(success) = query.success {
+            filters.push("success = ?");
+            args.push(Value::Integer(success as i64));
+        }
+        if let Some(provider) = &query.provider {
+            filters.push("provider = ?");
+            args.push(Value::Text(provider.clone()));
+        }
+        let filter = if filters.is_empty() { String::new() } else { format!(" WHERE {}", filters.join(" AND ")) };
+
+        let offset = query.offset.unwrap_or(0);
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        let db = self.db();
+        let total: i64 =
+            db.query_row(&format!("SELECT COUNT(*) FROM entries{}", filter), params_from_iter(&args), |row| {
+                row.get(0)
+            })?;
+
+        args.push(Value::Integer(limit.min(i64::MAX as usize) as i64));
+        args.push(Value::Integer(offset.min(i64::MAX as usize) as i64));
+        let mut stmt = db.prepare(&format!(
+            "SELECT id, entry FROM entries{} ORDER BY timestamp DESC, rowid DESC LIMIT ? OFFSET ?",
+            filter
+        ))?;
+        let rows = stmt.query_map(params_from_iter(&args), |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, json) = row?;
+            match serde_json::from_str(&json) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("Skipping unreadable history entry {}: {}", id, e),
+            }
+        }
+        Ok(HistoryPage { entries, total: total as usize, offset, limit })
+    }
+}
+
+/// Delete an entry and the blobs only it referenced.
+fn delete_entry(db: &Connection, id: &str) -> Result<bool> {
+    let hashes: Vec<String> = {
+        let mut stmt = db.prepare("SELECT hash FROM entry_blobs WHERE entry_id = ?1")?;
+        let hashes = stmt.query_map([id], |row| row.get(0))?;
+        hashes.collect::<rusqlite::Result<_>>()?
+    };
+    let removed = db.execute("DELETE FROM entries WHERE id = ?1", [id])? > 0;
+    for hash in hashes {
+        db.execute(
+            "DELETE FROM blobs WHERE hash = ?1 AND NOT EXISTS (SELECT 1 FROM entry_blobs WHERE hash = ?1)",
+            [hash],
+        )?;
+    }
+    Ok(removed)
+}
+
+fn init(db: &Connection) -> Result<()> {
+    db.pragma_update(None, "foreign_keys", true)?;
+    db.execute_batch(SCHEMA)?;
+    Ok(())
+}
+
+/// Value of the `operation` column: the serialized `OperationType`.
+fn operation_key(operation: &OperationType) -> String {
+    serde_json::to_value(operation).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
@@ -0,0 +1,1170 @@
+// core/src/imaging.rs
+//! Local image pipeline — EXIF rotation, photo cropping, rotation, upscaling,
+//! CLAHE / sharpen / denoise filters and metadata extraction.
+//! Everything that touches pixels is gated behind the `image-processing` feature.
+
+#[cfg(feature = "image-processing")]
+use crate::boxes;
+use crate::error::TissaiaError;
+use crate::models::{BoundingBox, CropResult};
+#[cfg(feature = "image-processing")]
+use crate::models::CroppedPhoto;
+use anyhow::{anyhow, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use log::info;
+#[cfg(feature = "image-processing")]
+use log::error;
+use std::borrow::Cow;
+
+// ============================================
+// ENCODE / DECODE HELPERS
+// ============================================
+
+/// Pick the output encoder matching the request MIME type (JPEG by default).
+#[cfg(feature = "image-processing")]
+pub fn output_format(mime_type: &str) -> image::ImageFormat {
+    match mime_type {
+        "image/png" => image::ImageFormat::Png,
+        "image/webp" => image::ImageFormat::WebP,
+        _ => image::ImageFormat::Jpeg,
+    }
+}
+
+/// MIME type for an image file name, by extension.
+pub fn mime_from_filename(name: &str) -> Option<&'static str> {
+    let ext = name.rsplit_once('.')?.1.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        _ => return None,
+    })
+}
+
+/// File extension for an image MIME type (`jpg` by default).
+pub fn extension_for_mime(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "image/bmp" => "bmp",
+        "image/tiff" => "tiff",
+        _ => "jpg",
+    }
+}
+
+/// Image handed to a local operation: base64 text (JSON bodies, Tauri IPC)
+/// or the raw bytes of an upload, which then never get base64-encoded.
+#[derive(Debug, Clone, Copy)]
+pub enum ImageData<'a> {
+    Base64(&'a str),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> ImageData<'a> {
+    /// Raw bytes, decoding base64 when needed.
+    pub fn bytes(&self) -> Result<Cow<'a, [u8]>> {
+        match *self {
+            ImageData::Base64(data) => decode_base64(data).map(Cow::Owned),
+            ImageData::Bytes(bytes) => Ok(Cow::Borrowed(bytes)),
+        }
+    }
+
+    #[cfg(feature = "image-processing")]
+    fn decode(&self) -> Result<image::DynamicImage> {
+        load_image(&self.bytes()?)
+    }
+}
+
+impl<'a> From<&'a str> for ImageData<'a> {
+    fn from(data: &'a str) -> Self {
+        ImageData::Base64(data)
+    }
+}
+
+impl<'a> From<&'a String> for ImageData<'a> {
+    fn from(data: &'a String) -> Self {
+        ImageData::Base64(data)
+    }
+}
+
+impl<'a> From<&'a [u8]> for ImageData<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        ImageData::Bytes(bytes)
+    }
+}
+
+/// Decode a base64 payload into raw bytes.
+pub fn decode_base64(image_base64: &str) -> Result<Vec<u8>> {
+    STANDARD.decode(image_base64)
+        .map_err(|e| TissaiaError::Decode(format!("Base64 decode error: {}", e)).into())
+}
+
+/// Decode image bytes, telling unsupported formats apart from corrupt data.
+#[cfg(feature = "image-processing")]
+fn load_image(image_bytes: &[u8]) -> Result<image::DynamicImage> {
+    image::load_from_memory(image_bytes).map_err(|e| match e {
+        image::ImageError::Unsupported(_) => TissaiaError::UnsupportedFormat(format!("Unsupported image format: {}", e)).into(),
+        _ => TissaiaError::Decode(format!("Image decode error: {}", e)).into(),
+    })
+}
+
+/// Decode a base64 payload into an image.
+#[cfg(feature = "image-processing")]
+pub fn decode_base64_image(image_base64: &str) -> Result<image::DynamicImage> {
+    load_image(&decode_base64(image_base64)?)
+}
+
+/// Encode an image in the format matching `mime_type` and return it as base64.
+#[cfg(feature = "image-processing")]
+pub fn encode_base64_image(img: &image::DynamicImage, mime_type: &str) -> Result<String> {
+    let mut buf = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut buf, output_format(mime_type))
+        .map_err(|e| anyhow!("Image encode error: {}", e))?;
+
+    Ok(STANDARD.encode(buf.into_inner()))
+}
+
+/// Re-encode an image as `mime_type` (PNG, WebP, otherwise JPEG).
+#[cfg(feature = "image-processing")]
+pub fn convert_image(image_base64: &str, mime_type: &str) -> Result<String> {
+    let img = decode_base64_image(image_base64)?;
+    // The JPEG encoder rejects alpha channels
+    let img = match output_format(mime_type) {
+        image::ImageFormat::Jpeg if img.color().has_alpha() => image::DynamicImage::ImageRgb8(img.to_rgb8()),
+        _ => img,
+    };
+    encode_base64_image(&img, mime_type)
+}
+
+#[cfg(not(feature = "image-processing"))]
+pub fn convert_image(_image_base64: &str, _mime_type: &str) -> Result<String> {
+    Err(feature_disabled())
+}
+
+#[cfg(not(feature = "image-processing"))]
+pub(crate) fn feature_disabled() -> anyhow::Error {
+    TissaiaError::FeatureDisabled(
+        "Image processing feature is not enabled. Rebuild with --features image-processing".to_string(),
+    )
+    .into()
+}
+
+// ============================================
+// SCANNER HELPERS
+// ============================================
+
+/// Auto-trim dark edges (scanner bed background) from a cropped photo.
+/// Scans inward from each edge and removes rows/columns where the average
+/// brightness is below a threshold. Preserves at least 90% of the image.
+#[cfg(feature = "image-processing")]
+pub fn auto_trim_dark_edges(img: &image::DynamicImage) -> image::DynamicImage {
+    use image::GenericImageView;
+
+    let (w, h) = img.dimensions();
+    if w < 20 || h < 20 {
+        return img.clone();
+    }
+
+    let brightness_threshold: u8 = 60; // pixels darker than this are "scanner bed" (increased from 40 to catch dark grey)
+    let min_dark_fraction = 0.55; // 55% of pixels in a row/col must be dark to trim (lowered for mixed edges)
+    let max_trim_fraction = 0.08; // trim at most 8% from each side (increased from 5%)
+
+    let max_trim_x = (w as f64 * max_trim_fraction) as u32;
+    let max_trim_y = (h as f64 * max_trim_fraction) as u32;
+
+    let is_dark_pixel = |x: u32, y: u32| -> bool {
+        let p = img.get_pixel(x, y);
+        let avg = (p[0] as u16 + p[1] as u16 + p[2] as u16) / 3;
+        avg < brightness_threshold as u16
+    };
+
+    // Scan from left
+    let mut left = 0u32;
+    for x in 0..max_trim_x {
+        let dark_count = (0..h).filter(|&y| is_dark_pixel(x, y)).count();
+        if dark_count as f64 / h as f64 >= min_dark_fraction {
+            left = x + 1;
+        } else {
+            break;
+        }
+    }
+
+    // Scan from right
+    let mut right = w;
+    for x in (w.saturating_sub(max_trim_x)..w).rev() {
+        let dark_count = (0..h).filter(|&y| is_dark_pixel(x, y)).count();
+        if dark_count as f64 / h as f64 >= min_dark_fraction {
+            right = x;
+        } else {
+            break;
+        }
+    }
+
+    // Scan from top
+    let mut top = 0u32;
+    for y in 0..max_trim_y {
+        let dark_count = (0..w).filter(|&x| is_dark_pixel(x, y)).count();
+        if dark_count as f64 / w as f64 >= min_dark_fraction {
+            top = y + 1;
+        } else {
+            break;
+        }
+    }
+
+    // Scan from bottom
+    let mut bottom = h;
+    for y in (h.saturating_sub(max_trim_y)..h).rev() {
+        let dark_count = (0..w).filter(|&x| is_dark_pixel(x, y)).count();
+        if dark_count as f64 / w as f64 >= min_dark_fraction {
+            bottom = y;
+        } else {
+            break;
+        }
+    }
+
+    let new_w = right.saturating_sub(left).max(1);
+    let new_h = bottom.saturating_sub(top).max(1);
+
+    if new_w < w || new_h < h {
+        info!("Auto-trim: {}x{} → {}x{} (trimmed L:{} R:{} T:{} B:{})",
+            w, h, new_w, new_h, left, w - right, top, h - bottom);
+        img.crop_imm(left, top, new_w, new_h)
+    } else {
+        img.clone()
+    }
+}
+
+/// Read EXIF orientation and apply rotation correction to base64 image.
+/// Returns corrected base64 image (or original if no EXIF rotation needed).
+#[cfg(feature = "image-processing")]
+pub fn apply_exif_rotation(image_base64: &str, mime_type: &str) -> Result<String> {
+    let image_bytes = decode_base64(image_base64)?;
+
+    // Try to read EXIF orientation
+    let orientation = {
+        let mut cursor = std::io::Cursor::new(&image_bytes);
+        match exif::Reader::new().read_from_container(&mut cursor) {
+            Ok(exif_data) => {
+                exif_data.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                    .and_then(|f| f.value.get_uint(0))
+                    .unwrap_or(1) // Default: normal orientation
+            }
+            Err(_) => 1, // No EXIF data, assume normal
+        }
+    };
+
+    // EXIF Orientation values:
+    // 1 = Normal, 2 = Flipped horizontal, 3 = Rotated 180°
+    // 4 = Flipped vertical, 5 = Transposed, 6 = Rotated 90° CW
+    // 7 = Transverse, 8 = Rotated 270° CW (90° CCW)
+    if orientation == 1 {
+        return Ok(image_base64.to_string()); // No rotation needed
+    }
+
+    info!("EXIF orientation detected: {} — applying correction", orientation);
+
+    let img = load_image(&image_bytes)?;
+
+    let corrected = match orientation {
+        3 => img.rotate180(),
+        6 => img.rotate90(),
+        8 => img.rotate270(),
+        // For flip cases (2,4,5,7) we just do the closest rotation
+        2 => img.fliph(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        7 => img.rotate270().fliph(),
+        _ => img,
+    };
+
+    encode_base64_image(&corrected, mime_type)
+}
+
+// ============================================
+// PHOTO CROPPING
+// ============================================
+
+/// Cut each detected photo out of a scan, correct its orientation and trim
+/// leftover scanner bed. Boxes are in normalized 0-1000 coordinates; a
+/// 4-point contour is warped to a rectangle, otherwise the box is cropped
+/// and deskewed.
+#[cfg(feature = "image-processing")]
+pub fn crop_photos(
+    image_base64: &str,
+    mime_type: &str,
+    bounding_boxes: &[BoundingBox],
+    original_filename: String,
+) -> Result<CropResult> {
+    use image::GenericImageView;
+
+    info!("=== CROP_PHOTOS START ===");
+    info!("Boxes: {}, filename: {}", bounding_boxes.len(), original_filename);
+
+    let start = std::time::Instant::now();
+
+    let img = decode_base64_image(image_base64)?;
+
+    let (img_width, img_height) = img.dimensions();
+    info!("Image dimensions: {}x{}", img_width, img_height);
+
+    let padding_factor = 0.005; // 0.5% minimal padding (AI bbox should be tight already)
+    let mut photos = Vec::new();
+    // Deskewing samples the whole scan; converted once, on first use
+    let mut scan_rgba: Option<image::RgbaImage> = None;
+
+    // Log all bounding boxes for debugging rotation issues
+    for (idx, bbox) in bounding_boxes.iter().enumerate() {
+        info!("Box {}: x={} y={} w={} h={} rotation_angle={} label={:?}",
+            idx, bbox.x, bbox.y, bbox.width, bbox.height, bbox.rotation_angle, bbox.label);
+    }
+
+    // Overlapping boxes would crop the same pixels twice
+    let mut fixed_boxes: Vec<BoundingBox> = bounding_boxes.to_vec();
+    boxes::separate_overlaps(&mut fixed_boxes);
+
+    for (idx, bbox) in fixed_boxes.iter().enumerate() {
+        // Convert normalized coords (0-1000) to pixel coords
+        let mut px = (bbox.x as f64 / 1000.0 * img_width as f64) as i64;
+        let mut py = (bbox.y as f64 / 1000.0 * img_height as f64) as i64;
+        let mut pw = (bbox.width as f64 / 1000.0 * img_width as f64) as i64;
+        let mut ph = (bbox.height as f64 / 1000.0 * img_height as f64) as i64;
+
+        // Add padding (snapped boxes already sit on the photo edge)
+        let padding = if bbox.edges_snapped { 0.0 } else { padding_factor };
+        let pad_x = (pw as f64 * padding) as i64;
+        let pad_y = (ph as f64 * padding) as i64;
+        px = (px - pad_x).max(0);
+        py = (py - pad_y).max(0);
+        pw = (pw + 2 * pad_x).min(img_width as i64 - px);
+        ph = (ph + 2 * pad_y).min(img_height as i64 - py);
+
+        if pw <= 0 || ph <= 0 {
+            error!("Invalid crop dimensions for box {}: {}x{}", idx, pw, ph);
+            continue;
+        }
+
+        // rotation_angle = quarter turns of the content plus the skew on the bed
+        let (orientation, skew) = split_rotation(bbox.rotation_angle);
+        let warped = perspective_quad(bbox, img_width, img_height).and_then(|quad| {
+            let scan = scan_rgba.get_or_insert_with(|| img.to_rgba8());
+            Some((warp_quad(scan, &quad)?, quad_angle(&quad)))
+        });
+        let (cropped, straightened) = if let Some((warped, angle)) = warped {
+            info!("Photo {} perspective-corrected from its contour ({:.1}°) → {}x{}",
+                idx, angle, warped.width(), warped.height());
+            (like_scan(&img, warped), angle)
+        } else if skew.abs() >= MIN_DESKEW_DEGREES {
+            let scan = scan_rgba.get_or_insert_with(|| img.to_rgba8());
+            let deskewed = deskew_crop(scan, bbox, [px as f64, py as f64, pw as f64, ph as f64], skew as f64);
+            info!("Photo {} deskewed by {:.1}° → {}x{}", idx, skew, deskewed.width(), deskewed.height());
+            (like_scan(&img, deskewed), skew)
+        } else {
+            (img.crop_imm(px as u32, py as u32, pw as u32, ph as u32), 0.0)
+        };
+
+        // Apply rotation CORRECTION based on detected angle.
+        // rotation_angle = current CW rotation from upright, so correction = (360 - angle).
+        // 90° detected (heads right) → correct with rotate270 (=90° CCW)
+        // 180° detected (upside down) → correct with rotate180
+        // 270° detected (heads left) → correct with rotate90 (=90° CW)
+        let rotated = match orientation {
+            90 => {
+                info!("Photo {} detected at 90° CW → correcting with 270° CW (90° CCW)", idx);
+                cropped.rotate270()
+            }
+            180 => {
+                info!("Photo {} detected at 180° → correcting with 180°", idx);
+                cropped.rotate180()
+            }
+            270 => {
+                info!("Photo {} detected at 270° CW → correcting with 90° CW", idx);
+                cropped.rotate90()
+            }
+            _ => cropped,
+        };
+
+        // Auto-trim dark scanner bed edges that the AI bbox may have included
+        let trimmed = if bbox.edges_snapped { rotated } else { auto_trim_dark_edges(&rotated) };
+        let (cw, ch) = trimmed.dimensions();
+
+        let cropped_base64 = encode_base64_image(&trimmed, mime_type)?;
+
+        photos.push(CroppedPhoto {
+            id: uuid::Uuid::new_v4().to_string(),
+            index: idx,
+            image_base64: cropped_base64,
+            mime_type: mime_type.to_string(),
+            width: cw,
+            height: ch,
+            source_box: bbox.clone(),
+            applied_rotation: orientation as f32 + straightened,
+        });
+
+        info!("Cropped photo {}: {}x{}", idx, cw, ch);
+    }
+
+    let result = CropResult {
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now(),
+        original_filename,
+        photos,
+        processing_time_ms: start.elapsed().as_millis() as u64,
+    };
+
+    info!("=== CROP_PHOTOS END === ({} photos, {}ms)", result.photos.len(), result.processing_time_ms);
+    Ok(result)
+}
+
+/// Smallest skew worth resampling a photo for, in degrees.
+#[cfg(feature = "image-processing")]
+const MIN_DESKEW_DEGREES: f32 = 0.1;
+
+/// Split a clockwise `rotation_angle` into quarter turns (0/90/180/270)
+/// and the remaining skew (-45..=45).
+#[cfg(feature = "image-processing")]
+fn split_rotation(angle: f32) -> (u32, f32) {
+    let turns = (angle / 90.0).round();
+    let skew = angle - turns * 90.0;
+    ((turns * 90.0).rem_euclid(360.0) as u32, skew)
+}
+
+/// Straighten a photo lying `skew` degrees clockwise on the bed, sampling
+/// the scan directly (bicubic) so the crop is resampled only once.
+/// The photo is cut to the largest rectangle inscribed in the crop box.
+/// Photos that need outpainting keep the whole rotated box instead, so
+/// outpainting gets the corners rather than losing them.
+#[cfg(feature = "image-processing")]
+fn deskew_crop(scan: &image::RgbaImage, bbox: &BoundingBox, crop: [f64; 4], skew: f64) -> image::RgbaImage {
+    let (sin, cos) = skew.to_radians().sin_cos();
+    let [px, py, pw, ph] = crop;
+    let centre = (px + pw / 2.0, py + ph / 2.0);
+    let size = if bbox.needs_outpaint {
+        let (s, c) = (sin.abs(), cos.abs());
+        (pw * c + ph * s, pw * s + ph * c)
+    } else {
+        largest_inscribed_rect(pw, ph, skew)
+    };
+
+    let (out_w, out_h) = (size.0.round().max(1.0) as u32, size.1.round().max(1.0) as u32);
+    image::RgbaImage::from_fn(out_w, out_h, |u, v| {
+        // Output axes are the photo's axes, turned `skew` clockwise in the scan
+        let (du, dv) = (u as f64 + 0.5 - out_w as f64 / 2.0, v as f64 + 0.5 - out_h as f64 / 2.0);
+        let x = centre.0 + du * cos - dv * sin;
+        let y = centre.1 + du * sin + dv * cos;
+        sample_bicubic(scan, x - 0.5, y - 0.5)
+    })
+}
+
+/// Photo corners in scan pixels (top-left, top-right, bottom-right,
+/// bottom-left) from a 4-point contour, when they form a usable convex
+/// quadrilateral. Irregular outlines (`needs_outpaint`) are left to the
+/// rectangle crop and outpainting.
+#[cfg(feature = "image-processing")]
+fn perspective_quad(bbox: &BoundingBox, width: u32, height: u32) -> Option<[(f64, f64); 4]> {
+    if bbox.contour.len() != 4 || bbox.needs_outpaint {
+        return None;
+    }
+    let (w, h) = (width as f64, height as f64);
+    let mut corners: Vec<(f64, f64)> =
+        bbox.contour.iter().map(|p| (p.x as f64 / 1000.0 * w, p.y as f64 / 1000.0 * h)).collect();
+
+    // Clockwise on screen from the top-left corner, whatever order they came in
+    let centre = (corners.iter().map(|c| c.0).sum::<f64>() / 4.0, corners.iter().map(|c| c.1).sum::<f64>() / 4.0);
+    let angle = |c: &(f64, f64)| (c.1 - centre.1).atan2(c.0 - centre.0);
+    corners.sort_by(|a, b| angle(a).partial_cmp(&angle(b)).unwrap_or(std::cmp::Ordering::Equal));
+    let first = (0..4)
+        .min_by(|&i, &j| {
+            (corners[i].0 + corners[i].1)
+                .partial_cmp(&(corners[j].0 + corners[j].1))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .unwrap_or(0);
+    corners.rotate_left(first);
+    let quad = [corners[0], corners[1], corners[2], corners[3]];
+
+    let convex = (0..4).all(|i| {
+        let (a, b, c) = (quad[i], quad[(i + 1) % 4], quad[(i + 2) % 4]);
+        (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0) > 0.0
+    });
+    let shortest = (0..4).map(|i| distance(quad[i], quad[(i + 1) % 4])).fold(f64::MAX, f64::min);
+    if !convex || shortest < 8.0 {
+        info!("Contour of {:?} is not a usable quadrilateral, cropping the box", bbox.label);
+        return None;
+    }
+    Some(quad)
+}
+
+#[cfg(feature = "image-processing")]
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+/// Clockwise tilt of a quadrilateral: mean angle of its top and bottom edges.
+#[cfg(feature = "image-processing")]
+fn quad_angle(quad: &[(f64, f64); 4]) -> f32 {
+    let edge = |a: (f64, f64), b: (f64, f64)| (b.1 - a.1).atan2(b.0 - a.0);
+    ((edge(quad[0], quad[1]) + edge(quad[3], quad[2])) / 2.0).to_degrees() as f32
+}
+
+/// Warp the quadrilateral `quad` (TL, TR, BR, BL in scan pixels) onto a
+/// rectangle as wide as its longer horizontal edge and as tall as its
+/// longer vertical edge, sampling the scan through the homography.
+/// `None` when the corners admit no homography.
+#[cfg(feature = "image-processing")]
+fn warp_quad(scan: &image::RgbaImage, quad: &[(f64, f64); 4]) -> Option<image::RgbaImage> {
+    let out_w = distance(quad[0], quad[1]).max(distance(quad[3], quad[2])).round().max(1.0);
+    let out_h = distance(quad[0], quad[3]).max(distance(quad[1], quad[2])).round().max(1.0);
+    let target = [(0.0, 0.0), (out_w, 0.0), (out_w, out_h), (0.0, out_h)];
+    let m = homography(&target, quad)?;
+    Some(image::RgbaImage::from_fn(out_w as u32, out_h as u32, |u, v| {
+        let (x, y) = (u as f64 + 0.5, v as f64 + 0.5);
+        let z = m[6] * x + m[7] * y + 1.0;
+        let sx = (m[0] * x + m[1] * y + m[2]) / z;
+        let sy = (m[3] * x + m[4] * y + m[5]) / z;
+        sample_bicubic(scan, sx - 0.5, sy - 0.5)
+    }))
+}
+
+/// Homography (row-major, h33 = 1) mapping the four `from` points onto
+/// `to`, by Gaussian elimination on the 8x8 system.
+#[cfg(feature = "image-processing")]
+fn homography(from: &[(f64, f64); 4], to: &[(f64, f64); 4]) -> Option<[f64; 8]> {
+    let mut a = [[0.0f64; 9]; 8];
+    for i in 0..4 {
+        let ((x, y), (u, v)) = (from[i], to[i]);
+        a[2 * i] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+        a[2 * i + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+    }
+    for col in 0..8 {
+        let pivot = (col..8).max_by(|&i, &j| a[i][col].abs().partial_cmp(&a[j][col].abs()).unwrap_or(std::cmp::Ordering::Equal))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        let pivot_row = a[col];
+        for (row, values) in a.iter_mut().enumerate() {
+            if row != col {
+                let factor = values[col] / pivot_row[col];
+                for (value, pivot_value) in values.iter_mut().zip(pivot_row).skip(col) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+    let mut m = [0.0; 8];
+    for (i, value) in m.iter_mut().enumerate() {
+        *value = a[i][8] / a[i][i];
+    }
+    Some(m)
+}
+
+/// A resampled RGBA crop in the scan's colour type (the JPEG encoder
+/// rejects alpha).
+#[cfg(feature = "image-processing")]
+fn like_scan(scan: &image::DynamicImage, crop: image::RgbaImage) -> image::DynamicImage {
+    let crop = image::DynamicImage::ImageRgba8(crop);
+    if scan.color().has_alpha() {
+        crop
+    } else {
+        image::DynamicImage::ImageRgb8(crop.to_rgb8())
+    }
+}
+
+/// Largest axis-aligned rectangle inside a `w`×`h` rectangle turned by
+/// `degrees`, in the turned frame. For the bounding box of a skewed photo
+/// this is the photo itself.
+#[cfg(feature = "image-processing")]
+fn largest_inscribed_rect(w: f64, h: f64, degrees: f64) -> (f64, f64) {
+    let (sin, cos) = (degrees.to_radians().sin().abs(), degrees.to_radians().cos().abs());
+    let (long, short) = if w >= h { (w, h) } else { (h, w) };
+    if short <= 2.0 * sin * cos * long || (sin - cos).abs() < 1e-10 {
+        // Half-constrained: two corners touch the longer sides
+        let x = 0.5 * short;
+        if w >= h { (x / sin, x / cos) } else { (x / cos, x / sin) }
+    } else {
+        let cos_2a = cos * cos - sin * sin;
+        ((w * cos - h * sin) / cos_2a, (h * cos - w * sin) / cos_2a)
+    }
+}
+
+/// Catmull-Rom bicubic sample at pixel-centre coordinates (pixel `i` is
+/// centred on `i`), clamped to the image edge.
+#[cfg(feature = "image-processing")]
+fn sample_bicubic(img: &image::RgbaImage, x: f64, y: f64) -> image::Rgba<u8> {
+    let weights = |t: f64| {
+        let (t2, t3) = (t * t, t * t * t);
+        [
+            (-t3 + 2.0 * t2 - t) / 2.0,
+            (3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+            (-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+            (t3 - t2) / 2.0,
+        ]
+    };
+    let (x0, y0) = (x.floor(), y.floor());
+    let (wx, wy) = (weights(x - x0), weights(y - y0));
+    let (max_x, max_y) = (img.width() as i64 - 1, img.height() as i64 - 1);
+
+    let mut acc = [0.0f64; 4];
+    for (j, wy) in wy.iter().enumerate() {
+        let sy = (y0 as i64 + j as i64 - 1).clamp(0, max_y) as u32;
+        for (i, wx) in wx.iter().enumerate() {
+            let sx = (x0 as i64 + i as i64 - 1).clamp(0, max_x) as u32;
+            let p = img.get_pixel(sx, sy);
+            for c in 0..4 {
+                acc[c] += wx * wy * p[c] as f64;
+            }
+        }
+    }
+    image::Rgba(acc.map(|v| v.round().clamp(0.0, 255.0) as u8))
+}
+
+#[cfg(not(feature = "image-processing"))]
+pub fn crop_photos(
+    _image_base64: &str,
+    _mime_type: &str,
+    _bounding_boxes: &[BoundingBox],
+    _original_filename: String,
+) -> Result<CropResult> {
+    Err(feature_disabled())
+}
+
+// ============================================
+// MANUAL IMAGE ROTATION
+// ============================================
+
+#[cfg(feature = "image-processing")]
+pub fn rotate_image(image: ImageData, mime_type: &str, degrees: i32) -> Result<String> {
+    info!("=== ROTATE_IMAGE {} degrees ===", degrees);
+
+    let img = image.decode()?;
+
+    // Normalize degrees to 0, 90, 180, 270
+    let normalized = degrees.rem_euclid(360);
+    let rotated = match normalized {
+        90 => img.rotate90(),
+        180 => img.rotate180(),
+        270 => img.rotate270(),
+        _ => img,
+    };
+
+    let result_base64 = encode_base64_image(&rotated, mime_type)?;
+    info!("=== ROTATE_IMAGE END ===");
+    Ok(result_base64)
+}
+
+#[cfg(not(feature = "image-processing"))]
+pub fn rotate_image(_image: ImageData, _mime_type: &str, _degrees: i32) -> Result<String> {
+    Err(feature_disabled())
+}
+
+// ============================================
+// UPSCALE IMAGE 2x (Resolution Enhancement)
+// ============================================
+
+/// Largest accepted `scale_factor` (keeps output size within memory limits).
+#[cfg(feature = "image-processing")]
+const MAX_UPSCALE_FACTOR: f64 = 8.0;
+
+#[cfg(feature = "image-processing")]
+pub fn upscale_image(image: ImageData, mime_type: &str, scale_factor: Option<f64>) -> Result<String> {
+    use image::GenericImageView;
+
+    let factor = scale_factor.unwrap_or(2.0);
+    if !(factor > 0.0 && factor <= MAX_UPSCALE_FACTOR) {
+        return Err(TissaiaError::Validation(format!(
+            "scale_factor must be greater than 0 and at most {}", MAX_UPSCALE_FACTOR
+        ))
+        .into());
+    }
+    info!("=== UPSCALE_IMAGE START === scale: {}x", factor);
+
+    let start = std::time::Instant::now();
+
+    let img = image.decode()?;
+
+    let (orig_w, orig_h) = img.dimensions();
+    let new_w = (orig_w as f64 * factor) as u32;
+    let new_h = (orig_h as f64 * factor) as u32;
+
+    info!("Upscaling {}x{} -> {}x{} ({}x)", orig_w, orig_h, new_w, new_h, factor);
+
+    // Use Lanczos3 for highest quality upscaling
+    let upscaled = img.resize_exact(new_w, new_h, image::imageops::FilterType::Lanczos3);
+
+    let result_base64 = encode_base64_image(&upscaled, mime_type)?;
+
+    info!("=== UPSCALE_IMAGE END === ({}x{} -> {}x{}, {}ms)",
+        orig_w, orig_h, new_w, new_h, start.elapsed().as_millis());
+
+    Ok(result_base64)
+}
+
+#[cfg(not(feature = "image-processing"))]
+pub fn upscale_image(_image: ImageData, _mime_type: &str, _scale_factor: Option<f64>) -> Result<String> {
+    Err(feature_disabled())
+}
+
+// ============================================
+// SAVE IMAGE TO DISK
+// ============================================
+
+pub fn save_image(image: ImageData, file_path: &str) -> Result<String> {
+    info!("=== SAVE_IMAGE START === path: {}", file_path);
+
+    let image_bytes = image.bytes()?;
+
+    std::fs::write(file_path, &image_bytes)
+        .map_err(|e| anyhow!("File write error: {}", e))?;
+
+    info!("=== SAVE_IMAGE END === ({} bytes written)", image_bytes.len());
+    Ok(file_path.to_string())
+}
+
+// ============================================
+// LOCAL IMAGE FILTERS (CLAHE, Sharpen, Bilateral-like, Dust, Colour)
+// ============================================
+
+#[cfg(feature = "image-processing")]
+pub fn apply_local_filters(
+    image: ImageData,
+    mime_type: &str,
+    filters: Option<Vec<String>>,
+) -> Result<String> {
+    use image::GenericImageView;
+
+    info!("=== APPLY_LOCAL_FILTERS START ===");
+    let start = std::time::Instant::now();
+
+    let img = image.decode()?;
+
+    let (w, h) = img.dimensions();
+    info!("Processing {}x{} image", w, h);
+
+    // Default filters if none specified
+    let active_filters = filters.unwrap_or_else(|| vec![
+        "clahe".to_string(),
+        "sharpen".to_string(),
+    ]);
+
+    let mut current = img;
+
+    for filter_name in &active_filters {
+        current = match filter_name.as_str() {
+            "clahe" => apply_clahe(&current),
+            "sharpen" => apply_unsharp_mask(&current, 1.0),
+            "sharpen_mild" => apply_unsharp_mask(&current, 0.5),
+            "sharpen_strong" => apply_unsharp_mask(&current, 2.0),
+            "bilateral" => apply_bilateral_approx(&current),
+            "denoise" => apply_gaussian_denoise(&current, 1.5),
+            "denoise_mild" => apply_gaussian_denoise(&current, 0.8),
+            "denoise_strong" => apply_gaussian_denoise(&current, 3.0),
+            "dust" => apply_dust_removal(&current, 0.5),
+            "white_balance" => apply_white_balance(&current, 1.0),
+            "levels" => apply_levels(&current, 1.0),
+            "fade" => apply_fade_correction(&current, 0.7),
+            _ => {
+                info!("Unknown filter: {}, skipping", filter_name);
+                current
+            }
+        };
+    }
+
+    let result = encode_base64_image(&current, mime_type)?;
+
+    info!("=== APPLY_LOCAL_FILTERS END === (filters: {:?}, {}ms)",
+        active_filters, start.elapsed().as_millis());
+
+    Ok(result)
+}
+
+#[cfg(not(feature = "image-processing"))]
+pub fn apply_local_filters(
+    _image: ImageData,
+    _mime_type: &str,
+    _filters: Option<Vec<String>>,
+) -> Result<String> {
+    Err(feature_disabled())
+}
+
+#[cfg(feature = "image-processing")]
+pub fn apply_clahe(img: &image::DynamicImage) -> image::DynamicImage {
+    use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
+
+    let (w, h) = img.dimensions();
+    let mut output = ImageBuffer::<Rgba<u8>, Vec<u8>>::new(w, h);
+
+    let tile_w = (w / 8).max(16);
+    let tile_h = (h / 8).max(16);
+    let clip_limit: u32 = 40;
+
+    for ty in (0..h).step_by(tile_h as usize) {
+        for tx in (0..w).step_by(tile_w as usize) {
+            let end_x = (tx + tile_w).min(w);
+            let end_y = (ty + tile_h).min(h);
+
+            // Build luminance histogram for this tile
+            let mut hist = [0u32; 256];
+            let mut count = 0u32;
+
+            for y in ty..end_y {
+                for x in tx..end_x {
+                    let pixel = img.get_pixel(x, y);
+                    let lum = (0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64) as u8;
+                    hist[lum as usize] += 1;
+                    count += 1;
+                }
+            }
+
+            if count == 0 { continue; }
+
+            // Clip histogram
+            let mut excess = 0u32;
+            for bin in hist.iter_mut() {
+                if *bin > clip_limit {
+                    excess += *bin - clip_limit;
+                    *bin = clip_limit;
+                }
+            }
+
+            // Redistribute excess
+            let redistrib = excess / 256;
+            for bin in hist.iter_mut() {
+                *bin += redistrib;
+            }
+
+            // Build CDF
+            let mut cdf = [0u32; 256];
+            cdf[0] = hist[0];
+            for i in 1..256 {
+                cdf[i] = cdf[i - 1] + hist[i];
+            }
+
+            let cdf_min = cdf.iter().copied().find(|&v| v > 0).unwrap_or(0);
+            let denom = (count - cdf_min).max(1);
+
+            // Apply equalization
+            for y in ty..end_y {
+                for x in tx..end_x {
+                    let pixel = img.get_pixel(x, y);
+                    let lum = (0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64) as u8;
+                    let new_lum = ((cdf[lum as usize] - cdf_min) as f64 / denom as f64 * 255.0).clamp(0.0, 255.0) as u8;
+
+                    let scale = if lum > 0 { new_lum as f64 / lum as f64 } else { 1.0 };
+                    let r = (pixel[0] as f64 * scale).clamp(0.0, 255.0) as u8;
+                    let g = (pixel[1] as f64 * scale).clamp(0.0, 255.0) as u8;
+                    let b = (pixel[2] as f64 * scale).clamp(0.0, 255.0) as u8;
+                    output.put_pixel(x, y, Rgba([r, g, b, pixel[3]]));
+                }
+            }
+        }
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+#[cfg(feature = "image-processing")]
+pub fn apply_unsharp_mask(img: &image::DynamicImage, amount: f64) -> image::DynamicImage {
+    use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
+
+    let (w, h) = img.dimensions();
+    if w < 3 || h < 3 { return img.clone(); }
+
+    // Simple 3x3 Gaussian blur for the mask
+    let blurred = img.blur(1.0);
+    let mut output = ImageBuffer::<Rgba<u8>, Vec<u8>>::new(w, h);
+
+    for y in 0..h {
+        for x in 0..w {
+            let orig = img.get_pixel(x, y);
+            let blur = blurred.get_pixel(x, y);
+
+            let r = ((orig[0] as f64 + amount * (orig[0] as f64 - blur[0] as f64)).clamp(0.0, 255.0)) as u8;
+            let g = ((orig[1] as f64 + amount * (orig[1] as f64 - blur[1] as f64)).clamp(0.0, 255.0)) as u8;
+            let b = ((orig[2] as f64 + amount * (orig[2] as f64 - blur[2] as f64)).clamp(0.0, 255.0)) as u8;
+            output.put_pixel(x, y, Rgba([r, g, b, orig[3]]));
+        }
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+#[cfg(feature = "image-processing")]
+pub fn apply_bilateral_approx(img: &image::DynamicImage) -> image::DynamicImage {
+    use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
+
+    let (w, h) = img.dimensions();
+    if w < 5 || h < 5 { return img.clone(); }
+
+    let mut output = ImageBuffer::<Rgba<u8>, Vec<u8>>::new(w, h);
+    let radius: i32 = 3;
+    let sigma_space: f64 = 3.0;
+    let sigma_color: f64 = 50.0;
+
+    for y in 0..h {
+        for x in 0..w {
+            let center = img.get_pixel(x, y);
+            let mut sum_r = 0.0f64;
+            let mut sum_g = 0.0f64;
+            let mut sum_b = 0.0f64;
+            let mut weight_sum = 0.0f64;
+
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let nx = x as i32 + dx;
+                    let ny = y as i32 + dy;
+                    if nx < 0 || ny < 0 || nx >= w as i32 || ny >= h as i32 { continue; }
+
+                    let neighbor = img.get_pixel(nx as u32, ny as u32);
+
+                    // Spatial weight
+                    let spatial = (-((dx * dx + dy * dy) as f64) / (2.0 * sigma_space * sigma_space)).exp();
+
+                    // Color weight
+                    let diff_r = center[0] as f64 - neighbor[0] as f64;
+                    let diff_g = center[1] as f64 - neighbor[1] as f64;
+                    let diff_b = center[2] as f64 - neighbor[2] as f64;
+                    let color_dist = diff_r * diff_r + diff_g * diff_g + diff_b * diff_b;
+                    let color_w = (-(color_dist) / (2.0 * sigma_color * sigma_color)).exp();
+
+                    let weight = spatial * color_w;
+                    sum_r += neighbor[0] as f64 * weight;
+                    sum_g += neighbor[1] as f64 * weight;
+                    sum_b += neighbor[2] as f64 * weight;
+                    weight_sum += weight;
+                }
+            }
+
+            if weight_sum > 0.0 {
+                output.put_pixel(x, y, Rgba([
+                    (sum_r / weight_sum).clamp(0.0, 255.0) as u8,
+                    (sum_g / weight_sum).clamp(0.0, 255.0) as u8,
+                    (sum_b / weight_sum).clamp(0.0, 255.0) as u8,
+                    center[3],
+                ]));
+            } else {
+                output.put_pixel(x, y, center);
+            }
+        }
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+#[cfg(feature = "image-processing")]
+pub fn apply_gaussian_denoise(img: &image::DynamicImage, sigma: f64) -> image::DynamicImage {
+    img.blur(sigma as f32)
+}
+
+/// Replace dust specks and thin scratches: pixels whose luminance differs
+/// from the median of their neighbourhood by more than a threshold take the
+/// neighbourhood median. Higher `strength` (0-1) widens the window (catching
+/// scratches up to 2 px) and lowers the threshold.
+#[cfg(feature = "image-processing")]
+pub fn apply_dust_removal(img: &image::DynamicImage, strength: f64) -> image::DynamicImage {
+    use image::{DynamicImage, Rgba};
+
+    let strength = strength.clamp(0.0, 1.0);
+    let src = img.to_rgba8();
+    let (w, h) = src.dimensions();
+    if w < 3 || h < 3 { return img.clone(); }
+
+    let radius: i64 = if strength > 0.5 { 2 } else { 1 };
+    let threshold = 70.0 - 50.0 * strength;
+    let luma = |p: &Rgba<u8>| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64;
+
+    let mut output = src.clone();
+    let mut window: Vec<Rgba<u8>> = Vec::with_capacity(25);
+    for y in 0..h as i64 {
+        for x in 0..w as i64 {
+            window.clear();
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let nx = (x + dx).clamp(0, w as i64 - 1) as u32;
+                    let ny = (y + dy).clamp(0, h as i64 - 1) as u32;
+                    window.push(*src.get_pixel(nx, ny));
+                }
+            }
+            window.sort_by(|a, b| luma(a).total_cmp(&luma(b)));
+            let median = window[window.len() / 2];
+            let pixel = src.get_pixel(x as u32, y as u32);
+            if (luma(pixel) - luma(&median)).abs() > threshold {
+                output.put_pixel(x as u32, y as u32, Rgba([median[0], median[1], median[2], pixel[3]]));
+            }
+        }
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+/// Gray-world white balance: scale each channel so the mean of the
+/// mid-tones is neutral. `strength` (0-1) blends from no change to the full
+/// correction; gains are limited to 0.5-2.0.
+#[cfg(feature = "image-processing")]
+pub fn apply_white_balance(img: &image::DynamicImage, strength: f64) -> image::DynamicImage {
+    use image::DynamicImage;
+
+    let strength = strength.clamp(0.0, 1.0);
+    let mut output = img.to_rgba8();
+
+    // Clipped shadows and highlights carry no colour information
+    let mut sums = [0.0f64; 3];
+    let mut count = 0u64;
+    for pixel in output.pixels() {
+        let lum = 0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64;
+        if (16.0..=240.0).contains(&lum) {
+            for c in 0..3 {
+                sums[c] += pixel[c] as f64;
+            }
+            count += 1;
+        }
+    }
+    if count == 0 { return img.clone(); }
+
+    let means = sums.map(|sum| sum / count as f64);
+    let gray = (means[0] + means[1] + means[2]) / 3.0;
+    let gains = means.map(|mean| {
+        let gain = if mean > 0.0 { (gray / mean).clamp(0.5, 2.0) } else { 1.0 };
+        1.0 + (gain - 1.0) * strength
+    });
+
+    for pixel in output.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = (pixel[c] as f64 * gains[c]).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+/// Levels: move the black and white points to the 0.5th and 99.5th
+/// luminance percentiles, the same mapping for every channel so hues are
+/// kept. `strength` (0-1) scales how far the points move.
+#[cfg(feature = "image-processing")]
+pub fn apply_levels(img: &image::DynamicImage, strength: f64) -> image::DynamicImage {
+    use image::DynamicImage;
+
+    let strength = strength.clamp(0.0, 1.0);
+    let mut output = img.to_rgba8();
+
+    let mut hist = [0u64; 256];
+    for pixel in output.pixels() {
+        let lum = 0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64;
+        hist[lum.round().clamp(0.0, 255.0) as usize] += 1;
+    }
+    let (low, high) = percentiles(&hist, 0.005);
+    // Flat images have no range to stretch
+    if high <= low + 10.0 { return img.clone(); }
+
+    let low = low * strength;
+    let high = 255.0 - (255.0 - high) * strength;
+    let scale = 255.0 / (high - low);
+    for pixel in output.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = ((pixel[c] as f64 - low) * scale).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+/// Fade correction for old prints: stretch every channel to its own 1st-99th
+/// percentile range (bringing back contrast and undoing the yellow/magenta
+/// shift of faded dyes), then boost saturation. `strength` (0-1) scales both.
+#[cfg(feature = "image-processing")]
+pub fn apply_fade_correction(img: &image::DynamicImage, strength: f64) -> image::DynamicImage {
+    use image::DynamicImage;
+
+    let strength = strength.clamp(0.0, 1.0);
+    let mut output = img.to_rgba8();
+
+    let mut hists = [[0u64; 256]; 3];
+    for pixel in output.pixels() {
+        for c in 0..3 {
+            hists[c][pixel[c] as usize] += 1;
+        }
+    }
+    let ranges = hists.map(|hist| {
+        let (low, high) = percentiles(&hist, 0.01);
+        if high <= low + 10.0 { (0.0, 255.0) } else { (low * strength, 255.0 - (255.0 - high) * strength) }
+    });
+    let saturation = 1.0 + 0.6 * strength;
+
+    for pixel in output.pixels_mut() {
+        let mut rgb = [0.0f64; 3];
+        for c in 0..3 {
+            let (low, high) = ranges[c];
+            rgb[c] = (pixel[c] as f64 - low) * 255.0 / (high - low);
+        }
+        let lum = 0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2];
+        for c in 0..3 {
+            pixel[c] = (lum + (rgb[c] - lum) * saturation).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+/// Values below which `fraction` of the histogram lies, from both ends.
+#[cfg(feature = "image-processing")]
+fn percentiles(hist: &[u64; 256], fraction: f64) -> (f64, f64) {
+    let total: u64 = hist.iter().sum();
+    let cut = (total as f64 * fraction) as u64;
+    let mut seen = 0u64;
+    let low = hist.iter().position(|&n| { seen += n; seen > cut }).unwrap_or(0);
+    seen = 0;
+    let high = 255 - hist.iter().rev().position(|&n| { seen += n; seen > cut }).unwrap_or(0);
+    (low as f64, high as f64)
+}
+
+// ============================================
+// EXIF METADATA EXTRACTION
+// ============================================
+
+#[cfg(feature = "image-processing")]
+pub fn extract_metadata(image: ImageData, mime_type: &str) -> Result<serde_json::Value> {
+    info!("=== EXTRACT_METADATA START ===");
+
+    let image_bytes = image.bytes()?;
+
+    let mut metadata = serde_json::Map::new();
+
+    // Get image dimensions
+    if let Ok(img) = image::load_from_memory(&image_bytes) {
+        use image::GenericImageView;
+        let (w, h) = img.dimensions();
+        metadata.insert("width".to_string(), serde_json::json!(w));
+        metadata.insert("height".to_string(), serde_json::json!(h));
+        metadata.insert("color_type".to_string(), serde_json::json!(format!("{:?}", img.color())));
+    }
+
+    metadata.insert("mime_type".to_string(), serde_json::json!(mime_type));
+    metadata.insert("file_size".to_string(), serde_json::json!(image_bytes.len()));
+
+    // Extract EXIF data
+    let mut cursor = std::io::Cursor::new(&image_bytes);
+    if let Ok(exif_data) = exif::Reader::new().read_from_container(&mut cursor) {
+        let mut exif_map = serde_json::Map::new();
+
+        for field in exif_data.fields() {
+            let tag_name = format!("{}", field.tag);
+            let value = format!("{}", field.display_value().with_unit(&exif_data));
+            exif_map.insert(tag_name, serde_json::json!(value));
+        }
+
+        if !exif_map.is_empty() {
+            metadata.insert("exif".to_string(), serde_json::Value::Object(exif_map));
+        }
+    }
+
+    info!("=== EXTRACT_METADATA END ===");
+    Ok(serde_json::Value::Object(metadata))
+}
+
+#[cfg(not(feature = "image-processing"))]
+pub fn extract_metadata(_image: ImageData, _mime_type: &str) -> Result<serde_json::Value> {
+    Err(feature_disabled())
+}
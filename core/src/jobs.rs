@@ -0,0 +1,448 @@
+// core/src/jobs.rs
+//! Asynchronous jobs for long-running AI operations.
+//! A provider call can take up to the 120 s client timeout, longer than
+//! proxies and browsers keep a request open. `JobManager::submit` returns
+//! immediately; the operation runs on a bounded worker pool and records
+//! per-stage progress, and the client polls `get` (or cancels). Finished
+//! jobs are kept for polling within a `Retention` budget: by count, by the
+//! size of their results (mostly base64 images) and for a limited time.
+
+use crate::batch::{self, BatchRequest};
+use crate::error::ApiError;
+use crate::models::RestorationOptions;
+use crate::operations;
+use crate::pipeline::{self, PipelineRequest};
+use crate::progress::{Stage, StageEvent, StageObserver, StageStatus};
+use crate::state::SharedState;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::AbortHandle;
+
+/// Jobs running concurrently unless `TISSAIA_JOB_WORKERS` says otherwise.
+pub const DEFAULT_WORKERS: usize = 2;
+/// Finished jobs kept for polling; older ones are dropped first.
+pub const DEFAULT_MAX_FINISHED: usize = 100;
+/// Total size of the results finished jobs keep.
+pub const DEFAULT_MAX_RESULT_BYTES: usize = 256 * 1024 * 1024;
+/// How long a finished job can still be polled.
+pub const DEFAULT_FINISHED_TTL: Duration = Duration::from_secs(60 * 60);
+
+// ============================================
+// JOB MODEL
+// ============================================
+
+/// Body of `POST /api/jobs`, tagged by `operation`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum JobRequest {
+    Restore {
+        image_base64: String,
+        mime_type: String,
+        /// Named restoration preset (see `presets`).
+        #[serde(default)]
+        preset: Option<String>,
+        /// Overrides `preset` when both are given.
+        #[serde(default)]
+        options: Option<RestorationOptions>,
+    },
+    Detect {
+        image_base64: String,
+        mime_type: String,
+    },
+    /// Detection followed by verification (`detect_photos_with_retry`).
+    DetectRetry {
+        image_base64: String,
+        mime_type: String,
+    },
+    /// The full scan → restored photos pipeline (see `pipeline`).
+    Pipeline(PipelineRequest),
+    /// A folder of scans through the pipeline (see `batch`).
+    Batch(BatchRequest),
+}
+
+impl JobRequest {
+    pub fn operation(&self) -> &'static str {
+        match self {
+            JobRequest::Restore { .. } => "restore",
+            JobRequest::Detect { .. } => "detect",
+            JobRequest::DetectRetry { .. } => "detect_retry",
+            JobRequest::Pipeline(_) => "pipeline",
+            JobRequest::Batch(_) => "batch",
+        }
+    }
+
+    /// Stages the job reports, in execution order.
+    pub fn stages(&self) -> Vec<Stage> {
+        match self {
+            JobRequest::Restore { .. } => vec![Stage::ExifFix, Stage::Restore],
+            JobRequest::Detect { .. } => vec![Stage::Detect],
+            JobRequest::DetectRetry { .. } => vec![Stage::Detect, Stage::Verify, Stage::Merge],
+            JobRequest::Pipeline(request) => request.options.stages(),
+            JobRequest::Batch(_) => vec![Stage::Batch],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StageProgress {
+    pub stage: Stage,
+    pub status: StageStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Job {
+    pub id: String,
+    pub operation: String,
+    pub status: JobStatus,
+    pub stages: Vec<StageProgress>,
+    /// Operation output once completed (same shape as the synchronous endpoint).
+    pub result: Option<Value>,
+    pub error: Option<ApiError>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl Job {
+    fn new(request: &JobRequest) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            operation: request.operation().to_string(),
+            status: JobStatus::Queued,
+            stages: request
+                .stages()
+                .into_iter()
+                .map(|stage| StageProgress {
+                    stage,
+                    status: StageStatus::Pending,
+                    message: None,
+                    started_at: None,
+                    finished_at: None,
+                })
+                .collect(),
+            result: None,
+            error: None,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
+    fn apply(&mut self, event: StageEvent) {
+        let now = Utc::now();
+        let index = match self.stages.iter().position(|s| s.stage == event.stage) {
+            Some(index) => index,
+            None => {
+                self.stages.push(StageProgress {
+                    stage: event.stage,
+                    status: StageStatus::Pending,
+                    message: None,
+                    started_at: None,
+                    finished_at: None,
+                });
+                self.stages.len() - 1
+            }
+        };
+        let progress = &mut self.stages[index];
+        if event.status == StageStatus::Running && progress.started_at.is_none() {
+            progress.started_at = Some(now);
+        }
+        if event.status.is_finished() {
+            progress.finished_at = Some(now);
+        }
+        progress.status = event.status;
+        progress.message = event.message;
+    }
+
+    fn finish(&mut self, status: JobStatus) {
+        let now = Utc::now();
+        self.status = status;
+        self.finished_at = Some(now);
+        // Whatever never got to run is skipped; an interrupted stage failed
+        for stage in self.stages.iter_mut().filter(|s| !s.status.is_finished()) {
+            if stage.status == StageStatus::Running {
+                stage.status = StageStatus::Failed;
+                stage.finished_at = Some(now);
+                if status == JobStatus::Cancelled {
+                    stage.message = Some("Cancelled".to_string());
+                }
+            } else {
+                stage.status = StageStatus::Skipped;
+            }
+        }
+    }
+}
+
+// ============================================
+// JOB MANAGER
+// ============================================
+
+/// How many finished jobs are kept for polling, and for how long.
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    pub max_finished: usize,
+    /// Total size of the kept results; the newest finished job is kept even
+    /// when its result alone is larger.
+    pub max_result_bytes: usize,
+    pub ttl: Duration,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            max_finished: DEFAULT_MAX_FINISHED,
+            max_result_bytes: DEFAULT_MAX_RESULT_BYTES,
+            ttl: DEFAULT_FINISHED_TTL,
+        }
+    }
+}
+
+struct JobSlot {
+    job: Job,
+    abort: Option<AbortHandle>,
+    /// Approximate size of `job.result`.
+    result_bytes: usize,
+}
+
+struct Inner {
+    jobs: Mutex<HashMap<String, JobSlot>>,
+    workers: Semaphore,
+    retention: Retention,
+}
+
+impl Inner {
+    /// Run `f` on an active job; finished (e.g. cancelled) jobs are left alone.
+    fn update_active(&self, id: &str, f: impl FnOnce(&mut JobSlot)) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(slot) = jobs.get_mut(id).filter(|slot| !slot.job.status.is_finished()) {
+            f(slot);
+        }
+    }
+
+    /// Drop expired finished jobs, then the oldest ones until the rest fit
+    /// the retention budget.
+    fn prune(&self) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let ttl = chrono::Duration::from_std(self.retention.ttl).unwrap_or(chrono::Duration::MAX);
+        let expired_before = Utc::now() - ttl;
+        jobs.retain(|_, slot| !slot.job.finished_at.is_some_and(|at| at < expired_before));
+
+        let mut finished: Vec<(DateTime<Utc>, String, usize)> = jobs
+            .values()
+            .filter_map(|slot| slot.job.finished_at.map(|at| (at, slot.job.id.clone(), slot.result_bytes)))
+            .collect();
+        finished.sort();
+        let mut count = finished.len();
+        let mut bytes: usize = finished.iter().map(|(_, _, size)| size).sum();
+        for (_, id, size) in finished {
+            if count <= 1 || (count <= self.retention.max_finished && bytes <= self.retention.max_result_bytes) {
+                break;
+            }
+            jobs.remove(&id);
+            count -= 1;
+            bytes -= size;
+        }
+    }
+}
+
+/// Approximate size of a job result; its strings (base64 images) dominate.
+fn result_size(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.len(),
+        Value::Array(items) => items.iter().map(result_size).sum(),
+        Value::Object(fields) => fields.iter().map(|(key, value)| key.len() + result_size(value)).sum(),
+        _ => 8,
+    }
+}
+
+/// Records a running job's stage events on the job.
+struct JobObserver {
+    inner: Arc<Inner>,
+    id: String,
+}
+
+impl StageObserver for JobObserver {
+    fn on_stage(&self, event: StageEvent) {
+        self.inner.update_active(&self.id, |slot| slot.job.apply(event));
+    }
+}
+
+/// In-memory job registry with a bounded worker pool. Cheap to clone.
+#[derive(Clone)]
+pub struct JobManager {
+    inner: Arc<Inner>,
+}
+
+impl JobManager {
+    pub fn new(workers: usize) -> Self {
+        Self::with_retention(workers, Retention::default())
+    }
+
+    pub fn with_retention(workers: usize, retention: Retention) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                jobs: Mutex::new(HashMap::new()),
+                workers: Semaphore::new(workers.max(1)),
+                retention,
+            }),
+        }
+    }
+
+    /// Worker count from `TISSAIA_JOB_WORKERS` (default `DEFAULT_WORKERS`).
+    pub fn from_env() -> Self {
+        let workers = std::env::var("TISSAIA_JOB_WORKERS")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_WORKERS);
+        Self::new(workers)
+    }
+
+    /// Queue a job; it starts as soon as a worker is free.
+    /// Must be called from within a Tokio runtime.
+    pub fn submit(&self, state: SharedState, request: JobRequest) -> Job {
+        self.inner.prune();
+        let job = Job::new(&request);
+        let id = job.id.clone();
+        info!("Job {} queued ({})", id, job.operation);
+
+        // The job only becomes visible together with its abort handle, so a
+        // cancel always reaches the task. The task itself waits for the lock
+        // before touching the job.
+        let mut jobs = self.lock();
+        let inner = self.inner.clone();
+        let task_id = id.clone();
+        let handle = tokio::spawn(async move {
+            let Ok(_permit) = inner.workers.acquire().await else {
+                return;
+            };
+            inner.update_active(&task_id, |slot| {
+                slot.job.status = JobStatus::Running;
+                slot.job.started_at = Some(Utc::now());
+            });
+            info!("Job {} started", task_id);
+
+            let observer = JobObserver { inner: inner.clone(), id: task_id.clone() };
+            let outcome = run(&state, request, &observer).await;
+
+            inner.update_active(&task_id, |slot| match outcome {
+                Ok(result) => {
+                    slot.result_bytes = result_size(&result);
+                    slot.job.result = Some(result);
+                    slot.job.finish(JobStatus::Completed);
+                }
+                Err(e) => {
+                    warn!("Job {} failed: {:#}", task_id, e);
+                    slot.job.error = Some(ApiError::from(&e));
+                    slot.job.finish(JobStatus::Failed);
+                }
+            });
+            info!("Job {} finished", task_id);
+            inner.prune();
+        });
+        jobs.insert(id, JobSlot { job: job.clone(), abort: Some(handle.abort_handle()), result_bytes: 0 });
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.lock().get(id).map(|slot| slot.job.clone())
+    }
+
+    /// Cancel a queued or running job, or forget a finished one.
+    /// Returns the job as of the call, `None` for an unknown id.
+    pub fn cancel(&self, id: &str) -> Option<Job> {
+        let mut jobs = self.lock();
+        let slot = jobs.get_mut(id)?;
+        if slot.job.status.is_finished() {
+            return jobs.remove(id).map(|slot| slot.job);
+        }
+
+        if let Some(abort) = slot.abort.take() {
+            abort.abort();
+        }
+        slot.job.finish(JobStatus::Cancelled);
+        info!("Job {} cancelled", id);
+        Some(slot.job.clone())
+    }
+
+    /// Jobs not yet finished.
+    pub fn active_count(&self) -> usize {
+        self.lock().values().filter(|slot| !slot.job.status.is_finished()).count()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, JobSlot>> {
+        self.inner.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_WORKERS)
+    }
+}
+
+// ============================================
+// EXECUTION
+// ============================================
+
+async fn run(state: &SharedState, request: JobRequest, observer: &dyn StageObserver) -> Result<Value> {
+    let value = match request {
+        JobRequest::Restore { image_base64, mime_type, preset, options } => {
+            let options = operations::restoration_options(state, preset.as_deref(), options).await?;
+            let result = operations::restore_image_observed(state, image_base64, mime_type, &options, observer).await?;
+            serde_json::to_value(result)?
+        }
+        JobRequest::Detect { image_base64, mime_type } => {
+            observer.running(Stage::Detect, None);
+            let result = match operations::detect_photos(state, &image_base64, &mime_type).await {
+                Ok(result) => result,
+                Err(e) => {
+                    observer.failed(Stage::Detect, &e);
+                    return Err(e);
+                }
+            };
+            observer.completed(Stage::Detect, Some(format!("{} photos", result.photo_count)));
+            serde_json::to_value(result)?
+        }
+        JobRequest::DetectRetry { image_base64, mime_type } => {
+            let (result, _) =
+                operations::detect_photos_with_retry_observed(state, &image_base64, &mime_type, observer).await?;
+            serde_json::to_value(result)?
+        }
+        JobRequest::Pipeline(request) => {
+            let result = pipeline::run_pipeline_observed(state, request, observer).await?;
+            serde_json::to_value(result)?
+        }
+        JobRequest::Batch(request) => {
+            let summary = batch::run_batch_observed(state, request, observer).await?;
+            serde_json::to_value(summary)?
+        }
+    };
+    Ok(value)
+}
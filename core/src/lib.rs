@@ -0,0 +1,39 @@
+// core/src/lib.rs
+//! Tissaia AI Studio — shared core
+//! =================================
+//! AI providers, data models, application state and the local image
+//! pipeline. `tissaia-server` (Axum) and the Tauri desktop app are thin
+//! transport adapters over this crate.
+
+pub mod ai;
+pub mod backends;
+pub mod batch;
+pub mod boxes;
+pub mod config;
+pub mod error;
+pub mod executor;
+pub mod failover;
+pub mod health;
+pub mod history;
+pub mod imaging;
+pub mod jobs;
+pub mod local_detection;
+pub mod local_restoration;
+pub mod models;
+pub mod operations;
+pub mod pipeline;
+pub mod presets;
+pub mod progress;
+pub mod prompts;
+pub mod refine;
+pub mod responses;
+pub mod state;
+
+pub use ai::AiProvider;
+pub use backends::{BackendRegistry, RestorationBackend};
+pub use error::{ApiError, TissaiaError};
+pub use history::HistoryStore;
+pub use jobs::JobManager;
+pub use presets::PresetStore;
+pub use prompts::PromptLibrary;
+pub use state::{AppState, SharedState};
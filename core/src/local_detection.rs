@@ -0,0 +1,632 @@
+// core/src/local_detection.rs
+//! Offline photo-boundary detection for flatbed scans.
+//! Photos on a uniform (dark or white) scanner bed are found without any AI:
+//! the bed colour is estimated from the scan border, pixels far enough from
+//! it (Otsu threshold on the colour distance) form a foreground mask, which
+//! is closed, hole-filled and opened before connected components are
+//! labelled. Each component large and rectangular enough becomes a photo;
+//! its minimum-area rectangle gives the `contour` and the skew
+//! (`rotation_angle`, degrees clockwise in -45..=45). Content orientation
+//! (a photo lying sideways) cannot be seen from pixels alone and stays 0.
+//! Photos that touch or overlap on the bed are reported as one.
+
+use crate::models::DetectionResult;
+use anyhow::Result;
+#[cfg(feature = "image-processing")]
+use crate::models::{BoundingBox, Point2D};
+#[cfg(feature = "image-processing")]
+use log::info;
+
+/// Provider name of the local detector in `ProviderStatus` and results.
+pub const LOCAL_PROVIDER: &str = "local";
+
+/// Tuning knobs; the defaults suit 300-600 dpi scans of prints.
+#[derive(Debug, Clone, Copy)]
+pub struct DetectorParams {
+    /// Long side of the downscaled working copy, in pixels.
+    pub work_size: u32,
+    /// Smallest photo, as a fraction of the scan area.
+    pub min_area: f32,
+    /// Smallest component area / min-area-rectangle area; rejects dust,
+    /// cables and other non-rectangular blobs.
+    pub min_fill: f32,
+    /// Lower bound for the colour-distance threshold (0-255), so scanner
+    /// noise on an empty bed never becomes foreground.
+    pub min_threshold: u8,
+}
+
+impl Default for DetectorParams {
+    fn default() -> Self {
+        Self {
+            work_size: 800,
+            min_area: 0.01,
+            min_fill: 0.6,
+            min_threshold: 24,
+        }
+    }
+}
+
+/// Detect photos on a base64-encoded scan.
+#[cfg(feature = "image-processing")]
+pub fn detect_photos(image_base64: &str, params: &DetectorParams) -> Result<DetectionResult> {
+    let img = crate::imaging::decode_base64_image(image_base64)?;
+    Ok(detect_in_image(&img.to_rgb8(), params))
+}
+
+#[cfg(not(feature = "image-processing"))]
+pub fn detect_photos(_image_base64: &str, _params: &DetectorParams) -> Result<DetectionResult> {
+    Err(crate::imaging::feature_disabled())
+}
+
+/// Detect photos on a decoded scan.
+#[cfg(feature = "image-processing")]
+pub fn detect_in_image(scan: &image::RgbImage, params: &DetectorParams) -> DetectionResult {
+    info!("=== LOCAL DETECTION START === ({}x{})", scan.width(), scan.height());
+    let start = std::time::Instant::now();
+
+    let work = downscale(scan, params.work_size);
+    let (w, h) = work.dimensions();
+    let (background, threshold, distance) = bed_model(&work, params);
+    info!("Bed colour {:?}, threshold {}", background, threshold);
+
+    let mut mask = Mask { width: w, height: h, bits: distance.iter().map(|&d| d > threshold).collect() };
+    let radius = (w.min(h) / 200).max(1);
+    mask = mask.dilate(radius).erode(radius);
+    mask.fill_holes();
+    mask = mask.erode(radius * 2).dilate(radius * 2);
+
+    let min_pixels = (params.min_area * (w * h) as f32) as usize;
+    let mut boxes: Vec<BoundingBox> = mask
+        .components()
+        .into_iter()
+        .filter(|c| c.area >= min_pixels.max(1))
+        .filter_map(|c| photo_box(&c, w, h, params.min_fill))
+        .collect();
+    sort_reading_order(&mut boxes);
+    for (i, bbox) in boxes.iter_mut().enumerate() {
+        bbox.label = Some(format!("photo {}", i + 1));
+    }
+
+    info!(
+        "=== LOCAL DETECTION END === ({} photos, {} ms)",
+        boxes.len(),
+        start.elapsed().as_millis()
+    );
+    DetectionResult {
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now(),
+        photo_count: boxes.len(),
+        bounding_boxes: boxes,
+        provider_used: LOCAL_PROVIDER.to_string(),
+        scan_width: scan.width(),
+        scan_height: scan.height(),
+        attempts: Vec::new(),
+        adjustments: Vec::new(),
+        prompt_version: None,
+    }
+}
+
+// ============================================
+// BACKGROUND & THRESHOLD
+// ============================================
+
+/// Bed colour and photo/bed threshold of a scan, estimated on a working
+/// copy. Shared with edge snapping (`refine`).
+#[cfg(feature = "image-processing")]
+pub(crate) fn bed_colour_and_threshold(scan: &image::RgbImage, params: &DetectorParams) -> ([u8; 3], u8) {
+    let (background, threshold, _) = bed_model(&downscale(scan, params.work_size), params);
+    (background, threshold)
+}
+
+/// Colour distance of a pixel from the bed: largest channel difference.
+#[cfg(feature = "image-processing")]
+pub(crate) fn bed_distance(pixel: &image::Rgb<u8>, background: [u8; 3]) -> u8 {
+    (0..3).map(|c| pixel[c].abs_diff(background[c])).max().unwrap_or(0)
+}
+
+/// Bed colour, threshold and per-pixel bed distance of a working copy.
+#[cfg(feature = "image-processing")]
+fn bed_model(work: &image::RgbImage, params: &DetectorParams) -> ([u8; 3], u8, Vec<u8>) {
+    let background = estimate_background(work);
+    let distance: Vec<u8> = work.pixels().map(|p| bed_distance(p, background)).collect();
+    let threshold = otsu_threshold(&distance).max(params.min_threshold);
+    (background, threshold, distance)
+}
+
+#[cfg(feature = "image-processing")]
+fn downscale(scan: &image::RgbImage, work_size: u32) -> image::RgbImage {
+    let long_side = scan.width().max(scan.height());
+    if long_side <= work_size {
+        return scan.clone();
+    }
+    let scale = work_size as f64 / long_side as f64;
+    let w = ((scan.width() as f64 * scale).round() as u32).max(1);
+    let h = ((scan.height() as f64 * scale).round() as u32).max(1);
+    // Box-filtered, so thin bright dust does not survive as hard pixels
+    image::imageops::resize(scan, w, h, image::imageops::FilterType::Triangle)
+}
+
+/// Per-channel median of a thin frame along the scan border. Photos touching
+/// the border are fine as long as the bed makes up most of the frame.
+#[cfg(feature = "image-processing")]
+fn estimate_background(img: &image::RgbImage) -> [u8; 3] {
+    let (w, h) = img.dimensions();
+    let margin = (w.min(h) / 50).max(1);
+    let mut channels: [Vec<u8>; 3] = Default::default();
+    for (x, y, p) in img.enumerate_pixels() {
+        if x < margin || y < margin || x >= w.saturating_sub(margin) || y >= h.saturating_sub(margin) {
+            for c in 0..3 {
+                channels[c].push(p[c]);
+            }
+        }
+    }
+    channels.map(|mut values| {
+        values.sort_unstable();
+        values.get(values.len() / 2).copied().unwrap_or(0)
+    })
+}
+
+/// Otsu's threshold: the level maximising between-class variance.
+#[cfg(feature = "image-processing")]
+fn otsu_threshold(values: &[u8]) -> u8 {
+    let mut histogram = [0u64; 256];
+    for &v in values {
+        histogram[v as usize] += 1;
+    }
+    let total = values.len() as f64;
+    let sum: f64 = histogram.iter().enumerate().map(|(i, &n)| i as f64 * n as f64).sum();
+
+    let (mut weight_below, mut sum_below) = (0.0, 0.0);
+    let (mut best, mut best_variance) = (0u8, 0.0);
+    for (level, &count) in histogram.iter().enumerate() {
+        weight_below += count as f64;
+        if weight_below == 0.0 {
+            continue;
+        }
+        let weight_above = total - weight_below;
+        if weight_above == 0.0 {
+            break;
+        }
+        sum_below += level as f64 * count as f64;
+        let mean_below = sum_below / weight_below;
+        let mean_above = (sum - sum_below) / weight_above;
+        let variance = weight_below * weight_above * (mean_below - mean_above).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = level as u8;
+        }
+    }
+    best
+}
+
+// ============================================
+// BINARY MASK
+// ============================================
+
+#[cfg(feature = "image-processing")]
+#[derive(Clone)]
+struct Mask {
+    width: u32,
+    height: u32,
+    bits: Vec<bool>,
+}
+
+/// A labelled foreground region.
+#[cfg(feature = "image-processing")]
+struct Component {
+    area: usize,
+    /// Leftmost and rightmost pixel per row: (y, min x, max x).
+    rows: Vec<(u32, u32, u32)>,
+}
+
+#[cfg(feature = "image-processing")]
+impl Mask {
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    /// Square dilation, done as a horizontal then a vertical sliding window.
+    fn dilate(&self, radius: u32) -> Mask {
+        self.sliding(radius, true)
+    }
+
+    fn erode(&self, radius: u32) -> Mask {
+        self.sliding(radius, false)
+    }
+
+    /// `any` (dilate) or `all` (erode) over a (2r+1)² window. Pixels outside
+    /// the image count as background for dilation and foreground for erosion,
+    /// so photos touching the border are not eaten away.
+    fn sliding(&self, radius: u32, any: bool) -> Mask {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let r = radius as usize;
+        let pass = |src: &[bool], len: usize, stride: usize, lines: usize, line_stride: usize| {
+            let mut out = vec![false; src.len()];
+            for line in 0..lines {
+                let base = line * line_stride;
+                let at = |i: usize| src[base + i * stride];
+                // Count of foreground pixels inside the window
+                let mut count = (0..=r.min(len - 1)).filter(|&i| at(i)).count();
+                for i in 0..len {
+                    let lo = i.saturating_sub(r);
+                    let hi = (i + r).min(len - 1);
+                    let window = hi - lo + 1;
+                    out[base + i * stride] = if any { count > 0 } else { count == window };
+                    if i >= r && at(i - r) {
+                        count -= 1;
+                    }
+                    if i + r + 1 < len && at(i + r + 1) {
+                        count += 1;
+                    }
+                }
+            }
+            out
+        };
+        let horizontal = pass(&self.bits, w, 1, h, w);
+        let bits = pass(&horizontal, h, w, w, 1);
+        Mask { width: self.width, height: self.height, bits }
+    }
+
+    /// Background regions not connected to the border become foreground:
+    /// bed-coloured areas inside a photo (white sky on a white bed).
+    fn fill_holes(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let mut outside = vec![false; self.bits.len()];
+        let mut stack: Vec<(u32, u32)> = Vec::new();
+        for x in 0..w {
+            stack.push((x, 0));
+            stack.push((x, h - 1));
+        }
+        for y in 0..h {
+            stack.push((0, y));
+            stack.push((w - 1, y));
+        }
+        while let Some((x, y)) = stack.pop() {
+            let i = self.index(x, y);
+            if self.bits[i] || outside[i] {
+                continue;
+            }
+            outside[i] = true;
+            if x > 0 {
+                stack.push((x - 1, y));
+            }
+            if x + 1 < w {
+                stack.push((x + 1, y));
+            }
+            if y > 0 {
+                stack.push((x, y - 1));
+            }
+            if y + 1 < h {
+                stack.push((x, y + 1));
+            }
+        }
+        for (bit, outside) in self.bits.iter_mut().zip(outside) {
+            *bit = !outside;
+        }
+    }
+
+    /// 4-connected foreground components.
+    fn components(&self) -> Vec<Component> {
+        let (w, h) = (self.width, self.height);
+        let mut seen = vec![false; self.bits.len()];
+        let mut components = Vec::new();
+        let mut stack = Vec::new();
+        for start in 0..self.bits.len() {
+            if !self.bits[start] || seen[start] {
+                continue;
+            }
+            let mut area = 0;
+            let mut rows: std::collections::BTreeMap<u32, (u32, u32)> = Default::default();
+            seen[start] = true;
+            stack.push(((start as u32) % w, (start as u32) / w));
+            while let Some((x, y)) = stack.pop() {
+                area += 1;
+                let row = rows.entry(y).or_insert((x, x));
+                row.0 = row.0.min(x);
+                row.1 = row.1.max(x);
+                let neighbours = [
+                    (x > 0).then(|| (x - 1, y)),
+                    (x + 1 < w).then(|| (x + 1, y)),
+                    (y > 0).then(|| (x, y - 1)),
+                    (y + 1 < h).then(|| (x, y + 1)),
+                ];
+                for (nx, ny) in neighbours.into_iter().flatten() {
+                    let i = self.index(nx, ny);
+                    if self.bits[i] && !seen[i] {
+                        seen[i] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+            components.push(Component {
+                area,
+                rows: rows.into_iter().map(|(y, (min, max))| (y, min, max)).collect(),
+            });
+        }
+        components
+    }
+}
+
+// ============================================
+// GEOMETRY
+// ============================================
+
+/// Min-area rectangle of a component as a `BoundingBox` in 0-1000 space,
+/// or `None` when the component is not photo-shaped.
+#[cfg(feature = "image-processing")]
+fn photo_box(component: &Component, w: u32, h: u32, min_fill: f32) -> Option<BoundingBox> {
+    // Pixel corners of each row's extremes
+    let mut points = Vec::with_capacity(component.rows.len() * 4);
+    for &(y, min, max) in &component.rows {
+        let (y, min, max) = (y as f64, min as f64, max as f64 + 1.0);
+        points.extend([(min, y), (max, y), (min, y + 1.0), (max, y + 1.0)]);
+    }
+    let hull = convex_hull(points);
+    let rect = min_area_rect(&hull)?;
+
+    let fill = component.area as f64 / rect.area;
+    if fill < min_fill as f64 {
+        info!("Skipping blob of {} px (fills {:.0}% of its rectangle)", component.area, fill * 100.0);
+        return None;
+    }
+
+    let normalize = |(x, y): (f64, f64)| Point2D {
+        x: (x / w as f64 * 1000.0).clamp(0.0, 1000.0) as f32,
+        y: (y / h as f64 * 1000.0).clamp(0.0, 1000.0) as f32,
+    };
+    let contour: Vec<Point2D> = rect.corners.iter().copied().map(normalize).collect();
+    let min_x = contour.iter().map(|p| p.x).fold(f32::MAX, f32::min).floor() as u32;
+    let min_y = contour.iter().map(|p| p.y).fold(f32::MAX, f32::min).floor() as u32;
+    let max_x = contour.iter().map(|p| p.x).fold(0.0, f32::max).ceil() as u32;
+    let max_y = contour.iter().map(|p| p.y).fold(0.0, f32::max).ceil() as u32;
+
+    Some(BoundingBox {
+        x: min_x,
+        y: min_y,
+        width: (max_x - min_x).max(1),
+        height: (max_y - min_y).max(1),
+        confidence: fill.min(0.99) as f32,
+        label: None,
+        rotation_angle: rect.angle as f32,
+        contour,
+        needs_outpaint: false,
+        edges_snapped: false,
+    })
+}
+
+/// Andrew's monotone chain; counter-clockwise in y-down coordinates' mirror,
+/// which is all `min_area_rect` needs.
+#[cfg(feature = "image-processing")]
+fn convex_hull(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0);
+    let mut hull: Vec<(f64, f64)> = Vec::with_capacity(points.len() * 2);
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &(f64, f64)>> =
+            if pass == 0 { Box::new(points.iter()) } else { Box::new(points.iter().rev()) };
+        for &p in iter {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+#[cfg(feature = "image-processing")]
+struct RotatedRect {
+    /// Top-left first, then clockwise on screen.
+    corners: [(f64, f64); 4],
+    area: f64,
+    /// Skew in degrees, clockwise positive, within -45..=45.
+    angle: f64,
+}
+
+/// Rotating calipers: the smallest rectangle has a side on a hull edge.
+#[cfg(feature = "image-processing")]
+fn min_area_rect(hull: &[(f64, f64)]) -> Option<RotatedRect> {
+    if hull.len() < 3 {
+        return None;
+    }
+    let mut best: Option<(f64, (f64, f64), [f64; 4])> = None;
+    for i in 0..hull.len() {
+        let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+        let length = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+        if length < 1e-9 {
+            continue;
+        }
+        let u = ((b.0 - a.0) / length, (b.1 - a.1) / length);
+        let (mut min_u, mut max_u, mut min_v, mut max_v) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+        for &(x, y) in hull {
+            let pu = x * u.0 + y * u.1;
+            let pv = -x * u.1 + y * u.0;
+            min_u = min_u.min(pu);
+            max_u = max_u.max(pu);
+            min_v = min_v.min(pv);
+            max_v = max_v.max(pv);
+        }
+        let area = (max_u - min_u) * (max_v - min_v);
+        if best.as_ref().map_or(true, |(best_area, _, _)| area < *best_area - 1e-9) {
+            best = Some((area, u, [min_u, max_u, min_v, max_v]));
+        }
+    }
+    let (area, u, [min_u, max_u, min_v, max_v]) = best?;
+
+    // Rotate the frame so its first axis is the one closest to horizontal
+    let mut angle = u.1.atan2(u.0).to_degrees();
+    while angle > 45.0 {
+        angle -= 90.0;
+    }
+    while angle <= -45.0 {
+        angle += 90.0;
+    }
+
+    let point = |pu: f64, pv: f64| (pu * u.0 - pv * u.1, pu * u.1 + pv * u.0);
+    let mut corners = [point(min_u, min_v), point(max_u, min_v), point(max_u, max_v), point(min_u, max_v)];
+    // Order on screen: top-left (smallest x + y) first, then clockwise
+    let centre = (
+        corners.iter().map(|c| c.0).sum::<f64>() / 4.0,
+        corners.iter().map(|c| c.1).sum::<f64>() / 4.0,
+    );
+    corners.sort_by(|a, b| {
+        let angle_of = |c: &(f64, f64)| (c.1 - centre.1).atan2(c.0 - centre.0);
+        angle_of(a).partial_cmp(&angle_of(b)).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let first = (0..4)
+        .min_by(|&i, &j| {
+            (corners[i].0 + corners[i].1)
+                .partial_cmp(&(corners[j].0 + corners[j].1))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .unwrap_or(0);
+    corners.rotate_left(first);
+
+    Some(RotatedRect { corners, area, angle })
+}
+
+/// Rows top to bottom (boxes whose tops lie within half a height of the
+/// row's first box), left to right within a row.
+#[cfg(feature = "image-processing")]
+fn sort_reading_order(boxes: &mut [BoundingBox]) {
+    boxes.sort_by_key(|b| (b.y, b.x));
+    let mut row_start = 0;
+    while row_start < boxes.len() {
+        let first = &boxes[row_start];
+        let limit = first.y + first.height / 2;
+        let row_end = boxes[row_start..].iter().position(|b| b.y > limit).map_or(boxes.len(), |n| row_start + n);
+        boxes[row_start..row_end].sort_by_key(|b| b.x);
+        row_start = row_end;
+    }
+}
+
+#[cfg(all(test, feature = "image-processing"))]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    /// (centre x, centre y, width, height, skew degrees clockwise, colour)
+    type Print = (f64, f64, f64, f64, f64, [u8; 3]);
+
+    /// A `w`×`h` bed with noise and the given photos on it.
+    fn scan(w: u32, h: u32, bed: [u8; 3], photos: &[Print]) -> RgbImage {
+        RgbImage::from_fn(w, h, |x, y| {
+            let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+            for &(cx, cy, pw, ph, skew, colour) in photos {
+                let (sin, cos) = skew.to_radians().sin_cos();
+                // Into the photo's frame (undo the clockwise rotation)
+                let (dx, dy) = (px - cx, py - cy);
+                let (u, v) = (dx * cos + dy * sin, -dx * sin + dy * cos);
+                if u.abs() <= pw / 2.0 && v.abs() <= ph / 2.0 {
+                    // A bed-coloured patch inside the picture (sky, shadow)
+                    let patch = u.abs() <= pw / 4.0 && v.abs() <= ph / 4.0;
+                    return Rgb(if patch { bed } else { colour });
+                }
+            }
+            // Scanner noise
+            let noise = ((x * 7 + y * 13) % 9) as u8;
+            Rgb(bed.map(|c| c.saturating_add(noise)))
+        })
+    }
+
+    fn params() -> DetectorParams {
+        DetectorParams { work_size: 600, ..DetectorParams::default() }
+    }
+
+    #[test]
+    fn finds_axis_aligned_photos_on_a_dark_bed() {
+        let img = scan(
+            800,
+            600,
+            [12, 12, 14],
+            &[(200.0, 150.0, 260.0, 180.0, 0.0, [200, 170, 120]), (560.0, 400.0, 300.0, 240.0, 0.0, [90, 140, 200])],
+        );
+        let result = detect_in_image(&img, &params());
+
+        assert_eq!(result.photo_count, 2);
+        assert_eq!(result.provider_used, LOCAL_PROVIDER);
+        assert_eq!((result.scan_width, result.scan_height), (800, 600));
+        let first = &result.bounding_boxes[0];
+        // (70, 60)–(330, 240) px → 87.5, 100, 325 x 300 in 0-1000 space
+        assert!((first.x as i32 - 88).abs() <= 4, "x = {}", first.x);
+        assert!((first.y as i32 - 100).abs() <= 4, "y = {}", first.y);
+        assert!((first.width as i32 - 325).abs() <= 6, "width = {}", first.width);
+        assert!((first.height as i32 - 300).abs() <= 6, "height = {}", first.height);
+        assert!(first.rotation_angle.abs() < 1.0);
+        assert_eq!(first.contour.len(), 4);
+        assert!(first.confidence > 0.9);
+        assert_eq!(result.bounding_boxes[1].label.as_deref(), Some("photo 2"));
+    }
+
+    #[test]
+    fn measures_skew_on_a_white_bed() {
+        let img = scan(
+            900,
+            700,
+            [238, 238, 236],
+            &[(250.0, 350.0, 300.0, 200.0, 8.0, [60, 80, 50]), (650.0, 350.0, 260.0, 360.0, -12.0, [150, 60, 60])],
+        );
+        let result = detect_in_image(&img, &params());
+
+        assert_eq!(result.photo_count, 2);
+        let angles: Vec<f32> = result.bounding_boxes.iter().map(|b| b.rotation_angle).collect();
+        assert!((angles[0] - 8.0).abs() < 1.5, "angles {:?}", angles);
+        assert!((angles[1] + 12.0).abs() < 1.5, "angles {:?}", angles);
+
+        // The contour is the rotated rectangle: its first edge follows the skew
+        let c = &result.bounding_boxes[0].contour;
+        let edge = ((c[1].y - c[0].y) as f64 * 700.0).atan2((c[1].x - c[0].x) as f64 * 900.0).to_degrees();
+        assert!((edge - 8.0).abs() < 1.5, "edge angle {}", edge);
+    }
+
+    #[test]
+    fn ignores_dust_and_empty_beds() {
+        let mut img = scan(600, 400, [10, 10, 10], &[(300.0, 200.0, 200.0, 150.0, 0.0, [180, 180, 160])]);
+        // Specks and a thin hair, far from the photo
+        for i in 0..6 {
+            img.put_pixel(20 + i * 9, 380, Rgb([250, 250, 250]));
+        }
+        for x in 450..590 {
+            img.put_pixel(x, 30, Rgb([230, 230, 230]));
+        }
+        assert_eq!(detect_in_image(&img, &params()).photo_count, 1);
+
+        let empty = scan(600, 400, [10, 10, 10], &[]);
+        assert_eq!(detect_in_image(&empty, &params()).photo_count, 0);
+    }
+
+    #[test]
+    fn reports_photos_in_reading_order() {
+        let img = scan(
+            900,
+            900,
+            [20, 20, 20],
+            &[
+                (700.0, 220.0, 250.0, 200.0, 0.0, [200, 200, 150]),
+                (220.0, 240.0, 250.0, 240.0, 0.0, [150, 200, 200]),
+                (450.0, 680.0, 300.0, 220.0, 0.0, [200, 150, 200]),
+            ],
+        );
+        let result = detect_in_image(&img, &params());
+        let xs: Vec<u32> = result.bounding_boxes.iter().map(|b| b.x).collect();
+        assert_eq!(result.photo_count, 3);
+        assert!(xs[0] < xs[1], "{:?}", xs);
+        assert!(result.bounding_boxes[2].y > 600);
+    }
+
+    #[test]
+    fn min_area_rect_of_a_square_is_the_square() {
+        let hull = convex_hull(vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (2.0, 2.0)]);
+        let rect = min_area_rect(&hull).unwrap();
+        assert!((rect.area - 16.0).abs() < 1e-9);
+        assert_eq!(rect.angle, 0.0);
+        assert_eq!(rect.corners[0], (0.0, 0.0));
+        assert_eq!(rect.corners[1], (4.0, 0.0));
+    }
+}
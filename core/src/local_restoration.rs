@@ -0,0 +1,190 @@
+// core/src/local_restoration.rs
+//! Local restoration engine for text-only providers.
+//! Anthropic, OpenAI, Ollama and the chat providers answer a restoration
+//! request with a plan, not with pixels. The engine runs that plan on the
+//! image with the local filters (`imaging`): dust and scratch removal,
+//! denoising, white balance, fade correction, levels, CLAHE and sharpening.
+//! Steps always run in that order, whatever order the model listed them in,
+//! so dust is removed before it can be smeared and sharpening comes last.
+//! Plans without machine-readable `operations` fall back to the steps named
+//! in the model's improvement texts, then to a gentle default cleanup.
+//! Steps the request's `RestorationOptions` switch off are never run.
+
+use crate::models::{PlanStep, RestorationOptions, RestorationPlan, RestorationResult, RestorationStep};
+use anyhow::Result;
+use log::info;
+
+/// Steps run when the plan names nothing the engine understands.
+const DEFAULT_PLAN: [PlanStep; 3] = [
+    PlanStep { step: RestorationStep::DustRemoval, strength: 0.3 },
+    PlanStep { step: RestorationStep::WhiteBalance, strength: 0.5 },
+    PlanStep { step: RestorationStep::Levels, strength: 0.5 },
+];
+
+/// Keywords in improvement / processing-step texts and the step they imply.
+const KEYWORDS: &[(&str, RestorationStep)] = &[
+    ("dust", RestorationStep::DustRemoval),
+    ("scratch", RestorationStep::DustRemoval),
+    ("speck", RestorationStep::DustRemoval),
+    ("noise", RestorationStep::Denoise),
+    ("grain", RestorationStep::Denoise),
+    ("white balance", RestorationStep::WhiteBalance),
+    ("colour cast", RestorationStep::WhiteBalance),
+    ("color cast", RestorationStep::WhiteBalance),
+    ("fade", RestorationStep::FadeCorrection),
+    ("levels", RestorationStep::Levels),
+    ("contrast", RestorationStep::Clahe),
+    ("sharp", RestorationStep::Sharpen),
+];
+
+impl RestorationStep {
+    /// Improvement text reported once the step was applied.
+    pub fn description(&self) -> &'static str {
+        match self {
+            RestorationStep::DustRemoval => "Dust and scratches removed",
+            RestorationStep::Denoise => "Noise and grain reduced",
+            RestorationStep::Bilateral => "Edge-preserving smoothing",
+            RestorationStep::WhiteBalance => "Colour cast neutralized",
+            RestorationStep::FadeCorrection => "Faded colours and contrast restored",
+            RestorationStep::Levels => "Black and white points adjusted",
+            RestorationStep::Clahe => "Local contrast enhanced",
+            RestorationStep::Sharpen => "Details sharpened",
+        }
+    }
+}
+
+/// Local steps the options permit: denoise covers dust, noise and
+/// smoothing, colorize the colour work, upscale sharpening and studio finish
+/// local contrast. Levels is a plain tonal correction and always allowed.
+pub fn allowed_steps(options: &RestorationOptions) -> Vec<RestorationStep> {
+    use RestorationStep::*;
+    [
+        (DustRemoval, options.denoise.enabled),
+        (Denoise, options.denoise.enabled),
+        (Bilateral, options.denoise.enabled),
+        (WhiteBalance, options.colorize.enabled),
+        (FadeCorrection, options.colorize.enabled),
+        (Levels, true),
+        (Clahe, options.studio_finish.enabled),
+        (Sharpen, options.upscale.enabled),
+    ]
+    .into_iter()
+    .filter(|&(_, allowed)| allowed)
+    .map(|(step, _)| step)
+    .collect()
+}
+
+/// The steps to execute for `plan` under `options`: one per kind, strength
+/// clamped to 0-1, zero-strength and disallowed steps dropped, sorted into
+/// execution order.
+pub fn plan_steps(plan: &RestorationPlan, options: &RestorationOptions) -> Vec<PlanStep> {
+    let allowed = allowed_steps(options);
+    let candidates: Vec<PlanStep> = if !plan.operations.is_empty() {
+        plan.operations.clone()
+    } else {
+        let text = plan.improvements.iter().chain(&plan.processing_steps)
+            .map(|s| s.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("\n");
+        KEYWORDS.iter()
+            .filter(|(keyword, _)| text.contains(keyword))
+            .map(|&(_, step)| PlanStep { step, strength: 0.5 })
+            .collect()
+    };
+
+    let mut steps = unique_steps(&candidates, &allowed);
+    if steps.is_empty() {
+        info!("Restoration plan names no executable steps — using the default cleanup");
+        steps = unique_steps(&DEFAULT_PLAN, &allowed);
+    }
+    steps
+}
+
+fn unique_steps(candidates: &[PlanStep], allowed: &[RestorationStep]) -> Vec<PlanStep> {
+    let mut steps: Vec<PlanStep> = Vec::new();
+    for candidate in candidates {
+        let strength = candidate.strength.clamp(0.0, 1.0);
+        if !allowed.contains(&candidate.step) {
+            info!("  {:?} switched off by the restoration options — skipped", candidate.step);
+        } else if strength > 0.0 && !steps.iter().any(|s| s.step == candidate.step) {
+            steps.push(PlanStep { step: candidate.step, strength });
+        }
+    }
+    steps.sort_by_key(|s| s.step);
+    steps
+}
+
+/// Execute `steps` on the image; returns the restored image (as `mime_type`)
+/// and one improvement text per step applied.
+#[cfg(feature = "image-processing")]
+pub fn execute_plan(image_base64: &str, mime_type: &str, steps: &[PlanStep]) -> Result<(String, Vec<String>)> {
+    use crate::imaging;
+
+    info!("=== LOCAL_RESTORATION START === ({} steps)", steps.len());
+    let start = std::time::Instant::now();
+
+    let mut current = imaging::decode_base64_image(image_base64)?;
+    let mut applied = Vec::new();
+    for step in steps {
+        let strength = step.strength.clamp(0.0, 1.0) as f64;
+        current = match step.step {
+            RestorationStep::DustRemoval => imaging::apply_dust_removal(&current, strength),
+            RestorationStep::Denoise => imaging::apply_gaussian_denoise(&current, 0.5 + 2.5 * strength),
+            RestorationStep::Bilateral => blend(&current, &imaging::apply_bilateral_approx(&current), strength),
+            RestorationStep::WhiteBalance => imaging::apply_white_balance(&current, strength),
+            RestorationStep::FadeCorrection => imaging::apply_fade_correction(&current, strength),
+            RestorationStep::Levels => imaging::apply_levels(&current, strength),
+            RestorationStep::Clahe => blend(&current, &imaging::apply_clahe(&current), strength),
+            RestorationStep::Sharpen => imaging::apply_unsharp_mask(&current, 2.0 * strength),
+        };
+        info!("  {:?} applied (strength {:.2})", step.step, strength);
+        applied.push(format!("{} ({:.0}%)", step.step.description(), strength * 100.0));
+    }
+
+    // The JPEG encoder rejects alpha channels
+    if matches!(imaging::output_format(mime_type), image::ImageFormat::Jpeg) && current.color().has_alpha() {
+        current = image::DynamicImage::ImageRgb8(current.to_rgb8());
+    }
+    let restored = imaging::encode_base64_image(&current, mime_type)?;
+
+    info!("=== LOCAL_RESTORATION END === ({}ms)", start.elapsed().as_millis());
+    Ok((restored, applied))
+}
+
+#[cfg(not(feature = "image-processing"))]
+pub fn execute_plan(_image_base64: &str, _mime_type: &str, _steps: &[PlanStep]) -> Result<(String, Vec<String>)> {
+    Err(crate::imaging::feature_disabled())
+}
+
+/// Run the plan a text-only provider attached to `result`: the restored
+/// image replaces the original and `improvements` lists what was applied.
+/// Without the `image-processing` feature the result is left as it is.
+pub fn apply_plan(result: &mut RestorationResult, mime_type: &str, options: &RestorationOptions) -> Result<()> {
+    let Some(plan) = &result.plan else {
+        return Ok(());
+    };
+    if !cfg!(feature = "image-processing") {
+        info!("Image processing disabled — restoration plan from {} not executed", result.provider_used);
+        return Ok(());
+    }
+    let start = std::time::Instant::now();
+    let steps = plan_steps(plan, options);
+    let (restored, applied) = execute_plan(&result.original_image, mime_type, &steps)?;
+    result.restored_image = restored;
+    result.improvements = applied;
+    result.processing_time_ms += start.elapsed().as_millis() as u64;
+    Ok(())
+}
+
+/// Mix `filtered` into `original` by `amount` (0 = original, 1 = filtered).
+#[cfg(feature = "image-processing")]
+fn blend(original: &image::DynamicImage, filtered: &image::DynamicImage, amount: f64) -> image::DynamicImage {
+    let mut output = original.to_rgba8();
+    let filtered = filtered.to_rgba8();
+    for (pixel, other) in output.pixels_mut().zip(filtered.pixels()) {
+        for c in 0..3 {
+            pixel[c] = (pixel[c] as f64 + (other[c] as f64 - pixel[c] as f64) * amount).round() as u8;
+        }
+    }
+    image::DynamicImage::ImageRgba8(output)
+}
@@ -0,0 +1,528 @@
+// core/src/models.rs
+//! Data models shared by the server and the desktop app.
+//! Serde serialization format unchanged (frontend types match).
+
+use crate::config::{default_provider_configs, ProviderConfigUpdates, ProviderConfigs};
+use crate::health::ProviderHealth;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestorationResult {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub original_image: String,
+    pub restored_image: String,
+    pub improvements: Vec<String>,
+    pub provider_used: String,
+    pub processing_time_ms: u64,
+    /// Providers tried before (and including) the one that succeeded.
+    #[serde(default)]
+    pub attempts: Vec<ProviderAttempt>,
+    /// Plan returned by a text-only provider and executed locally
+    /// (`local_restoration`); `None` when the provider returned pixels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<RestorationPlan>,
+    /// Options the restoration ran with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<RestorationOptions>,
+    /// Prompt template used (`<name>.v<N>`, see `prompts`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
+}
+
+/// Toggle and strength of one restoration step.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StepOption {
+    pub enabled: bool,
+    /// 0.0 (lightest touch) to 1.0 (strongest).
+    #[serde(default = "full_strength")]
+    pub strength: f32,
+}
+
+impl StepOption {
+    pub const fn on(strength: f32) -> Self {
+        Self { enabled: true, strength }
+    }
+
+    pub const OFF: Self = Self { enabled: false, strength: 0.0 };
+}
+
+impl Default for StepOption {
+    fn default() -> Self {
+        Self::on(1.0)
+    }
+}
+
+fn full_strength() -> f32 {
+    1.0
+}
+
+/// What a restoration may do to a photo. Every provider prompt is built
+/// from these; the default enables every step at full strength.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestorationOptions {
+    /// Straighten and inpaint missing corners / torn edges.
+    pub geometry: StepOption,
+    /// Neutralize flash glare and blown highlights.
+    pub flash: StepOption,
+    /// Grain, noise, dust and scratch removal.
+    pub denoise: StepOption,
+    /// Facial detail and natural skin tone.
+    pub faces: StepOption,
+    /// Colorize black & white photos, revive faded colours.
+    pub colorize: StepOption,
+    /// Super-resolution and sharpening.
+    pub upscale: StepOption,
+    /// Studio lighting, vignette and colour grading.
+    pub studio_finish: StepOption,
+}
+
+/// Named set of `RestorationOptions` (see `presets`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestorationPreset {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub options: RestorationOptions,
+    /// Shipped with the app; cannot be changed or deleted.
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+/// Restoration plan from a provider that cannot return images.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestorationPlan {
+    /// Improvements the model suggested, in its own words.
+    #[serde(default)]
+    pub improvements: Vec<String>,
+    #[serde(default)]
+    pub processing_steps: Vec<String>,
+    /// Machine-readable steps for the local engine.
+    #[serde(default)]
+    pub operations: Vec<PlanStep>,
+}
+
+/// One step of a `RestorationPlan`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlanStep {
+    #[serde(alias = "operation")]
+    pub step: RestorationStep,
+    /// 0.0 (off) to 1.0 (strongest).
+    #[serde(default = "default_strength")]
+    pub strength: f32,
+}
+
+fn default_strength() -> f32 {
+    0.5
+}
+
+/// Operations the local restoration engine can execute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestorationStep {
+    /// Remove dust specks and thin scratches.
+    DustRemoval,
+    /// Gaussian noise / grain reduction.
+    Denoise,
+    /// Edge-preserving smoothing.
+    Bilateral,
+    /// Neutralize a colour cast.
+    WhiteBalance,
+    /// Restore contrast and saturation lost to fading.
+    FadeCorrection,
+    /// Stretch black and white points.
+    Levels,
+    /// Local contrast (CLAHE).
+    Clahe,
+    Sharpen,
+}
+
+/// One provider call made by the failover chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderAttempt {
+    pub provider: String,
+    pub success: bool,
+    pub error: Option<String>,
+    /// Whether the failure allowed moving on to the next provider.
+    pub retryable: bool,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub operation: OperationType,
+    pub input_preview: String,
+    pub result_preview: Option<String>,
+    pub provider: String,
+    pub success: bool,
+    pub error_message: Option<String>,
+    #[serde(default)]
+    pub processing_time_ms: u64,
+    /// Full input images, stored in the history blob store.
+    #[serde(default)]
+    pub inputs: Vec<BlobRef>,
+    /// Full output images (restored image, crops, ...).
+    #[serde(default)]
+    pub outputs: Vec<BlobRef>,
+    /// Complete operation result with image payloads moved to `outputs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<VerificationResult>,
+    #[serde(default)]
+    pub attempts: Vec<ProviderAttempt>,
+    /// Settings in effect when the operation ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<AppSettings>,
+}
+
+/// Content address of an image kept by the history store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobRef {
+    /// Hex SHA-256 of the decoded bytes.
+    pub hash: String,
+    pub mime_type: String,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationType {
+    Restoration,
+    PhotoSeparation,
+    Verification,
+    Detection,
+    Crop,
+    Outpaint,
+    Rotate,
+    Upscale,
+    Filters,
+    Metadata,
+    Save,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderStatus {
+    pub name: String,
+    pub enabled: bool,
+    pub available: bool,
+    pub priority: u8,
+    pub last_error: Option<String>,
+    /// What the registered backend can do (all false if no backend is registered).
+    #[serde(default)]
+    pub capabilities: BackendCapabilities,
+    /// Circuit breaker and probe results (`health`).
+    #[serde(default)]
+    pub health: ProviderHealth,
+}
+
+/// Feature flags advertised by a `RestorationBackend`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackendCapabilities {
+    /// Can run the restoration operation at all.
+    pub restore: bool,
+    /// Returns restored pixels; `false` means text analysis only.
+    pub image_output: bool,
+    /// Can detect photo boundaries on a scan.
+    pub detection: bool,
+    /// Can act as the verification agent.
+    pub verification: bool,
+    /// Needs an API key to be usable.
+    pub requires_api_key: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSettings {
+    pub language: String,
+    pub theme: String,
+    pub auto_save: bool,
+    pub output_quality: u8,
+    pub preferred_provider: Option<String>,
+    #[serde(default = "default_true")]
+    pub verification_enabled: bool,
+    /// Snap detected boxes to the photo edges in the scan (`refine`).
+    #[serde(default = "default_true")]
+    pub edge_snapping: bool,
+    /// Endpoint config per provider. Read-only here: saving goes
+    /// through `SettingsUpdate`.
+    #[serde(default)]
+    pub providers: ProviderConfigs,
+}
+
+/// Body of `save_settings`: `AppSettings` with partial provider configs,
+/// merged field by field; omitted providers are kept.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SettingsUpdate {
+    #[serde(flatten)]
+    pub settings: AppSettings,
+    #[serde(default)]
+    pub providers: ProviderConfigUpdates,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            language: "pl".to_string(),
+            theme: "dark".to_string(),
+            auto_save: true,
+            output_quality: 90,
+            preferred_provider: None,
+            verification_enabled: true,
+            edge_snapping: true,
+            providers: default_provider_configs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub status: String,
+    pub version: String,
+    pub providers: Vec<ProviderStatus>,
+    pub uptime_seconds: u64,
+}
+
+impl RestorationResult {
+    pub fn new(provider: &str, original: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            original_image: original,
+            restored_image: String::new(),
+            improvements: Vec::new(),
+            provider_used: provider.to_string(),
+            processing_time_ms: 0,
+            attempts: Vec::new(),
+            plan: None,
+            options: None,
+            prompt_version: None,
+        }
+    }
+}
+
+impl HistoryEntry {
+    pub fn new(operation: OperationType, input: String, provider: &str) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            operation,
+            input_preview: input,
+            result_preview: None,
+            provider: provider.to_string(),
+            success: false,
+            error_message: None,
+            processing_time_ms: 0,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            result: None,
+            verification: None,
+            attempts: Vec::new(),
+            settings: None,
+        }
+    }
+
+    /// Every blob this entry references.
+    pub fn blobs(&self) -> impl Iterator<Item = &BlobRef> {
+        self.inputs.iter().chain(self.outputs.iter())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiModel {
+    pub id: String,
+    pub name: String,
+    pub provider: String,
+}
+
+// ============================================
+// PHOTO SEPARATION / CROP TYPES
+// ============================================
+
+/// A 2D point in normalized 0-1000 coordinate space.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Point2D {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub confidence: f32,
+    pub label: Option<String>,
+    /// Rotation angle in degrees (clockwise). 0 = upright, 90 = rotated right, etc.
+    #[serde(default)]
+    pub rotation_angle: f32,
+    /// Precise polygon contour of the photo (normalized 0-1000 coordinates).
+    /// If present, describes the actual photo shape (may not be rectangular).
+    /// The area between the polygon and the bounding box rectangle should be
+    /// filled generatively (outpainting).
+    #[serde(default)]
+    pub contour: Vec<Point2D>,
+    /// Whether this photo needs generative outpainting to fill non-rectangular edges.
+    #[serde(default)]
+    pub needs_outpaint: bool,
+    /// Edges were snapped to the photo boundary in the scan (`refine`);
+    /// cropping then uses the box as is, without padding or dark-edge trim.
+    #[serde(default)]
+    pub edges_snapped: bool,
+}
+
+/// Edge snapping result for one side of a box.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EdgeAdjustment {
+    /// A photo/bed transition was found and the edge follows it.
+    pub snapped: bool,
+    /// Movement of the edge in 0-1000 units; positive = outward (box grew).
+    pub shift: f32,
+    /// Mean colour step across the edge (0-255); 0 when none was found.
+    pub contrast: f32,
+}
+
+/// What edge snapping changed on one detected box.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BoxAdjustment {
+    /// Index into `DetectionResult::bounding_boxes`.
+    pub index: usize,
+    /// Whether the refined geometry replaced the detected one.
+    pub applied: bool,
+    pub left: EdgeAdjustment,
+    pub top: EdgeAdjustment,
+    pub right: EdgeAdjustment,
+    pub bottom: EdgeAdjustment,
+    /// Measured skew in degrees (clockwise), if any edge was found.
+    pub skew: Option<f32>,
+    /// Why the box was left alone, or what was kept from the detection.
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectionResult {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub photo_count: usize,
+    pub bounding_boxes: Vec<BoundingBox>,
+    pub provider_used: String,
+    pub scan_width: u32,
+    pub scan_height: u32,
+    #[serde(default)]
+    pub attempts: Vec<ProviderAttempt>,
+    /// Per-box edge snapping report; empty when snapping is off.
+    #[serde(default)]
+    pub adjustments: Vec<BoxAdjustment>,
+    /// Prompt template used; `None` for the local detector.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CroppedPhoto {
+    pub id: String,
+    pub index: usize,
+    pub image_base64: String,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub source_box: BoundingBox,
+    /// Clockwise rotation undone on this crop, in degrees: quarter turns
+    /// plus deskew (93.5 = turned back 90° and straightened by 3.5°).
+    #[serde(default)]
+    pub applied_rotation: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CropResult {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub original_filename: String,
+    pub photos: Vec<CroppedPhoto>,
+    pub processing_time_ms: u64,
+}
+
+// ============================================
+// VERIFICATION AGENT TYPES
+// ============================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationStatus {
+    Pass,
+    Warning,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationStage {
+    Restoration,
+    Detection,
+    Crop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationIssue {
+    pub severity: String,
+    pub description: String,
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationResult {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub stage: VerificationStage,
+    pub status: VerificationStatus,
+    pub confidence: u8,
+    pub checks: Vec<VerificationCheck>,
+    pub issues: Vec<VerificationIssue>,
+    pub recommendations: Vec<String>,
+    pub processing_time_ms: u64,
+    pub model_used: String,
+    /// Bounding boxes for photos that the verifier detected as missing from the original detection.
+    #[serde(default)]
+    pub missing_boxes: Vec<BoundingBox>,
+    #[serde(default)]
+    pub attempts: Vec<ProviderAttempt>,
+    /// Prompt template used (`<name>.v<N>`, see `prompts`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
+}
+
+impl VerificationResult {
+    pub fn new(stage: VerificationStage) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            stage,
+            status: VerificationStatus::Pass,
+            confidence: 0,
+            checks: Vec::new(),
+            issues: Vec::new(),
+            recommendations: Vec::new(),
+            processing_time_ms: 0,
+            model_used: "gemini-3-flash-preview".to_string(),
+            missing_boxes: Vec::new(),
+            attempts: Vec::new(),
+            prompt_version: None,
+        }
+    }
+}
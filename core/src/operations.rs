@@ -0,0 +1,818 @@
+// core/src/operations.rs
+//! Operations shared by the Axum handlers and Tauri commands.
+//! Provider-backed functions select a `RestorationBackend` from `SharedState`
+//! by capability; local ones wrap `imaging`. Every call writes a history
+//! entry (successful or failed), so the transport layers stay thin adapters.
+
+use crate::backends::{Capability, VerificationRequest};
+use crate::boxes::{self, BoxChange};
+use crate::error::TissaiaError;
+use crate::failover::{failed_attempts, run_with_failover};
+use crate::history::PreparedBlob;
+use crate::health::CircuitState;
+use crate::imaging::{self, ImageData};
+use crate::models::{
+    AiModel, BoundingBox, CropResult, DetectionResult, HealthResponse, HistoryEntry,
+    OperationType, Point2D, ProviderStatus, RestorationOptions, RestorationPreset, RestorationResult,
+    VerificationResult,
+};
+use crate::progress::{Stage, StageObserver};
+use crate::prompts::PromptInfo;
+use crate::refine;
+use crate::state::SharedState;
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::{info, warn};
+use std::time::Instant;
+
+/// Provider name recorded for operations that run locally.
+const LOCAL_PROVIDER: &str = "local";
+
+// ============================================
+// STATUS
+// ============================================
+
+/// `healthy` while at least one AI provider is available with a closed
+/// circuit, `degraded` otherwise (only the local fallbacks are left).
+pub async fn health_check(state: &SharedState, version: &str) -> HealthResponse {
+    let mut state = state.lock().await;
+    let providers = state.provider_statuses();
+    let healthy = providers
+        .iter()
+        .any(|p| p.name != LOCAL_PROVIDER && p.enabled && p.available && p.health.circuit == CircuitState::Closed);
+    HealthResponse {
+        status: if healthy { "healthy" } else { "degraded" }.to_string(),
+        version: version.to_string(),
+        providers,
+        uptime_seconds: state.uptime_seconds(),
+    }
+}
+
+pub async fn providers_status(state: &SharedState) -> Vec<ProviderStatus> {
+    state.lock().await.provider_statuses()
+}
+
+pub async fn get_ollama_models(state: &SharedState) -> Result<Vec<AiModel>> {
+    let ai = state.lock().await.ai_provider();
+    ai.get_ollama_models().await
+}
+
+// ============================================
+// RESTORATION
+// ============================================
+
+/// Restore a photo as `options` allow (see `presets` for named sets).
+pub async fn restore_image(
+    state: &SharedState,
+    image_base64: String,
+    mime_type: String,
+    options: &RestorationOptions,
+) -> Result<RestorationResult> {
+    restore_image_observed(state, image_base64, mime_type, options, &()).await
+}
+
+/// `restore_image`, reporting the EXIF fix and restore stages to `observer`.
+pub async fn restore_image_observed(
+    state: &SharedState,
+    image_base64: String,
+    mime_type: String,
+    options: &RestorationOptions,
+    observer: &dyn StageObserver,
+) -> Result<RestorationResult> {
+    // Apply EXIF orientation correction before sending to AI
+    observer.running(Stage::ExifFix, None);
+    #[cfg(feature = "image-processing")]
+    let image_base64 = crate::imaging::apply_exif_rotation(&image_base64, &mime_type).unwrap_or(image_base64);
+    observer.completed(Stage::ExifFix, None);
+
+    observer.running(Stage::Restore, None);
+    let recorder = Recorder::start(OperationType::Restoration, &[(&image_base64, &mime_type)]);
+    let image = image_base64.as_str();
+    let mime = mime_type.as_str();
+    let outcome = run_with_failover(state, Capability::Restore, |backend, ctx| async move {
+        backend.restore(&ctx, image, mime, options).await
+    })
+    .await
+    .map(|outcome| {
+        let mut result = outcome.value;
+        result.attempts = outcome.attempts;
+        result.options = Some(*options);
+        result
+    });
+
+    recorder
+        .finish(state, &outcome, |entry, result| {
+            entry.provider = result.provider_used.clone();
+            entry.result_preview = Some(preview(&result.restored_image));
+            entry.result = result_without_images(result, &["original_image", "restored_image"]);
+            entry.attempts = result.attempts.clone();
+            vec![(result.restored_image.as_str(), mime)]
+        })
+        .await;
+
+    match &outcome {
+        Ok(result) => observer.completed(Stage::Restore, Some(format!("provider: {}", result.provider_used))),
+        Err(e) => observer.failed(Stage::Restore, e),
+    }
+    outcome
+}
+
+// ============================================
+// RESTORATION PRESETS
+// ============================================
+
+pub async fn list_presets(state: &SharedState) -> Vec<RestorationPreset> {
+    state.lock().await.presets.list()
+}
+
+/// Add or replace a user preset; built-in names are rejected.
+pub async fn save_preset(state: &SharedState, preset: RestorationPreset) -> Result<()> {
+    info!("Saving restoration preset '{}'", preset.name);
+    state.lock().await.presets.save(preset)
+}
+
+/// Delete a user preset; `NotFound` when there is none by that name.
+pub async fn delete_preset(state: &SharedState, name: &str) -> Result<()> {
+    if !state.lock().await.presets.delete(name)? {
+        return Err(TissaiaError::NotFound(format!("Restoration preset '{}' not found", name)).into());
+    }
+    info!("Deleted restoration preset '{}'", name);
+    Ok(())
+}
+
+/// Options for a request naming a `preset`, explicit `options`, or both
+/// (options win).
+pub async fn restoration_options(
+    state: &SharedState,
+    preset: Option<&str>,
+    options: Option<RestorationOptions>,
+) -> Result<RestorationOptions> {
+    state.lock().await.presets.resolve(preset, options)
+}
+
+/// Prompt templates with their available and active versions.
+pub async fn list_prompts(state: &SharedState) -> Vec<PromptInfo> {
+    state.lock().await.prompts.list()
+}
+
+// ============================================
+// PHOTO SEPARATION
+// ============================================
+
+pub async fn detect_photos(
+    state: &SharedState,
+    image_base64: &str,
+    mime_type: &str,
+) -> Result<DetectionResult> {
+    info!("=== DETECT_PHOTOS START ===");
+    info!("Image size: {} bytes, MIME type: {}", image_base64.len(), mime_type);
+
+    let recorder = Recorder::start(OperationType::Detection, &[(image_base64, mime_type)]);
+    let outcome = detect_with_failover(state, image_base64, mime_type).await;
+    recorder.finish(state, &outcome, record_detection).await;
+
+    let result = outcome?;
+    info!("=== DETECT_PHOTOS END === (found {} photos)", result.photo_count);
+    Ok(result)
+}
+
+/// Detect photos with automatic retry: runs detection, then verification.
+/// If verification finds missing photos, merges the verifier's suggested
+/// boxes into the result.
+pub async fn detect_photos_with_retry(
+    state: &SharedState,
+    image_base64: &str,
+    mime_type: &str,
+) -> Result<DetectionResult> {
+    detect_photos_with_retry_observed(state, image_base64, mime_type, &())
+        .await
+        .map(|(result, _)| result)
+}
+
+/// `detect_photos_with_retry`, reporting the detect and verify stages to
+/// `observer`. Also returns the verification the merge was based on.
+pub async fn detect_photos_with_retry_observed(
+    state: &SharedState,
+    image_base64: &str,
+    mime_type: &str,
+    observer: &dyn StageObserver,
+) -> Result<(DetectionResult, Option<VerificationResult>)> {
+    info!("=== DETECT_PHOTOS_WITH_RETRY START ===");
+
+    let recorder = Recorder::start(OperationType::Detection, &[(image_base64, mime_type)]);
+    let outcome = detect_and_verify(state, image_base64, mime_type, observer).await;
+    recorder
+        .finish(state, &outcome, |entry, (result, verification)| {
+            entry.verification = verification.clone();
+            record_detection(entry, result)
+        })
+        .await;
+
+    let (result, verification) = outcome?;
+    info!("=== DETECT_PHOTOS_WITH_RETRY END === (found {} photos)", result.photo_count);
+    Ok((result, verification))
+}
+
+async fn detect_and_verify(
+    state: &SharedState,
+    image_base64: &str,
+    mime_type: &str,
+    observer: &dyn StageObserver,
+) -> Result<(DetectionResult, Option<VerificationResult>)> {
+    let verification_enabled = state.lock().await.settings.verification_enabled;
+
+    // Step 1: Initial detection
+    observer.running(Stage::Detect, None);
+    let mut result = match detect_with_failover(state, image_base64, mime_type).await {
+        Ok(result) => result,
+        Err(e) => {
+            observer.failed(Stage::Detect, &e);
+            return Err(e);
+        }
+    };
+
+    info!("Initial detection found {} photos", result.photo_count);
+    observer.completed_with(
+        Stage::Detect,
+        Some(format!("{} photos", result.photo_count)),
+        serde_json::to_value(&result).ok(),
+    );
+
+    // Step 2: Verify if enabled
+    if !verification_enabled {
+        info!("Verification disabled, returning initial result");
+        observer.skipped(Stage::Verify, "Verification is disabled in settings");
+        return Ok((result, None));
+    }
+
+    observer.running(Stage::Verify, None);
+    let verification = run_verification(state, VerificationRequest::Detection {
+        image_base64,
+        mime_type,
+        bounding_boxes: &result.bounding_boxes,
+    })
+    .await
+    .map(|(verification, _)| verification);
+
+    let verification = match verification {
+        Ok(v) => v,
+        Err(e) => {
+            info!("Verification failed ({}), returning initial result", e);
+            observer.failed(Stage::Verify, &e);
+            return Ok((result, None));
+        }
+    };
+
+    info!("Verification status: {:?}, missing boxes: {}", verification.status, verification.missing_boxes.len());
+    observer.completed_with(
+        Stage::Verify,
+        Some(format!("{:?}", verification.status)),
+        serde_json::to_value(&verification).ok(),
+    );
+
+    // Step 3: If completeness check failed and we have missing boxes, merge them
+    let completeness_failed = verification.checks.iter()
+        .any(|c| c.name == "completeness" && !c.passed);
+
+    if completeness_failed && !verification.missing_boxes.is_empty() {
+        info!("Completeness check failed — merging {} missing boxes from verifier", verification.missing_boxes.len());
+        observer.running(Stage::Merge, None);
+
+        let first_merged = result.bounding_boxes.len();
+        for (i, missing) in verification.missing_boxes.iter().enumerate() {
+            // Re-label the missing box
+            let mut merged_box = missing.clone();
+            merged_box.label = Some(format!("photo {}", result.bounding_boxes.len() + 1));
+            // Lower confidence since it came from verifier fallback
+            merged_box.confidence = merged_box.confidence.min(0.80);
+
+            info!("  Merging missing box {}: x={}, y={}, w={}, h={} (conf: {:.2})",
+                i + 1, merged_box.x, merged_box.y, merged_box.width, merged_box.height, merged_box.confidence);
+
+            result.bounding_boxes.push(merged_box);
+        }
+        snap_edges(state, image_base64, &mut result, first_merged).await;
+        let kept = resolve_conflicts(&mut result);
+        let merged_boxes: Vec<&BoundingBox> = result.bounding_boxes.iter().zip(&kept)
+            .filter(|(_, &index)| index >= first_merged)
+            .map(|(b, _)| b)
+            .collect();
+
+        result.photo_count = result.bounding_boxes.len();
+        info!("After merge: {} total photos", result.photo_count);
+        observer.completed_with(
+            Stage::Merge,
+            Some(format!("{} boxes merged, {} photos", merged_boxes.len(), result.photo_count)),
+            Some(serde_json::json!({ "merged_boxes": merged_boxes, "photo_count": result.photo_count })),
+        );
+    } else {
+        observer.skipped(Stage::Merge, "No missing photos to merge");
+    }
+
+    Ok((result, Some(verification)))
+}
+
+async fn detect_with_failover(
+    state: &SharedState,
+    image_base64: &str,
+    mime_type: &str,
+) -> Result<DetectionResult> {
+    let outcome = run_with_failover(state, Capability::Detection, |backend, ctx| async move {
+        backend.detect(&ctx, image_base64, mime_type).await
+    })
+    .await?;
+    info!("Detection backend: {}", outcome.provider);
+
+    let mut result = outcome.value;
+    result.attempts = outcome.attempts;
+    snap_edges(state, image_base64, &mut result, 0).await;
+    resolve_conflicts(&mut result);
+    Ok(result)
+}
+
+/// Snap boxes from `first` on to the photo edges in the scan (`refine`).
+/// Best effort: when snapping is off or the scan cannot be read here, the
+/// detected boxes are kept.
+async fn snap_edges(state: &SharedState, image_base64: &str, result: &mut DetectionResult, first: usize) {
+    if !state.lock().await.settings.edge_snapping {
+        return;
+    }
+    // Decodes and scans the full image; keep it off the async workers
+    let (image_base64, mut snapped) = (image_base64.to_string(), result.clone());
+    let refined = tokio::task::spawn_blocking(move || {
+        refine::refine_detection(&image_base64, &mut snapped, first).map(|()| snapped)
+    })
+    .await;
+    match refined {
+        Ok(Ok(snapped)) => *result = snapped,
+        Ok(Err(e)) => info!("Edge snapping skipped: {}", e),
+        Err(e) => warn!("Edge snapping task failed: {}", e),
+    }
+}
+
+/// Drop duplicate, group and contained boxes and separate overlaps
+/// (`boxes::resolve`). Adjustments follow their boxes; returns the former
+/// index of every box kept.
+fn resolve_conflicts(result: &mut DetectionResult) -> Vec<usize> {
+    let changes = boxes::resolve(&mut result.bounding_boxes);
+    let dropped: Vec<usize> = changes.iter().filter_map(BoxChange::dropped).collect();
+    let kept: Vec<usize> = (0..result.bounding_boxes.len() + dropped.len())
+        .filter(|i| !dropped.contains(i))
+        .collect();
+    result.adjustments.retain(|a| !dropped.contains(&a.index));
+    for adjustment in &mut result.adjustments {
+        adjustment.index = kept.iter().position(|&i| i == adjustment.index).unwrap_or(adjustment.index);
+    }
+    result.photo_count = result.bounding_boxes.len();
+    if !changes.is_empty() {
+        info!("Box conflicts resolved: {} changes, {} photos left", changes.len(), result.photo_count);
+    }
+    kept
+}
+
+fn record_detection<'o>(entry: &mut HistoryEntry, result: &'o DetectionResult) -> Vec<(&'o str, &'o str)> {
+    entry.provider = result.provider_used.clone();
+    entry.result_preview = Some(format!("{} photos", result.photo_count));
+    entry.result = result_without_images(result, &[]);
+    entry.attempts = result.attempts.clone();
+    Vec::new()
+}
+
+/// Apply generative outpainting to fill non-rectangular photo edges.
+/// Takes a cropped photo region and its polygon contour,
+/// returns a clean rectangular image with outpainted edges.
+pub async fn outpaint_photo(
+    state: &SharedState,
+    cropped_base64: String,
+    mime_type: &str,
+    contour: &[Point2D],
+    bbox_width: u32,
+    bbox_height: u32,
+) -> Result<String> {
+    info!("=== OUTPAINT_PHOTO START ===");
+
+    // A contour with fewer than 3 points encloses nothing: the crop is kept
+    // as is, and recorded as a local no-op.
+    let skipped = contour.len() < 3;
+    let recorder = Recorder::start(OperationType::Outpaint, &[(&cropped_base64, mime_type)]);
+    let outcome = if skipped {
+        info!("Contour has < 3 points, returning original image");
+        Ok(cropped_base64.clone())
+    } else {
+        outpaint_with_google(state, &cropped_base64, mime_type, contour, bbox_width, bbox_height).await
+    };
+    recorder
+        .provider(if skipped { LOCAL_PROVIDER } else { "google" })
+        .finish(state, &outcome, |entry, image| {
+            entry.result_preview = Some(preview(image));
+            entry.result = Some(serde_json::json!({ "outpainted": !skipped, "contour_points": contour.len() }));
+            vec![(image.as_str(), mime_type)]
+        })
+        .await;
+
+    let result = outcome?;
+    info!("=== OUTPAINT_PHOTO END ===");
+    Ok(result)
+}
+
+async fn outpaint_with_google(
+    state: &SharedState,
+    cropped_base64: &str,
+    mime_type: &str,
+    contour: &[Point2D],
+    bbox_width: u32,
+    bbox_height: u32,
+) -> Result<String> {
+    let (api_key, ai) = {
+        let state_guard = state.lock().await;
+        let key = state_guard.get_api_key("google")
+            .ok_or_else(|| TissaiaError::NoProvider("Google API key required for outpainting".to_string()))?
+            .clone();
+        (key, state_guard.ai_provider())
+    };
+
+    ai.outpaint_to_rectangle(&api_key, cropped_base64, mime_type, contour, bbox_width, bbox_height)
+        .await
+}
+
+// ============================================
+// LOCAL IMAGE OPERATIONS
+// ============================================
+
+pub async fn crop_photos(
+    state: &SharedState,
+    image_base64: &str,
+    mime_type: &str,
+    bounding_boxes: &[BoundingBox],
+    original_filename: String,
+) -> Result<CropResult> {
+    let recorder = Recorder::start(OperationType::Crop, &[(image_base64, mime_type)]);
+    let outcome = imaging::crop_photos(image_base64, mime_type, bounding_boxes, original_filename);
+    recorder
+        .provider(LOCAL_PROVIDER)
+        .finish(state, &outcome, |entry, result| {
+            entry.result_preview = Some(format!("{} photos", result.photos.len()));
+            let mut value = result_without_images(result, &[]);
+            if let Some(photos) = value.as_mut().and_then(|v| v["photos"].as_array_mut()) {
+                for photo in photos.iter_mut().filter_map(|p| p.as_object_mut()) {
+                    photo.remove("image_base64");
+                }
+            }
+            entry.result = value;
+            result
+                .photos
+                .iter()
+                .map(|p| (p.image_base64.as_str(), p.mime_type.as_str()))
+                .collect()
+        })
+        .await;
+    outcome
+}
+
+/// The local operations take the image as base64 or raw upload bytes
+/// (`ImageData`); both are recorded in history the same way.
+pub async fn rotate_image<'a>(
+    state: &SharedState,
+    image: impl Into<ImageData<'a>>,
+    mime_type: &str,
+    degrees: i32,
+) -> Result<String> {
+    let image = image.into();
+    let recorder = Recorder::start_image(OperationType::Rotate, image, mime_type);
+    let outcome = imaging::rotate_image(image, mime_type, degrees);
+    recorder
+        .provider(LOCAL_PROVIDER)
+        .finish(state, &outcome, image_output(mime_type, serde_json::json!({ "degrees": degrees })))
+        .await;
+    outcome
+}
+
+pub async fn upscale_image<'a>(
+    state: &SharedState,
+    image: impl Into<ImageData<'a>>,
+    mime_type: &str,
+    scale_factor: Option<f64>,
+) -> Result<String> {
+    let image = image.into();
+    let recorder = Recorder::start_image(OperationType::Upscale, image, mime_type);
+    let outcome = imaging::upscale_image(image, mime_type, scale_factor);
+    recorder
+        .provider(LOCAL_PROVIDER)
+        .finish(state, &outcome, image_output(mime_type, serde_json::json!({ "scale_factor": scale_factor })))
+        .await;
+    outcome
+}
+
+pub async fn apply_local_filters<'a>(
+    state: &SharedState,
+    image: impl Into<ImageData<'a>>,
+    mime_type: &str,
+    filters: Option<Vec<String>>,
+) -> Result<String> {
+    let image = image.into();
+    let recorder = Recorder::start_image(OperationType::Filters, image, mime_type);
+    let params = serde_json::json!({ "filters": filters });
+    let outcome = imaging::apply_local_filters(image, mime_type, filters);
+    recorder
+        .provider(LOCAL_PROVIDER)
+        .finish(state, &outcome, image_output(mime_type, params))
+        .await;
+    outcome
+}
+
+pub async fn extract_metadata<'a>(
+    state: &SharedState,
+    image: impl Into<ImageData<'a>>,
+    mime_type: &str,
+) -> Result<serde_json::Value> {
+    let image = image.into();
+    let recorder = Recorder::start_image(OperationType::Metadata, image, mime_type);
+    let outcome = imaging::extract_metadata(image, mime_type);
+    recorder
+        .provider(LOCAL_PROVIDER)
+        .finish(state, &outcome, |entry, metadata| {
+            entry.result = Some(metadata.clone());
+            Vec::new()
+        })
+        .await;
+    outcome
+}
+
+pub async fn save_image<'a>(state: &SharedState, image: impl Into<ImageData<'a>>, file_path: &str) -> Result<String> {
+    let recorder = Recorder::start(OperationType::Save, &[]).preview(file_path.to_string());
+    let outcome = imaging::save_image(image.into(), file_path);
+    recorder
+        .provider(LOCAL_PROVIDER)
+        .finish(state, &outcome, |entry, path| {
+            entry.result_preview = Some(path.clone());
+            entry.result = Some(serde_json::json!({ "file_path": path }));
+            Vec::new()
+        })
+        .await;
+    outcome
+}
+
+/// Completion for local image → image operations: keeps the output image
+/// and the parameters it was produced with.
+fn image_output<'o>(
+    mime_type: &'o str,
+    params: serde_json::Value,
+) -> impl FnOnce(&mut HistoryEntry, &'o String) -> Vec<(&'o str, &'o str)> {
+    move |entry, image| {
+        entry.result_preview = Some(preview(image));
+        entry.result = Some(params);
+        vec![(image.as_str(), mime_type)]
+    }
+}
+
+// ============================================
+// VERIFICATION AGENT
+// ============================================
+
+/// Run a verification request through the verification failover chain,
+/// refusing when verification is switched off in settings.
+async fn run_verification(
+    state: &SharedState,
+    request: VerificationRequest<'_>,
+) -> Result<(VerificationResult, &'static str)> {
+    if !state.lock().await.settings.verification_enabled {
+        return Err(TissaiaError::FeatureDisabled("Verification is disabled in settings".to_string()).into());
+    }
+
+    let outcome = run_with_failover(state, Capability::Verification, |backend, ctx| async move {
+        backend.verify(&ctx, request).await
+    })
+    .await?;
+
+    let mut result = outcome.value;
+    result.attempts = outcome.attempts;
+    Ok((result, outcome.provider))
+}
+
+/// Run `request` and record it in history as `label_<result id>`.
+async fn verify_and_record(
+    state: &SharedState,
+    label: String,
+    images: &[(&str, &str)],
+    request: VerificationRequest<'_>,
+) -> Result<VerificationResult> {
+    let recorder = Recorder::start(OperationType::Verification, images);
+    let outcome = run_verification(state, request).await;
+    recorder
+        .finish(state, &outcome, |entry, (result, provider)| {
+            entry.input_preview = format!("{}_{}", label, result.id);
+            entry.provider = provider.to_string();
+            entry.verification = Some(result.clone());
+            entry.attempts = result.attempts.clone();
+            Vec::new()
+        })
+        .await;
+    outcome.map(|(result, _)| result)
+}
+
+pub async fn verify_restoration(
+    state: &SharedState,
+    original_base64: &str,
+    restored_base64: &str,
+    mime_type: &str,
+) -> Result<VerificationResult> {
+    info!("=== VERIFY_RESTORATION START ===");
+
+    let result = verify_and_record(
+        state,
+        "verify_restoration".to_string(),
+        &[(original_base64, mime_type), (restored_base64, mime_type)],
+        VerificationRequest::Restoration { original_base64, restored_base64, mime_type },
+    )
+    .await?;
+
+    info!("=== VERIFY_RESTORATION END === (status: {:?}, confidence: {})", result.status, result.confidence);
+    Ok(result)
+}
+
+pub async fn verify_detection(
+    state: &SharedState,
+    image_base64: &str,
+    mime_type: &str,
+    bounding_boxes: &[BoundingBox],
+) -> Result<VerificationResult> {
+    info!("=== VERIFY_DETECTION START ===");
+
+    let result = verify_and_record(
+        state,
+        "verify_detection".to_string(),
+        &[(image_base64, mime_type)],
+        VerificationRequest::Detection { image_base64, mime_type, bounding_boxes },
+    )
+    .await?;
+
+    info!("=== VERIFY_DETECTION END === (status: {:?})", result.status);
+    Ok(result)
+}
+
+pub async fn verify_crop(
+    state: &SharedState,
+    cropped_base64: &str,
+    mime_type: &str,
+    crop_index: usize,
+) -> Result<VerificationResult> {
+    info!("=== VERIFY_CROP {} START ===", crop_index);
+
+    let result = verify_and_record(
+        state,
+        format!("verify_crop_{}", crop_index),
+        &[(cropped_base64, mime_type)],
+        VerificationRequest::Crop { cropped_base64, mime_type, crop_index },
+    )
+    .await?;
+
+    info!("=== VERIFY_CROP {} END === (status: {:?})", crop_index, result.status);
+    Ok(result)
+}
+
+// ============================================
+// HISTORY HELPERS
+// ============================================
+
+/// Collects what a history entry needs while an operation runs; `finish`
+/// writes the entry whether the operation succeeded or failed.
+struct Recorder<'a> {
+    operation: OperationType,
+    input_preview: String,
+    inputs: Vec<(ImageData<'a>, &'a str)>,
+    provider: &'static str,
+    start: Instant,
+}
+
+impl<'a> Recorder<'a> {
+    /// `inputs` are `(base64, mime)` pairs.
+    fn start(operation: OperationType, inputs: &[(&'a str, &'a str)]) -> Self {
+        Self {
+            operation,
+            input_preview: inputs.first().map(|(data, _)| preview(data)).unwrap_or_default(),
+            inputs: inputs.iter().map(|&(data, mime)| (ImageData::Base64(data), mime)).collect(),
+            provider: "none",
+            start: Instant::now(),
+        }
+    }
+
+    fn start_image(operation: OperationType, image: ImageData<'a>, mime_type: &'a str) -> Self {
+        let input_preview = match image {
+            ImageData::Base64(data) => preview(data),
+            // 75 bytes are the first 100 base64 characters
+            ImageData::Bytes(bytes) => STANDARD.encode(&bytes[..bytes.len().min(75)]),
+        };
+        Self {
+            operation,
+            input_preview,
+            inputs: vec![(image, mime_type)],
+            provider: "none",
+            start: Instant::now(),
+        }
+    }
+
+    fn preview(mut self, input_preview: String) -> Self {
+        self.input_preview = input_preview;
+        self
+    }
+
+    /// Provider recorded when the operation does not report one itself.
+    fn provider(mut self, provider: &'static str) -> Self {
+        self.provider = provider;
+        self
+    }
+
+    /// On success `complete` fills in the result and returns the output
+    /// images as `(base64, mime)`; on failure the error text and any failover
+    /// attempts are recorded, attributed to the last provider tried.
+    ///
+    /// Images are decoded, hashed and stored on the blocking pool; the state
+    /// lock is only held to read settings and take the history handle.
+    async fn finish<'o, T>(
+        self,
+        state: &SharedState,
+        outcome: &'o Result<T>,
+        complete: impl FnOnce(&mut HistoryEntry, &'o T) -> Vec<(&'o str, &'o str)>,
+    ) {
+        let (settings, history) = {
+            let state_guard = state.lock().await;
+            (state_guard.settings.clone(), state_guard.history.clone())
+        };
+        let mut entry = HistoryEntry::new(self.operation, self.input_preview, self.provider);
+        entry.processing_time_ms = self.start.elapsed().as_millis() as u64;
+        entry.settings = Some(settings);
+
+        let mut images: Vec<PendingBlob> =
+            self.inputs.iter().map(|&(image, mime)| PendingBlob::new(image, mime)).collect();
+        let input_count = images.len();
+        match outcome {
+            Ok(value) => {
+                entry.success = true;
+                let outputs = complete(&mut entry, value);
+                images.extend(
+                    outputs.into_iter().map(|(data, mime)| PendingBlob::new(ImageData::Base64(data), mime)),
+                );
+            }
+            Err(e) => {
+                entry.error_message = Some(e.to_string());
+                entry.attempts = failed_attempts(e).to_vec();
+                if let Some(last) = entry.attempts.last() {
+                    entry.provider = last.provider.clone();
+                }
+                warn!("{:?} failed via {}: {}", entry.operation, entry.provider, e);
+            }
+        }
+
+        let stored = tokio::task::spawn_blocking(move || {
+            let blobs: Vec<PreparedBlob> = images.into_iter().map(PendingBlob::prepare).collect();
+            entry.inputs = blobs[..input_count].iter().map(|b| b.blob.clone()).collect();
+            entry.outputs = blobs[input_count..].iter().map(|b| b.blob.clone()).collect();
+            history.add_prepared(entry, blobs)
+        })
+        .await;
+        match stored {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to persist history entry: {:#}", e),
+            Err(e) => warn!("Failed to store history entry: {}", e),
+        }
+    }
+}
+
+/// An operation image copied out of the request so it can be stored on the
+/// blocking pool.
+enum PendingBlob {
+    Base64(String, String),
+    Bytes(Vec<u8>, String),
+}
+
+impl PendingBlob {
+    fn new(image: ImageData, mime_type: &str) -> Self {
+        match image {
+            ImageData::Base64(data) => Self::Base64(data.to_string(), mime_type.to_string()),
+            ImageData::Bytes(bytes) => Self::Bytes(bytes.to_vec(), mime_type.to_string()),
+        }
+    }
+
+    fn prepare(self) -> PreparedBlob {
+        match self {
+            Self::Base64(data, mime_type) => PreparedBlob::from_base64(&data, &mime_type),
+            Self::Bytes(bytes, mime_type) => PreparedBlob::new(bytes, &mime_type),
+        }
+    }
+}
+
+/// First 100 characters, kept on entries for list views.
+fn preview(data: &str) -> String {
+    data.chars().take(100).collect()
+}
+
+/// Serialize `result` for history, dropping image payloads already kept as blobs.
+fn result_without_images<T: serde::Serialize>(result: &T, image_fields: &[&str]) -> Option<serde_json::Value> {
+    let mut value = serde_json::to_value(result).ok()?;
+    if let Some(object) = value.as_object_mut() {
+        for field in image_fields {
+            object.remove(*field);
+        }
+    }
+    Some(value)
+}
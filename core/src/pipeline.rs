@@ -0,0 +1,454 @@
+// core/src/pipeline.rs
+//! End-to-end "scan → restored photos" pipeline.
+//! One call runs EXIF fix → detect (+ verify / merge) → crop, then for every
+//! photo outpaint → crop verification → restore → restoration verification
+//! → filters → format conversion, keeping all intermediates in memory.
+//! Each stage goes through `operations`, so history and provider failover
+//! behave exactly as for the individual endpoints. A failing per-photo stage
+//! is reported, not fatal: the photo keeps its last good image.
+
+use crate::error::TissaiaError;
+use crate::imaging;
+use crate::models::{BoundingBox, CroppedPhoto, DetectionResult, RestorationOptions, VerificationResult};
+use crate::operations;
+use crate::progress::{Stage, StageEvent, StageObserver, StageStatus};
+use crate::state::SharedState;
+use anyhow::Result;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Formats `PipelineOptions::output_format` accepts.
+pub const OUTPUT_FORMATS: &[&str] = &["image/jpeg", "image/png", "image/webp"];
+
+// ============================================
+// OPTIONS & RESULTS
+// ============================================
+
+/// Which stages run and how; every field is optional in JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineOptions {
+    /// Verify the detection, every crop and every restoration. Also needs
+    /// verification enabled in settings.
+    pub verify: bool,
+    /// Outpaint photos whose box has `needs_outpaint`.
+    pub outpaint: bool,
+    pub restore: bool,
+    /// Named restoration preset (see `presets`).
+    pub preset: Option<String>,
+    /// Restoration options; override `preset` when both are given.
+    pub restoration: Option<RestorationOptions>,
+    /// Local filters applied last (see `apply_local_filters`); empty = none.
+    pub filters: Vec<String>,
+    /// MIME type of the returned photos (`OUTPUT_FORMATS`); `None` keeps the scan's.
+    pub output_format: Option<String>,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        Self {
+            verify: true,
+            outpaint: true,
+            restore: true,
+            preset: None,
+            restoration: None,
+            filters: Vec::new(),
+            output_format: None,
+        }
+    }
+}
+
+impl PipelineOptions {
+    /// Stages a run with these options reports, in order.
+    pub fn stages(&self) -> Vec<Stage> {
+        let mut stages = vec![Stage::ExifFix, Stage::Detect];
+        if self.verify {
+            stages.extend([Stage::Verify, Stage::Merge]);
+        }
+        stages.push(Stage::Crop);
+        if self.outpaint {
+            stages.push(Stage::Outpaint);
+        }
+        if self.verify {
+            stages.push(Stage::VerifyCrop);
+        }
+        if self.restore {
+            stages.push(Stage::Restore);
+            if self.verify {
+                stages.push(Stage::VerifyRestoration);
+            }
+        }
+        if !self.filters.is_empty() {
+            stages.push(Stage::Filters);
+        }
+        if self.output_format.is_some() {
+            stages.push(Stage::Convert);
+        }
+        stages
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(format) = &self.output_format {
+            if !OUTPUT_FORMATS.contains(&format.as_str()) {
+                return Err(TissaiaError::Validation(format!(
+                    "Unsupported output_format '{}' (expected one of {})",
+                    format,
+                    OUTPUT_FORMATS.join(", ")
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineRequest {
+    pub image_base64: String,
+    pub mime_type: String,
+    #[serde(default)]
+    pub original_filename: Option<String>,
+    #[serde(default)]
+    pub options: PipelineOptions,
+}
+
+/// Outcome of one stage, for the scan or for a single photo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageReport {
+    pub stage: Stage,
+    pub status: StageStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelinePhoto {
+    pub index: usize,
+    /// `{scan stem}_{index + 1}.{ext}`
+    pub filename: String,
+    /// Final image: restored / filtered / converted as far as the stages got.
+    pub image_base64: String,
+    pub mime_type: String,
+    pub source_box: BoundingBox,
+    pub restored: bool,
+    /// Provider that restored the photo.
+    pub provider: Option<String>,
+    pub improvements: Vec<String>,
+    pub crop_verification: Option<VerificationResult>,
+    pub restoration_verification: Option<VerificationResult>,
+    pub stages: Vec<StageReport>,
+}
+
+impl PipelinePhoto {
+    /// Whether every stage that ran for this photo succeeded.
+    pub fn succeeded(&self) -> bool {
+        self.stages.iter().all(|s| s.status != StageStatus::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineResult {
+    pub id: String,
+    pub original_filename: String,
+    /// Detection after the verifier's missing boxes were merged.
+    pub detection: DetectionResult,
+    pub detection_verification: Option<VerificationResult>,
+    /// Scan-level stages (EXIF fix, detection, verification, merge, crop).
+    pub stages: Vec<StageReport>,
+    pub photos: Vec<PipelinePhoto>,
+    pub processing_time_ms: u64,
+}
+
+// ============================================
+// PIPELINE
+// ============================================
+
+pub async fn run_pipeline(state: &SharedState, request: PipelineRequest) -> Result<PipelineResult> {
+    run_pipeline_observed(state, request, &()).await
+}
+
+/// `run_pipeline`, reporting every stage transition to `observer`
+/// (per-photo events are prefixed with "photo N/M").
+pub async fn run_pipeline_observed(
+    state: &SharedState,
+    request: PipelineRequest,
+    observer: &dyn StageObserver,
+) -> Result<PipelineResult> {
+    let PipelineRequest { image_base64, mime_type, original_filename, mut options } = request;
+    options.validate()?;
+    // Resolved up front so an unknown preset fails before any provider call
+    options.restoration =
+        Some(operations::restoration_options(state, options.preset.as_deref(), options.restoration).await?);
+
+    info!("=== PIPELINE START === (stages: {:?})", options.stages());
+    let start = Instant::now();
+    let original_filename =
+        original_filename.unwrap_or_else(|| format!("scan.{}", imaging::extension_for_mime(&mime_type)));
+    let scan = Reporter::new(observer, None);
+
+    scan.running(Stage::ExifFix, None);
+    #[cfg(feature = "image-processing")]
+    let image_base64 = imaging::apply_exif_rotation(&image_base64, &mime_type).unwrap_or(image_base64);
+    scan.completed(Stage::ExifFix, None);
+
+    let (detection, detection_verification) = if options.verify {
+        operations::detect_photos_with_retry_observed(state, &image_base64, &mime_type, &scan).await?
+    } else {
+        scan.running(Stage::Detect, None);
+        let detection = operations::detect_photos(state, &image_base64, &mime_type)
+            .await
+            .inspect_err(|e| scan.failed(Stage::Detect, e))?;
+        scan.completed_with(
+            Stage::Detect,
+            Some(format!("{} photos", detection.photo_count)),
+            serde_json::to_value(&detection).ok(),
+        );
+        (detection, None)
+    };
+
+    scan.running(Stage::Crop, None);
+    let crop = operations::crop_photos(
+        state,
+        &image_base64,
+        &mime_type,
+        &detection.bounding_boxes,
+        original_filename.clone(),
+    )
+    .await
+    .inspect_err(|e| scan.failed(Stage::Crop, e))?;
+    scan.completed(Stage::Crop, Some(format!("{} photos", crop.photos.len())));
+    drop(image_base64);
+
+    // Verification switched off in settings overrides the request
+    let verify = options.verify && state.lock().await.settings.verification_enabled;
+    let stem = original_filename
+        .rsplit_once('.')
+        .map_or(original_filename.as_str(), |(stem, _)| stem)
+        .to_string();
+    let total = crop.photos.len();
+    let mut photos = Vec::with_capacity(total);
+    for photo in crop.photos {
+        photos.push(process_photo(state, &options, verify, photo, total, &stem, observer).await);
+    }
+
+    let result = PipelineResult {
+        id: uuid::Uuid::new_v4().to_string(),
+        original_filename,
+        detection,
+        detection_verification,
+        stages: scan.into_reports(),
+        photos,
+        processing_time_ms: start.elapsed().as_millis() as u64,
+    };
+    info!(
+        "=== PIPELINE END === ({} photos, {} fully succeeded, {} ms)",
+        result.photos.len(),
+        result.photos.iter().filter(|p| p.succeeded()).count(),
+        result.processing_time_ms
+    );
+    Ok(result)
+}
+
+async fn process_photo(
+    state: &SharedState,
+    options: &PipelineOptions,
+    verify: bool,
+    photo: CroppedPhoto,
+    total: usize,
+    stem: &str,
+    observer: &dyn StageObserver,
+) -> PipelinePhoto {
+    let CroppedPhoto { index, mut image_base64, mut mime_type, source_box, .. } = photo;
+    let reporter = Reporter::new(observer, Some(format!("photo {}/{}", index + 1, total)));
+    let mut restored = false;
+    let mut provider = None;
+    let mut improvements = Vec::new();
+    let mut crop_verification = None;
+    let mut restoration_verification = None;
+    const VERIFICATION_OFF: &str = "Verification is disabled in settings";
+
+    if options.outpaint {
+        if source_box.needs_outpaint && source_box.contour.len() >= 3 {
+            let outpainted = operations::outpaint_photo(
+                state,
+                image_base64.clone(),
+                &mime_type,
+                &source_box.contour,
+                source_box.width,
+                source_box.height,
+            );
+            if let Some(image) = step(&reporter, Stage::Outpaint, outpainted, |_| None).await {
+                image_base64 = image;
+            }
+        } else {
+            reporter.skipped(Stage::Outpaint, "Photo is rectangular");
+        }
+    }
+
+    if options.verify {
+        if verify {
+            let verification = operations::verify_crop(state, &image_base64, &mime_type, index);
+            crop_verification =
+                step(&reporter, Stage::VerifyCrop, verification, |v| Some(format!("{:?}", v.status))).await;
+        } else {
+            reporter.skipped(Stage::VerifyCrop, VERIFICATION_OFF);
+        }
+    }
+
+    if options.restore {
+        let before = image_base64.clone();
+        let restoration_options = options.restoration.unwrap_or_default();
+        let restoration = operations::restore_image(state, image_base64.clone(), mime_type.clone(), &restoration_options);
+        let restoration =
+            step(&reporter, Stage::Restore, restoration, |r| Some(format!("provider: {}", r.provider_used))).await;
+        match restoration {
+            Some(restoration) => {
+                restored = true;
+                provider = Some(restoration.provider_used);
+                improvements = restoration.improvements;
+                image_base64 = restoration.restored_image;
+
+                if options.verify {
+                    if verify {
+                        let verification =
+                            operations::verify_restoration(state, &before, &image_base64, &mime_type);
+                        restoration_verification = step(&reporter, Stage::VerifyRestoration, verification, |v| {
+                            Some(format!("{:?}", v.status))
+                        })
+                        .await;
+                    } else {
+                        reporter.skipped(Stage::VerifyRestoration, VERIFICATION_OFF);
+                    }
+                }
+            }
+            None if options.verify => reporter.skipped(Stage::VerifyRestoration, "Restoration failed"),
+            None => {}
+        }
+    }
+
+    if !options.filters.is_empty() {
+        let filtered = operations::apply_local_filters(state, &image_base64, &mime_type, Some(options.filters.clone()));
+        if let Some(image) = step(&reporter, Stage::Filters, filtered, |_| None).await {
+            image_base64 = image;
+        }
+    }
+
+    if let Some(format) = &options.output_format {
+        if *format == mime_type {
+            reporter.skipped(Stage::Convert, "Already in the requested format");
+        } else {
+            let converted = async { imaging::convert_image(&image_base64, format) };
+            if let Some(image) = step(&reporter, Stage::Convert, converted, |_| Some(format.clone())).await {
+                image_base64 = image;
+                mime_type = format.clone();
+            }
+        }
+    }
+
+    PipelinePhoto {
+        index,
+        filename: format!("{}_{}.{}", stem, index + 1, imaging::extension_for_mime(&mime_type)),
+        image_base64,
+        mime_type,
+        source_box,
+        restored,
+        provider,
+        improvements,
+        crop_verification,
+        restoration_verification,
+        stages: reporter.into_reports(),
+    }
+}
+
+/// Run one per-photo stage; a failure is reported and yields `None`.
+async fn step<T>(
+    reporter: &Reporter<'_>,
+    stage: Stage,
+    operation: impl Future<Output = Result<T>>,
+    describe: impl FnOnce(&T) -> Option<String>,
+) -> Option<T> {
+    reporter.running(stage, None);
+    match operation.await {
+        Ok(value) => {
+            reporter.completed(stage, describe(&value));
+            Some(value)
+        }
+        Err(e) => {
+            reporter.failed(stage, &e);
+            None
+        }
+    }
+}
+
+// ============================================
+// STAGE REPORTS
+// ============================================
+
+/// Turns stage events into `StageReport`s (with durations) and forwards
+/// them to the caller's observer, labelled with the photo they belong to.
+struct Reporter<'a> {
+    observer: &'a dyn StageObserver,
+    label: Option<String>,
+    started: Mutex<HashMap<Stage, Instant>>,
+    reports: Mutex<Vec<StageReport>>,
+}
+
+impl<'a> Reporter<'a> {
+    fn new(observer: &'a dyn StageObserver, label: Option<String>) -> Self {
+        Self {
+            observer,
+            label,
+            started: Mutex::new(HashMap::new()),
+            reports: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn into_reports(self) -> Vec<StageReport> {
+        self.reports.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl StageObserver for Reporter<'_> {
+    fn on_stage(&self, mut event: StageEvent) {
+        let duration_ms = {
+            let mut started = self.started.lock().unwrap_or_else(|e| e.into_inner());
+            if event.status == StageStatus::Running {
+                started.entry(event.stage).or_insert_with(Instant::now);
+            }
+            started.get(&event.stage).map_or(0, |at| at.elapsed().as_millis() as u64)
+        };
+
+        {
+            let mut reports = self.reports.lock().unwrap_or_else(|e| e.into_inner());
+            let index = match reports.iter().position(|r| r.stage == event.stage) {
+                Some(index) => index,
+                None => {
+                    reports.push(StageReport {
+                        stage: event.stage,
+                        status: StageStatus::Pending,
+                        message: None,
+                        duration_ms: 0,
+                    });
+                    reports.len() - 1
+                }
+            };
+            let report = &mut reports[index];
+            report.status = event.status;
+            report.message = event.message.clone();
+            report.duration_ms = duration_ms;
+        }
+
+        if let Some(label) = &self.label {
+            event.message = Some(match event.message.take() {
+                Some(message) => format!("{}: {}", label, message),
+                None => label.clone(),
+            });
+        }
+        self.observer.on_stage(event);
+    }
+}
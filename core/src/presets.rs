@@ -0,0 +1,184 @@
+// core/src/presets.rs
+//! Named restoration presets.
+//! Three built-in presets ship with the app (`archival-faithful`,
+//! `gentle-cleanup`, `full-colorize`); user presets are kept in one JSON
+//! file and survive restarts. Without a file the store keeps user presets
+//! in memory (tests, read-only deployments).
+//!
+//! File: `TISSAIA_PRESETS_FILE`, default `data/presets.json` in the working directory.
+
+use crate::error::TissaiaError;
+use crate::models::{RestorationOptions, RestorationPreset, StepOption};
+use anyhow::{Context, Result};
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Longest accepted preset name.
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Presets shipped with the app, in display order.
+pub fn builtin_presets() -> Vec<RestorationPreset> {
+    let builtin = |name: &str, description: &str, options: RestorationOptions| RestorationPreset {
+        name: name.to_string(),
+        description: description.to_string(),
+        options,
+        builtin: true,
+    };
+    vec![
+        builtin(
+            "archival-faithful",
+            "Light dust and noise cleanup only; no colorization, retouching, upscaling or inpainting",
+            RestorationOptions {
+                geometry: StepOption::OFF,
+                flash: StepOption::OFF,
+                denoise: StepOption::on(0.3),
+                faces: StepOption::OFF,
+                colorize: StepOption::OFF,
+                upscale: StepOption::OFF,
+                studio_finish: StepOption::OFF,
+            },
+        ),
+        builtin(
+            "gentle-cleanup",
+            "Straighten, remove glare and noise with a light hand; colours and resolution kept",
+            RestorationOptions {
+                geometry: StepOption::on(0.5),
+                flash: StepOption::on(0.5),
+                denoise: StepOption::on(0.5),
+                faces: StepOption::on(0.3),
+                colorize: StepOption::OFF,
+                upscale: StepOption::OFF,
+                studio_finish: StepOption::OFF,
+            },
+        ),
+        builtin("full-colorize", "Every step at full strength, including colorization", RestorationOptions::default()),
+    ]
+}
+
+pub struct PresetStore {
+    path: Option<PathBuf>,
+    /// User presets, sorted by name.
+    presets: Vec<RestorationPreset>,
+}
+
+impl PresetStore {
+    pub fn in_memory() -> Self {
+        Self { path: None, presets: Vec::new() }
+    }
+
+    /// Open the preset file at `path`; a missing file is an empty store.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut presets: Vec<RestorationPreset> = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).with_context(|| format!("parse presets file {:?}", path))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("read presets file {:?}", path)),
+        };
+        presets.retain(|p| !is_builtin(&p.name));
+        presets.sort_by(|a, b| a.name.cmp(&b.name));
+        info!("Preset store at {:?} ({} user presets)", path, presets.len());
+        Ok(Self { path: Some(path), presets })
+    }
+
+    /// Store at `TISSAIA_PRESETS_FILE` (default `data/presets.json`); falls
+    /// back to memory when the file cannot be read.
+    pub fn from_env() -> Self {
+        let path = std::env::var("TISSAIA_PRESETS_FILE").unwrap_or_else(|_| "data/presets.json".to_string());
+        Self::open(&path).unwrap_or_else(|e| {
+            warn!("Presets file {} unavailable ({:#}); keeping presets in memory", path, e);
+            Self::in_memory()
+        })
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Built-in presets first, then user presets by name.
+    pub fn list(&self) -> Vec<RestorationPreset> {
+        builtin_presets().into_iter().chain(self.presets.iter().cloned()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<RestorationPreset> {
+        self.list().into_iter().find(|p| p.name == name)
+    }
+
+    /// Add or replace a user preset.
+    pub fn save(&mut self, mut preset: RestorationPreset) -> Result<()> {
+        preset.name = preset.name.trim().to_string();
+        if preset.name.is_empty() || preset.name.chars().count() > MAX_NAME_LEN {
+            return Err(TissaiaError::Validation(format!(
+                "Preset name must be 1-{} characters",
+                MAX_NAME_LEN
+            ))
+            .into());
+        }
+        if is_builtin(&preset.name) {
+            return Err(TissaiaError::Validation(format!("'{}' is a built-in preset", preset.name)).into());
+        }
+        preset.builtin = false;
+
+        let mut presets = self.presets.clone();
+        presets.retain(|p| p.name != preset.name);
+        presets.push(preset);
+        presets.sort_by(|a, b| a.name.cmp(&b.name));
+        self.write(&presets)?;
+        self.presets = presets;
+        Ok(())
+    }
+
+    /// Remove a user preset; `false` when there is none by that name.
+    pub fn delete(&mut self, name: &str) -> Result<bool> {
+        if is_builtin(name) {
+            return Err(TissaiaError::Validation(format!("'{}' is a built-in preset", name)).into());
+        }
+        let mut presets = self.presets.clone();
+        presets.retain(|p| p.name != name);
+        if presets.len() == self.presets.len() {
+            return Ok(false);
+        }
+        self.write(&presets)?;
+        self.presets = presets;
+        Ok(true)
+    }
+
+    /// Options for a request: explicit `options` win over `preset`; neither
+    /// gives the defaults. Unknown presets are a validation error.
+    pub fn resolve(&self, preset: Option<&str>, options: Option<RestorationOptions>) -> Result<RestorationOptions> {
+        if let Some(options) = options {
+            return Ok(options);
+        }
+        match preset {
+            None => Ok(RestorationOptions::default()),
+            Some(name) => self
+                .get(name)
+                .map(|p| p.options)
+                .ok_or_else(|| TissaiaError::Validation(format!("Unknown restoration preset '{}'", name)).into()),
+        }
+    }
+
+    fn write(&self, presets: &[RestorationPreset]) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).context("create presets dir")?;
+        }
+        // Write-then-rename so a crash never leaves a half-written file
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(presets)?).context("write presets file")?;
+        fs::rename(&tmp, path).context("replace presets file")?;
+        Ok(())
+    }
+}
+
+impl Default for PresetStore {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+fn is_builtin(name: &str) -> bool {
+    builtin_presets().iter().any(|p| p.name == name)
+}
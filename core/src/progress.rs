@@ -0,0 +1,111 @@
+// core/src/progress.rs
+//! Stage progress for multi-step operations
+//! (EXIF fix → detect → verify → merge → crop, then per photo outpaint →
+//! crop verification → restore → restoration verification → filters →
+//! format conversion; folder batches report a single `Batch` stage).
+//! Operations report through a `StageObserver`; plain calls pass `&()`,
+//! the job subsystem records every event on the job, and the streaming
+//! transports (SSE, Tauri events) forward them through a channel.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedSender;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    ExifFix,
+    Detect,
+    Verify,
+    /// Merging boxes the verifier reported as missing.
+    Merge,
+    Crop,
+    Outpaint,
+    VerifyCrop,
+    Restore,
+    VerifyRestoration,
+    /// Local filters (CLAHE, sharpen, denoise).
+    Filters,
+    /// Re-encoding to the requested output format.
+    Convert,
+    /// Folder batch: one progress note per finished scan.
+    Batch,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StageStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Skipped,
+}
+
+impl StageStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, StageStatus::Completed | StageStatus::Failed | StageStatus::Skipped)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StageEvent {
+    pub stage: Stage,
+    pub status: StageStatus,
+    /// Progress note ("2/3 photos") or the error of a failed stage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Intermediate result of a completed stage: the initial
+    /// `DetectionResult`, the `VerificationResult`, the merged boxes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Value>,
+}
+
+/// Receives stage transitions while an operation runs.
+pub trait StageObserver: Send + Sync {
+    fn on_stage(&self, event: StageEvent);
+
+    fn running(&self, stage: Stage, message: Option<String>) {
+        self.on_stage(StageEvent { stage, status: StageStatus::Running, message, payload: None });
+    }
+
+    fn completed(&self, stage: Stage, message: Option<String>) {
+        self.on_stage(StageEvent { stage, status: StageStatus::Completed, message, payload: None });
+    }
+
+    /// `completed`, carrying the stage's intermediate result.
+    fn completed_with(&self, stage: Stage, message: Option<String>, payload: Option<Value>) {
+        self.on_stage(StageEvent { stage, status: StageStatus::Completed, message, payload });
+    }
+
+    fn failed(&self, stage: Stage, error: &anyhow::Error) {
+        self.on_stage(StageEvent {
+            stage,
+            status: StageStatus::Failed,
+            message: Some(error.to_string()),
+            payload: None,
+        });
+    }
+
+    fn skipped(&self, stage: Stage, reason: &str) {
+        self.on_stage(StageEvent {
+            stage,
+            status: StageStatus::Skipped,
+            message: Some(reason.to_string()),
+            payload: None,
+        });
+    }
+}
+
+/// No-op observer for calls nobody tracks.
+impl StageObserver for () {
+    fn on_stage(&self, _event: StageEvent) {}
+}
+
+/// Forwards events to a stream consumer; a closed receiver (client gone)
+/// does not interrupt the operation.
+impl StageObserver for UnboundedSender<StageEvent> {
+    fn on_stage(&self, event: StageEvent) {
+        let _ = self.send(event);
+    }
+}
@@ -0,0 +1,275 @@
+// core/src/prompts.rs
+//! Versioned prompt templates.
+//! Every prompt sent to a provider is a template file `<name>.v<N>.txt`.
+//! The defaults in `core/prompts/` are embedded in the binary; files in the
+//! override directory replace an embedded version or add new ones. The
+//! highest version of each prompt is used unless a version is pinned, so a
+//! new template can be tried next to the old one and an old output
+//! reproduced with the version its result recorded (`prompt_version`).
+//!
+//! Templates use `{variable}` placeholders; `PromptKind::variables` lists
+//! the ones each prompt accepts. Other text, including JSON examples, is
+//! sent as written.
+//!
+//! Dir:  `TISSAIA_PROMPTS_DIR` (optional).
+//! Env:  `TISSAIA_PROMPT_VERSIONS` pins versions, e.g. `detect=1,restore_plan=2`.
+
+use crate::error::TissaiaError;
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptKind {
+    /// Restoration by a provider that returns the restored image.
+    RestoreImage,
+    /// Restoration plan from a text-only provider (`local_restoration`).
+    RestorePlan,
+    Detect,
+    Outpaint,
+    VerifyRestoration,
+    VerifyDetection,
+    VerifyCrop,
+}
+
+impl PromptKind {
+    pub const ALL: [PromptKind; 7] = [
+        PromptKind::RestoreImage,
+        PromptKind::RestorePlan,
+        PromptKind::Detect,
+        PromptKind::Outpaint,
+        PromptKind::VerifyRestoration,
+        PromptKind::VerifyDetection,
+        PromptKind::VerifyCrop,
+    ];
+
+    /// Template file stem.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PromptKind::RestoreImage => "restore_image",
+            PromptKind::RestorePlan => "restore_plan",
+            PromptKind::Detect => "detect",
+            PromptKind::Outpaint => "outpaint",
+            PromptKind::VerifyRestoration => "verify_restoration",
+            PromptKind::VerifyDetection => "verify_detection",
+            PromptKind::VerifyCrop => "verify_crop",
+        }
+    }
+
+    /// Placeholders the template may use.
+    pub fn variables(&self) -> &'static [&'static str] {
+        match self {
+            PromptKind::RestoreImage => &["instructions", "options_json"],
+            PromptKind::RestorePlan => &["instructions", "options_json", "allowed_steps"],
+            PromptKind::Detect | PromptKind::VerifyRestoration => &[],
+            PromptKind::Outpaint => &["contour", "bbox_width", "bbox_height"],
+            PromptKind::VerifyDetection => &["boxes_json"],
+            PromptKind::VerifyCrop => &["crop_number"],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PromptKind> {
+        PromptKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// Templates compiled into the binary, as (kind, version, text).
+const EMBEDDED: [(PromptKind, u32, &str); 7] = [
+    (PromptKind::RestoreImage, 1, include_str!("../prompts/restore_image.v1.txt")),
+    (PromptKind::RestorePlan, 1, include_str!("../prompts/restore_plan.v1.txt")),
+    (PromptKind::Detect, 1, include_str!("../prompts/detect.v1.txt")),
+    (PromptKind::Outpaint, 1, include_str!("../prompts/outpaint.v1.txt")),
+    (PromptKind::VerifyRestoration, 1, include_str!("../prompts/verify_restoration.v1.txt")),
+    (PromptKind::VerifyDetection, 1, include_str!("../prompts/verify_detection.v1.txt")),
+    (PromptKind::VerifyCrop, 1, include_str!("../prompts/verify_crop.v1.txt")),
+];
+
+/// A rendered prompt and the template version it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prompt {
+    pub text: String,
+    /// `<name>.v<N>`, recorded in results as `prompt_version`.
+    pub version: String,
+}
+
+/// Available and active versions of one prompt (`GET /api/prompts`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptInfo {
+    pub name: String,
+    pub versions: Vec<u32>,
+    pub active: u32,
+    pub variables: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PromptLibrary {
+    templates: BTreeMap<PromptKind, BTreeMap<u32, String>>,
+    pinned: BTreeMap<PromptKind, u32>,
+}
+
+impl PromptLibrary {
+    /// The embedded templates only.
+    pub fn embedded() -> Self {
+        let mut templates: BTreeMap<PromptKind, BTreeMap<u32, String>> = BTreeMap::new();
+        for (kind, version, text) in EMBEDDED {
+            templates.entry(kind).or_default().insert(version, text.trim_end().to_string());
+        }
+        Self { templates, pinned: BTreeMap::new() }
+    }
+
+    /// Embedded templates overlaid with the `<name>.v<N>.txt` files in `dir`.
+    /// A file with an unknown placeholder fails the load; other files are skipped.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut library = Self::embedded();
+        let entries = fs::read_dir(dir).with_context(|| format!("read prompts dir {:?}", dir))?;
+        for entry in entries {
+            let path = entry?.path();
+            let Some((kind, version)) = path.file_name().and_then(|n| n.to_str()).and_then(parse_file_name) else {
+                warn!("Skipping {:?}: not a <prompt>.v<N>.txt template", path);
+                continue;
+            };
+            let text = fs::read_to_string(&path).with_context(|| format!("read prompt {:?}", path))?;
+            check_placeholders(kind, &text).with_context(|| format!("prompt {:?}", path))?;
+            let replaced = library
+                .templates
+                .entry(kind)
+                .or_default()
+                .insert(version, text.trim_end().to_string())
+                .is_some();
+            info!("Prompt {}.v{} {} from {:?}", kind.name(), version, if replaced { "overridden" } else { "added" }, path);
+        }
+        Ok(library)
+    }
+
+    /// Library from `TISSAIA_PROMPTS_DIR` and `TISSAIA_PROMPT_VERSIONS`;
+    /// falls back to the embedded templates when either is unusable.
+    pub fn from_env() -> Self {
+        let mut library = match std::env::var("TISSAIA_PROMPTS_DIR") {
+            Ok(dir) => Self::load(&dir).unwrap_or_else(|e| {
+                warn!("Prompts dir {} unusable ({:#}); using embedded prompts", dir, e);
+                Self::embedded()
+            }),
+            Err(_) => Self::embedded(),
+        };
+        if let Ok(pins) = std::env::var("TISSAIA_PROMPT_VERSIONS") {
+            if let Err(e) = library.pin_all(&pins) {
+                warn!("Ignoring TISSAIA_PROMPT_VERSIONS ({:#})", e);
+                library.pinned.clear();
+            }
+        }
+        library
+    }
+
+    /// Use `version` of `kind` instead of the latest one.
+    pub fn pin(&mut self, kind: PromptKind, version: u32) -> Result<()> {
+        if !self.templates.get(&kind).is_some_and(|versions| versions.contains_key(&version)) {
+            return Err(TissaiaError::Validation(format!("Prompt {}.v{} does not exist", kind.name(), version)).into());
+        }
+        self.pinned.insert(kind, version);
+        Ok(())
+    }
+
+    /// Pin from a `name=version,...` list.
+    pub fn pin_all(&mut self, pins: &str) -> Result<()> {
+        for pin in pins.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, version) = pin.split_once('=').ok_or_else(|| anyhow!("'{}' is not name=version", pin))?;
+            let kind = PromptKind::from_name(name.trim()).ok_or_else(|| anyhow!("unknown prompt '{}'", name))?;
+            let version = version.trim().trim_start_matches('v').parse().with_context(|| format!("version of {}", name))?;
+            self.pin(kind, version)?;
+        }
+        Ok(())
+    }
+
+    /// Version `render` uses for `kind`.
+    pub fn active_version(&self, kind: PromptKind) -> u32 {
+        self.pinned
+            .get(&kind)
+            .copied()
+            .or_else(|| self.templates.get(&kind).and_then(|v| v.keys().next_back().copied()))
+            .unwrap_or(1)
+    }
+
+    /// Fill the active template of `kind` with `vars`.
+    pub fn render(&self, kind: PromptKind, vars: &[(&str, &str)]) -> Prompt {
+        let version = self.active_version(kind);
+        let template = self.templates.get(&kind).and_then(|v| v.get(&version)).map(String::as_str).unwrap_or("");
+        Prompt { text: substitute(template, vars), version: format!("{}.v{}", kind.name(), version) }
+    }
+
+    pub fn list(&self) -> Vec<PromptInfo> {
+        PromptKind::ALL
+            .iter()
+            .map(|kind| PromptInfo {
+                name: kind.name().to_string(),
+                versions: self.templates.get(kind).map(|v| v.keys().copied().collect()).unwrap_or_default(),
+                active: self.active_version(*kind),
+                variables: kind.variables().iter().map(|v| v.to_string()).collect(),
+            })
+            .collect()
+    }
+}
+
+impl Default for PromptLibrary {
+    fn default() -> Self {
+        Self::embedded()
+    }
+}
+
+/// `detect.v2.txt` → (Detect, 2).
+fn parse_file_name(name: &str) -> Option<(PromptKind, u32)> {
+    let (stem, version) = name.strip_suffix(".txt")?.rsplit_once(".v")?;
+    Some((PromptKind::from_name(stem)?, version.parse().ok()?))
+}
+
+/// `{identifier}` placeholders in `text`, as byte ranges and names.
+fn placeholders(text: &str) -> Vec<(std::ops::Range<usize>, &str)> {
+    let mut found = Vec::new();
+    let mut rest = 0;
+    while let Some(open) = text[rest..].find('{').map(|i| rest + i) {
+        let name_len = text[open + 1..]
+            .find(|c: char| !(c.is_ascii_lowercase() || c == '_'))
+            .unwrap_or(text.len() - open - 1);
+        let close = open + 1 + name_len;
+        if name_len > 0 && text[close..].starts_with('}') {
+            found.push((open..close + 1, &text[open + 1..close]));
+            rest = close + 1;
+        } else {
+            rest = open + 1;
+        }
+    }
+    found
+}
+
+fn check_placeholders(kind: PromptKind, text: &str) -> Result<()> {
+    for (_, name) in placeholders(text) {
+        if !kind.variables().contains(&name) {
+            return Err(TissaiaError::Validation(format!(
+                "unknown placeholder {{{}}} in {} (allowed: {})",
+                name,
+                kind.name(),
+                kind.variables().join(", ")
+            ))
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn substitute(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut last = 0;
+    for (range, name) in placeholders(template) {
+        if let Some((_, value)) = vars.iter().find(|(var, _)| *var == name) {
+            text.push_str(&template[last..range.start]);
+            text.push_str(value);
+            last = range.end;
+        }
+    }
+    text.push_str(&template[last..]);
+    text
+}
@@ -0,0 +1,528 @@
+// core/src/refine.rs
+//! Edge snapping for detected boxes (hybrid detection).
+//! AI boxes are coarse: they often include a strip of scanner bed or clip
+//! a photo edge, and say nothing about skew. Each box edge is searched for
+//! the nearest strong bed → photo transition in the full-resolution scan,
+//! in several segments along the edge; a line fitted through the segment
+//! hits becomes the new edge. The four lines give a tight quadrilateral
+//! `contour`, the skew (added to the content orientation in
+//! `rotation_angle`) and the box, and every box gets a `BoxAdjustment`
+//! saying what moved. Edges without a clear transition (a photo border
+//! matching the bed) keep their detected position.
+
+use crate::models::DetectionResult;
+use anyhow::Result;
+#[cfg(feature = "image-processing")]
+use crate::local_detection::{bed_colour_and_threshold, bed_distance, DetectorParams};
+#[cfg(feature = "image-processing")]
+use crate::models::{BoundingBox, BoxAdjustment, EdgeAdjustment, Point2D};
+#[cfg(feature = "image-processing")]
+use log::info;
+
+/// Segments each edge is split into; each gives one point of the edge line.
+#[cfg(feature = "image-processing")]
+const SEGMENTS: usize = 8;
+/// Fewest segment hits for an edge to count as found.
+#[cfg(feature = "image-processing")]
+const MIN_HITS: usize = 4;
+/// Steeper edges are not a skewed photo but something else.
+#[cfg(feature = "image-processing")]
+const MAX_SKEW_DEGREES: f64 = 20.0;
+/// Snapped edges disagreeing by more than this use the median angle.
+#[cfg(feature = "image-processing")]
+const SKEW_SPREAD_DEGREES: f64 = 3.0;
+
+/// Snap `result.bounding_boxes[first..]` to the photo edges in the scan and
+/// append their `BoxAdjustment`s. Also fills in the scan dimensions.
+#[cfg(feature = "image-processing")]
+pub fn refine_detection(image_base64: &str, result: &mut DetectionResult, first: usize) -> Result<()> {
+    let scan = crate::imaging::decode_base64_image(image_base64)?.to_rgb8();
+    info!(
+        "=== EDGE SNAPPING START === ({} boxes, {}x{})",
+        result.bounding_boxes.len().saturating_sub(first),
+        scan.width(),
+        scan.height()
+    );
+    result.scan_width = scan.width();
+    result.scan_height = scan.height();
+
+    let (bed, tolerance) = bed_colour_and_threshold(&scan, &DetectorParams::default());
+    let scan = Scan { img: &scan, bed, tolerance: tolerance as f64 };
+    for (index, bbox) in result.bounding_boxes.iter_mut().enumerate().skip(first) {
+        let mut adjustment = snap_box(&scan, bbox);
+        adjustment.index = index;
+        result.adjustments.push(adjustment);
+    }
+
+    let applied = result.adjustments.iter().skip(first).filter(|a| a.applied).count();
+    info!("=== EDGE SNAPPING END === ({} boxes refined)", applied);
+    Ok(())
+}
+
+#[cfg(not(feature = "image-processing"))]
+pub fn refine_detection(_image_base64: &str, _result: &mut DetectionResult, _first: usize) -> Result<()> {
+    Err(crate::imaging::feature_disabled())
+}
+
+// ============================================
+// PER-BOX SNAPPING
+// ============================================
+
+#[cfg(feature = "image-processing")]
+struct Scan<'a> {
+    img: &'a image::RgbImage,
+    bed: [u8; 3],
+    /// Bed distance separating bed from photo (Otsu on the scan).
+    tolerance: f64,
+}
+
+#[cfg(feature = "image-processing")]
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Left,
+    Top,
+    Right,
+    Bottom,
+}
+
+#[cfg(feature = "image-processing")]
+impl Side {
+    /// Edge runs top to bottom: position `p` is x, `q` (along) is y.
+    fn vertical(self) -> bool {
+        matches!(self, Side::Left | Side::Right)
+    }
+
+    /// Direction of increasing `p` that points into the photo.
+    fn inward(self) -> f64 {
+        if matches!(self, Side::Left | Side::Top) { 1.0 } else { -1.0 }
+    }
+}
+
+/// `p = a + b * q` in pixel-edge coordinates.
+#[cfg(feature = "image-processing")]
+#[derive(Clone, Copy)]
+struct EdgeLine {
+    a: f64,
+    b: f64,
+}
+
+#[cfg(feature = "image-processing")]
+struct SnappedEdge {
+    line: EdgeLine,
+    contrast: f64,
+}
+
+/// Box in pixel-edge coordinates: x0, y0, x1, y1.
+#[cfg(feature = "image-processing")]
+type PixelRect = [f64; 4];
+
+/// Refine one box in place; returns what changed (index left at 0).
+#[cfg(feature = "image-processing")]
+fn snap_box(scan: &Scan, bbox: &mut BoundingBox) -> BoxAdjustment {
+    let (w, h) = (scan.img.width() as f64, scan.img.height() as f64);
+    let rect: PixelRect = [
+        bbox.x as f64 / 1000.0 * w,
+        bbox.y as f64 / 1000.0 * h,
+        (bbox.x + bbox.width).min(1000) as f64 / 1000.0 * w,
+        (bbox.y + bbox.height).min(1000) as f64 / 1000.0 * h,
+    ];
+    let mut adjustment = BoxAdjustment::default();
+    if rect[2] - rect[0] < 16.0 || rect[3] - rect[1] < 16.0 {
+        adjustment.note = Some("box too small to snap".to_string());
+        return adjustment;
+    }
+
+    let sides = [Side::Left, Side::Top, Side::Right, Side::Bottom];
+    let snapped = sides.map(|side| snap_edge(scan, side, &rect));
+    for (side, edge) in sides.iter().zip(&snapped) {
+        if let Some(edge) = edge {
+            let report = edge_report(&mut adjustment, *side);
+            report.snapped = true;
+            report.contrast = edge.contrast as f32;
+        }
+    }
+    if snapped.iter().all(Option::is_none) {
+        adjustment.note = Some("no photo edge found".to_string());
+        return adjustment;
+    }
+
+    let skew = measure_skew(&sides, &snapped);
+    adjustment.skew = Some(skew as f32);
+
+    // Edges without a hit keep their detected position, tilted by the skew
+    let lines: Vec<EdgeLine> = sides
+        .iter()
+        .zip(&snapped)
+        .map(|(&side, edge)| match edge {
+            Some(edge) => edge.line,
+            None => fallback_line(side, &rect, skew),
+        })
+        .collect();
+    let (left, top, right, bottom) = (lines[0], lines[1], lines[2], lines[3]);
+    let corners = [
+        intersect(left, top),
+        intersect(right, top),
+        intersect(right, bottom),
+        intersect(left, bottom),
+    ];
+
+    // Reject geometry that is no longer the detected photo
+    let ordered = corners[0].0 < corners[1].0
+        && corners[3].0 < corners[2].0
+        && corners[0].1 < corners[3].1
+        && corners[1].1 < corners[2].1;
+    let min_x = corners.iter().map(|c| c.0).fold(f64::MAX, f64::min).max(0.0);
+    let min_y = corners.iter().map(|c| c.1).fold(f64::MAX, f64::min).max(0.0);
+    let max_x = corners.iter().map(|c| c.0).fold(f64::MIN, f64::max).min(w);
+    let max_y = corners.iter().map(|c| c.1).fold(f64::MIN, f64::max).min(h);
+    let area_ratio = ((max_x - min_x) * (max_y - min_y)) / ((rect[2] - rect[0]) * (rect[3] - rect[1]));
+    if !ordered || !(0.5..=1.6).contains(&area_ratio) {
+        adjustment.note = Some(format!("snapped edges do not form the detected photo (area x{:.2})", area_ratio));
+        return adjustment;
+    }
+
+    let to_norm_x = |x: f64| (x / w * 1000.0).clamp(0.0, 1000.0);
+    let to_norm_y = |y: f64| (y / h * 1000.0).clamp(0.0, 1000.0);
+    let (x0, y0) = (to_norm_x(min_x).floor() as u32, to_norm_y(min_y).floor() as u32);
+    let (x1, y1) = (to_norm_x(max_x).ceil() as u32, to_norm_y(max_y).ceil() as u32);
+
+    adjustment.left.shift = bbox.x as f32 - x0 as f32;
+    adjustment.top.shift = bbox.y as f32 - y0 as f32;
+    adjustment.right.shift = x1 as f32 - (bbox.x + bbox.width) as f32;
+    adjustment.bottom.shift = y1 as f32 - (bbox.y + bbox.height) as f32;
+    adjustment.applied = true;
+
+    bbox.x = x0;
+    bbox.y = y0;
+    bbox.width = (x1 - x0).max(1);
+    bbox.height = (y1 - y0).max(1);
+    // Keep the detected content orientation, replace whatever skew it had
+    let orientation = ((bbox.rotation_angle / 90.0).round() * 90.0).rem_euclid(360.0);
+    bbox.rotation_angle = orientation + skew as f32;
+    if bbox.needs_outpaint {
+        // A torn or irregular outline is more than four corners can describe
+        adjustment.note = Some("kept the detected contour of a photo that needs outpainting".to_string());
+    } else {
+        bbox.contour = corners
+            .iter()
+            .map(|&(x, y)| Point2D { x: to_norm_x(x) as f32, y: to_norm_y(y) as f32 })
+            .collect();
+    }
+    bbox.edges_snapped = true;
+
+    info!(
+        "Snapped box {:?}: {}/4 edges, skew {:.1}°, shifts L{:+.0} T{:+.0} R{:+.0} B{:+.0}",
+        bbox.label,
+        snapped.iter().filter(|e| e.is_some()).count(),
+        skew,
+        adjustment.left.shift,
+        adjustment.top.shift,
+        adjustment.right.shift,
+        adjustment.bottom.shift
+    );
+    adjustment
+}
+
+#[cfg(feature = "image-processing")]
+fn edge_report(adjustment: &mut BoxAdjustment, side: Side) -> &mut EdgeAdjustment {
+    match side {
+        Side::Left => &mut adjustment.left,
+        Side::Top => &mut adjustment.top,
+        Side::Right => &mut adjustment.right,
+        Side::Bottom => &mut adjustment.bottom,
+    }
+}
+
+/// Skew of the snapped edges in degrees, clockwise: the contrast-weighted
+/// mean, or the median when the edges disagree.
+#[cfg(feature = "image-processing")]
+fn measure_skew(sides: &[Side; 4], snapped: &[Option<SnappedEdge>; 4]) -> f64 {
+    let mut angles: Vec<(f64, f64)> = sides
+        .iter()
+        .zip(snapped)
+        .filter_map(|(side, edge)| {
+            let edge = edge.as_ref()?;
+            // A clockwise turn tilts horizontal edges down (+b), vertical ones left (-b)
+            let angle = edge.line.b.atan().to_degrees();
+            Some((if side.vertical() { -angle } else { angle }, edge.contrast))
+        })
+        .collect();
+    angles.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let spread = angles[angles.len() - 1].0 - angles[0].0;
+    if spread > SKEW_SPREAD_DEGREES {
+        return angles[angles.len() / 2].0;
+    }
+    let weight: f64 = angles.iter().map(|a| a.1).sum();
+    angles.iter().map(|a| a.0 * a.1).sum::<f64>() / weight
+}
+
+/// Line at the detected edge with the slope of `skew`, placed inside the
+/// box and touching its side at one end (where the corner of a skewed
+/// photo meets its bounding box).
+#[cfg(feature = "image-processing")]
+fn fallback_line(side: Side, rect: &PixelRect, skew: f64) -> EdgeLine {
+    let tan = skew.to_radians().tan();
+    let b = if side.vertical() { -tan } else { tan };
+    let (p0, q_lo, q_hi) = edge_span(side, rect);
+    let (lo, hi) = ((b * q_lo).min(b * q_hi), (b * q_lo).max(b * q_hi));
+    let a = if side.inward() > 0.0 { p0 - lo } else { p0 - hi };
+    EdgeLine { a, b }
+}
+
+/// Position of the edge and its extent along the edge.
+#[cfg(feature = "image-processing")]
+fn edge_span(side: Side, rect: &PixelRect) -> (f64, f64, f64) {
+    let [x0, y0, x1, y1] = *rect;
+    match side {
+        Side::Left => (x0, y0, y1),
+        Side::Right => (x1, y0, y1),
+        Side::Top => (y0, x0, x1),
+        Side::Bottom => (y1, x0, x1),
+    }
+}
+
+/// Corner where a vertical edge (`x = a + b y`) meets a horizontal one
+/// (`y = a + b x`).
+#[cfg(feature = "image-processing")]
+fn intersect(vertical: EdgeLine, horizontal: EdgeLine) -> (f64, f64) {
+    let x = (vertical.a + vertical.b * horizontal.a) / (1.0 - vertical.b * horizontal.b);
+    (x, horizontal.a + horizontal.b * x)
+}
+
+// ============================================
+// EDGE SEARCH
+// ============================================
+
+/// Search around one detected edge for the bed → photo transition and fit
+/// a line through the hits of the individual segments.
+#[cfg(feature = "image-processing")]
+fn snap_edge(scan: &Scan, side: Side, rect: &PixelRect) -> Option<SnappedEdge> {
+    let (w, h) = scan.img.dimensions();
+    let (dim_p, dim_q) = if side.vertical() { (w, h) } else { (h, w) };
+    let (p0, q_lo, q_hi) = edge_span(side, rect);
+    let across = if side.vertical() { rect[2] - rect[0] } else { rect[3] - rect[1] };
+    let along = q_hi - q_lo;
+
+    // Outward: sloppy boxes; inward: also the inset of a skewed edge
+    let reach_out = 0.02 * dim_p as f64 + 0.03 * across;
+    let reach_in = (0.02 * dim_p as f64 + 0.2 * along).min(0.45 * across);
+    let (lo, hi) = if side.inward() > 0.0 { (p0 - reach_out, p0 + reach_in) } else { (p0 - reach_in, p0 + reach_out) };
+    let lo = lo.floor().max(0.0) as u32;
+    let hi = (hi.ceil() as u32).min(dim_p);
+    let window = (w.max(h) as usize / 1000).clamp(2, 6);
+    if (hi.saturating_sub(lo) as usize) < 2 * window + 2 {
+        return None;
+    }
+
+    // Segments skip the first and last tenth, where the other edges cross
+    let segment = along * 0.8 / SEGMENTS as f64;
+    let mut hits: Vec<(f64, f64, f64)> = Vec::new();
+    for n in 0..SEGMENTS {
+        let qa = q_lo + along * 0.1 + n as f64 * segment;
+        let q_start = qa.floor().max(0.0) as u32;
+        let q_end = ((qa + segment).ceil() as u32).min(dim_q);
+        if q_end <= q_start {
+            continue;
+        }
+        let stride = ((q_end - q_start) / 24).max(1) as usize;
+        let samples: Vec<u32> = (q_start..q_end).step_by(stride).collect();
+        let profile: Vec<f64> = (lo..hi)
+            .map(|p| {
+                let total: u32 = samples
+                    .iter()
+                    .map(|&q| {
+                        let (x, y) = if side.vertical() { (p, q) } else { (q, p) };
+                        bed_distance(scan.img.get_pixel(x, y), scan.bed) as u32
+                    })
+                    .sum();
+                total as f64 / samples.len() as f64
+            })
+            .collect();
+        if let Some((offset, contrast)) = find_transition(&profile, window, side.inward(), scan.tolerance, p0 - lo as f64) {
+            hits.push((qa + segment / 2.0, lo as f64 + offset, contrast));
+        }
+    }
+
+    let line = fit_line(&hits, (along / 500.0).max(2.0))?;
+    if line.b.atan().to_degrees().abs() > MAX_SKEW_DEGREES {
+        return None;
+    }
+    let contrast = hits.iter().map(|h| h.2).sum::<f64>() / hits.len() as f64;
+    Some(SnappedEdge { line, contrast })
+}
+
+/// Boundary in `profile` (bed distance per position) where the outer side
+/// looks like bed and the inner side like photo. Among strong candidates
+/// the one nearest the detected edge (`expected`) wins, so a dark area
+/// inside the photo does not pull the edge inwards. Returns the boundary
+/// offset (between samples `i - 1` and `i`) and its contrast.
+#[cfg(feature = "image-processing")]
+fn find_transition(profile: &[f64], window: usize, inward: f64, tolerance: f64, expected: f64) -> Option<(f64, f64)> {
+    let mean = |range: std::ops::Range<usize>| profile[range.clone()].iter().sum::<f64>() / range.len() as f64;
+    let candidates: Vec<(usize, f64)> = (window..=profile.len() - window)
+        .filter_map(|i| {
+            let (before, after) = (mean(i - window..i), mean(i..i + window));
+            let (outer, inner) = if inward > 0.0 { (before, after) } else { (after, before) };
+            let contrast = inner - outer;
+            (outer < tolerance && contrast >= tolerance * 0.5).then_some((i, contrast))
+        })
+        .collect();
+    let best = candidates.iter().map(|c| c.1).fold(0.0, f64::max);
+    candidates
+        .into_iter()
+        .filter(|c| c.1 >= best * 0.6)
+        .min_by(|a, b| {
+            let distance = |i: usize| (i as f64 - expected).abs();
+            distance(a.0).partial_cmp(&distance(b.0)).unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|(i, contrast)| (i as f64, contrast))
+}
+
+/// Least-squares `p = a + b q` through (q, p, _) hits, refitted once
+/// without hits further than `tolerance` px from the first fit.
+#[cfg(feature = "image-processing")]
+fn fit_line(hits: &[(f64, f64, f64)], tolerance: f64) -> Option<EdgeLine> {
+    let fit = |points: &[&(f64, f64, f64)]| -> Option<EdgeLine> {
+        let n = points.len() as f64;
+        let (mean_q, mean_p) = (
+            points.iter().map(|h| h.0).sum::<f64>() / n,
+            points.iter().map(|h| h.1).sum::<f64>() / n,
+        );
+        let var_q: f64 = points.iter().map(|h| (h.0 - mean_q).powi(2)).sum();
+        if var_q < 1e-9 {
+            return None;
+        }
+        let b = points.iter().map(|h| (h.0 - mean_q) * (h.1 - mean_p)).sum::<f64>() / var_q;
+        Some(EdgeLine { a: mean_p - b * mean_q, b })
+    };
+    if hits.len() < MIN_HITS {
+        return None;
+    }
+    let all: Vec<&(f64, f64, f64)> = hits.iter().collect();
+    let first = fit(&all)?;
+    let inliers: Vec<&(f64, f64, f64)> =
+        hits.iter().filter(|h| (first.a + first.b * h.0 - h.1).abs() <= tolerance).collect();
+    if inliers.len() < MIN_HITS {
+        return None;
+    }
+    fit(&inliers)
+}
+
+#[cfg(all(test, feature = "image-processing"))]
+mod tests {
+    use super::*;
+    use crate::models::{BoundingBox, DetectionResult};
+    use base64::Engine;
+    use image::{Rgb, RgbImage};
+
+    /// 1000x800 dark bed with one photo of `size` centred at `centre`,
+    /// skewed `skew` degrees clockwise; a dark patch sits inside the photo
+    /// next to its left edge.
+    fn scan(centre: (f64, f64), size: (f64, f64), skew: f64) -> RgbImage {
+        let (sin, cos) = skew.to_radians().sin_cos();
+        RgbImage::from_fn(1000, 800, |x, y| {
+            let (dx, dy) = (x as f64 + 0.5 - centre.0, y as f64 + 0.5 - centre.1);
+            let (u, v) = (dx * cos + dy * sin, -dx * sin + dy * cos);
+            if u.abs() <= size.0 / 2.0 && v.abs() <= size.1 / 2.0 {
+                let dark = u < -size.0 / 2.0 + 60.0 && u > -size.0 / 2.0 + 30.0 && v.abs() < size.1 / 4.0;
+                return if dark { Rgb([20, 18, 16]) } else { Rgb([190, 160, 120]) };
+            }
+            Rgb([10 + ((x * 7 + y * 3) % 6) as u8; 3])
+        })
+    }
+
+    fn detection(boxes: Vec<BoundingBox>) -> DetectionResult {
+        DetectionResult {
+            id: "d".to_string(),
+            timestamp: chrono::Utc::now(),
+            photo_count: boxes.len(),
+            bounding_boxes: boxes,
+            provider_used: "test".to_string(),
+            scan_width: 0,
+            scan_height: 0,
+            attempts: Vec::new(),
+            adjustments: Vec::new(),
+            prompt_version: None,
+        }
+    }
+
+    fn coarse_box(x: u32, y: u32, width: u32, height: u32) -> BoundingBox {
+        BoundingBox {
+            x,
+            y,
+            width,
+            height,
+            confidence: 0.9,
+            label: Some("photo 1".to_string()),
+            rotation_angle: 0.0,
+            contour: Vec::new(),
+            needs_outpaint: false,
+            edges_snapped: false,
+        }
+    }
+
+    fn refine(img: &RgbImage, boxes: Vec<BoundingBox>) -> DetectionResult {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(bytes.into_inner());
+        let mut result = detection(boxes);
+        refine_detection(&encoded, &mut result, 0).unwrap();
+        result
+    }
+
+    #[test]
+    fn snaps_loose_and_clipped_edges() {
+        // Photo spans x 300..700 px (300..700), y 200..600 px (250..750)
+        let img = scan((500.0, 400.0), (400.0, 400.0), 0.0);
+        // Left and top include bed, right and bottom clip the photo
+        let result = refine(&img, vec![coarse_box(270, 220, 415, 515)]);
+
+        let bbox = &result.bounding_boxes[0];
+        assert!(bbox.edges_snapped);
+        assert!((bbox.x as i32 - 300).abs() <= 2, "x = {}", bbox.x);
+        assert!((bbox.y as i32 - 250).abs() <= 2, "y = {}", bbox.y);
+        assert!(((bbox.x + bbox.width) as i32 - 700).abs() <= 2, "right = {}", bbox.x + bbox.width);
+        assert!(((bbox.y + bbox.height) as i32 - 750).abs() <= 2, "bottom = {}", bbox.y + bbox.height);
+        assert!(bbox.rotation_angle.abs() < 0.5);
+        assert_eq!((result.scan_width, result.scan_height), (1000, 800));
+
+        let adjustment = &result.adjustments[0];
+        assert!(adjustment.applied);
+        assert!(adjustment.left.snapped && adjustment.bottom.snapped);
+        assert!(adjustment.left.shift < -20.0, "left moved in: {}", adjustment.left.shift);
+        assert!(adjustment.right.shift > 10.0, "right moved out: {}", adjustment.right.shift);
+    }
+
+    #[test]
+    fn measures_skew_and_keeps_orientation() {
+        let img = scan((500.0, 400.0), (420.0, 300.0), 6.0);
+        let mut coarse = coarse_box(260, 190, 480, 620);
+        coarse.rotation_angle = 90.0;
+        let result = refine(&img, vec![coarse]);
+
+        let bbox = &result.bounding_boxes[0];
+        let skew = result.adjustments[0].skew.unwrap();
+        assert!((skew - 6.0).abs() < 1.0, "skew {}", skew);
+        assert!((bbox.rotation_angle - 96.0).abs() < 1.0, "rotation {}", bbox.rotation_angle);
+
+        // Top-left corner of the rotated photo, in 0-1000 space
+        let (sin, cos) = 6f64.to_radians().sin_cos();
+        let (cx, cy) = (500.0 - 210.0 * cos + 150.0 * sin, 400.0 - 210.0 * sin - 150.0 * cos);
+        let corner = &bbox.contour[0];
+        assert!((corner.x as f64 - cx / 1000.0 * 1000.0).abs() < 4.0, "corner {:?} vs ({}, {})", corner, cx, cy);
+        assert!((corner.y as f64 - cy / 800.0 * 1000.0).abs() < 5.0, "corner {:?} vs ({}, {})", corner, cx, cy);
+    }
+
+    #[test]
+    fn leaves_boxes_without_edges_alone() {
+        let img = scan((500.0, 400.0), (400.0, 400.0), 0.0);
+        // A box over empty bed
+        let result = refine(&img, vec![coarse_box(20, 30, 150, 150)]);
+
+        let bbox = &result.bounding_boxes[0];
+        assert!(!bbox.edges_snapped);
+        assert_eq!((bbox.x, bbox.y, bbox.width, bbox.height), (20, 30, 150, 150));
+        assert!(!result.adjustments[0].applied);
+        assert!(result.adjustments[0].note.is_some());
+    }
+}
@@ -0,0 +1,385 @@
+// core/src/responses.rs
+//! Typed AI JSON replies.
+//! Detection, verification and restoration plan answers are deserialized into the structs
+//! below and validated; nothing the prompt asks for is silently defaulted
+//! (a verifier reply without `status` is an error, not a pass). Before
+//! parsing, `repair_json` fixes the usual formatting slips: Markdown fences,
+//! prose around the object and trailing commas. A reply that still fails
+//! comes back with the reason, which `ai` sends to the model in a bounded
+//! re-ask. `AiReply::schema` describes the same structs for Gemini
+//! `responseSchema` and OpenAI-style `json_schema` requests.
+
+use crate::models::{BoundingBox, PlanStep, Point2D, RestorationPlan, VerificationStatus};
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Value};
+
+/// A reply type the providers are asked to produce.
+pub trait AiReply: DeserializeOwned {
+    /// Schema name for `json_schema` response formats.
+    const NAME: &'static str;
+
+    /// JSON Schema (the subset Gemini and OpenAI both accept).
+    fn schema() -> Value;
+
+    /// Checks serde cannot express; the message goes back to the model.
+    fn validate(&self) -> Result<(), String>;
+}
+
+/// Repair, deserialize and validate `text` as `T`.
+pub fn parse_reply<T: AiReply>(text: &str) -> Result<T, String> {
+    let reply: T = serde_json::from_str(&repair_json(text)).map_err(|e| format!("invalid JSON: {}", e))?;
+    reply.validate()?;
+    Ok(reply)
+}
+
+/// Best-effort cleanup of a model's JSON reply: keeps the outermost object
+/// (dropping code fences and surrounding prose) and removes trailing commas
+/// before `}` / `]`. Text without an object is returned trimmed.
+pub fn repair_json(text: &str) -> String {
+    let text = text.trim();
+    let object = match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => text,
+    };
+
+    let mut repaired = String::with_capacity(object.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in object.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = object[i + 1..].trim_start().chars().next();
+            if matches!(next, Some('}') | Some(']')) {
+                continue;
+            }
+        }
+        repaired.push(c);
+    }
+    repaired
+}
+
+/// Gemini `responseSchema` form of `schema`: upper-case type names.
+pub fn gemini_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| match (key.as_str(), value) {
+                    ("type", Value::String(t)) => (key.clone(), Value::String(t.to_uppercase())),
+                    // Property names are not schemas; recurse into their values only
+                    ("properties", Value::Object(props)) => (
+                        key.clone(),
+                        Value::Object(props.iter().map(|(k, v)| (k.clone(), gemini_schema(v))).collect()),
+                    ),
+                    _ => (key.clone(), gemini_schema(value)),
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(gemini_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+fn check_number(name: &str, value: f64, min: f64, max: f64) -> Result<(), String> {
+    if !value.is_finite() || value < min || value > max {
+        return Err(format!("{} must be between {} and {} (got {})", name, min, max, value));
+    }
+    Ok(())
+}
+
+// ============================================
+// DETECTION
+// ============================================
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DetectionReply {
+    /// Numbers may arrive as floats (`3.0`).
+    pub photo_count: f64,
+    pub bounding_boxes: Vec<ReplyBox>,
+}
+
+/// Box in normalized 0-1000 coordinates, as the detection and verification
+/// prompts describe it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplyBox {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub confidence: f64,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub rotation_angle: f64,
+    #[serde(default)]
+    pub rotation_reasoning: Option<String>,
+    #[serde(default)]
+    pub contour: Vec<ReplyPoint>,
+    #[serde(default)]
+    pub needs_outpaint: bool,
+}
+
+/// `[x, y]` or `{"x": .., "y": ..}`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum ReplyPoint {
+    Pair([f64; 2]),
+    Object { x: f64, y: f64 },
+}
+
+impl ReplyPoint {
+    fn point(self) -> Point2D {
+        let (x, y) = match self {
+            ReplyPoint::Pair([x, y]) | ReplyPoint::Object { x, y } => (x, y),
+        };
+        Point2D { x: x.clamp(0.0, 1000.0) as f32, y: y.clamp(0.0, 1000.0) as f32 }
+    }
+}
+
+impl ReplyBox {
+    fn validate(&self, name: &str) -> Result<(), String> {
+        check_number(&format!("{}.x", name), self.x, 0.0, 1000.0)?;
+        check_number(&format!("{}.y", name), self.y, 0.0, 1000.0)?;
+        check_number(&format!("{}.width", name), self.width, 1.0, 1000.0)?;
+        check_number(&format!("{}.height", name), self.height, 1.0, 1000.0)?;
+        check_number(&format!("{}.confidence", name), self.confidence, 0.0, 1.0)?;
+        check_number(&format!("{}.rotation_angle", name), self.rotation_angle, -360.0, 360.0)
+    }
+
+    /// The box with its extent clamped to the 0-1000 space.
+    pub fn into_bounding_box(self) -> BoundingBox {
+        let x = self.x.round() as u32;
+        let y = self.y.round() as u32;
+        BoundingBox {
+            x,
+            y,
+            width: (self.width.round() as u32).min(1000 - x).max(1),
+            height: (self.height.round() as u32).min(1000 - y).max(1),
+            confidence: self.confidence as f32,
+            label: self.label,
+            rotation_angle: self.rotation_angle as f32,
+            contour: self.contour.into_iter().map(ReplyPoint::point).collect(),
+            needs_outpaint: self.needs_outpaint,
+            edges_snapped: false,
+        }
+    }
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "x": {"type": "number"},
+                "y": {"type": "number"},
+                "width": {"type": "number"},
+                "height": {"type": "number"},
+                "confidence": {"type": "number"},
+                "label": {"type": "string"},
+                "rotation_angle": {"type": "number"},
+                "rotation_reasoning": {"type": "string"},
+                "contour": {"type": "array", "items": {"type": "array", "items": {"type": "number"}}},
+                "needs_outpaint": {"type": "boolean"}
+            },
+            "required": ["x", "y", "width", "height", "confidence"]
+        })
+    }
+}
+
+impl AiReply for DetectionReply {
+    const NAME: &'static str = "photo_detection";
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "photo_count": {"type": "integer"},
+                "bounding_boxes": {"type": "array", "items": ReplyBox::schema()}
+            },
+            "required": ["photo_count", "bounding_boxes"]
+        })
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        check_number("photo_count", self.photo_count, 0.0, 1000.0)?;
+        for (i, b) in self.bounding_boxes.iter().enumerate() {
+            b.validate(&format!("bounding_boxes[{}]", i))?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================
+// VERIFICATION
+// ============================================
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerificationReply {
+    pub status: VerificationStatus,
+    /// 0-100.
+    pub confidence: f64,
+    pub checks: Vec<ReplyCheck>,
+    #[serde(default)]
+    pub issues: Vec<ReplyIssue>,
+    #[serde(default)]
+    pub recommendations: Vec<String>,
+    /// Photos the detection verifier found missing.
+    #[serde(default)]
+    pub missing_boxes: Vec<ReplyBox>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplyCheck {
+    pub name: String,
+    pub passed: bool,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplyIssue {
+    pub severity: String,
+    pub description: String,
+    #[serde(default)]
+    pub suggestion: Option<String>,
+}
+
+const SEVERITIES: [&str; 3] = ["critical", "warning", "info"];
+
+impl AiReply for VerificationReply {
+    const NAME: &'static str = "verification";
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "status": {"type": "string", "enum": ["pass", "warning", "fail"]},
+                "confidence": {"type": "number"},
+                "checks": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"type": "string"},
+                            "passed": {"type": "boolean"},
+                            "detail": {"type": "string"}
+                        },
+                        "required": ["name", "passed"]
+                    }
+                },
+                "issues": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "severity": {"type": "string", "enum": SEVERITIES},
+                            "description": {"type": "string"},
+                            "suggestion": {"type": "string"}
+                        },
+                        "required": ["severity", "description"]
+                    }
+                },
+                "recommendations": {"type": "array", "items": {"type": "string"}},
+                "missing_boxes": {"type": "array", "items": ReplyBox::schema()}
+            },
+            "required": ["status", "confidence", "checks"]
+        })
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        check_number("confidence", self.confidence, 0.0, 100.0)?;
+        if let Some(issue) = self.issues.iter().find(|i| !SEVERITIES.contains(&i.severity.as_str())) {
+            return Err(format!("issue severity must be critical, warning or info (got '{}')", issue.severity));
+        }
+        for (i, b) in self.missing_boxes.iter().enumerate() {
+            b.validate(&format!("missing_boxes[{}]", i))?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================
+// RESTORATION PLAN
+// ============================================
+
+/// Plan from a text-only provider, executed by the local engine.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlanReply {
+    pub improvements: Vec<String>,
+    #[serde(default)]
+    pub processing_steps: Vec<String>,
+    /// Older replies list improvements only; the engine then derives steps
+    /// from them. Steps the engine does not know are skipped.
+    #[serde(default, deserialize_with = "known_steps")]
+    pub operations: Vec<PlanStep>,
+}
+
+fn known_steps<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PlanStep>, D::Error> {
+    let items = Vec::<Value>::deserialize(deserializer)?;
+    Ok(items
+        .into_iter()
+        .filter_map(|item| match serde_json::from_value::<PlanStep>(item.clone()) {
+            Ok(step) => Some(step),
+            Err(e) => {
+                debug!("Skipping plan operation {}: {}", item, e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// `RestorationStep` names, for the schema.
+const PLAN_STEPS: [&str; 8] =
+    ["dust_removal", "denoise", "bilateral", "white_balance", "fade_correction", "levels", "clahe", "sharpen"];
+
+impl PlanReply {
+    pub fn into_plan(self) -> RestorationPlan {
+        RestorationPlan {
+            improvements: self.improvements,
+            processing_steps: self.processing_steps,
+            operations: self.operations,
+        }
+    }
+}
+
+impl AiReply for PlanReply {
+    const NAME: &'static str = "restoration_plan";
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "improvements": {"type": "array", "items": {"type": "string"}},
+                "processing_steps": {"type": "array", "items": {"type": "string"}},
+                "estimated_quality_improvement": {"type": "number"},
+                "operations": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "step": {"type": "string", "enum": PLAN_STEPS},
+                            "strength": {"type": "number"}
+                        },
+                        "required": ["step"]
+                    }
+                }
+            },
+            "required": ["improvements"]
+        })
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (i, op) in self.operations.iter().enumerate() {
+            check_number(&format!("operations[{}].strength", i), op.strength as f64, 0.0, 1.0)?;
+        }
+        Ok(())
+    }
+}
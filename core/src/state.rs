@@ -0,0 +1,354 @@
+// core/src/state.rs
+//! Application state shared by the Axum server and the Tauri shell.
+//! No framework dependencies. Pure Rust state management.
+
+use crate::ai::AiProvider;
+use crate::backends::{BackendContext, BackendRegistry, Capability, RestorationBackend};
+use crate::config::{load_provider_configs, merge_provider_settings};
+use crate::executor::RequestExecutor;
+use crate::health::{CircuitBreakerConfig, ProbeFailure};
+use crate::history::HistoryStore;
+use crate::jobs::JobManager;
+use crate::models::{AppSettings, HistoryEntry, ProviderStatus, SettingsUpdate};
+use crate::presets::PresetStore;
+use crate::prompts::PromptLibrary;
+use chrono::Utc;
+use log::warn;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// State handle shared between request handlers / Tauri commands.
+pub type SharedState = Arc<Mutex<AppState>>;
+
+pub struct AppState {
+    pub history: HistoryStore,
+    pub presets: PresetStore,
+    /// Prompt templates (`TISSAIA_PROMPTS_DIR` / `TISSAIA_PROMPT_VERSIONS`).
+    pub prompts: Arc<PromptLibrary>,
+    pub jobs: JobManager,
+    pub settings: AppSettings,
+    pub api_keys: HashMap<String, String>,
+    pub providers: Vec<ProviderStatus>,
+    pub backends: BackendRegistry,
+    /// Failures that open a provider's circuit and for how long (`health`).
+    pub circuit_breaker: CircuitBreakerConfig,
+    pub start_time: Instant,
+    client: Client,
+    /// Retries and per-provider limits shared by every provider call.
+    executor: Arc<RequestExecutor>,
+}
+
+impl AppState {
+    /// State with an in-memory history; see `with_history` for a durable one.
+    pub fn new() -> Self {
+        Self::with_history(HistoryStore::in_memory())
+    }
+
+    pub fn with_history(history: HistoryStore) -> Self {
+        let api_keys = Self::load_api_keys();
+        let backends = BackendRegistry::with_defaults();
+        let providers = Self::init_providers(&api_keys, &backends);
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(120))
+            .connect_timeout(Duration::from_secs(5))
+            .build()
+            .unwrap_or_default();
+
+        let settings = AppSettings {
+            providers: load_provider_configs(),
+            ..AppSettings::default()
+        };
+
+        Self {
+            history,
+            presets: PresetStore::in_memory(),
+            prompts: Arc::new(PromptLibrary::from_env()),
+            jobs: JobManager::from_env(),
+            settings,
+            api_keys,
+            providers,
+            backends,
+            circuit_breaker: CircuitBreakerConfig::from_env(),
+            start_time: Instant::now(),
+            client,
+            executor: Arc::new(RequestExecutor::new()),
+        }
+    }
+
+    /// Replace the (in-memory) preset store, e.g. with a file-backed one.
+    pub fn with_presets(mut self, presets: PresetStore) -> Self {
+        self.presets = presets;
+        self
+    }
+
+    fn load_api_keys() -> HashMap<String, String> {
+        let mut keys = HashMap::new();
+
+        if let Ok(key) = std::env::var("GOOGLE_API_KEY") {
+            keys.insert("google".to_string(), key);
+        }
+        if let Ok(key) = std::env::var("ANTHROPIC_API_KEY") {
+            keys.insert("anthropic".to_string(), key);
+        }
+        if let Ok(key) = std::env::var("OPENAI_API_KEY") {
+            keys.insert("openai".to_string(), key);
+        }
+        if let Ok(key) = std::env::var("MISTRAL_API_KEY") {
+            keys.insert("mistral".to_string(), key);
+        }
+        if let Ok(key) = std::env::var("GROQ_API_KEY") {
+            keys.insert("groq".to_string(), key);
+        }
+
+        keys
+    }
+
+    fn init_providers(api_keys: &HashMap<String, String>, backends: &BackendRegistry) -> Vec<ProviderStatus> {
+        let mut providers = vec![
+            ProviderStatus {
+                name: "google".to_string(),
+                enabled: true,
+                available: api_keys.contains_key("google"),
+                priority: 1, // Primary (Gemini 3 Pro)
+                last_error: None,
+                capabilities: Default::default(),
+                health: Default::default(),
+            },
+            ProviderStatus {
+                name: "anthropic".to_string(),
+                enabled: true,
+                available: api_keys.contains_key("anthropic"),
+                priority: 2, // Fallback 1 (Claude)
+                last_error: None,
+                capabilities: Default::default(),
+                health: Default::default(),
+            },
+            ProviderStatus {
+                name: "openai".to_string(),
+                enabled: true,
+                available: api_keys.contains_key("openai"),
+                priority: 3, // Fallback 2 (GPT-4o)
+                last_error: None,
+                capabilities: Default::default(),
+                health: Default::default(),
+            },
+            ProviderStatus {
+                name: "mistral".to_string(),
+                enabled: true,
+                available: api_keys.contains_key("mistral"),
+                priority: 4,
+                last_error: None,
+                capabilities: Default::default(),
+                health: Default::default(),
+            },
+            ProviderStatus {
+                name: "groq".to_string(),
+                enabled: true,
+                available: api_keys.contains_key("groq"),
+                priority: 5,
+                last_error: None,
+                capabilities: Default::default(),
+                health: Default::default(),
+            },
+            ProviderStatus {
+                name: "ollama".to_string(),
+                enabled: true,
+                available: false, // Don't assume Ollama is running; verify first
+                priority: 6,
+                last_error: None,
+                capabilities: Default::default(),
+                health: Default::default(),
+            },
+            ProviderStatus {
+                name: "local".to_string(),
+                enabled: true,
+                available: true, // Offline detector, last resort
+                priority: 7,
+                last_error: None,
+                capabilities: Default::default(),
+                health: Default::default(),
+            },
+        ];
+
+        for provider in &mut providers {
+            provider.capabilities = backends.capabilities(&provider.name);
+        }
+        providers
+    }
+
+    /// Replace settings, merging provider config field by field. Fails
+    /// without changing anything if an update touches a base URL or timeout.
+    pub fn update_settings(&mut self, update: SettingsUpdate) -> anyhow::Result<()> {
+        let mut providers = self.settings.providers.clone();
+        merge_provider_settings(&mut providers, update.providers)?;
+        self.settings = AppSettings { providers, ..update.settings };
+        Ok(())
+    }
+
+    pub fn set_api_key(&mut self, provider: &str, key: String) {
+        self.api_keys.insert(provider.to_string(), key);
+        self.update_provider_availability(provider, true);
+    }
+
+    pub fn get_api_key(&self, provider: &str) -> Option<&String> {
+        self.api_keys.get(provider)
+    }
+
+    fn update_provider_availability(&mut self, provider: &str, available: bool) {
+        if let Some(p) = self.providers.iter_mut().find(|p| p.name == provider) {
+            p.available = available;
+        }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// `AiProvider` using the shared client and executor and the configured endpoints.
+    pub fn ai_provider(&self) -> AiProvider {
+        AiProvider::with_config(self.client.clone(), self.settings.providers.clone())
+            .with_prompts(self.prompts.clone())
+            .with_executor(self.executor.clone())
+    }
+
+    pub fn get_available_provider(&self) -> Option<&str> {
+        // Check preferred provider first
+        if let Some(ref preferred) = self.settings.preferred_provider {
+            if let Some(provider) = self.providers.iter().find(|p| &p.name == preferred && p.enabled && p.available) {
+                return Some(&provider.name);
+            }
+        }
+        // Fallback to priority-based selection
+        self.providers
+            .iter()
+            .filter(|p| p.enabled && p.available)
+            .min_by_key(|p| p.priority)
+            .map(|p| p.name.as_str())
+    }
+
+    /// Pick the backend for an operation: the head of `backend_chain`.
+    pub fn select_backend(&self, capability: Capability) -> Option<(Arc<dyn RestorationBackend>, BackendContext)> {
+        self.backend_chain(capability).into_iter().next()
+    }
+
+    /// Failover order for an operation: healthy providers first (preferred, then
+    /// by priority). Providers demoted by a recent failure stay in the chain as
+    /// a last resort so they can recover on the next success; providers whose
+    /// circuit is open are left out until it turns half-open.
+    pub fn backend_chain(&self, capability: Capability) -> Vec<(Arc<dyn RestorationBackend>, BackendContext)> {
+        let preferred = self.settings.preferred_provider.as_deref();
+        let now = Utc::now();
+        let mut candidates: Vec<&ProviderStatus> = self
+            .providers
+            .iter()
+            .filter(|p| p.enabled && p.capabilities.supports(capability))
+            .filter(|p| p.available || p.last_error.is_some())
+            .filter(|p| p.health.allows_requests(now))
+            .collect();
+        candidates.sort_by_key(|p| (!p.available, Some(p.name.as_str()) != preferred, p.priority));
+
+        candidates
+            .into_iter()
+            .filter_map(|p| {
+                let backend = self.backends.get(&p.name)?;
+                let ctx = BackendContext {
+                    client: self.client.clone(),
+                    executor: self.executor.clone(),
+                    api_key: self.api_keys.get(&p.name).cloned(),
+                    config: self.settings.providers.clone(),
+                    prompts: self.prompts.clone(),
+                };
+                Some((backend, ctx))
+            })
+            .collect()
+    }
+
+    /// Claim a call to `provider` from its circuit breaker; `false` while
+    /// another caller runs its half-open trial.
+    pub fn claim_provider(&mut self, provider: &str) -> bool {
+        let now = Utc::now();
+        self.providers
+            .iter_mut()
+            .find(|p| p.name == provider)
+            .map_or(true, |p| p.health.claim(now))
+    }
+
+    /// Mark a provider healthy after a successful call that took `latency`;
+    /// closes its circuit.
+    pub fn record_provider_success(&mut self, provider: &str, latency: Duration) {
+        if let Some(p) = self.providers.iter_mut().find(|p| p.name == provider) {
+            p.available = true;
+            p.last_error = None;
+            p.health.record_success(Utc::now());
+            p.health.latency.record(latency);
+        }
+    }
+
+    /// Record a failed call; retryable failures (outage, rate limit) also
+    /// demote the provider behind healthy ones and count towards its
+    /// circuit breaker.
+    pub fn record_provider_failure(&mut self, provider: &str, error: String, retryable: bool) {
+        let config = self.circuit_breaker;
+        if let Some(p) = self.providers.iter_mut().find(|p| p.name == provider) {
+            p.last_error = Some(error);
+            if retryable {
+                p.available = false;
+                p.health.record_failure(&config, Utc::now());
+            } else {
+                p.health.release_trial();
+            }
+        }
+    }
+
+    /// Record a health probe: its latency, or the error as a failure. Any
+    /// failed probe demotes the provider; only retryable ones count towards
+    /// its circuit breaker.
+    pub fn record_probe(&mut self, provider: &str, result: Result<Duration, ProbeFailure>) {
+        let now = Utc::now();
+        let failed = result.is_err();
+        match result {
+            Ok(latency) => self.record_provider_success(provider, latency),
+            Err(failure) => self.record_provider_failure(provider, failure.error, failure.retryable),
+        }
+        if let Some(p) = self.providers.iter_mut().find(|p| p.name == provider) {
+            if failed {
+                p.available = false;
+            }
+            p.health.last_checked = Some(now);
+        }
+    }
+
+    /// Provider statuses, with circuits whose open period is over shown half-open.
+    pub fn provider_statuses(&mut self) -> Vec<ProviderStatus> {
+        let now = Utc::now();
+        for p in &mut self.providers {
+            p.health.refresh(now);
+        }
+        self.providers.clone()
+    }
+
+    /// Record an entry; a storage failure is logged, never fails the operation.
+    pub fn add_history(&mut self, entry: HistoryEntry) {
+        if let Err(e) = self.history.add(entry) {
+            warn!("Failed to persist history entry: {:#}", e);
+        }
+    }
+
+    pub fn clear_history(&mut self) -> anyhow::Result<()> {
+        self.history.clear()
+    }
+
+    pub fn uptime_seconds(&self) -> u64 {
+        self.start_time.elapsed().as_secs()
+    }
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
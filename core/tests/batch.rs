@@ -0,0 +1,182 @@
+// core/tests/batch.rs
+//! Folder batches: output names, manifests, resuming and path confinement.
+
+mod common;
+
+use common::{chat_reply, state_with, MockServer};
+use serde_json::json;
+use std::path::Path;
+use tissaia_core::batch::{run_batch, BatchRequest};
+#[cfg(feature = "image-processing")]
+use tissaia_core::batch::{BatchManifest, ScanStatus, MANIFEST_CSV, MANIFEST_JSON};
+#[cfg(feature = "image-processing")]
+use tissaia_core::pipeline::PipelineOptions;
+use tissaia_core::ApiError;
+
+fn request(input_dir: &Path, output_dir: &Path) -> BatchRequest {
+    serde_json::from_value(json!({"input_dir": input_dir, "output_dir": output_dir})).unwrap()
+}
+
+#[cfg(feature = "image-processing")]
+fn write_scan(path: &Path, width: u32) {
+    let img = image::RgbImage::from_fn(width, 200, |x, _| {
+        if x < width / 2 { image::Rgb([180, 120, 90]) } else { image::Rgb([90, 140, 200]) }
+    });
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    // Always PNG bytes; the decoder sniffs the format, not the extension
+    img.save_with_format(path, image::ImageFormat::Png).unwrap();
+}
+
+#[cfg(feature = "image-processing")]
+fn two_photo_detection() -> common::MockReply {
+    chat_reply(
+        &json!({
+            "photo_count": 2,
+            "bounding_boxes": [
+                {"x": 50, "y": 100, "width": 400, "height": 800, "confidence": 0.9},
+                {"x": 550, "y": 100, "width": 400, "height": 800, "confidence": 0.9}
+            ]
+        })
+        .to_string(),
+    )
+}
+
+#[cfg(feature = "image-processing")]
+fn detect_and_crop_only(mut request: BatchRequest) -> BatchRequest {
+    request.options = PipelineOptions { verify: false, outpaint: false, restore: false, ..PipelineOptions::default() };
+    request
+}
+
+#[cfg(feature = "image-processing")]
+fn read_manifest(output_dir: &Path) -> BatchManifest {
+    serde_json::from_slice(&std::fs::read(output_dir.join(MANIFEST_JSON)).unwrap()).unwrap()
+}
+
+#[cfg(feature = "image-processing")]
+#[tokio::test]
+async fn writes_photos_and_manifests_then_resumes() {
+    let server = MockServer::start(vec![two_photo_detection()]).await;
+    let state = state_with("groq", &server);
+    let input = tempfile::tempdir().unwrap();
+    write_scan(&input.path().join("album.png"), 400);
+    write_scan(&input.path().join("album.jpg"), 400);
+    write_scan(&input.path().join("box/loose.png"), 400);
+    std::fs::write(input.path().join("notes.txt"), "not a scan").unwrap();
+    // Output inside the input folder is not walked
+    let output = input.path().join("restored");
+
+    let mut batch = detect_and_crop_only(request(input.path(), &output));
+    batch.recursive = true;
+    batch.concurrency = Some(2);
+
+    let summary = run_batch(&state, batch.clone()).await.unwrap();
+    assert_eq!((summary.total, summary.processed, summary.skipped, summary.failed), (3, 3, 0, 0));
+    assert_eq!(summary.photos_written, 6);
+    // Two scans share a stem: the full file name keeps their photos apart
+    for name in ["album_png_1.png", "album_png_2.png", "album_jpg_1.jpg", "box/loose_1.png", "box/loose_2.png"] {
+        assert!(output.join(name).is_file(), "missing {}", name);
+    }
+
+    let manifest = read_manifest(&output);
+    let sources: Vec<&str> = manifest.entries.iter().map(|e| e.source.as_str()).collect();
+    assert_eq!(sources, vec!["album.jpg", "album.png", "box/loose.png"]);
+    assert!(manifest.entries.iter().all(|e| e.status == ScanStatus::Completed));
+    let csv = std::fs::read_to_string(output.join(MANIFEST_CSV)).unwrap();
+    assert_eq!(csv.lines().count(), 1 + 6);
+    assert!(csv.contains("box/loose.png,completed,2,box/loose_2.png,false,,true,"));
+    assert_eq!(server.requests().len(), 3);
+
+    // Second run: nothing to do
+    let summary = run_batch(&state, batch.clone()).await.unwrap();
+    assert_eq!((summary.processed, summary.skipped), (0, 3));
+    assert_eq!(server.requests().len(), 3);
+
+    // A changed scan and a deleted output are processed again
+    write_scan(&input.path().join("album.png"), 600);
+    std::fs::remove_file(output.join("box/loose_1.png")).unwrap();
+    let summary = run_batch(&state, batch).await.unwrap();
+    assert_eq!((summary.processed, summary.skipped), (2, 1));
+    assert!(output.join("box/loose_1.png").is_file());
+    assert_eq!(server.requests().len(), 5);
+}
+
+#[cfg(feature = "image-processing")]
+#[tokio::test]
+async fn failed_scans_are_recorded_and_retried() {
+    let server = MockServer::start(vec![two_photo_detection()]).await;
+    let state = state_with("groq", &server);
+    let input = tempfile::tempdir().unwrap();
+    let output = tempfile::tempdir().unwrap();
+    write_scan(&input.path().join("good.png"), 400);
+    std::fs::write(input.path().join("broken.jpg"), b"not really a jpeg").unwrap();
+    let batch = detect_and_crop_only(request(input.path(), output.path()));
+
+    let summary = run_batch(&state, batch.clone()).await.unwrap();
+    assert_eq!((summary.processed, summary.failed, summary.photos_written), (2, 1, 2));
+    let manifest = read_manifest(output.path());
+    let broken = manifest.entries.iter().find(|e| e.source == "broken.jpg").unwrap();
+    assert_eq!(broken.status, ScanStatus::Failed);
+    assert!(broken.photos.is_empty());
+    assert!(broken.error.is_some());
+
+    let summary = run_batch(&state, batch).await.unwrap();
+    assert_eq!((summary.processed, summary.skipped, summary.failed), (1, 1, 1));
+}
+
+#[cfg(feature = "image-processing")]
+#[tokio::test]
+async fn changed_options_reprocess_and_remove_stale_outputs() {
+    let one_photo = chat_reply(
+        &json!({"photo_count": 1, "bounding_boxes": [{"x": 50, "y": 100, "width": 900, "height": 800, "confidence": 0.9}]})
+            .to_string(),
+    );
+    let server = MockServer::start(vec![two_photo_detection(), one_photo]).await;
+    let state = state_with("groq", &server);
+    let input = tempfile::tempdir().unwrap();
+    let output = tempfile::tempdir().unwrap();
+    write_scan(&input.path().join("scan.png"), 400);
+    let batch = detect_and_crop_only(request(input.path(), output.path()));
+
+    run_batch(&state, batch.clone()).await.unwrap();
+    assert!(output.path().join("scan_2.png").is_file());
+
+    let mut sharpened = batch.clone();
+    sharpened.options.filters = vec!["sharpen".to_string()];
+    let summary = run_batch(&state, sharpened.clone()).await.unwrap();
+    assert_eq!((summary.processed, summary.skipped), (1, 0));
+    assert!(output.path().join("scan_1.png").is_file());
+    assert!(!output.path().join("scan_2.png").exists());
+
+    let manifest = read_manifest(output.path());
+    assert_eq!(manifest.entries[0].photos.len(), 1);
+    assert_eq!(manifest.entries[0].options.as_ref(), Some(&sharpened.options));
+
+    // Same options again: nothing to do
+    let summary = run_batch(&state, sharpened).await.unwrap();
+    assert_eq!((summary.processed, summary.skipped), (0, 1));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn missing_input_folder_is_a_validation_error() {
+    let server = MockServer::start(vec![chat_reply("{}")]).await;
+    let output = tempfile::tempdir().unwrap();
+    let error: ApiError =
+        run_batch(&state_with("groq", &server), request(&output.path().join("missing"), output.path()))
+            .await
+            .unwrap_err()
+            .into();
+    assert_eq!(error.code, "validation_error");
+}
+
+#[test]
+fn confined_requests_stay_below_the_root() {
+    let root = Path::new("/data/scans");
+
+    let batch = request(Path::new("2024/in"), Path::new("/data/scans/2024/out")).confined_to(root).unwrap();
+    assert_eq!(batch.input_dir, root.join("2024/in"));
+    assert_eq!(batch.output_dir, root.join("2024/out"));
+
+    assert!(request(Path::new("../etc"), Path::new("out")).confined_to(root).is_err());
+    assert!(request(Path::new("in"), Path::new("/tmp/out")).confined_to(root).is_err());
+}
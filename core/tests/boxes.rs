@@ -0,0 +1,195 @@
+// core/tests/boxes.rs
+//! Box conflict resolution: a few worked examples plus invariants checked
+//! over seeded random box sets.
+
+use tissaia_core::boxes::{self, BoxChange};
+use tissaia_core::models::{BoundingBox, Point2D};
+
+fn bbox(x: u32, y: u32, width: u32, height: u32, confidence: f32) -> BoundingBox {
+    BoundingBox {
+        x,
+        y,
+        width,
+        height,
+        confidence,
+        label: None,
+        rotation_angle: 0.0,
+        contour: Vec::new(),
+        needs_outpaint: false,
+        edges_snapped: false,
+    }
+}
+
+fn overlaps(a: &BoundingBox, b: &BoundingBox) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+fn within(inner: &BoundingBox, outer: &BoundingBox) -> bool {
+    inner.x >= outer.x
+        && inner.y >= outer.y
+        && inner.x + inner.width <= outer.x + outer.width
+        && inner.y + inner.height <= outer.y + outer.height
+}
+
+/// Random boxes inside the 0-1000 space: some disjoint, some touching,
+/// some nested and some duplicated with jitter.
+fn random_boxes(rng: &mut fastrand::Rng) -> Vec<BoundingBox> {
+    let count = rng.usize(0..12);
+    let mut list: Vec<BoundingBox> = Vec::new();
+    for _ in 0..count {
+        let next = match (list.is_empty(), rng.u8(0..4)) {
+            (false, 0) => {
+                // Near-duplicate of an earlier box
+                let base = list[rng.usize(0..list.len())].clone();
+                let x = (base.x + rng.u32(0..20)).min(990);
+                let y = (base.y + rng.u32(0..20)).min(990);
+                bbox(x, y, base.width.min(1000 - x).max(1), base.height.min(1000 - y).max(1), rng.f32())
+            }
+            (false, 1) => {
+                // Detail inside an earlier box
+                let base = list[rng.usize(0..list.len())].clone();
+                let width = rng.u32(1..=base.width);
+                let height = rng.u32(1..=base.height);
+                bbox(base.x + rng.u32(0..=base.width - width), base.y + rng.u32(0..=base.height - height), width, height, rng.f32())
+            }
+            _ => {
+                let x = rng.u32(0..990);
+                let y = rng.u32(0..990);
+                bbox(x, y, rng.u32(1..=(1000 - x).min(400)), rng.u32(1..=(1000 - y).min(400)), rng.f32())
+            }
+        };
+        list.push(next);
+    }
+    list
+}
+
+#[test]
+fn duplicate_keeps_the_more_confident_box() {
+    let mut list = vec![bbox(100, 100, 300, 300, 0.6), bbox(110, 105, 295, 300, 0.9)];
+    let changes = boxes::resolve(&mut list);
+
+    assert_eq!(list, vec![bbox(110, 105, 295, 300, 0.9)]);
+    assert!(matches!(changes[..], [BoxChange::Duplicate { dropped: 0, kept: 1, .. }]));
+}
+
+#[test]
+fn group_box_is_dropped_and_members_kept() {
+    let mut list = vec![
+        bbox(50, 50, 900, 400, 0.9),
+        bbox(60, 60, 400, 380, 0.8),
+        bbox(520, 60, 400, 380, 0.8),
+    ];
+    let changes = boxes::resolve(&mut list);
+
+    assert_eq!(list.len(), 2);
+    assert_eq!(changes, vec![BoxChange::Group { dropped: 0, members: 2 }]);
+}
+
+#[test]
+fn detail_inside_a_photo_is_dropped() {
+    let mut list = vec![bbox(100, 100, 400, 400, 0.7), bbox(200, 200, 50, 50, 0.95)];
+    let changes = boxes::resolve(&mut list);
+
+    assert_eq!(list, vec![bbox(100, 100, 400, 400, 0.7)]);
+    assert_eq!(changes, vec![BoxChange::Contained { dropped: 1, container: 0 }]);
+}
+
+#[test]
+fn staggered_neighbours_are_split_in_the_middle() {
+    let mut list = vec![bbox(100, 100, 300, 300, 0.9), bbox(380, 110, 300, 300, 0.9)];
+    let changes = boxes::resolve(&mut list);
+
+    assert_eq!(list[0].x + list[0].width, 390);
+    assert_eq!(list[1].x, 390);
+    assert_eq!(list[1].x + list[1].width, 680);
+    assert_eq!(changes, vec![BoxChange::Split { a: 0, b: 1 }]);
+}
+
+#[test]
+fn shrunk_box_loses_its_snapped_outline() {
+    let mut snapped = bbox(100, 100, 300, 300, 0.9);
+    snapped.edges_snapped = true;
+    snapped.contour = vec![
+        Point2D { x: 100.0, y: 100.0 },
+        Point2D { x: 400.0, y: 100.0 },
+        Point2D { x: 400.0, y: 400.0 },
+        Point2D { x: 100.0, y: 400.0 },
+    ];
+    let mut list = vec![snapped, bbox(380, 110, 300, 300, 0.9)];
+    boxes::resolve(&mut list);
+
+    assert!(!list[0].edges_snapped);
+    assert!(list[0].contour.is_empty());
+}
+
+#[test]
+fn property_no_overlaps_remain() {
+    for seed in 0..500 {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let mut list = random_boxes(&mut rng);
+        boxes::resolve(&mut list);
+        for (i, a) in list.iter().enumerate() {
+            for b in &list[i + 1..] {
+                assert!(!overlaps(a, b), "seed {}: {:?} overlaps {:?}", seed, a, b);
+            }
+        }
+    }
+}
+
+#[test]
+fn property_boxes_only_shrink_or_disappear() {
+    for seed in 0..500 {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let input = random_boxes(&mut rng);
+        let mut list = input.clone();
+        let changes = boxes::resolve(&mut list);
+
+        let dropped: Vec<usize> = changes.iter().filter_map(BoxChange::dropped).collect();
+        let kept: Vec<&BoundingBox> = input.iter().enumerate()
+            .filter(|(i, _)| !dropped.contains(i))
+            .map(|(_, b)| b)
+            .collect();
+        assert_eq!(list.len(), kept.len(), "seed {}", seed);
+        assert!(list.len() <= input.len());
+        for (output, original) in list.iter().zip(kept) {
+            assert!(within(output, original), "seed {}: {:?} outside {:?}", seed, output, original);
+            assert!(output.width >= 1 && output.height >= 1, "seed {}: empty box {:?}", seed, output);
+            assert_eq!(output.confidence, original.confidence);
+        }
+    }
+}
+
+#[test]
+fn property_resolving_twice_changes_nothing() {
+    for seed in 0..500 {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let mut list = random_boxes(&mut rng);
+        boxes::resolve(&mut list);
+        let once = list.clone();
+
+        assert_eq!(boxes::resolve(&mut list), Vec::new(), "seed {}", seed);
+        assert_eq!(list, once);
+    }
+}
+
+#[test]
+fn property_disjoint_boxes_are_untouched() {
+    for seed in 0..200 {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        // One box per cell of a 4x4 grid, each strictly inside its cell
+        let cells: Vec<u32> = (0..16).filter(|_| rng.bool()).collect();
+        let mut list: Vec<BoundingBox> = cells
+            .into_iter()
+            .map(|cell| {
+                let (cx, cy) = ((cell % 4) * 250, (cell / 4) * 250);
+                let x = cx + rng.u32(0..100);
+                let y = cy + rng.u32(0..100);
+                bbox(x, y, rng.u32(1..=cx + 250 - x), rng.u32(1..=cy + 250 - y), rng.f32())
+            })
+            .collect();
+        let input = list.clone();
+
+        assert_eq!(boxes::resolve(&mut list), Vec::new(), "seed {}", seed);
+        assert_eq!(list, input);
+    }
+}
@@ -0,0 +1,147 @@
+// core/tests/chat_providers.rs
+//! Mistral / Groq (OpenAI-compatible chat) providers against a local mock server.
+
+mod common;
+
+use common::{app_with, chat_reply, shared, use_mock, MockReply, MockServer};
+use serde_json::json;
+use tissaia_core::ai::{AiProvider, ChatProvider, Verifier};
+use tissaia_core::config::default_provider_configs;
+use tissaia_core::failover::is_retryable;
+use tissaia_core::models::{BoundingBox, RestorationOptions, VerificationStatus};
+use tissaia_core::operations;
+
+const IMAGE: &str = "aGVsbG8=";
+/// 4x4 PNG, for calls that reach the local restoration engine.
+const PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAQAAAAECAIAAAAmkwkpAAAAEElEQVR4nGM4UREARwzEcQCR0hkB/rK5kAAAAABJRU5ErkJggg==";
+
+fn ai_for(provider: &str, server: &MockServer) -> AiProvider {
+    let mut configs = default_provider_configs();
+    use_mock(&mut configs, provider, server);
+    AiProvider::with_config(reqwest::Client::new(), configs)
+}
+
+#[tokio::test]
+async fn mistral_restoration_parses_plan_and_sends_vision_request() {
+    let server = MockServer::start(vec![chat_reply(
+        r#"{"improvements": ["Scratches removed", "Faded colors restored"], "processing_steps": []}"#,
+    )])
+    .await;
+    let result = ai_for("mistral", &server)
+        .restore_with_chat(ChatProvider::MISTRAL, "test-key", IMAGE, "image/jpeg", &RestorationOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(result.provider_used, "mistral");
+    assert_eq!(result.restored_image, IMAGE);
+    assert_eq!(result.improvements, vec!["Scratches removed", "Faded colors restored"]);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/v1/chat/completions");
+    assert_eq!(requests[0].authorization.as_deref(), Some("Bearer test-key"));
+    assert_eq!(requests[0].body["model"], "pixtral-large-latest");
+    assert_eq!(requests[0].body["response_format"]["type"], "json_schema");
+    assert_eq!(requests[0].body["response_format"]["json_schema"]["name"], "restoration_plan");
+    assert_eq!(
+        requests[0].body["messages"][0]["content"][1]["image_url"]["url"],
+        format!("data:image/jpeg;base64,{}", IMAGE)
+    );
+}
+
+#[tokio::test]
+async fn groq_detection_parses_fenced_json() {
+    let server = MockServer::start(vec![chat_reply(
+        "```json\n{\"photo_count\": 2, \"bounding_boxes\": [\
+         {\"x\": 10, \"y\": 20, \"width\": 400, \"height\": 300, \"confidence\": 0.9, \"label\": \"photo 1\", \"rotation_angle\": 90},\
+         {\"x\": 520, \"y\": 20, \"width\": 400, \"height\": 300, \"confidence\": 0.8, \"label\": \"photo 2\", \"rotation_angle\": 0}\
+         ]}\n```",
+    )])
+    .await;
+    let result = ai_for("groq", &server)
+        .detect_with_chat(ChatProvider::GROQ, "test-key", IMAGE, "image/png")
+        .await
+        .unwrap();
+
+    assert_eq!(result.provider_used, "groq");
+    assert_eq!(result.photo_count, 2);
+    assert_eq!(result.bounding_boxes[0].x, 10);
+    assert_eq!(result.bounding_boxes[0].rotation_angle, 90.0);
+    assert_eq!(result.bounding_boxes[1].label.as_deref(), Some("photo 2"));
+}
+
+#[tokio::test]
+async fn chat_verifier_populates_verification_result() {
+    let server = MockServer::start(vec![chat_reply(
+        &json!({
+            "status": "fail",
+            "confidence": 40,
+            "checks": [{"name": "completeness", "passed": false, "detail": "one photo missed"}],
+            "issues": [],
+            "recommendations": ["re-run detection"],
+            "missing_boxes": [{"x": 20, "y": 600, "width": 300, "height": 200, "confidence": 0.7}]
+        })
+        .to_string(),
+    )])
+    .await;
+    let boxes = vec![BoundingBox {
+        x: 0,
+        y: 0,
+        width: 500,
+        height: 500,
+        confidence: 0.9,
+        label: Some("photo 1".to_string()),
+        rotation_angle: 0.0,
+        contour: Vec::new(),
+        needs_outpaint: false,
+        edges_snapped: false,
+    }];
+
+    let result = ai_for("mistral", &server)
+        .verify_detection(Verifier::Chat(ChatProvider::MISTRAL), "test-key", IMAGE, "image/jpeg", &boxes)
+        .await
+        .unwrap();
+
+    assert!(matches!(result.status, VerificationStatus::Fail));
+    assert_eq!(result.confidence, 40);
+    assert_eq!(result.model_used, "pixtral-large-latest");
+    assert_eq!(result.missing_boxes.len(), 1);
+    assert_eq!(result.missing_boxes[0].y, 600);
+}
+
+#[tokio::test]
+async fn rate_limited_response_is_retryable() {
+    let server = MockServer::start(vec![MockReply::status(429, json!({"error": "slow down"}))]).await;
+    let err = ai_for("groq", &server)
+        .restore_with_chat(ChatProvider::GROQ, "test-key", IMAGE, "image/jpeg", &RestorationOptions::default())
+        .await
+        .unwrap_err();
+
+    assert!(is_retryable(&err));
+    assert!(err.to_string().contains("Groq API error (429)"));
+}
+
+#[tokio::test]
+async fn restoration_fails_over_from_mistral_outage_to_groq() {
+    let mistral = MockServer::start(vec![MockReply::status(503, json!({"error": "overloaded"}))]).await;
+    let groq = MockServer::start(vec![chat_reply(r#"{"improvements": ["Denoised"]}"#)]).await;
+
+    let state = shared(app_with(&[("mistral", &mistral), ("groq", &groq)]));
+
+    let result = operations::restore_image(&state, PNG.to_string(), "image/png".to_string(), &RestorationOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(result.provider_used, "groq");
+    assert_eq!(result.attempts.len(), 2);
+    assert_eq!(result.attempts[0].provider, "mistral");
+    assert!(!result.attempts[0].success && result.attempts[0].retryable);
+    assert!(result.attempts[1].success);
+
+    let state = state.lock().await;
+    let mistral_status = state.providers.iter().find(|p| p.name == "mistral").unwrap();
+    assert!(!mistral_status.available);
+    assert!(mistral_status.last_error.as_deref().unwrap().contains("503"));
+    let groq_status = state.providers.iter().find(|p| p.name == "groq").unwrap();
+    assert!(groq_status.available && groq_status.last_error.is_none());
+}
@@ -0,0 +1,167 @@
+// core/tests/common/mod.rs
+//! Local mock HTTP server for provider tests.
+//! Replies with canned responses in order (the last one repeats) and
+//! records every request body so tests can assert on what was sent.
+//! `state_with` / `app_with` build an `AppState` whose providers talk to it.
+
+#![allow(dead_code)]
+
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::any;
+use axum::{Json, Router};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tissaia_core::config::ProviderConfigs;
+use tissaia_core::{AppState, SharedState};
+
+#[derive(Clone)]
+pub struct MockReply {
+    pub status: StatusCode,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Value,
+    pub delay: Option<Duration>,
+}
+
+impl MockReply {
+    pub fn ok(body: Value) -> Self {
+        Self { status: StatusCode::OK, headers: Vec::new(), body, delay: None }
+    }
+
+    pub fn status(status: u16, body: Value) -> Self {
+        Self {
+            status: StatusCode::from_u16(status).expect("valid status"),
+            headers: Vec::new(),
+            body,
+            delay: None,
+        }
+    }
+
+    /// Hold the response back, e.g. to observe an operation mid-flight.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub path: String,
+    pub authorization: Option<String>,
+    pub body: Value,
+}
+
+#[derive(Clone)]
+struct MockState {
+    replies: Arc<Vec<MockReply>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+pub struct MockServer {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    /// Start a server on an ephemeral port serving `replies` in order.
+    pub async fn start(replies: Vec<MockReply>) -> Self {
+        assert!(!replies.is_empty(), "mock server needs at least one reply");
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let state = MockState { replies: Arc::new(replies), requests: requests.clone() };
+
+        let app = Router::new().fallback(any(handle)).with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind mock server");
+        let addr = listener.local_addr().expect("mock server address");
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("mock server");
+        });
+
+        Self { base_url: format!("http://{}", addr), requests }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn handle(
+    State(state): State<MockState>,
+    uri: axum::http::Uri,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Response {
+    let index = {
+        let mut requests = state.requests.lock().unwrap();
+        requests.push(RecordedRequest {
+            path: uri.path().to_string(),
+            authorization: headers
+                .get("authorization")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string()),
+            body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+        });
+        requests.len() - 1
+    };
+
+    let reply = &state.replies[index.min(state.replies.len() - 1)];
+    if let Some(delay) = reply.delay {
+        tokio::time::sleep(delay).await;
+    }
+    let mut response = (reply.status, Json(reply.body.clone())).into_response();
+    for (name, value) in &reply.headers {
+        response.headers_mut().insert(*name, value.parse().expect("header value"));
+    }
+    response
+}
+
+/// OpenAI-style chat completion whose message content is `content`.
+pub fn chat_reply(content: &str) -> MockReply {
+    MockReply::ok(serde_json::json!({
+        "choices": [{"message": {"role": "assistant", "content": content}}]
+    }))
+}
+
+/// Point `provider` at `server` in `configs` (`/v1`, bare for Ollama),
+/// without retries.
+pub fn use_mock(configs: &mut ProviderConfigs, provider: &str, server: &MockServer) {
+    let config = configs.get_mut(provider).expect("known provider");
+    config.base_url = server.url(if provider == "ollama" { "" } else { "/v1" });
+    // Fail on the first error; retries are covered in `request_retry.rs`
+    config.limits.max_retries = 0;
+}
+
+/// App with exactly `providers` enabled, each served by its mock server and
+/// given a `<name>-key` API key (Ollama needs none).
+pub fn app_with(providers: &[(&str, &MockServer)]) -> AppState {
+    let mut app = AppState::new();
+    for p in &mut app.providers {
+        p.enabled = providers.iter().any(|(name, _)| *name == p.name);
+    }
+    for (name, server) in providers {
+        use_mock(&mut app.settings.providers, name, server);
+        if *name != "ollama" {
+            app.set_api_key(name, format!("{}-key", name));
+        }
+    }
+    app
+}
+
+/// Shared state with only `provider` enabled, served by `server`.
+pub fn state_with(provider: &str, server: &MockServer) -> SharedState {
+    shared(app_with(&[(provider, server)]))
+}
+
+pub fn shared(app: AppState) -> SharedState {
+    Arc::new(tokio::sync::Mutex::new(app))
+}
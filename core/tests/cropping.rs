@@ -0,0 +1,178 @@
+// core/tests/cropping.rs
+//! crop_photos: deskewing by arbitrary angles, perspective correction from
+//! contours, and the reported rotation.
+#![cfg(feature = "image-processing")]
+
+use base64::Engine;
+use image::{GenericImageView, Rgb, RgbImage};
+use tissaia_core::imaging::{crop_photos, decode_base64_image};
+use tissaia_core::models::{BoundingBox, CroppedPhoto, Point2D};
+
+const PHOTO: [u8; 3] = [200, 150, 90];
+const BED: [u8; 3] = [10, 10, 10];
+
+/// 1000x1000 bed with a 400x240 photo centred at (500, 500), turned `skew`
+/// degrees clockwise. Returns the scan and the photo corners in 0-1000.
+fn skewed_scan(skew: f64) -> (String, Vec<Point2D>) {
+    let (sin, cos) = skew.to_radians().sin_cos();
+    let img = RgbImage::from_fn(1000, 1000, |x, y| {
+        let (dx, dy) = (x as f64 + 0.5 - 500.0, y as f64 + 0.5 - 500.0);
+        let (u, v) = (dx * cos + dy * sin, -dx * sin + dy * cos);
+        Rgb(if u.abs() <= 200.0 && v.abs() <= 120.0 { PHOTO } else { BED })
+    });
+    let corners = [(-200.0, -120.0), (200.0, -120.0), (200.0, 120.0), (-200.0, 120.0)]
+        .iter()
+        .map(|&(u, v)| Point2D { x: (500.0 + u * cos - v * sin) as f32, y: (500.0 + u * sin + v * cos) as f32 })
+        .collect();
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+    (base64::engine::general_purpose::STANDARD.encode(bytes.into_inner()), corners)
+}
+
+/// Bounding box of `corners`, as detection would report it.
+fn enclosing_box(corners: &[Point2D], rotation_angle: f32) -> BoundingBox {
+    let min_x = corners.iter().map(|p| p.x).fold(f32::MAX, f32::min).floor() as u32;
+    let min_y = corners.iter().map(|p| p.y).fold(f32::MAX, f32::min).floor() as u32;
+    let max_x = corners.iter().map(|p| p.x).fold(0.0, f32::max).ceil() as u32;
+    let max_y = corners.iter().map(|p| p.y).fold(0.0, f32::max).ceil() as u32;
+    BoundingBox {
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x,
+        height: max_y - min_y,
+        confidence: 0.9,
+        label: None,
+        rotation_angle,
+        contour: Vec::new(),
+        needs_outpaint: false,
+        edges_snapped: true,
+    }
+}
+
+fn crop_one(scan: &str, bbox: BoundingBox) -> (CroppedPhoto, image::DynamicImage) {
+    let result = crop_photos(scan, "image/png", &[bbox], "scan.png".to_string()).unwrap();
+    let photo = result.photos.into_iter().next().unwrap();
+    let img = decode_base64_image(&photo.image_base64).unwrap();
+    (photo, img)
+}
+
+/// Every pixel within `inset` of the border is photo, not bed.
+fn no_bed_wedges(img: &image::DynamicImage, inset: u32) -> bool {
+    let (w, h) = img.dimensions();
+    [(inset, inset), (w - 1 - inset, inset), (inset, h - 1 - inset), (w - 1 - inset, h - 1 - inset)]
+        .iter()
+        .all(|&(x, y)| img.get_pixel(x, y).0[..3].iter().any(|&c| c > 150))
+}
+
+#[test]
+fn skewed_photo_is_straightened_to_its_inscribed_rectangle() {
+    let (scan, corners) = skewed_scan(7.0);
+    let (photo, img) = crop_one(&scan, enclosing_box(&corners, 7.0));
+
+    assert!((photo.applied_rotation - 7.0).abs() < 1e-4);
+    assert!((img.width() as i32 - 400).abs() <= 4, "{}x{}", img.width(), img.height());
+    assert!((img.height() as i32 - 240).abs() <= 4, "{}x{}", img.width(), img.height());
+    assert!(no_bed_wedges(&img, 2));
+}
+
+#[test]
+fn contour_corners_drive_the_deskew_after_quarter_turns() {
+    let (scan, corners) = skewed_scan(-4.5);
+    let mut bbox = enclosing_box(&corners, 90.0 - 4.5);
+    bbox.contour = corners;
+    let (photo, img) = crop_one(&scan, bbox);
+
+    assert!((photo.applied_rotation - 85.5).abs() < 1e-4);
+    // Turned back by a quarter: the long side is now vertical
+    assert!((img.width() as i32 - 240).abs() <= 3, "{}x{}", img.width(), img.height());
+    assert!((img.height() as i32 - 400).abs() <= 3, "{}x{}", img.width(), img.height());
+    assert!(no_bed_wedges(&img, 2));
+}
+
+#[test]
+fn photos_needing_outpaint_keep_their_corners() {
+    let (scan, corners) = skewed_scan(10.0);
+    let mut bbox = enclosing_box(&corners, 10.0);
+    bbox.needs_outpaint = true;
+    let (width, height) = (bbox.width, bbox.height);
+    let (photo, img) = crop_one(&scan, bbox);
+
+    assert!((photo.applied_rotation - 10.0).abs() < 1e-4);
+    assert!(img.width() >= width && img.height() >= height);
+    // The corners are left for outpainting to fill
+    assert!(img.get_pixel(1, 1)[0] < 100);
+}
+
+#[test]
+fn negligible_skew_is_not_resampled() {
+    let (scan, corners) = skewed_scan(0.0);
+    let (photo, img) = crop_one(&scan, enclosing_box(&corners, 0.05));
+
+    assert_eq!(photo.applied_rotation, 0.0);
+    assert_eq!((img.width(), img.height()), (400, 240));
+}
+
+/// Scan with a photo photographed at an angle: quadrants red, green, blue,
+/// white (reading order) painted onto the quadrilateral `quad` (pixels).
+fn perspective_scan(quad: [(f64, f64); 4]) -> String {
+    let mut img = RgbImage::from_pixel(1000, 800, Rgb(BED));
+    let steps = 2000;
+    for i in 0..steps {
+        for j in 0..steps {
+            let (u, v) = (i as f64 / steps as f64, j as f64 / steps as f64);
+            let at = |k: usize| {
+                (1.0 - u) * (1.0 - v) * [quad[0].0, quad[0].1][k]
+                    + u * (1.0 - v) * [quad[1].0, quad[1].1][k]
+                    + u * v * [quad[2].0, quad[2].1][k]
+                    + (1.0 - u) * v * [quad[3].0, quad[3].1][k]
+            };
+            let colour = match (u < 0.5, v < 0.5) {
+                (true, true) => [220, 30, 30],
+                (false, true) => [30, 220, 30],
+                (true, false) => [30, 30, 220],
+                (false, false) => [240, 240, 240],
+            };
+            img.put_pixel(at(0) as u32, at(1) as u32, Rgb(colour));
+        }
+    }
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+    base64::engine::general_purpose::STANDARD.encode(bytes.into_inner())
+}
+
+#[test]
+fn four_point_contour_is_perspective_corrected() {
+    let quad = [(300.0, 200.0), (700.0, 240.0), (660.0, 560.0), (330.0, 600.0)];
+    let scan = perspective_scan(quad);
+    let corners: Vec<Point2D> =
+        quad.iter().map(|&(x, y)| Point2D { x: (x / 1000.0 * 1000.0) as f32, y: (y / 800.0 * 1000.0) as f32 }).collect();
+    let mut bbox = enclosing_box(&corners, 0.0);
+    bbox.edges_snapped = false;
+    // Corner order does not matter
+    bbox.contour = vec![corners[2].clone(), corners[0].clone(), corners[3].clone(), corners[1].clone()];
+    let (_, img) = crop_one(&scan, bbox);
+
+    // Longer of the opposite edges: top ≈ 402, left ≈ 401
+    assert!((img.width() as i32 - 402).abs() <= 2, "{}x{}", img.width(), img.height());
+    assert!((img.height() as i32 - 401).abs() <= 2, "{}x{}", img.width(), img.height());
+    let (w, h) = (img.width(), img.height());
+    let quadrant = |x: u32, y: u32| img.get_pixel(x, y).0;
+    assert!(quadrant(w / 4, h / 4)[0] > 180 && quadrant(w / 4, h / 4)[1] < 80);
+    assert!(quadrant(3 * w / 4, h / 4)[1] > 180 && quadrant(3 * w / 4, h / 4)[0] < 80);
+    assert!(quadrant(w / 4, 3 * h / 4)[2] > 180 && quadrant(w / 4, 3 * h / 4)[0] < 80);
+    assert!(quadrant(3 * w / 4, 3 * h / 4)[0] > 200);
+    // No bed left in the corners
+    assert!(no_bed_wedges(&img, 3));
+}
+
+#[test]
+fn unusable_contour_falls_back_to_the_rectangle() {
+    let (scan, corners) = skewed_scan(0.0);
+    let mut bbox = enclosing_box(&corners, 0.0);
+    // Three distinct corners only
+    bbox.contour = vec![corners[0].clone(), corners[1].clone(), corners[1].clone(), corners[2].clone()];
+    let (photo, img) = crop_one(&scan, bbox);
+
+    assert_eq!(photo.applied_rotation, 0.0);
+    assert_eq!((img.width(), img.height()), (400, 240));
+}
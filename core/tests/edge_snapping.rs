@@ -0,0 +1,68 @@
+// core/tests/edge_snapping.rs
+//! Hybrid detection: coarse AI boxes are snapped to the photo edges.
+#![cfg(feature = "image-processing")]
+
+mod common;
+
+use base64::Engine;
+use common::{chat_reply, state_with, MockServer};
+use serde_json::json;
+use tissaia_core::operations;
+
+/// 800x600 dark bed with a print at x 200..600 px, y 150..450 px
+/// (250..750 and 250..750 in 0-1000 space).
+fn scan_base64() -> String {
+    let img = image::RgbImage::from_fn(800, 600, |x, y| {
+        if (200..600).contains(&x) && (150..450).contains(&y) {
+            image::Rgb([210, 180, 140])
+        } else {
+            image::Rgb([12, 12, 12])
+        }
+    });
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+    base64::engine::general_purpose::STANDARD.encode(bytes.into_inner())
+}
+
+/// The print, with bed on the left and its bottom edge cut off.
+fn coarse_detection() -> common::MockReply {
+    chat_reply(
+        &json!({
+            "photo_count": 1,
+            "bounding_boxes": [{"x": 220, "y": 240, "width": 520, "height": 480, "confidence": 0.9}]
+        })
+        .to_string(),
+    )
+}
+
+#[tokio::test]
+async fn detected_boxes_are_snapped_and_reported() {
+    let server = MockServer::start(vec![coarse_detection()]).await;
+    let state = state_with("groq", &server);
+
+    let result = operations::detect_photos(&state, &scan_base64(), "image/png").await.unwrap();
+    let bbox = &result.bounding_boxes[0];
+    assert!(bbox.edges_snapped);
+    assert_eq!((bbox.x, bbox.y, bbox.width, bbox.height), (250, 250, 500, 500));
+    assert_eq!(bbox.contour.len(), 4);
+    assert_eq!((result.scan_width, result.scan_height), (800, 600));
+
+    let adjustment = &result.adjustments[0];
+    assert!(adjustment.applied);
+    assert_eq!(adjustment.left.shift, -30.0);
+    assert_eq!(adjustment.bottom.shift, 30.0);
+    assert!(adjustment.skew.unwrap().abs() < 0.5);
+}
+
+#[tokio::test]
+async fn snapping_can_be_turned_off() {
+    let server = MockServer::start(vec![coarse_detection()]).await;
+    let state = state_with("groq", &server);
+    state.lock().await.settings.edge_snapping = false;
+
+    let result = operations::detect_photos(&state, &scan_base64(), "image/png").await.unwrap();
+    let bbox = &result.bounding_boxes[0];
+    assert!(!bbox.edges_snapped);
+    assert_eq!((bbox.x, bbox.y, bbox.width, bbox.height), (220, 240, 520, 480));
+    assert!(result.adjustments.is_empty());
+}
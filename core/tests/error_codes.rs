@@ -0,0 +1,92 @@
+// core/tests/error_codes.rs
+//! Typed error classification: codes, HTTP statuses, provider and retry hints.
+
+mod common;
+
+use common::{state_with, MockReply, MockServer};
+use serde_json::json;
+use tissaia_core::models::RestorationOptions;
+use std::sync::Arc;
+use tissaia_core::{operations, ApiError, AppState, SharedState};
+use tokio::sync::Mutex;
+
+const IMAGE: &str = "aGVsbG8=";
+
+async fn restore_error(state: &SharedState) -> ApiError {
+    operations::restore_image(state, IMAGE.to_string(), "image/jpeg".to_string(), &RestorationOptions::default())
+        .await
+        .unwrap_err()
+        .into()
+}
+
+#[tokio::test]
+async fn provider_rate_limit_maps_to_429_with_retry_after() {
+    let server = MockServer::start(vec![
+        MockReply::status(429, json!({"error": "slow down"})).header("retry-after", "17"),
+    ])
+    .await;
+    let error = restore_error(&state_with("groq", &server)).await;
+
+    assert_eq!(error.code, "rate_limited");
+    assert_eq!(error.status, 429);
+    assert_eq!(error.provider.as_deref(), Some("groq"));
+    assert_eq!(error.retry_after_secs, Some(17));
+}
+
+#[tokio::test]
+async fn provider_outage_maps_to_502() {
+    let server = MockServer::start(vec![MockReply::status(503, json!({"error": "down"}))]).await;
+    let error = restore_error(&state_with("mistral", &server)).await;
+
+    assert_eq!(error.code, "provider_error");
+    assert_eq!(error.status, 502);
+    assert_eq!(error.provider.as_deref(), Some("mistral"));
+    assert!(error.error.contains("All providers failed"));
+}
+
+#[tokio::test]
+async fn missing_provider_maps_to_503() {
+    let server = MockServer::start(vec![MockReply::ok(json!({}))]).await;
+    let state = state_with("groq", &server);
+    state.lock().await.providers.iter_mut().for_each(|p| p.enabled = false);
+
+    let error = restore_error(&state).await;
+    assert_eq!(error.code, "no_provider");
+    assert_eq!(error.status, 503);
+    assert_eq!(error.provider, None);
+}
+
+#[tokio::test]
+async fn disabled_verification_maps_to_feature_disabled() {
+    let server = MockServer::start(vec![MockReply::ok(json!({}))]).await;
+    let state = state_with("groq", &server);
+    state.lock().await.settings.verification_enabled = false;
+
+    let error: ApiError = operations::verify_crop(&state, IMAGE, "image/jpeg", 0).await.unwrap_err().into();
+    assert_eq!(error.code, "feature_disabled");
+    assert_eq!(error.status, 503);
+}
+
+#[tokio::test]
+async fn bad_base64_maps_to_400() {
+    let state: SharedState = Arc::new(Mutex::new(AppState::new()));
+
+    let error: ApiError = operations::save_image(&state, "%%%", "out.png").await.unwrap_err().into();
+    assert_eq!(error.code, "decode_error");
+    assert_eq!(error.status, 400);
+}
+
+#[cfg(feature = "image-processing")]
+#[tokio::test]
+async fn invalid_input_maps_to_4xx() {
+    let state: SharedState = Arc::new(Mutex::new(AppState::new()));
+
+    // Valid base64, but not an image
+    let error: ApiError = operations::rotate_image(&state, IMAGE, "image/png", 90).await.unwrap_err().into();
+    assert_eq!(error.code, "unsupported_format");
+    assert_eq!(error.status, 422);
+
+    let error: ApiError = operations::upscale_image(&state, IMAGE, "image/png", Some(-1.0)).await.unwrap_err().into();
+    assert_eq!(error.code, "validation_error");
+    assert_eq!(error.status, 422);
+}
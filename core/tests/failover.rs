@@ -0,0 +1,86 @@
+// core/tests/failover.rs
+//! Failover chain: provider order, which errors stop it, and the provider
+//! status it leaves behind.
+
+mod common;
+
+use common::{app_with, chat_reply, shared, MockReply, MockServer};
+use serde_json::json;
+use tissaia_core::failover::failed_attempts;
+use tissaia_core::models::ProviderStatus;
+use tissaia_core::{operations, ApiError, SharedState};
+
+const IMAGE: &str = "aGVsbG8=";
+const DETECTION: &str =
+    r#"{"photo_count": 1, "bounding_boxes": [{"x": 0, "y": 0, "width": 500, "height": 500, "confidence": 0.9}]}"#;
+
+async fn status(state: &SharedState, name: &str) -> ProviderStatus {
+    operations::providers_status(state).await.into_iter().find(|p| p.name == name).unwrap()
+}
+
+#[tokio::test]
+async fn retryable_failure_falls_over_in_priority_order() {
+    let mistral = MockServer::start(vec![MockReply::status(503, json!({"error": "overloaded"}))]).await;
+    let groq = MockServer::start(vec![chat_reply(DETECTION)]).await;
+    let state = shared(app_with(&[("groq", &groq), ("mistral", &mistral)]));
+
+    let result = operations::detect_photos(&state, IMAGE, "image/png").await.unwrap();
+
+    assert_eq!(result.provider_used, "groq");
+    let attempts: Vec<_> = result.attempts.iter().map(|a| (a.provider.as_str(), a.success, a.retryable)).collect();
+    assert_eq!(attempts, [("mistral", false, true), ("groq", true, false)]);
+    assert_eq!((mistral.requests().len(), groq.requests().len()), (1, 1));
+
+    // The failed provider is demoted behind the healthy one
+    let result = operations::detect_photos(&state, IMAGE, "image/png").await.unwrap();
+    assert_eq!(result.attempts.len(), 1);
+    assert_eq!(result.attempts[0].provider, "groq");
+    assert_eq!(mistral.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn non_retryable_failure_stops_the_chain() {
+    let mistral = MockServer::start(vec![MockReply::status(401, json!({"error": "bad key"}))]).await;
+    let groq = MockServer::start(vec![chat_reply(DETECTION)]).await;
+    let state = shared(app_with(&[("groq", &groq), ("mistral", &mistral)]));
+
+    let err = operations::detect_photos(&state, IMAGE, "image/png").await.unwrap_err();
+
+    assert_eq!(ApiError::from(&err).code, "provider_error");
+    let attempts = failed_attempts(&err);
+    assert_eq!(attempts.len(), 1);
+    assert_eq!((attempts[0].provider.as_str(), attempts[0].retryable), ("mistral", false));
+    assert!(groq.requests().is_empty());
+}
+
+#[tokio::test]
+async fn failures_update_provider_status() {
+    let mistral = MockServer::start(vec![MockReply::status(401, json!({"error": "bad key"}))]).await;
+    let groq = MockServer::start(vec![MockReply::status(503, json!({"error": "down"})), chat_reply(DETECTION)]).await;
+    let state = shared(app_with(&[("groq", &groq), ("mistral", &mistral)]));
+    state.lock().await.settings.preferred_provider = Some("groq".to_string());
+
+    let err = operations::detect_photos(&state, IMAGE, "image/png").await.unwrap_err();
+    assert_eq!(failed_attempts(&err).len(), 2);
+
+    // Retryable: demoted and counted towards the circuit breaker
+    let groq_status = status(&state, "groq").await;
+    assert!(!groq_status.available);
+    assert!(groq_status.last_error.as_deref().unwrap().contains("503"));
+    assert_eq!(groq_status.health.consecutive_failures, 1);
+
+    // Not retryable: the error is shown, but the provider stays available
+    let mistral_status = status(&state, "mistral").await;
+    assert!(mistral_status.available);
+    assert!(mistral_status.last_error.as_deref().unwrap().contains("401"));
+    assert_eq!(mistral_status.health.consecutive_failures, 0);
+
+    // A success clears the error
+    state.lock().await.settings.preferred_provider = None;
+    state.lock().await.providers.iter_mut().for_each(|p| p.enabled = p.name == "groq");
+    operations::detect_photos(&state, IMAGE, "image/png").await.unwrap();
+    let groq_status = status(&state, "groq").await;
+    assert!(groq_status.available);
+    assert!(groq_status.last_error.is_none());
+    assert_eq!(groq_status.health.consecutive_failures, 0);
+}
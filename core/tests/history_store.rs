@@ -0,0 +1,151 @@
+// core/tests/history_store.rs
+//! SQLite history store: persistence, pagination/filtering, blob dedupe,
+//! concurrent writers and the in-memory blob cap.
+
+use tissaia_core::history::{HistoryQuery, HistoryStore, PreparedBlob};
+use tissaia_core::models::{HistoryEntry, OperationType};
+
+const IMAGE: &str = "aGVsbG8=";
+
+fn entry(operation: OperationType, provider: &str, success: bool) -> HistoryEntry {
+    let mut entry = HistoryEntry::new(operation, "preview".to_string(), provider);
+    entry.success = success;
+    entry
+}
+
+#[test]
+fn entries_and_blobs_survive_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let id = {
+        let store = HistoryStore::open(dir.path()).unwrap();
+        let mut e = entry(OperationType::Restoration, "google", true);
+        e.processing_time_ms = 1234;
+        e.inputs = vec![store.put_blob(IMAGE, "image/jpeg").unwrap()];
+        let id = e.id.clone();
+        store.add(e).unwrap();
+        id
+    };
+
+    let store = HistoryStore::open(dir.path()).unwrap();
+    assert!(store.is_persistent());
+    let loaded = store.get(&id).unwrap().expect("entry reloaded");
+    assert_eq!(loaded.processing_time_ms, 1234);
+    assert_eq!(loaded.inputs[0].mime_type, "image/jpeg");
+    assert_eq!(loaded.inputs[0].size, 5);
+
+    let bytes = store.get_blob(&loaded.inputs[0].hash).unwrap().unwrap();
+    assert_eq!(bytes, b"hello");
+    assert_eq!(store.get_blob_base64(&loaded.inputs[0].hash).unwrap().as_deref(), Some(IMAGE));
+}
+
+#[test]
+fn list_filters_and_paginates_newest_first() {
+    let store = HistoryStore::in_memory();
+    for i in 0..5 {
+        store.add(entry(OperationType::Restoration, "google", i % 2 == 0)).unwrap();
+    }
+    store.add(entry(OperationType::Verification, "mistral", true)).unwrap();
+
+    let all = store.list(&HistoryQuery::default()).unwrap();
+    assert_eq!(all.total, 6);
+    assert_eq!(all.entries[0].operation, OperationType::Verification);
+
+    let restorations = store.list(&HistoryQuery {
+        operation: Some(OperationType::Restoration),
+        offset: Some(1),
+        limit: Some(2),
+        ..Default::default()
+    }).unwrap();
+    assert_eq!(restorations.total, 5);
+    assert_eq!(restorations.entries.len(), 2);
+
+    let failed = store.list(&HistoryQuery { success: Some(false), ..Default::default() }).unwrap();
+    assert_eq!(failed.total, 2);
+
+    let mistral = store.list(&HistoryQuery { provider: Some("mistral".to_string()), ..Default::default() }).unwrap();
+    assert_eq!(mistral.total, 1);
+}
+
+#[test]
+fn shared_blobs_are_kept_until_last_reference_is_deleted() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = HistoryStore::open(dir.path()).unwrap();
+
+    let mut first = entry(OperationType::Restoration, "google", true);
+    let mut second = entry(OperationType::Verification, "google", true);
+    first.inputs = vec![store.put_blob(IMAGE, "image/png").unwrap()];
+    second.inputs = vec![store.put_blob(IMAGE, "image/png").unwrap()];
+    let hash = first.inputs[0].hash.clone();
+    assert_eq!(hash, second.inputs[0].hash);
+    let (first_id, second_id) = (first.id.clone(), second.id.clone());
+    store.add(first).unwrap();
+    store.add(second).unwrap();
+
+    assert!(store.delete(&first_id).unwrap());
+    assert!(store.get_blob(&hash).unwrap().is_some());
+
+    assert!(store.delete(&second_id).unwrap());
+    assert!(store.get_blob(&hash).unwrap().is_none());
+    assert!(!store.delete(&second_id).unwrap());
+    assert!(HistoryStore::open(dir.path()).unwrap().is_empty());
+}
+
+#[test]
+fn oldest_entries_are_pruned_past_the_limit() {
+    let store = HistoryStore::in_memory().with_max_entries(3);
+    let first = entry(OperationType::Restoration, "google", true);
+    let first_id = first.id.clone();
+    store.add(first).unwrap();
+    for _ in 0..3 {
+        store.add(entry(OperationType::Restoration, "google", true)).unwrap();
+    }
+
+    assert_eq!(store.len(), 3);
+    assert!(store.get(&first_id).unwrap().is_none());
+}
+
+#[test]
+fn in_memory_blobs_are_capped_oldest_first() {
+    let store = HistoryStore::in_memory().with_memory_limit(10);
+    let mut ids = Vec::new();
+    for data in [b"aaaa", b"bbbb", b"cccc"] {
+        let mut e = entry(OperationType::Rotate, "local", true);
+        e.inputs = vec![PreparedBlob::new(data.to_vec(), "image/png").blob];
+        ids.push((e.id.clone(), e.inputs[0].hash.clone()));
+        store.add_prepared(e, vec![PreparedBlob::new(data.to_vec(), "image/png")]).unwrap();
+    }
+
+    assert_eq!(store.len(), 3);
+    assert!(store.get(&ids[0].0).unwrap().is_some());
+    assert!(store.get_blob(&ids[0].1).unwrap().is_none());
+    assert_eq!(store.get_blob(&ids[2].1).unwrap().as_deref(), Some(&b"cccc"[..]));
+}
+
+#[test]
+fn clones_write_the_same_blob_concurrently() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = HistoryStore::open(dir.path()).unwrap();
+
+    let writers: Vec<_> = (0..8)
+        .map(|_| {
+            let store = store.clone();
+            std::thread::spawn(move || {
+                let blob = PreparedBlob::from_base64(IMAGE, "image/png");
+                let mut e = entry(OperationType::Restoration, "google", true);
+                e.outputs = vec![blob.blob.clone()];
+                store.add_prepared(e, vec![blob]).unwrap();
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    let reopened = HistoryStore::open(dir.path()).unwrap();
+    let page = reopened.list(&HistoryQuery::default()).unwrap();
+    assert_eq!(page.total, 8);
+    let hash = &page.entries[0].outputs[0].hash;
+    assert_eq!(reopened.get_blob_base64(hash).unwrap().as_deref(), Some(IMAGE));
+    assert_eq!(reopened.blob_ref(hash).unwrap().unwrap().mime_type, "image/png");
+}
@@ -0,0 +1,183 @@
+// core/tests/jobs.rs
+//! Asynchronous jobs: completion, per-stage progress, failure, cancellation
+//! and how long finished jobs are kept.
+
+mod common;
+
+use common::{chat_reply, state_with, MockReply, MockServer};
+use serde_json::json;
+use std::time::Duration;
+use tissaia_core::jobs::{Job, JobManager, JobRequest, JobStatus, Retention};
+use tissaia_core::progress::{Stage, StageStatus};
+
+const IMAGE: &str = "aGVsbG8=";
+
+fn restore_request() -> JobRequest {
+    JobRequest::Restore {
+        image_base64: IMAGE.to_string(),
+        mime_type: "image/jpeg".to_string(),
+        preset: None,
+        options: None,
+    }
+}
+
+/// Poll until the job leaves the queue / running states.
+async fn wait_finished(jobs: &JobManager, id: &str) -> Job {
+    for _ in 0..500 {
+        let job = jobs.get(id).expect("job exists");
+        if job.status.is_finished() {
+            return job;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("job {} did not finish", id);
+}
+
+async fn wait_until(jobs: &JobManager, id: &str, condition: impl Fn(&Job) -> bool) {
+    for _ in 0..500 {
+        if jobs.get(id).is_some_and(|job| condition(&job)) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("job {} never reached the expected state", id);
+}
+
+fn stage_status(job: &Job, stage: Stage) -> StageStatus {
+    job.stages.iter().find(|s| s.stage == stage).expect("stage listed").status
+}
+
+#[tokio::test]
+async fn detect_retry_job_completes_with_stage_progress() {
+    let server = MockServer::start(vec![chat_reply(
+        r#"{"photo_count": 1, "bounding_boxes": [{"x": 0, "y": 0, "width": 500, "height": 500, "confidence": 0.9}]}"#,
+    )])
+    .await;
+    let state = state_with("groq", &server);
+    state.lock().await.settings.verification_enabled = false;
+    let jobs = JobManager::new(1);
+
+    let job = jobs.submit(
+        state.clone(),
+        JobRequest::DetectRetry { image_base64: IMAGE.to_string(), mime_type: "image/png".to_string() },
+    );
+    assert_eq!(job.operation, "detect_retry");
+    assert!(job.stages.iter().all(|s| s.status == StageStatus::Pending));
+
+    let job = wait_finished(&jobs, &job.id).await;
+    assert_eq!(job.status, JobStatus::Completed);
+    assert_eq!(job.result.as_ref().unwrap()["photo_count"], 1);
+    assert_eq!(stage_status(&job, Stage::Detect), StageStatus::Completed);
+    assert_eq!(stage_status(&job, Stage::Verify), StageStatus::Skipped);
+    assert_eq!(stage_status(&job, Stage::Merge), StageStatus::Skipped);
+    assert!(job.started_at.is_some() && job.finished_at.is_some());
+
+    // The operation still lands in history
+    assert_eq!(state.lock().await.history.len(), 1);
+}
+
+#[tokio::test]
+async fn failed_job_reports_typed_error_and_failed_stage() {
+    let server = MockServer::start(vec![MockReply::status(503, json!({"error": "down"}))]).await;
+    let jobs = JobManager::new(1);
+
+    let job = jobs.submit(state_with("mistral", &server), restore_request());
+    let job = wait_finished(&jobs, &job.id).await;
+
+    assert_eq!(job.status, JobStatus::Failed);
+    let error = job.error.clone().unwrap();
+    assert_eq!(error.code, "provider_error");
+    assert_eq!(error.provider.as_deref(), Some("mistral"));
+    assert_eq!(stage_status(&job, Stage::ExifFix), StageStatus::Completed);
+    assert_eq!(stage_status(&job, Stage::Restore), StageStatus::Failed);
+}
+
+#[tokio::test]
+async fn worker_pool_queues_and_cancels_jobs() {
+    let server = MockServer::start(vec![
+        chat_reply(r#"{"improvements": ["Dust removed"]}"#).delay(Duration::from_secs(30)),
+    ])
+    .await;
+    let state = state_with("groq", &server);
+    let jobs = JobManager::new(1);
+
+    let running = jobs.submit(state.clone(), restore_request());
+    let queued = jobs.submit(state.clone(), restore_request());
+    wait_until(&jobs, &running.id, |job| stage_status(job, Stage::Restore) == StageStatus::Running).await;
+    assert_eq!(jobs.get(&running.id).unwrap().status, JobStatus::Running);
+    assert_eq!(jobs.get(&queued.id).unwrap().status, JobStatus::Queued);
+    assert_eq!(jobs.active_count(), 2);
+
+    let cancelled = jobs.cancel(&queued.id).unwrap();
+    assert_eq!(cancelled.status, JobStatus::Cancelled);
+    assert!(cancelled.stages.iter().all(|s| s.status == StageStatus::Skipped));
+
+    let cancelled = jobs.cancel(&running.id).unwrap();
+    assert_eq!(cancelled.status, JobStatus::Cancelled);
+    assert_eq!(stage_status(&cancelled, Stage::Restore), StageStatus::Failed);
+    assert_eq!(jobs.active_count(), 0);
+
+    // Cancelled jobs stay pollable; a second DELETE forgets them
+    assert_eq!(jobs.get(&running.id).unwrap().status, JobStatus::Cancelled);
+    assert!(jobs.cancel(&running.id).is_some());
+    assert!(jobs.get(&running.id).is_none());
+    assert!(jobs.cancel("missing").is_none());
+}
+
+#[test]
+fn job_request_is_tagged_by_operation() {
+    let request: JobRequest = serde_json::from_value(json!({
+        "operation": "pipeline",
+        "image_base64": IMAGE,
+        "mime_type": "image/jpeg",
+        "options": {"outpaint": false, "filters": ["clahe"]}
+    }))
+    .unwrap();
+    assert_eq!(request.operation(), "pipeline");
+    assert_eq!(
+        request.stages(),
+        vec![
+            Stage::ExifFix,
+            Stage::Detect,
+            Stage::Verify,
+            Stage::Merge,
+            Stage::Crop,
+            Stage::VerifyCrop,
+            Stage::Restore,
+            Stage::VerifyRestoration,
+            Stage::Filters,
+        ]
+    );
+
+    assert!(serde_json::from_value::<JobRequest>(json!({"operation": "unknown"})).is_err());
+}
+
+fn detect_request() -> JobRequest {
+    JobRequest::Detect { image_base64: IMAGE.to_string(), mime_type: "image/png".to_string() }
+}
+
+#[tokio::test]
+async fn finished_results_are_evicted_by_size_and_age() {
+    let server = MockServer::start(vec![chat_reply(
+        r#"{"photo_count": 1, "bounding_boxes": [{"x": 0, "y": 0, "width": 500, "height": 500, "confidence": 0.9}]}"#,
+    )])
+    .await;
+    let state = state_with("groq", &server);
+
+    // Room for one result: the newest finished job is kept, older ones go
+    let jobs = JobManager::with_retention(1, Retention { max_result_bytes: 100, ..Retention::default() });
+    let first = jobs.submit(state.clone(), detect_request());
+    wait_finished(&jobs, &first.id).await;
+    let second = jobs.submit(state.clone(), detect_request());
+    let second = wait_finished(&jobs, &second.id).await;
+    assert!(second.result.is_some());
+    assert!(jobs.get(&first.id).is_none());
+
+    let jobs = JobManager::with_retention(1, Retention { ttl: Duration::from_millis(200), ..Retention::default() });
+    let expired = jobs.submit(state.clone(), detect_request());
+    wait_finished(&jobs, &expired.id).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let fresh = jobs.submit(state, detect_request());
+    assert!(jobs.get(&expired.id).is_none());
+    wait_finished(&jobs, &fresh.id).await;
+}
@@ -0,0 +1,61 @@
+// core/tests/local_detection.rs
+//! The offline detector as a provider: fallback when AI is unreachable and
+//! selectable as the preferred provider.
+#![cfg(feature = "image-processing")]
+
+mod common;
+
+use base64::Engine;
+use common::{app_with, shared, MockReply, MockServer};
+use serde_json::json;
+use tissaia_core::{operations, SharedState};
+
+/// `provider` (pointing at the mock server) plus the local detector.
+fn state_with(provider: &str, server: &MockServer) -> SharedState {
+    let mut app = app_with(&[(provider, server)]);
+    for p in &mut app.providers {
+        p.enabled |= p.name == "local";
+    }
+    shared(app)
+}
+
+/// A dark bed with two prints side by side.
+fn scan_base64() -> String {
+    let img = image::RgbImage::from_fn(600, 400, |x, y| {
+        if (50..250).contains(&x) && (100..300).contains(&y) {
+            image::Rgb([200, 160, 110])
+        } else if (350..560).contains(&x) && (80..330).contains(&y) {
+            image::Rgb([90, 140, 200])
+        } else {
+            image::Rgb([15, 15, 15])
+        }
+    });
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+    base64::engine::general_purpose::STANDARD.encode(bytes.into_inner())
+}
+
+#[tokio::test]
+async fn falls_back_to_local_detection_when_ai_is_down() {
+    let server = MockServer::start(vec![MockReply::status(503, json!({"error": "down"}))]).await;
+    let state = state_with("groq", &server);
+
+    let result = operations::detect_photos(&state, &scan_base64(), "image/png").await.unwrap();
+    assert_eq!(result.provider_used, "local");
+    assert_eq!(result.photo_count, 2);
+    assert_eq!((result.scan_width, result.scan_height), (600, 400));
+    assert!(result.bounding_boxes.iter().all(|b| b.contour.len() == 4));
+    assert!(!server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn preferred_local_provider_skips_ai() {
+    let server = MockServer::start(vec![MockReply::ok(json!({}))]).await;
+    let state = state_with("groq", &server);
+    state.lock().await.settings.preferred_provider = Some("local".to_string());
+
+    let result = operations::detect_photos(&state, &scan_base64(), "image/png").await.unwrap();
+    assert_eq!(result.provider_used, "local");
+    assert_eq!(result.bounding_boxes[0].label.as_deref(), Some("photo 1"));
+    assert!(server.requests().is_empty());
+}
@@ -0,0 +1,149 @@
+// core/tests/local_restoration.rs
+//! Local restoration engine: new filters, plan handling and text providers
+//! returning a real restored image.
+#![cfg(feature = "image-processing")]
+
+mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::{chat_reply, state_with, MockServer};
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use tissaia_core::imaging;
+use tissaia_core::local_restoration::plan_steps;
+use tissaia_core::models::{PlanStep, RestorationOptions, RestorationPlan, RestorationStep};
+use tissaia_core::operations;
+
+/// Faded print: a gradient squeezed into 110-170 with a warm cast.
+fn faded_print() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+        let v = 110 + ((x + y) * 60 / 126) as u8;
+        Rgb([v.saturating_add(25), v, v.saturating_sub(20)])
+    }))
+}
+
+fn png_base64(img: &DynamicImage) -> String {
+    let mut buf = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut buf, image::ImageFormat::Png).unwrap();
+    STANDARD.encode(buf.into_inner())
+}
+
+fn channel_means(img: &DynamicImage) -> [f64; 3] {
+    let rgb = img.to_rgb8();
+    let n = (rgb.width() * rgb.height()) as f64;
+    let mut sums = [0.0; 3];
+    for p in rgb.pixels() {
+        for c in 0..3 {
+            sums[c] += p[c] as f64;
+        }
+    }
+    sums.map(|s| s / n)
+}
+
+fn luma_range(img: &DynamicImage) -> (u8, u8) {
+    let luma = img.to_luma8();
+    (*luma.iter().min().unwrap(), *luma.iter().max().unwrap())
+}
+
+#[test]
+fn dust_removal_clears_specks_and_keeps_flat_areas() {
+    let mut scan = RgbImage::from_pixel(32, 32, Rgb([120, 110, 100]));
+    scan.put_pixel(10, 10, Rgb([255, 255, 255]));
+    scan.put_pixel(20, 5, Rgb([0, 0, 0]));
+    let cleaned = imaging::apply_dust_removal(&DynamicImage::ImageRgb8(scan), 0.5);
+
+    assert_eq!(cleaned.get_pixel(10, 10).0[..3], [120, 110, 100]);
+    assert_eq!(cleaned.get_pixel(20, 5).0[..3], [120, 110, 100]);
+    assert_eq!(cleaned.get_pixel(0, 0).0[..3], [120, 110, 100]);
+}
+
+#[test]
+fn white_balance_neutralizes_a_cast() {
+    let img = faded_print();
+    let [r, g, b] = channel_means(&imaging::apply_white_balance(&img, 1.0));
+
+    assert!((r - g).abs() < 2.0 && (b - g).abs() < 2.0, "means {:?}", [r, g, b]);
+    // Zero strength changes nothing
+    assert_eq!(imaging::apply_white_balance(&img, 0.0).to_rgb8(), img.to_rgb8());
+}
+
+#[test]
+fn levels_and_fade_correction_restore_contrast() {
+    let img = faded_print();
+    let (low, high) = luma_range(&img);
+
+    let (levels_low, levels_high) = luma_range(&imaging::apply_levels(&img, 1.0));
+    assert!(levels_low < low.saturating_sub(50) && levels_high > high + 50, "{}-{}", levels_low, levels_high);
+
+    let faded = imaging::apply_fade_correction(&img, 1.0);
+    let (fade_low, fade_high) = luma_range(&faded);
+    assert!(fade_high - fade_low > 200, "{}-{}", fade_low, fade_high);
+    // Per-channel stretch also removes most of the cast
+    let [r, _, b] = channel_means(&faded);
+    assert!((r - b).abs() < 15.0, "r {} b {}", r, b);
+}
+
+#[test]
+fn plan_steps_are_deduplicated_clamped_and_ordered() {
+    let plan = RestorationPlan {
+        operations: vec![
+            PlanStep { step: RestorationStep::Sharpen, strength: 2.0 },
+            PlanStep { step: RestorationStep::DustRemoval, strength: 0.4 },
+            PlanStep { step: RestorationStep::Sharpen, strength: 0.1 },
+            PlanStep { step: RestorationStep::Levels, strength: 0.0 },
+        ],
+        ..RestorationPlan::default()
+    };
+
+    assert_eq!(
+        plan_steps(&plan, &RestorationOptions::default()),
+        vec![
+            PlanStep { step: RestorationStep::DustRemoval, strength: 0.4 },
+            PlanStep { step: RestorationStep::Sharpen, strength: 1.0 },
+        ]
+    );
+}
+
+#[test]
+fn plan_without_operations_uses_improvement_texts() {
+    let plan = RestorationPlan {
+        improvements: vec!["Removed scratches".to_string(), "Corrected faded colours".to_string()],
+        ..RestorationPlan::default()
+    };
+    let steps: Vec<RestorationStep> = plan_steps(&plan, &RestorationOptions::default()).iter().map(|s| s.step).collect();
+
+    assert_eq!(steps, vec![RestorationStep::DustRemoval, RestorationStep::FadeCorrection]);
+}
+
+#[tokio::test]
+async fn text_provider_plan_produces_a_restored_image() {
+    let server = MockServer::start(vec![chat_reply(
+        r#"{"improvements": ["Fixed fading"], "operations": [
+            {"step": "fade_correction", "strength": 0.8},
+            {"step": "white_balance", "strength": 1.0},
+            {"step": "time_travel", "strength": 1.0}
+        ]}"#,
+    )])
+    .await;
+    let state = state_with("groq", &server);
+    let original = png_base64(&faded_print());
+
+    let result = operations::restore_image(&state, original.clone(), "image/png".to_string(), &RestorationOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(result.provider_used, "groq");
+    assert_ne!(result.restored_image, original);
+    assert_eq!(
+        result.improvements,
+        vec!["Colour cast neutralized (100%)", "Faded colours and contrast restored (80%)"]
+    );
+    let plan = result.plan.expect("plan recorded");
+    assert_eq!(plan.improvements, vec!["Fixed fading"]);
+    // The unknown step is skipped, not fatal
+    assert_eq!(plan.operations.len(), 2);
+
+    let restored = imaging::decode_base64_image(&result.restored_image).unwrap();
+    assert_eq!(restored.dimensions(), (64, 64));
+    let (low, high) = luma_range(&restored);
+    assert!(high - low > 150, "{}-{}", low, high);
+}
@@ -0,0 +1,105 @@
+// core/tests/operation_history.rs
+//! Every operation writes a history entry, including failures.
+
+mod common;
+
+use common::{chat_reply, state_with, MockReply, MockServer};
+use serde_json::json;
+use std::sync::Arc;
+use tissaia_core::history::HistoryQuery;
+use tissaia_core::models::{HistoryEntry, OperationType, RestorationOptions};
+use tissaia_core::{operations, AppState, SharedState};
+use tokio::sync::Mutex;
+
+const IMAGE: &str = "aGVsbG8=";
+
+async fn entries(state: &SharedState) -> Vec<HistoryEntry> {
+    state.lock().await.history.list(&HistoryQuery::default()).unwrap().entries
+}
+
+#[tokio::test]
+async fn failed_restoration_records_error_and_provider() {
+    let server = MockServer::start(vec![MockReply::status(401, json!({"error": "bad key"}))]).await;
+    let state = state_with("mistral", &server);
+
+    let err = operations::restore_image(&state, IMAGE.to_string(), "image/jpeg".to_string(), &RestorationOptions::default())
+        .await
+        .unwrap_err();
+
+    let entries = entries(&state).await;
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry.operation, OperationType::Restoration);
+    assert!(!entry.success);
+    assert_eq!(entry.provider, "mistral");
+    assert_eq!(entry.error_message.as_deref(), Some(err.to_string().as_str()));
+    assert!(entry.error_message.as_deref().unwrap().contains("401"));
+    assert_eq!(entry.attempts.len(), 1);
+    assert_eq!(entry.inputs.len(), 1);
+}
+
+#[tokio::test]
+async fn detection_is_recorded_with_result() {
+    let server = MockServer::start(vec![chat_reply(
+        r#"{"photo_count": 1, "bounding_boxes": [{"x": 0, "y": 0, "width": 500, "height": 500, "confidence": 0.9}]}"#,
+    )])
+    .await;
+    let state = state_with("groq", &server);
+
+    operations::detect_photos(&state, IMAGE, "image/png").await.unwrap();
+
+    let entries = entries(&state).await;
+    assert_eq!(entries[0].operation, OperationType::Detection);
+    assert!(entries[0].success);
+    assert_eq!(entries[0].provider, "groq");
+    assert_eq!(entries[0].result.as_ref().unwrap()["photo_count"], 1);
+}
+
+#[tokio::test]
+async fn operation_without_provider_is_recorded_as_failed() {
+    let server = MockServer::start(vec![chat_reply("{}")]).await;
+    let state = state_with("groq", &server);
+    state.lock().await.providers.iter_mut().for_each(|p| p.enabled = false);
+
+    assert!(operations::detect_photos(&state, IMAGE, "image/png").await.is_err());
+
+    let entries = entries(&state).await;
+    assert!(!entries[0].success);
+    assert_eq!(entries[0].provider, "none");
+    assert!(entries[0].error_message.as_deref().unwrap().contains("No AI provider available"));
+}
+
+#[tokio::test]
+async fn failed_local_operation_is_recorded() {
+    let state: SharedState = Arc::new(Mutex::new(AppState::new()));
+
+    assert!(operations::rotate_image(&state, "not an image", "image/png", 90).await.is_err());
+    assert!(operations::save_image(&state, "%%%", "/nonexistent/dir/out.png").await.is_err());
+
+    let entries = entries(&state).await;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].operation, OperationType::Save);
+    assert_eq!(entries[0].input_preview, "/nonexistent/dir/out.png");
+    assert_eq!(entries[1].operation, OperationType::Rotate);
+    for entry in &entries {
+        assert!(!entry.success);
+        assert_eq!(entry.provider, "local");
+        assert!(entry.error_message.is_some());
+    }
+}
+
+#[tokio::test]
+async fn outpaint_without_a_contour_is_recorded_as_a_no_op() {
+    let state: SharedState = Arc::new(Mutex::new(AppState::new()));
+
+    let image = operations::outpaint_photo(&state, IMAGE.to_string(), "image/png", &[], 10, 10).await.unwrap();
+    assert_eq!(image, IMAGE);
+
+    let entries = entries(&state).await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].operation, OperationType::Outpaint);
+    assert!(entries[0].success);
+    assert_eq!(entries[0].provider, "local");
+    assert_eq!(entries[0].result.as_ref().unwrap()["outpainted"], false);
+    assert_eq!(entries[0].inputs, entries[0].outputs);
+}
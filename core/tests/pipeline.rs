@@ -0,0 +1,150 @@
+// core/tests/pipeline.rs
+//! End-to-end pipeline: stage selection, per-photo reports, failures that
+//! stay local to one photo.
+
+mod common;
+
+use common::{chat_reply, state_with, MockReply, MockServer};
+use serde_json::json;
+use tissaia_core::pipeline::{run_pipeline, PipelineOptions, PipelineRequest};
+#[cfg(feature = "image-processing")]
+use tissaia_core::pipeline::StageReport;
+#[cfg(feature = "image-processing")]
+use tissaia_core::progress::{Stage, StageStatus};
+use tissaia_core::ApiError;
+
+#[cfg(feature = "image-processing")]
+fn scan_png() -> String {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    let img = image::RgbImage::from_fn(400, 200, |x, _| {
+        if x < 200 { image::Rgb([180, 120, 90]) } else { image::Rgb([90, 140, 200]) }
+    });
+    let mut buf = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgb8(img).write_to(&mut buf, image::ImageFormat::Png).unwrap();
+    STANDARD.encode(buf.into_inner())
+}
+
+fn two_photo_detection() -> MockReply {
+    chat_reply(
+        &json!({
+            "photo_count": 2,
+            "bounding_boxes": [
+                {"x": 50, "y": 100, "width": 400, "height": 800, "confidence": 0.9},
+                {"x": 550, "y": 100, "width": 400, "height": 800, "confidence": 0.9}
+            ]
+        })
+        .to_string(),
+    )
+}
+
+#[cfg(feature = "image-processing")]
+fn status(stages: &[StageReport], stage: Stage) -> Option<StageStatus> {
+    stages.iter().find(|r| r.stage == stage).map(|r| r.status)
+}
+
+#[cfg(feature = "image-processing")]
+#[tokio::test]
+async fn runs_selected_stages_and_reports_per_photo() {
+    let server = MockServer::start(vec![
+        two_photo_detection(),
+        chat_reply(r#"{"improvements": ["Dust removed"]}"#),
+    ])
+    .await;
+    let state = state_with("groq", &server);
+    // Requested, but switched off in settings
+    state.lock().await.settings.verification_enabled = false;
+
+    let result = run_pipeline(
+        &state,
+        PipelineRequest {
+            image_base64: scan_png(),
+            mime_type: "image/png".to_string(),
+            original_filename: Some("album.png".to_string()),
+            options: PipelineOptions {
+                filters: vec!["sharpen".to_string()],
+                output_format: Some("image/jpeg".to_string()),
+                ..PipelineOptions::default()
+            },
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(status(&result.stages, Stage::Detect), Some(StageStatus::Completed));
+    assert_eq!(status(&result.stages, Stage::Verify), Some(StageStatus::Skipped));
+    assert_eq!(status(&result.stages, Stage::Crop), Some(StageStatus::Completed));
+    assert_eq!(result.photos.len(), 2);
+
+    for (i, photo) in result.photos.iter().enumerate() {
+        assert_eq!(photo.filename, format!("album_{}.jpg", i + 1));
+        assert_eq!(photo.mime_type, "image/jpeg");
+        assert!(photo.restored);
+        assert_eq!(photo.provider.as_deref(), Some("groq"));
+        // The groq plan ran through the local engine
+        assert_eq!(photo.improvements, vec!["Dust and scratches removed (50%)"]);
+        assert_eq!(status(&photo.stages, Stage::Outpaint), Some(StageStatus::Skipped));
+        assert_eq!(status(&photo.stages, Stage::VerifyCrop), Some(StageStatus::Skipped));
+        assert_eq!(status(&photo.stages, Stage::Restore), Some(StageStatus::Completed));
+        assert_eq!(status(&photo.stages, Stage::VerifyRestoration), Some(StageStatus::Skipped));
+        assert_eq!(status(&photo.stages, Stage::Filters), Some(StageStatus::Completed));
+        assert_eq!(status(&photo.stages, Stage::Convert), Some(StageStatus::Completed));
+        assert!(photo.succeeded());
+    }
+
+    // One detection, then one restoration per photo; the scan was uploaded once
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[cfg(feature = "image-processing")]
+#[tokio::test]
+async fn failed_restoration_keeps_the_crop() {
+    let server = MockServer::start(vec![
+        two_photo_detection(),
+        MockReply::status(503, json!({"error": "down"})),
+    ])
+    .await;
+    let state = state_with("mistral", &server);
+
+    let result = run_pipeline(
+        &state,
+        PipelineRequest {
+            image_base64: scan_png(),
+            mime_type: "image/png".to_string(),
+            original_filename: None,
+            options: PipelineOptions { verify: false, ..PipelineOptions::default() },
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(result.photos.len(), 2);
+    let photo = &result.photos[0];
+    assert_eq!(photo.filename, "scan_1.png");
+    assert!(!photo.restored);
+    assert!(!photo.succeeded());
+    assert_eq!(status(&photo.stages, Stage::Restore), Some(StageStatus::Failed));
+    assert_eq!(status(&photo.stages, Stage::VerifyRestoration), None);
+    assert!(!photo.image_base64.is_empty());
+}
+
+#[tokio::test]
+async fn rejects_unknown_output_format_before_any_provider_call() {
+    let server = MockServer::start(vec![two_photo_detection()]).await;
+    let state = state_with("groq", &server);
+
+    let error: ApiError = run_pipeline(
+        &state,
+        PipelineRequest {
+            image_base64: "aGVsbG8=".to_string(),
+            mime_type: "image/png".to_string(),
+            original_filename: None,
+            options: PipelineOptions { output_format: Some("image/gif".to_string()), ..PipelineOptions::default() },
+        },
+    )
+    .await
+    .unwrap_err()
+    .into();
+
+    assert_eq!(error.code, "validation_error");
+    assert!(server.requests().is_empty());
+}
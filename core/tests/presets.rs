@@ -0,0 +1,129 @@
+// core/tests/presets.rs
+//! Restoration presets: built-ins, persistence, resolution, and prompts and
+//! local plans that follow the chosen options.
+
+mod common;
+
+use common::{chat_reply, MockServer};
+use std::sync::Arc;
+use tissaia_core::local_restoration::plan_steps;
+use tissaia_core::models::{
+    PlanStep, RestorationOptions, RestorationPlan, RestorationPreset, RestorationStep, StepOption,
+};
+use tissaia_core::presets::PresetStore;
+use tissaia_core::{operations, ApiError, AppState};
+use tokio::sync::Mutex;
+
+const PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAQAAAAECAIAAAAmkwkpAAAAEElEQVR4nGM4UREARwzEcQCR0hkB/rK5kAAAAABJRU5ErkJggg==";
+
+fn preset(name: &str, options: RestorationOptions) -> RestorationPreset {
+    RestorationPreset { name: name.to_string(), description: String::new(), options, builtin: false }
+}
+
+fn archival() -> RestorationOptions {
+    PresetStore::in_memory().get("archival-faithful").unwrap().options
+}
+
+#[test]
+fn builtin_presets_are_listed_first_and_protected() {
+    let mut store = PresetStore::in_memory();
+    store.save(preset("sepia-keep", RestorationOptions::default())).unwrap();
+
+    let names: Vec<String> = store.list().into_iter().map(|p| p.name).collect();
+    assert_eq!(names, vec!["archival-faithful", "gentle-cleanup", "full-colorize", "sepia-keep"]);
+    assert!(!archival().colorize.enabled);
+
+    let err = store.save(preset("full-colorize", archival())).unwrap_err();
+    assert_eq!(ApiError::from(&err).code, "validation_error");
+    assert!(store.delete("archival-faithful").is_err());
+    assert!(store.save(preset("  ", archival())).is_err());
+}
+
+#[test]
+fn user_presets_survive_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("presets.json");
+    let mut options = archival();
+    options.flash = StepOption::on(0.6);
+
+    {
+        let mut store = PresetStore::open(&path).unwrap();
+        store.save(preset("flash-only", options)).unwrap();
+        store.save(preset("scratch", RestorationOptions::default())).unwrap();
+        assert!(store.delete("scratch").unwrap());
+        assert!(!store.delete("scratch").unwrap());
+    }
+
+    let store = PresetStore::open(&path).unwrap();
+    assert!(store.is_persistent());
+    let loaded = store.get("flash-only").expect("preset reloaded");
+    assert_eq!(loaded.options, options);
+    assert!(!loaded.builtin);
+    assert!(store.get("scratch").is_none());
+}
+
+#[test]
+fn explicit_options_win_over_preset() {
+    let store = PresetStore::in_memory();
+    assert_eq!(store.resolve(None, None).unwrap(), RestorationOptions::default());
+    assert_eq!(store.resolve(Some("archival-faithful"), None).unwrap(), archival());
+    assert_eq!(
+        store.resolve(Some("archival-faithful"), Some(RestorationOptions::default())).unwrap(),
+        RestorationOptions::default()
+    );
+
+    let err = store.resolve(Some("sepia"), None).unwrap_err();
+    assert_eq!(ApiError::from(&err).code, "validation_error");
+}
+
+#[test]
+fn partial_options_default_to_full_strength() {
+    let options: RestorationOptions =
+        serde_json::from_str(r#"{"colorize": {"enabled": false}, "denoise": {"enabled": true, "strength": 0.2}}"#)
+            .unwrap();
+    assert_eq!(options.colorize, StepOption { enabled: false, strength: 1.0 });
+    assert_eq!(options.denoise, StepOption::on(0.2));
+    assert_eq!(options.geometry, StepOption::on(1.0));
+}
+
+#[test]
+fn disabled_steps_are_dropped_from_local_plans() {
+    let plan = RestorationPlan {
+        operations: vec![
+            PlanStep { step: RestorationStep::FadeCorrection, strength: 0.8 },
+            PlanStep { step: RestorationStep::DustRemoval, strength: 0.5 },
+            PlanStep { step: RestorationStep::Sharpen, strength: 0.7 },
+        ],
+        ..RestorationPlan::default()
+    };
+
+    assert_eq!(
+        plan_steps(&plan, &archival()),
+        vec![PlanStep { step: RestorationStep::DustRemoval, strength: 0.5 }]
+    );
+}
+
+#[tokio::test]
+async fn provider_prompt_follows_the_preset() {
+    let server = MockServer::start(vec![chat_reply(r#"{"improvements": []}"#)]).await;
+    let mut app = AppState::new();
+    for p in &mut app.providers {
+        p.enabled = p.name == "mistral";
+    }
+    app.settings.providers.get_mut("mistral").unwrap().base_url = server.url("/v1");
+    app.set_api_key("mistral", "test-key".to_string());
+    let state = Arc::new(Mutex::new(app));
+
+    let options = operations::restoration_options(&state, Some("archival-faithful"), None).await.unwrap();
+    let result = operations::restore_image(&state, PNG.to_string(), "image/png".to_string(), &options)
+        .await
+        .unwrap();
+    assert_eq!(result.options, Some(archival()));
+
+    let requests = server.requests();
+    let prompt = requests[0].body["messages"][0]["content"][0]["text"].as_str().unwrap();
+    assert!(prompt.contains("1. DENOISING (GENTLE)"), "{}", prompt);
+    assert!(prompt.contains("Do NOT colorize"), "{}", prompt);
+    assert!(!prompt.contains("STUDIO QUALITY ("), "{}", prompt);
+    assert!(!prompt.contains("sharpen"), "{}", prompt);
+}
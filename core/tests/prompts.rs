@@ -0,0 +1,101 @@
+// core/tests/prompts.rs
+//! Prompt templates: embedded defaults, directory overrides, version pinning
+//! and the version recorded in results.
+
+mod common;
+
+use common::{chat_reply, MockServer};
+use std::fs;
+use std::sync::Arc;
+use tissaia_core::ai::{AiProvider, ChatProvider, Verifier};
+use tissaia_core::config::default_provider_configs;
+use tissaia_core::prompts::{PromptKind, PromptLibrary};
+use tissaia_core::ApiError;
+
+const IMAGE: &str = "aGVsbG8=";
+
+fn ai_with(server: &MockServer, prompts: PromptLibrary) -> AiProvider {
+    let mut configs = default_provider_configs();
+    configs.get_mut("groq").unwrap().base_url = server.url("/v1");
+    AiProvider::with_config(reqwest::Client::new(), configs).with_prompts(Arc::new(prompts))
+}
+
+#[test]
+fn embedded_templates_render_their_variables() {
+    let library = PromptLibrary::embedded();
+    for info in library.list() {
+        assert_eq!(info.versions, vec![1], "{}", info.name);
+        assert_eq!(info.active, 1);
+    }
+
+    let prompt = library.render(PromptKind::VerifyDetection, &[("boxes_json", "[{\"x\": 1}]")]);
+    assert_eq!(prompt.version, "verify_detection.v1");
+    assert!(prompt.text.contains("coordinates):\n[{\"x\": 1}]\n"));
+    // JSON examples in the template are not placeholders
+    assert!(prompt.text.contains("{\"name\": \"boundary_accuracy\""));
+    assert!(!prompt.text.contains("{{"));
+
+    let crop = library.render(PromptKind::VerifyCrop, &[("crop_number", "3")]);
+    assert!(crop.text.contains("cropped image #3 extracted"));
+}
+
+#[test]
+fn directory_overrides_and_adds_versions() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("verify_crop.v1.txt"), "Check crop {crop_number}.\n").unwrap();
+    fs::write(dir.path().join("detect.v2.txt"), "Find every photo.").unwrap();
+    fs::write(dir.path().join("notes.md"), "ignored").unwrap();
+
+    let mut library = PromptLibrary::load(dir.path()).unwrap();
+    assert_eq!(library.render(PromptKind::VerifyCrop, &[("crop_number", "2")]).text, "Check crop 2.");
+
+    let detect = library.render(PromptKind::Detect, &[]);
+    assert_eq!(detect.version, "detect.v2");
+    assert_eq!(detect.text, "Find every photo.");
+
+    library.pin_all("detect=1").unwrap();
+    let pinned = library.render(PromptKind::Detect, &[]);
+    assert_eq!(pinned.version, "detect.v1");
+    assert!(pinned.text.starts_with("You are a photo boundary detection expert"));
+
+    let info = library.list().into_iter().find(|i| i.name == "detect").unwrap();
+    assert_eq!((info.versions, info.active), (vec![1, 2], 1));
+}
+
+#[test]
+fn bad_templates_and_pins_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("verify_detection.v2.txt"), "Boxes: {boxes}").unwrap();
+    let err = PromptLibrary::load(dir.path()).unwrap_err();
+    assert!(format!("{:#}", err).contains("unknown placeholder {boxes}"), "{:#}", err);
+
+    let mut library = PromptLibrary::embedded();
+    let err = library.pin(PromptKind::Outpaint, 7).unwrap_err();
+    assert_eq!(ApiError::from(&err).code, "validation_error");
+    assert!(library.pin_all("summarize=1").is_err());
+    assert!(library.pin_all("detect").is_err());
+}
+
+#[tokio::test]
+async fn results_record_the_prompt_version() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("detect.v2.txt"), "List the photos as JSON.").unwrap();
+    let server = MockServer::start(vec![
+        chat_reply(r#"{"photo_count": 0, "bounding_boxes": []}"#),
+        chat_reply(r#"{"status": "pass", "confidence": 90, "checks": []}"#),
+    ])
+    .await;
+    let ai = ai_with(&server, PromptLibrary::load(dir.path()).unwrap());
+
+    let detection = ai.detect_with_chat(ChatProvider::GROQ, "test-key", IMAGE, "image/png").await.unwrap();
+    assert_eq!(detection.prompt_version.as_deref(), Some("detect.v2"));
+
+    let verification = ai
+        .verify_crop(Verifier::Chat(ChatProvider::GROQ), "test-key", IMAGE, "image/png", 0)
+        .await
+        .unwrap();
+    assert_eq!(verification.prompt_version.as_deref(), Some("verify_crop.v1"));
+
+    let requests = server.requests();
+    assert_eq!(requests[0].body["messages"][0]["content"][0]["text"], "List the photos as JSON.");
+}
@@ -0,0 +1,173 @@
+// core/tests/provider_config.rs
+//! Provider endpoint config: resolution, settings merge and mock-server routing.
+
+mod common;
+
+use common::{MockReply, MockServer};
+use serde_json::json;
+use std::time::Duration;
+use tissaia_core::config::{
+    default_provider_configs, OperationConfig, ProviderConfig, ProviderOperation, RequestLimits, MAX_SETTINGS_TIMEOUT_SECS,
+};
+use tissaia_core::models::SettingsUpdate;
+use tissaia_core::{AiProvider, ApiError, AppState};
+
+#[test]
+fn operation_overrides_take_precedence_over_provider_defaults() {
+    let mut configs = default_provider_configs();
+    let google = configs.get_mut("google").unwrap();
+    google.base_url = "http://proxy.local/v1beta/".to_string();
+    google.timeout_secs = 30;
+    google.verify = Some(OperationConfig {
+        model: "gemini-flash-custom".to_string(),
+        max_tokens: 1024,
+        base_url: Some("http://verifier.local".to_string()),
+        timeout_secs: Some(5),
+    });
+
+    let restore = google.endpoint(ProviderOperation::Restore).unwrap();
+    assert_eq!(restore.base_url, "http://proxy.local/v1beta");
+    assert_eq!(restore.model, "gemini-3-pro-image-preview");
+    assert_eq!(restore.timeout, Duration::from_secs(30));
+
+    let verify = google.endpoint(ProviderOperation::Verify).unwrap();
+    assert_eq!(verify.base_url, "http://verifier.local");
+    assert_eq!(verify.model, "gemini-flash-custom");
+    assert_eq!(verify.max_tokens, 1024);
+    assert_eq!(verify.timeout, Duration::from_secs(5));
+
+    assert!(configs["anthropic"].endpoint(ProviderOperation::Detect).is_none());
+}
+
+/// `save_settings` body: the stored settings with `providers` replaced.
+fn settings_update(app: &AppState, providers: serde_json::Value) -> SettingsUpdate {
+    let mut body = serde_json::to_value(&app.settings).unwrap();
+    body["providers"] = providers;
+    serde_json::from_value(body).unwrap()
+}
+
+#[test]
+fn saving_settings_merges_provider_config() {
+    let mut app = AppState::new();
+    app.settings.providers.get_mut("openai").unwrap().base_url = "http://gateway.local/v1".to_string();
+
+    // Older clients send settings without the providers section
+    let legacy: SettingsUpdate = serde_json::from_value(json!({
+        "language": "en",
+        "theme": "light",
+        "auto_save": false,
+        "output_quality": 80,
+        "preferred_provider": "openai"
+    }))
+    .unwrap();
+    app.update_settings(legacy).unwrap();
+    assert_eq!(app.settings.language, "en");
+    assert_eq!(app.settings.providers["openai"].base_url, "http://gateway.local/v1");
+
+    // Clients that send the whole config back change nothing by doing so
+    let full = serde_json::to_value(&app.settings.providers).unwrap();
+    app.update_settings(settings_update(&app, full)).unwrap();
+    assert_eq!(app.settings.providers, {
+        let mut configs = default_provider_configs();
+        configs.get_mut("openai").unwrap().base_url = "http://gateway.local/v1".to_string();
+        configs
+    });
+}
+
+#[test]
+fn partial_provider_update_only_changes_the_fields_sent() {
+    let mut app = AppState::new();
+    let update = settings_update(
+        &app,
+        json!({"google": {"verify": {"model": "gemini-flash-custom"}, "limits": {"max_concurrent": 1}}}),
+    );
+    app.update_settings(update).unwrap();
+
+    let google = &app.settings.providers["google"];
+    let defaults = &default_provider_configs()["google"];
+    assert_eq!(google.verify.as_ref().unwrap().model, "gemini-flash-custom");
+    assert_eq!(google.verify.as_ref().unwrap().max_tokens, defaults.verify.as_ref().unwrap().max_tokens);
+    assert_eq!((&google.restore, &google.detect, &google.outpaint), (&defaults.restore, &defaults.detect, &defaults.outpaint));
+    assert_eq!(google.limits.max_concurrent, 1);
+    assert_eq!(google.limits.max_retries, defaults.limits.max_retries);
+
+    // `null` switches an operation off
+    app.update_settings(settings_update(&app, json!({"google": {"outpaint": null}}))).unwrap();
+    assert!(app.settings.providers["google"].outpaint.is_none());
+    assert!(app.settings.providers["google"].restore.is_some());
+}
+
+#[test]
+fn settings_api_cannot_redirect_provider_endpoints() {
+    let mut app = AppState::new();
+    let before = app.settings.clone();
+    for providers in [
+        json!({"google": {"base_url": "http://attacker.example"}}),
+        json!({"groq": {"detect": {"base_url": "http://attacker.example"}}}),
+        json!({"exfil": {"base_url": "http://attacker.example"}}),
+        json!({"openai": {"timeout_secs": "soon"}}),
+    ] {
+        let err = app.update_settings(settings_update(&app, providers.clone())).unwrap_err();
+        assert_eq!(ApiError::from(&err).code, "validation_error", "{}", providers);
+    }
+    assert_eq!(app.settings.providers, before.providers);
+}
+
+#[test]
+fn settings_api_sets_timeouts_within_bounds() {
+    let mut app = AppState::new();
+    app.update_settings(settings_update(
+        &app,
+        json!({
+            "anthropic": {"timeout_secs": 86400},
+            "google": {"timeout_secs": 300, "verify": {"timeout_secs": 0}}
+        }),
+    ))
+    .unwrap();
+
+    let providers = &app.settings.providers;
+    assert_eq!(providers["anthropic"].timeout_secs, MAX_SETTINGS_TIMEOUT_SECS);
+    assert_eq!(providers["google"].timeout_secs, 300);
+    assert_eq!(providers["google"].verify.as_ref().unwrap().timeout_secs, Some(1));
+    let endpoint = providers["google"].endpoint(ProviderOperation::Detect).unwrap();
+    assert_eq!(endpoint.timeout, Duration::from_secs(300));
+}
+
+#[test]
+fn request_limits_default_per_field() {
+    let config: ProviderConfig = serde_json::from_value(json!({"base_url": "http://gemini.local"})).unwrap();
+    assert_eq!(config.limits, RequestLimits::default());
+
+    let config: ProviderConfig = serde_json::from_value(json!({
+        "base_url": "http://gemini.local",
+        "limits": {"max_concurrent": 1, "requests_per_minute": 20}
+    }))
+    .unwrap();
+    assert_eq!(config.limits.max_concurrent, 1);
+    assert_eq!(config.limits.requests_per_minute, Some(20));
+    assert_eq!(config.limits.max_retries, RequestLimits::default().max_retries);
+}
+
+#[tokio::test]
+async fn gemini_requests_use_configured_base_url_and_model() {
+    let server = MockServer::start(vec![MockReply::ok(json!({
+        "candidates": [{"content": {"parts": [{"text": "{\"photo_count\": 0, \"bounding_boxes\": []}"}]}}]
+    }))])
+    .await;
+
+    let mut configs = default_provider_configs();
+    let google = configs.get_mut("google").unwrap();
+    google.base_url = server.url("/gemini");
+    google.detect.as_mut().unwrap().model = "detector-test".to_string();
+    google.detect.as_mut().unwrap().max_tokens = 512;
+
+    let result = AiProvider::with_config(reqwest::Client::new(), configs)
+        .detect_photo_boundaries("test-key", "aGVsbG8=", "image/jpeg")
+        .await
+        .unwrap();
+    assert_eq!(result.photo_count, 0);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/gemini/models/detector-test:generateContent");
+    assert_eq!(requests[0].body["generationConfig"]["maxOutputTokens"], 512);
+}
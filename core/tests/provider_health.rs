@@ -0,0 +1,181 @@
+// core/tests/provider_health.rs
+//! Provider health: circuit breaker transitions, latency percentiles,
+//! background probes and the failover chain skipping open circuits.
+
+mod common;
+
+use chrono::{Duration as Span, Utc};
+use common::{app_with, shared, MockReply, MockServer};
+use serde_json::json;
+use std::time::Duration;
+use tissaia_core::health::{self, CircuitBreakerConfig, CircuitState, LatencyStats, ProviderHealth};
+use tissaia_core::models::{ProviderStatus, RestorationOptions};
+use tissaia_core::{operations, ApiError, SharedState};
+
+const PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAQAAAAECAIAAAAmkwkpAAAAEElEQVR4nGM4UREARwzEcQCR0hkB/rK5kAAAAABJRU5ErkJggg==";
+
+/// `common::app_with`, with a circuit that opens after two failures.
+fn state_with(providers: &[(&str, &MockServer)]) -> SharedState {
+    let mut app = app_with(providers);
+    app.circuit_breaker = CircuitBreakerConfig { failure_threshold: 2, open_secs: 60 };
+    shared(app)
+}
+
+fn status<'a>(providers: &'a [ProviderStatus], name: &str) -> &'a ProviderStatus {
+    providers.iter().find(|p| p.name == name).unwrap()
+}
+
+#[test]
+fn circuit_opens_after_consecutive_failures_and_recovers_through_a_trial() {
+    let config = CircuitBreakerConfig { failure_threshold: 3, open_secs: 30 };
+    let now = Utc::now();
+    let mut health = ProviderHealth::default();
+
+    health.record_failure(&config, now);
+    health.record_failure(&config, now);
+    assert_eq!(health.circuit, CircuitState::Closed);
+    health.record_success(now);
+    health.record_failure(&config, now);
+    health.record_failure(&config, now);
+    assert_eq!(health.circuit, CircuitState::Closed);
+    health.record_failure(&config, now);
+    assert_eq!(health.circuit, CircuitState::Open);
+    assert!(!health.allows_requests(now + Span::seconds(29)));
+
+    // Open period over: only one caller gets the trial, and its failure reopens it
+    let later = now + Span::seconds(30);
+    assert!(health.allows_requests(later));
+    assert!(health.claim(later));
+    assert_eq!(health.circuit, CircuitState::HalfOpen);
+    assert!(!health.claim(later));
+    assert!(!health.allows_requests(later));
+    health.record_failure(&config, later);
+    assert_eq!(health.circuit, CircuitState::Open);
+    assert_eq!(health.open_until, Some(later + Span::seconds(30)));
+
+    let latest = later + Span::seconds(30);
+    assert!(health.claim(latest));
+    health.record_success(latest);
+    assert!(health.claim(latest) && health.claim(latest));
+    assert_eq!((health.circuit, health.consecutive_failures, health.open_until), (CircuitState::Closed, 0, None));
+}
+
+#[test]
+fn latency_percentiles_cover_the_recent_window() {
+    let mut latency = LatencyStats::default();
+    for ms in 1..=100 {
+        latency.record(Duration::from_millis(ms));
+    }
+    // Last 50 samples: 51..=100 ms
+    assert_eq!(latency.samples, 50);
+    assert_eq!((latency.p50_ms, latency.p90_ms, latency.p99_ms), (Some(75), Some(95), Some(100)));
+}
+
+#[tokio::test]
+async fn open_circuit_takes_provider_out_of_the_chain() {
+    let mistral = MockServer::start(vec![MockReply::status(503, json!({"error": "overloaded"}))]).await;
+    let state = state_with(&[("mistral", &mistral)]);
+    let restore = || async {
+        let err = operations::restore_image(&state, PNG.to_string(), "image/png".to_string(), &RestorationOptions::default())
+            .await
+            .unwrap_err();
+        ApiError::from(&err).code
+    };
+
+    // A demoted provider stays in the chain as a last resort until its circuit opens
+    assert_eq!(restore().await, "provider_error");
+    assert_eq!(restore().await, "provider_error");
+    let providers = operations::providers_status(&state).await;
+    assert_eq!(status(&providers, "mistral").health.circuit, CircuitState::Open);
+    assert_eq!(status(&providers, "mistral").health.consecutive_failures, 2);
+
+    assert_eq!(restore().await, "no_provider");
+    assert_eq!(mistral.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn half_open_provider_gets_a_single_trial_call() {
+    let mistral = MockServer::start(vec![MockReply::status(503, json!({"error": "overloaded"}))
+        .delay(Duration::from_millis(300))])
+    .await;
+    let state = state_with(&[("mistral", &mistral)]);
+    {
+        let mut app = state.lock().await;
+        let provider = app.providers.iter_mut().find(|p| p.name == "mistral").unwrap();
+        provider.health.circuit = CircuitState::Open;
+        provider.health.open_until = Some(Utc::now());
+        provider.last_error = Some("down".to_string());
+    }
+    let restore = || async {
+        let err = operations::restore_image(&state, PNG.to_string(), "image/png".to_string(), &RestorationOptions::default())
+            .await
+            .unwrap_err();
+        ApiError::from(&err).code
+    };
+
+    let (first, second) = tokio::join!(restore(), async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        restore().await
+    });
+
+    assert_eq!((first.as_str(), second.as_str()), ("provider_error", "no_provider"));
+    assert_eq!(mistral.requests().len(), 1);
+    let providers = operations::providers_status(&state).await;
+    assert_eq!(status(&providers, "mistral").health.circuit, CircuitState::Open);
+}
+
+#[tokio::test]
+async fn successful_calls_record_latency() {
+    let groq = MockServer::start(vec![common::chat_reply(r#"{"photo_count": 0, "bounding_boxes": []}"#)]).await;
+    let state = state_with(&[("groq", &groq)]);
+
+    operations::detect_photos(&state, PNG, "image/png").await.unwrap();
+
+    let providers = operations::providers_status(&state).await;
+    assert_eq!(status(&providers, "groq").health.latency.samples, 1);
+}
+
+#[tokio::test]
+async fn probes_record_latency_errors_and_ollama_availability() {
+    let ollama = MockServer::start(vec![MockReply::ok(json!({"models": [{"name": "llava"}]}))]).await;
+    let groq = MockServer::start(vec![MockReply::ok(json!({"data": []}))]).await;
+    let mistral = MockServer::start(vec![MockReply::status(401, json!({"error": "bad key"}))]).await;
+    let state = state_with(&[("ollama", &ollama), ("groq", &groq), ("mistral", &mistral)]);
+    assert!(!status(&state.lock().await.providers, "ollama").available);
+
+    health::probe_providers(&state).await;
+
+    let providers = operations::providers_status(&state).await;
+    let ollama_status = status(&providers, "ollama");
+    assert!(ollama_status.available);
+    assert_eq!(ollama_status.health.latency.samples, 1);
+    assert!(ollama_status.health.latency.p50_ms.is_some());
+    assert!(status(&providers, "groq").health.last_checked.is_some());
+
+    // A bad key demotes the provider without counting towards its circuit
+    let mistral_status = status(&providers, "mistral");
+    assert!(!mistral_status.available);
+    assert!(mistral_status.last_error.as_deref().unwrap().contains("401"));
+    assert_eq!(mistral_status.health.consecutive_failures, 0);
+
+    assert_eq!(ollama.requests()[0].path, "/api/tags");
+    let request = &groq.requests()[0];
+    assert_eq!((request.path.as_str(), request.authorization.as_deref()), ("/v1/models", Some("Bearer groq-key")));
+
+    health::probe_providers(&state).await;
+    let report = operations::health_check(&state, "test").await;
+    assert_eq!(report.status, "healthy");
+    assert_eq!(status(&report.providers, "mistral").health.circuit, CircuitState::Closed);
+}
+
+#[tokio::test]
+async fn health_is_degraded_without_a_working_ai_provider() {
+    let groq = MockServer::start(vec![MockReply::status(503, json!({"error": "down"}))]).await;
+    let state = state_with(&[("groq", &groq)]);
+
+    health::probe_providers(&state).await;
+    health::probe_providers(&state).await;
+    let report = operations::health_check(&state, "test").await;
+    assert_eq!(report.status, "degraded");
+    assert_eq!(status(&report.providers, "groq").health.circuit, CircuitState::Open);
+}
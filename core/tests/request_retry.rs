@@ -0,0 +1,132 @@
+// core/tests/request_retry.rs
+//! Request executor: retries with backoff, `Retry-After`, concurrency and
+//! rate limits, against a fault-injecting mock server.
+
+mod common;
+
+use common::{chat_reply, MockReply, MockServer};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tissaia_core::ai::{AiProvider, ChatProvider};
+use tissaia_core::config::{default_provider_configs, RequestLimits};
+use tissaia_core::executor::{parse_retry_after, RequestExecutor};
+use tissaia_core::models::RestorationOptions;
+use tissaia_core::ApiError;
+
+const IMAGE: &str = "aGVsbG8=";
+
+/// Fast backoff so retry tests stay quick.
+fn limits() -> RequestLimits {
+    RequestLimits { max_retries: 2, backoff_base_ms: 10, backoff_max_ms: 1_000, ..RequestLimits::default() }
+}
+
+fn ai_with(provider: &str, server: &MockServer, limits: RequestLimits) -> AiProvider {
+    let mut configs = default_provider_configs();
+    let config = configs.get_mut(provider).unwrap();
+    config.base_url = server.url(if provider == "google" { "/gemini" } else { "/v1" });
+    config.limits = limits;
+    AiProvider::with_config(reqwest::Client::new(), configs)
+}
+
+async fn restore(ai: &AiProvider) -> anyhow::Result<tissaia_core::models::RestorationResult> {
+    ai.restore_with_chat(ChatProvider::GROQ, "test-key", IMAGE, "image/png", &RestorationOptions::default()).await
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried() {
+    let server = MockServer::start(vec![
+        MockReply::status(503, json!({"error": "overloaded"})),
+        MockReply::status(429, json!({"error": "slow down"})).header("retry-after", "0"),
+        chat_reply(r#"{"improvements": ["Denoised"]}"#),
+    ])
+    .await;
+
+    let result = restore(&ai_with("groq", &server, limits())).await.unwrap();
+    assert_eq!(result.improvements, vec!["Denoised"]);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn gemini_calls_are_retried() {
+    let server = MockServer::start(vec![
+        MockReply::status(503, json!({"error": "model overloaded"})),
+        MockReply::ok(json!({
+            "candidates": [{"content": {"parts": [{"text": "{\"photo_count\": 0, \"bounding_boxes\": []}"}]}}]
+        })),
+    ])
+    .await;
+
+    let result = ai_with("google", &server, limits())
+        .detect_photo_boundaries("test-key", IMAGE, "image/png")
+        .await
+        .unwrap();
+    assert_eq!(result.photo_count, 0);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn retries_are_bounded_and_client_errors_are_not_retried() {
+    let server = MockServer::start(vec![MockReply::status(503, json!({"error": "down"}))]).await;
+    let err = restore(&ai_with("groq", &server, limits())).await.unwrap_err();
+    assert!(err.to_string().contains("Groq API error (503)"), "{}", err);
+    assert_eq!(server.requests().len(), 3);
+
+    let server = MockServer::start(vec![MockReply::status(400, json!({"error": "bad request"}))]).await;
+    assert!(restore(&ai_with("groq", &server, limits())).await.is_err());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn long_retry_after_fails_fast_for_failover() {
+    let server = MockServer::start(vec![
+        MockReply::status(429, json!({"error": "quota"})).header("retry-after", "120"),
+    ])
+    .await;
+
+    let err = restore(&ai_with("groq", &server, limits())).await.unwrap_err();
+    let error = ApiError::from(&err);
+    assert_eq!(error.code, "rate_limited");
+    assert_eq!(error.retry_after_secs, Some(120));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn concurrency_is_limited_per_provider() {
+    let delay = Duration::from_millis(200);
+    let server = MockServer::start(vec![chat_reply(r#"{"improvements": []}"#).delay(delay)]).await;
+    let ai = ai_with("groq", &server, RequestLimits { max_concurrent: 1, ..limits() });
+
+    let start = Instant::now();
+    let (first, second) = tokio::join!(restore(&ai), restore(&ai));
+    assert!(first.is_ok() && second.is_ok());
+    assert!(start.elapsed() >= delay * 2, "{:?}", start.elapsed());
+}
+
+#[tokio::test]
+async fn token_bucket_spaces_requests_and_is_shared() {
+    let server = MockServer::start(vec![chat_reply(r#"{"improvements": []}"#)]).await;
+    // 10 requests per second, one at a time: 100 ms between requests
+    let limits = RequestLimits { requests_per_minute: Some(600), burst: 1, ..limits() };
+    let executor = Arc::new(RequestExecutor::new());
+    let first = ai_with("groq", &server, limits.clone()).with_executor(executor.clone());
+    let second = ai_with("groq", &server, limits).with_executor(executor);
+
+    let start = Instant::now();
+    restore(&first).await.unwrap();
+    restore(&second).await.unwrap();
+    restore(&first).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(190), "{:?}", start.elapsed());
+}
+
+#[test]
+fn retry_after_accepts_seconds_and_http_dates() {
+    assert_eq!(parse_retry_after(" 7 "), Some(Duration::from_secs(7)));
+    assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+
+    let in_a_minute = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+    let wait = parse_retry_after(&in_a_minute).unwrap();
+    assert!(wait > Duration::from_secs(55) && wait <= Duration::from_secs(60), "{:?}", wait);
+
+    assert_eq!(parse_retry_after("soon"), None);
+}
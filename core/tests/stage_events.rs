@@ -0,0 +1,87 @@
+// core/tests/stage_events.rs
+//! Stage events (with intermediate payloads) from detect_photos_with_retry.
+
+mod common;
+
+use common::{chat_reply, state_with, MockReply, MockServer};
+use serde_json::json;
+use tissaia_core::operations;
+use tissaia_core::progress::{Stage, StageEvent, StageStatus};
+use tissaia_core::SharedState;
+use tokio::sync::mpsc;
+
+const IMAGE: &str = "aGVsbG8=";
+
+fn detection_reply() -> MockReply {
+    chat_reply(
+        r#"{"photo_count": 1, "bounding_boxes": [{"x": 0, "y": 0, "width": 500, "height": 500, "confidence": 0.9}]}"#,
+    )
+}
+
+async fn collect(state: &SharedState) -> Vec<StageEvent> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    operations::detect_photos_with_retry_observed(state, IMAGE, "image/png", &tx).await.unwrap();
+    drop(tx);
+    let mut events = Vec::new();
+    while let Some(event) = rx.recv().await {
+        events.push(event);
+    }
+    events
+}
+
+#[tokio::test]
+async fn emits_detection_verification_and_merge_payloads() {
+    let server = MockServer::start(vec![
+        detection_reply(),
+        chat_reply(
+            &json!({
+                "status": "fail",
+                "confidence": 40,
+                "checks": [{"name": "completeness", "passed": false, "detail": "one photo missed"}],
+                "issues": [],
+                "recommendations": [],
+                "missing_boxes": [{"x": 20, "y": 600, "width": 300, "height": 200, "confidence": 0.95}]
+            })
+            .to_string(),
+        ),
+    ])
+    .await;
+    let events = collect(&state_with("groq", &server)).await;
+
+    let sequence: Vec<(Stage, StageStatus)> = events.iter().map(|e| (e.stage, e.status)).collect();
+    assert_eq!(
+        sequence,
+        vec![
+            (Stage::Detect, StageStatus::Running),
+            (Stage::Detect, StageStatus::Completed),
+            (Stage::Verify, StageStatus::Running),
+            (Stage::Verify, StageStatus::Completed),
+            (Stage::Merge, StageStatus::Running),
+            (Stage::Merge, StageStatus::Completed),
+        ]
+    );
+
+    // Initial detection, before the merge
+    let detection = events[1].payload.as_ref().unwrap();
+    assert_eq!(detection["photo_count"], 1);
+    assert_eq!(events[3].payload.as_ref().unwrap()["status"], "fail");
+
+    let merge = events[5].payload.as_ref().unwrap();
+    assert_eq!(merge["photo_count"], 2);
+    assert_eq!(merge["merged_boxes"][0]["label"], "photo 2");
+    // Verifier-suggested boxes are capped at 0.80 confidence
+    assert!((merge["merged_boxes"][0]["confidence"].as_f64().unwrap() - 0.8).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn disabled_verification_is_reported_as_skipped() {
+    let server = MockServer::start(vec![detection_reply()]).await;
+    let state = state_with("groq", &server);
+    state.lock().await.settings.verification_enabled = false;
+
+    let events = collect(&state).await;
+    let last = events.last().unwrap();
+    assert_eq!((last.stage, last.status), (Stage::Verify, StageStatus::Skipped));
+    assert!(last.payload.is_none());
+    assert_eq!(server.requests().len(), 1);
+}
@@ -0,0 +1,157 @@
+// core/tests/structured_replies.rs
+//! Typed AI replies (detection, verification, restoration plans): JSON
+//! repair, validation, schema requests and the bounded re-ask when a reply
+//! is invalid.
+
+mod common;
+
+use common::{chat_reply, MockReply, MockServer};
+use serde_json::json;
+use tissaia_core::ai::{AiProvider, ChatProvider, Verifier};
+use tissaia_core::config::default_provider_configs;
+use tissaia_core::failover::is_retryable;
+use tissaia_core::models::{RestorationOptions, VerificationStatus};
+use tissaia_core::responses::{parse_reply, repair_json, DetectionReply, PlanReply, VerificationReply};
+use tissaia_core::ApiError;
+
+const IMAGE: &str = "aGVsbG8=";
+
+fn ai_for(provider: &str, base_url: String) -> AiProvider {
+    let mut configs = default_provider_configs();
+    configs.get_mut(provider).unwrap().base_url = base_url;
+    AiProvider::with_config(reqwest::Client::new(), configs)
+}
+
+#[test]
+fn repair_strips_fences_prose_and_trailing_commas() {
+    let text = "Here you go:\n```json\n{\"photo_count\": 1, \"bounding_boxes\": [\n  {\"x\": 10, \"y\": 20, \"width\": 300, \"height\": 200, \"confidence\": 0.9, \"label\": \"a, }\"},\n],}\n```";
+    assert_eq!(
+        repair_json(text),
+        "{\"photo_count\": 1, \"bounding_boxes\": [\n  {\"x\": 10, \"y\": 20, \"width\": 300, \"height\": 200, \"confidence\": 0.9, \"label\": \"a, }\"}\n]}"
+    );
+
+    let reply: DetectionReply = parse_reply(text).unwrap();
+    assert_eq!(reply.bounding_boxes[0].label.as_deref(), Some("a, }"));
+}
+
+#[test]
+fn missing_or_out_of_range_fields_are_rejected() {
+    // Used to default to a passing verification
+    let err = parse_reply::<VerificationReply>(r#"{"confidence": 90, "checks": []}"#).unwrap_err();
+    assert!(err.contains("missing field `status`"), "{}", err);
+
+    let err = parse_reply::<VerificationReply>(r#"{"status": "pass", "confidence": 140, "checks": []}"#).unwrap_err();
+    assert!(err.contains("confidence must be between 0 and 100"), "{}", err);
+
+    let err = parse_reply::<DetectionReply>(
+        r#"{"photo_count": 1, "bounding_boxes": [{"x": 10, "y": 20, "width": 300, "height": 200}]}"#,
+    )
+    .unwrap_err();
+    assert!(err.contains("missing field `confidence`"), "{}", err);
+
+    let err = parse_reply::<PlanReply>(r#"{"processing_steps": []}"#).unwrap_err();
+    assert!(err.contains("missing field `improvements`"), "{}", err);
+
+    let err = parse_reply::<PlanReply>(r#"{"improvements": [], "operations": [{"step": "levels", "strength": 3}]}"#)
+        .unwrap_err();
+    assert!(err.contains("operations[0].strength must be between 0 and 1"), "{}", err);
+}
+
+#[tokio::test]
+async fn invalid_reply_is_reasked_with_the_error() {
+    let server = MockServer::start(vec![
+        chat_reply(r#"{"photo_count": 1, "bounding_boxes": [{"x": 10, "y": 20, "width": 300, "height": 200}]}"#),
+        chat_reply(
+            r#"{"photo_count": 1, "bounding_boxes": [{"x": 10, "y": 20, "width": 300, "height": 200, "confidence": 0.8}]}"#,
+        ),
+    ])
+    .await;
+
+    let result = ai_for("groq", server.url("/v1"))
+        .detect_with_chat(ChatProvider::GROQ, "test-key", IMAGE, "image/png")
+        .await
+        .unwrap();
+    assert_eq!(result.bounding_boxes.len(), 1);
+    assert_eq!(result.bounding_boxes[0].confidence, 0.8);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    let format = &requests[0].body["response_format"];
+    assert_eq!(format["type"], "json_schema");
+    assert_eq!(format["json_schema"]["name"], "photo_detection");
+    assert_eq!(format["json_schema"]["schema"]["required"], json!(["photo_count", "bounding_boxes"]));
+
+    let reasked = requests[1].body["messages"][0]["content"][0]["text"].as_str().unwrap();
+    assert!(reasked.contains("Your previous reply was invalid"), "{}", reasked);
+    assert!(reasked.contains("missing field `confidence`"), "{}", reasked);
+}
+
+#[tokio::test]
+async fn reply_still_invalid_after_reasks_fails_instead_of_passing() {
+    let server = MockServer::start(vec![chat_reply(r#"{"confidence": 90}"#)]).await;
+
+    let err = ai_for("mistral", server.url("/v1"))
+        .verify_crop(Verifier::Chat(ChatProvider::MISTRAL), "test-key", IMAGE, "image/png", 0)
+        .await
+        .unwrap_err();
+
+    assert_eq!(server.requests().len(), 3);
+    assert_eq!(ApiError::from(&err).code, "provider_error");
+    assert!(is_retryable(&err));
+    assert!(err.to_string().contains("mistral returned an invalid reply"), "{}", err);
+}
+
+#[tokio::test]
+async fn gemini_requests_carry_a_response_schema() {
+    let detection = r#"{"photo_count": 1.0, "bounding_boxes": [{"x": 1000, "y": 990, "width": 50, "height": 50, "confidence": 0.9, "contour": [[1000, 990], {"x": 1200, "y": 995}]}]}"#;
+    let verification = r#"{"status": "warning", "confidence": 72.6, "checks": [{"name": "count", "passed": false}], "issues": [{"severity": "warning", "description": "Edge cut"}]}"#;
+    let gemini = |text: &str| MockReply::ok(json!({"candidates": [{"content": {"parts": [{"text": text}]}}]}));
+    let server = MockServer::start(vec![gemini(detection), gemini(verification)]).await;
+    let ai = ai_for("google", server.url("/gemini"));
+
+    let result = ai.detect_photo_boundaries("test-key", IMAGE, "image/png").await.unwrap();
+    let b = &result.bounding_boxes[0];
+    assert_eq!((result.photo_count, b.x, b.width, b.height), (1, 1000, 1, 10));
+    assert_eq!(b.contour[1].x, 1000.0);
+
+    let verdict = ai
+        .verify_detection(Verifier::GeminiFlash, "test-key", IMAGE, "image/png", &result.bounding_boxes)
+        .await
+        .unwrap();
+    assert!(matches!(verdict.status, VerificationStatus::Warning));
+    assert_eq!(verdict.confidence, 73);
+    assert!(!verdict.checks[0].passed);
+
+    let requests = server.requests();
+    let schema = &requests[0].body["generationConfig"]["responseSchema"];
+    assert_eq!(schema["type"], "OBJECT");
+    assert_eq!(schema["properties"]["photo_count"]["type"], "INTEGER");
+    assert_eq!(schema["properties"]["bounding_boxes"]["items"]["properties"]["x"]["type"], "NUMBER");
+    let schema = &requests[1].body["generationConfig"]["responseSchema"];
+    assert_eq!(schema["properties"]["status"]["enum"], json!(["pass", "warning", "fail"]));
+}
+
+#[tokio::test]
+async fn invalid_plan_is_reasked_and_openai_requests_a_schema() {
+    let openai = |text: &str| MockReply::ok(json!({"choices": [{"message": {"content": text}}]}));
+    let server = MockServer::start(vec![
+        openai("I would remove the scratches."),
+        openai(r#"{"improvements": ["Scratches removed"], "operations": [{"step": "dust_removal", "strength": 0.6}]}"#),
+    ])
+    .await;
+
+    let result = ai_for("openai", server.url("/v1"))
+        .restore_with_openai("test-key", IMAGE, "image/png", &RestorationOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(result.improvements, vec!["Scratches removed"]);
+    assert_eq!(result.plan.unwrap().operations.len(), 1);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    let format = &requests[0].body["response_format"];
+    assert_eq!(format["type"], "json_schema");
+    assert_eq!(format["json_schema"]["name"], "restoration_plan");
+    let reasked = requests[1].body["messages"][0]["content"][0]["text"].as_str().unwrap();
+    assert!(reasked.contains("Your previous reply was invalid: invalid JSON"), "{}", reasked);
+}
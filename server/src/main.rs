@@ -4,28 +4,31 @@
 //! Replaces the Tauri desktop shell with a pure HTTP API.
 //! Deploy on Fly.io (Frankfurt region) for low-latency access.
 
-mod ai;
 mod handlers;
-mod models;
-mod state;
+mod payload;
 
 use axum::{Router, routing::{get, post, delete}};
-use handlers::SharedState;
-use state::AppState;
+use axum::extract::DefaultBodyLimit;
+use axum::http::{header, HeaderName};
 use std::sync::Arc;
+use tissaia_core::health;
+use tissaia_core::{AppState, HistoryStore, PresetStore, SharedState};
 use tokio::sync::Mutex;
 use tower_http::cors::{CorsLayer, Any};
 use tower_http::trace::TraceLayer;
 use tower_http::limit::RequestBodyLimitLayer;
 use tracing::info;
 
+/// Largest request body (image uploads).
+const MAX_BODY_BYTES: usize = 60 * 1024 * 1024;
+
 #[tokio::main]
 async fn main() {
     // Initialize tracing (replaces tauri-plugin-log + env_logger)
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "tissaia_server=info,tower_http=info".into()),
+                .unwrap_or_else(|_| "tissaia_server=info,tissaia_core=info,tower_http=info".into()),
         )
         .init();
 
@@ -40,8 +43,16 @@ async fn main() {
     info!("ANTHROPIC_API_KEY present: {}", std::env::var("ANTHROPIC_API_KEY").is_ok());
     info!("OPENAI_API_KEY present: {}", std::env::var("OPENAI_API_KEY").is_ok());
 
-    // Create shared state
-    let shared_state: SharedState = Arc::new(Mutex::new(AppState::new()));
+    // Create shared state (history persisted under TISSAIA_HISTORY_DIR,
+    // restoration presets in TISSAIA_PRESETS_FILE)
+    let shared_state: SharedState = Arc::new(Mutex::new(
+        AppState::with_history(HistoryStore::from_env()).with_presets(PresetStore::from_env()),
+    ));
+
+    // Background provider health probes (TISSAIA_HEALTH_INTERVAL_SECS, 0 = off)
+    if let Some(every) = health::monitor_interval_from_env() {
+        tokio::spawn(health::run_monitor(shared_state.clone(), every));
+    }
 
     // CORS configuration — allow frontend origin (Vercel) + localhost dev
     let frontend_origin = std::env::var("FRONTEND_ORIGIN")
@@ -56,19 +67,48 @@ async fn main() {
         )
         .allow_methods(Any)
         .allow_headers(Any)
+        .expose_headers([HeaderName::from_static("x-total-count"), header::LOCATION])
         .max_age(std::time::Duration::from_secs(86400)); // 24h preflight cache
 
-    // Build router
-    let app = Router::new()
+    let app = router(shared_state).layer(cors);
+
+    // Bind to port
+    let port = std::env::var("PORT")
+        .unwrap_or_else(|_| "8080".to_string())
+        .parse::<u16>()
+        .unwrap_or(8080);
+
+    let addr = format!("0.0.0.0:{}", port);
+    info!("🟢 Tissaia AI Server v{} starting on {}", env!("CARGO_PKG_VERSION"), addr);
+
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .expect("Failed to bind address");
+
+    // Graceful shutdown on SIGTERM/SIGINT (Fly.io sends SIGTERM)
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .expect("Server error");
+}
+
+/// All API routes with tracing and the body limit; CORS is added by `main`.
+fn router(shared_state: SharedState) -> Router {
+    Router::new()
         // Health & Status
         .route("/api/health", get(handlers::health_check))
         .route("/api/providers", get(handlers::get_providers_status))
         .route("/api/models/ollama", get(handlers::get_ollama_models))
         // Restoration
         .route("/api/restore", post(handlers::restore_image))
+        .route("/api/presets", get(handlers::get_presets))
+        .route("/api/presets", post(handlers::save_preset))
+        .route("/api/presets/{name}", delete(handlers::delete_preset))
+        .route("/api/prompts", get(handlers::get_prompts))
         // Photo Separation (Detection + Crop)
         .route("/api/detect", post(handlers::detect_photos))
         .route("/api/detect/retry", post(handlers::detect_photos_with_retry))
+        .route("/api/detect/retry/stream", post(handlers::detect_photos_with_retry_stream))
         .route("/api/crop", post(handlers::crop_photos))
         .route("/api/outpaint", post(handlers::outpaint_photo))
         // Image Processing
@@ -81,37 +121,28 @@ async fn main() {
         .route("/api/verify/restoration", post(handlers::verify_restoration))
         .route("/api/verify/detection", post(handlers::verify_detection))
         .route("/api/verify/crop", post(handlers::verify_crop))
+        // End-to-end pipeline (scan → restored photos)
+        .route("/api/pipeline", post(handlers::run_pipeline))
+        // Asynchronous jobs (long-running AI operations)
+        .route("/api/jobs", post(handlers::create_job))
+        .route("/api/jobs/{id}", get(handlers::get_job))
+        .route("/api/jobs/{id}", delete(handlers::cancel_job))
         // History
         .route("/api/history", get(handlers::get_history))
         .route("/api/history", delete(handlers::clear_history))
+        .route("/api/history/{id}", get(handlers::get_history_entry))
+        .route("/api/history/{id}", delete(handlers::delete_history_entry))
+        .route("/api/history/blobs/{hash}", get(handlers::get_history_blob))
         // Settings & API Keys
         .route("/api/settings", get(handlers::get_settings))
         .route("/api/settings", post(handlers::save_settings))
         .route("/api/keys", post(handlers::set_api_key))
         // Middleware
-        .layer(cors)
         .layer(TraceLayer::new_for_http())
-        .layer(RequestBodyLimitLayer::new(60 * 1024 * 1024)) // 60MB body limit (images)
-        .with_state(shared_state);
-
-    // Bind to port
-    let port = std::env::var("PORT")
-        .unwrap_or_else(|_| "8080".to_string())
-        .parse::<u16>()
-        .unwrap_or(8080);
-
-    let addr = format!("0.0.0.0:{}", port);
-    info!("🟢 Tissaia AI Server v{} starting on {}", env!("CARGO_PKG_VERSION"), addr);
-
-    let listener = tokio::net::TcpListener::bind(&addr)
-        .await
-        .expect("Failed to bind address");
-
-    // Graceful shutdown on SIGTERM/SIGINT (Fly.io sends SIGTERM)
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .expect("Server error");
+        // 60MB body limit (images); replaces the extractors' 2MB default
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(MAX_BODY_BYTES))
+        .with_state(shared_state)
 }
 
 async fn shutdown_signal() {
@@ -137,3 +168,56 @@ async fn shutdown_signal() {
         _ = terminate => info!("Received SIGTERM, shutting down..."),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use tower::ServiceExt;
+
+    /// Above axum's 2MB extractor default.
+    const LARGE_UPLOAD: usize = 3 * 1024 * 1024;
+
+    async fn post_metadata(content_type: &str, body: Vec<u8>) -> (StatusCode, serde_json::Value) {
+        let request = Request::post("/api/metadata")
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(Body::from(body))
+            .unwrap();
+        let response = router(Arc::new(Mutex::new(AppState::new()))).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn uploads_above_two_megabytes_are_accepted() {
+        let scan = vec![0u8; LARGE_UPLOAD];
+
+        let (status, metadata) = post_metadata("image/jpeg", scan.clone()).await;
+        assert_eq!(status, StatusCode::OK, "{}", metadata);
+        assert_eq!(metadata["file_size"], LARGE_UPLOAD);
+
+        let mut form = b"--XYZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"scan.jpg\"\r\n\
+                         Content-Type: image/jpeg\r\n\r\n"
+            .to_vec();
+        form.extend_from_slice(&scan);
+        form.extend_from_slice(b"\r\n--XYZ--\r\n");
+        let (status, metadata) = post_metadata("multipart/form-data; boundary=XYZ", form).await;
+        assert_eq!(status, StatusCode::OK, "{}", metadata);
+        assert_eq!(metadata["file_size"], LARGE_UPLOAD);
+
+        let json = serde_json::json!({"image_base64": STANDARD.encode(&scan), "mime_type": "image/jpeg"});
+        let (status, metadata) = post_metadata("application/json", json.to_string().into_bytes()).await;
+        assert_eq!(status, StatusCode::OK, "{}", metadata);
+        assert_eq!(metadata["file_size"], LARGE_UPLOAD);
+    }
+
+    #[tokio::test]
+    async fn bodies_above_the_limit_are_rejected() {
+        let (status, _) = post_metadata("image/jpeg", vec![0; MAX_BODY_BYTES + 1]).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,595 @@
+// server/src/payload.rs
+//! Image request/response encodings besides base64-in-JSON.
+//!
+//! Requests (`ImageInput<T>`), picked by `Content-Type`:
+//! - `application/json` — the DTO as before.
+//! - `multipart/form-data` — a file part named `file` or `image` fills the
+//!   DTO's main image field; other file parts `X` fill `X_base64` (e.g.
+//!   `original` + `restored`). Text parts are the remaining DTO fields, parsed
+//!   as JSON when possible (`degrees=90`, `bounding_boxes=[...]`).
+//! - `image/*` — the raw body is the image; other fields come from the query.
+//!
+//! `mime_type` defaults to the part / body content type.
+//!
+//! Endpoints whose core operation accepts raw bytes (the local image
+//! operations) use `ImageUpload<T>` instead, so a raw or multipart upload is
+//! handed to core as is rather than base64-encoded.
+//!
+//! Responses (`ResponseFormat`): `?format=json|binary|multipart`, or an
+//! `Accept` header of `image/*` / `multipart/*`. Binary returns the image
+//! bytes; `crop` and `pipeline` return a `multipart/mixed` bundle (JSON
+//! result + one part per photo).
+
+use crate::handlers::AppError;
+use axum::body::{Body, Bytes};
+use axum::extract::{FromRequest, FromRequestParts, Multipart, Query, Request};
+use axum::http::request::Parts;
+use axum::http::{header, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tissaia_core::imaging::{extension_for_mime, mime_from_filename, ImageData};
+use tissaia_core::models::CropResult;
+use tissaia_core::pipeline::PipelineResult;
+use tissaia_core::TissaiaError;
+
+// ============================================
+// REQUESTS
+// ============================================
+
+/// Request DTO carrying an image.
+pub trait ImagePayload {
+    /// Field filled by a `file`/`image` multipart part or a raw body.
+    const IMAGE_FIELD: &'static str = "image_base64";
+}
+
+/// A DTO read from JSON, multipart form data or a raw image body.
+pub struct ImageInput<T>(pub T);
+
+impl<T, S> FromRequest<S> for ImageInput<T>
+where
+    T: DeserializeOwned + ImagePayload,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        if content_type.starts_with("multipart/form-data") || content_type.starts_with("image/") {
+            let (mut fields, image) = read_upload::<T, S>(req, state, &content_type).await?;
+            if let Some(bytes) = image {
+                fields.insert(T::IMAGE_FIELD.to_string(), Value::String(STANDARD.encode(&bytes)));
+            }
+            return from_fields(fields).map(ImageInput);
+        }
+
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|e| validation(e.body_text()))?;
+        Ok(ImageInput(value))
+    }
+}
+
+/// A DTO plus its main image, kept binary for raw and multipart uploads.
+/// The image is taken out of the fields, so the DTO has no image field.
+pub struct ImageUpload<T> {
+    pub request: T,
+    pub image: UploadedImage,
+}
+
+pub enum UploadedImage {
+    /// From a JSON body.
+    Base64(String),
+    Bytes(Bytes),
+}
+
+impl UploadedImage {
+    pub fn data(&self) -> ImageData<'_> {
+        match self {
+            UploadedImage::Base64(data) => ImageData::Base64(data),
+            UploadedImage::Bytes(bytes) => ImageData::Bytes(bytes),
+        }
+    }
+}
+
+impl<T, S> FromRequest<S> for ImageUpload<T>
+where
+    T: DeserializeOwned + ImagePayload,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        let (fields, image) = if content_type.starts_with("multipart/form-data") || content_type.starts_with("image/") {
+            let (fields, image) = read_upload::<T, S>(req, state, &content_type).await?;
+            (fields, image.map(UploadedImage::Bytes))
+        } else {
+            let Json(mut fields) = Json::<Map<String, Value>>::from_request(req, state)
+                .await
+                .map_err(|e| validation(e.body_text()))?;
+            let image = match fields.remove(T::IMAGE_FIELD) {
+                Some(Value::String(data)) => Some(UploadedImage::Base64(data)),
+                _ => None,
+            };
+            (fields, image)
+        };
+
+        let image = image.ok_or_else(|| validation(format!("Missing image ({})", T::IMAGE_FIELD)))?;
+        Ok(ImageUpload { request: from_fields(fields)?, image })
+    }
+}
+
+/// Fields and main image of a multipart or raw `image/*` body. Other file
+/// parts are base64-encoded into their fields.
+async fn read_upload<T: ImagePayload, S: Send + Sync>(
+    req: Request,
+    state: &S,
+    content_type: &str,
+) -> Result<(Map<String, Value>, Option<Bytes>), AppError> {
+    if content_type.starts_with("multipart/form-data") {
+        let multipart = Multipart::from_request(req, state)
+            .await
+            .map_err(|e| validation(format!("Invalid multipart body: {}", e)))?;
+        return read_multipart::<T>(multipart).await;
+    }
+
+    let mut fields = query_fields(req.uri())?;
+    let bytes = Bytes::from_request(req, state)
+        .await
+        .map_err(|e| validation(format!("Failed to read body: {}", e)))?;
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    fields.entry("mime_type").or_insert_with(|| Value::String(mime.to_string()));
+    Ok((fields, Some(bytes)))
+}
+
+async fn read_multipart<T: ImagePayload>(
+    mut multipart: Multipart,
+) -> Result<(Map<String, Value>, Option<Bytes>), AppError> {
+    let mut fields = Map::new();
+    let mut image = None;
+    // MIME of the main image part, else of the first other image part
+    let mut main_mime = None;
+    let mut other_mime = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| validation(format!("Invalid multipart body: {}", e)))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        let is_file = field.file_name().is_some()
+            || field.content_type().is_some_and(|ct| ct.starts_with("image/") || ct == "application/octet-stream");
+
+        if is_file {
+            let mime = field
+                .content_type()
+                .filter(|ct| ct.starts_with("image/"))
+                .map(str::to_string)
+                .or_else(|| field.file_name().and_then(mime_from_filename).map(str::to_string));
+            let target = match name.as_str() {
+                "file" | "image" | "" => T::IMAGE_FIELD.to_string(),
+                n if n.ends_with("_base64") => n.to_string(),
+                n => format!("{}_base64", n),
+            };
+            let bytes = field
+                .bytes()
+                .await
+                .map_err(|e| validation(format!("Failed to read part '{}': {}", name, e)))?;
+            if target == T::IMAGE_FIELD {
+                main_mime = mime;
+                image = Some(bytes);
+            } else {
+                if other_mime.is_none() {
+                    other_mime = mime;
+                }
+                fields.insert(target, Value::String(STANDARD.encode(&bytes)));
+            }
+        } else {
+            let text = field
+                .text()
+                .await
+                .map_err(|e| validation(format!("Failed to read field '{}': {}", name, e)))?;
+            push_field(&mut fields, name, text);
+        }
+    }
+
+    if let Some(mime) = main_mime.or(other_mime) {
+        fields.entry("mime_type").or_insert(Value::String(mime));
+    }
+    Ok((fields, image))
+}
+
+fn query_fields(uri: &axum::http::Uri) -> Result<Map<String, Value>, AppError> {
+    let Query(pairs) = Query::<Vec<(String, String)>>::try_from_uri(uri)
+        .map_err(|e| validation(format!("Invalid query string: {}", e)))?;
+    let mut fields = Map::new();
+    for (name, value) in pairs {
+        push_field(&mut fields, name, value);
+    }
+    Ok(fields)
+}
+
+/// Insert a text field, parsing JSON values; repeated names become arrays.
+fn push_field(fields: &mut Map<String, Value>, name: String, text: String) {
+    let value = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    match fields.get_mut(&name) {
+        Some(Value::Array(items)) => items.push(value),
+        Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+        None => {
+            fields.insert(name, value);
+        }
+    }
+}
+
+fn from_fields<T: DeserializeOwned>(fields: Map<String, Value>) -> Result<T, AppError> {
+    serde_json::from_value(Value::Object(fields)).map_err(|e| validation(format!("Invalid request: {}", e)))
+}
+
+fn validation(message: String) -> AppError {
+    TissaiaError::Validation(message).into()
+}
+
+// ============================================
+// RESPONSES
+// ============================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    /// Raw image bytes.
+    Binary,
+    /// `multipart/mixed` bundle (crop and pipeline results).
+    Multipart,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ResponseFormat {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let explicit = parts
+            .uri
+            .query()
+            .into_iter()
+            .flat_map(|q| q.split('&'))
+            .find_map(|pair| pair.strip_prefix("format="));
+        if let Some(format) = explicit {
+            return match format {
+                "json" => Ok(ResponseFormat::Json),
+                "binary" => Ok(ResponseFormat::Binary),
+                "multipart" => Ok(ResponseFormat::Multipart),
+                other => Err(validation(format!("Unknown format '{}' (json, binary, multipart)", other))),
+            };
+        }
+
+        let accept = parts
+            .headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        Ok(if accept.contains("multipart/") {
+            ResponseFormat::Multipart
+        } else if accept.split(',').any(|t| t.trim().starts_with("image/")) {
+            ResponseFormat::Binary
+        } else {
+            ResponseFormat::Json
+        })
+    }
+}
+
+impl ResponseFormat {
+    /// `value` as JSON, or the decoded image when binary output was requested.
+    pub fn image<T: Serialize>(
+        self,
+        value: &T,
+        image_base64: &str,
+        mime_type: &str,
+    ) -> Result<Response, AppError> {
+        if self == ResponseFormat::Json {
+            return Ok(Json(value).into_response());
+        }
+        let bytes = tissaia_core::imaging::decode_base64(image_base64)?;
+        Ok(([(header::CONTENT_TYPE, mime_type.to_string())], bytes).into_response())
+    }
+
+    /// Crop results as JSON or as a `multipart/mixed` bundle: a JSON part with
+    /// the result (without image data), then one image part per photo.
+    pub fn crop(self, result: CropResult) -> Result<Response, AppError> {
+        if self == ResponseFormat::Json {
+            return Ok(Json(result).into_response());
+        }
+
+        let stem = result
+            .original_filename
+            .rsplit_once('.')
+            .map_or(result.original_filename.as_str(), |(stem, _)| stem)
+            .to_string();
+        let files = result
+            .photos
+            .iter()
+            .map(|photo| BundleFile {
+                filename: format!("{}_{}.{}", stem, photo.index + 1, extension_for_mime(&photo.mime_type)),
+                mime_type: &photo.mime_type,
+                image_base64: &photo.image_base64,
+            })
+            .collect();
+        bundle(&result, files)
+    }
+
+    /// Pipeline results as JSON or, like `crop`, as a `multipart/mixed`
+    /// bundle of the report plus the final photos.
+    pub fn pipeline(self, result: PipelineResult) -> Result<Response, AppError> {
+        if self == ResponseFormat::Json {
+            return Ok(Json(result).into_response());
+        }
+
+        let files = result
+            .photos
+            .iter()
+            .map(|photo| BundleFile {
+                filename: photo.filename.clone(),
+                mime_type: &photo.mime_type,
+                image_base64: &photo.image_base64,
+            })
+            .collect();
+        bundle(&result, files)
+    }
+}
+
+/// Image part of a `multipart/mixed` bundle.
+struct BundleFile<'a> {
+    filename: String,
+    mime_type: &'a str,
+    image_base64: &'a str,
+}
+
+/// `result` (its `photos[].image_base64` stripped) as a JSON part named
+/// `result`, followed by `files` as parts `photo_1`, `photo_2`, ...
+fn bundle<T: Serialize>(result: &T, files: Vec<BundleFile>) -> Result<Response, AppError> {
+    let boundary = format!("tissaia-{}", uuid::Uuid::new_v4().simple());
+
+    let mut summary = serde_json::to_value(result).map_err(anyhow::Error::from)?;
+    if let Some(photos) = summary["photos"].as_array_mut() {
+        for photo in photos.iter_mut().filter_map(|p| p.as_object_mut()) {
+            photo.remove("image_base64");
+        }
+    }
+
+    let mut body = Vec::new();
+    push_part(&mut body, &boundary, "application/json", "inline; name=\"result\"", summary.to_string().as_bytes());
+    for (i, file) in files.iter().enumerate() {
+        let bytes = tissaia_core::imaging::decode_base64(file.image_base64)?;
+        let disposition = format!("attachment; name=\"photo_{}\"; {}", i + 1, filename_params(&file.filename));
+        push_part(&mut body, &boundary, file.mime_type, &disposition, &bytes);
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    let content_type = HeaderValue::from_str(&format!("multipart/mixed; boundary={}", boundary))
+        .map_err(anyhow::Error::from)?;
+    Ok(([(header::CONTENT_TYPE, content_type)], Body::from(body)).into_response())
+}
+
+/// `filename` parameters for a client-supplied name: an ASCII fallback with
+/// quotes, backslashes and control characters replaced, plus the exact name
+/// as RFC 5987 `filename*` when it is not plain ASCII.
+fn filename_params(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+    if fallback == filename {
+        return format!("filename=\"{}\"", fallback);
+    }
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'!' | b'#' | b'$' | b'&' | b'+' | b'-' | b'.' | b'^' | b'_'
+            | b'`' | b'|' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+fn push_part(body: &mut Vec<u8>, boundary: &str, content_type: &str, disposition: &str, data: &[u8]) {
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Type: {}\r\nContent-Disposition: {}\r\n\r\n",
+            boundary, content_type, disposition
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(b"\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Dto {
+        image_base64: String,
+        mime_type: String,
+        degrees: i32,
+        #[serde(default)]
+        filters: Option<Vec<String>>,
+    }
+
+    impl ImagePayload for Dto {}
+
+    #[derive(Debug, Deserialize)]
+    struct PairDto {
+        original_base64: String,
+        restored_base64: String,
+        mime_type: String,
+    }
+
+    impl ImagePayload for PairDto {
+        const IMAGE_FIELD: &'static str = "original_base64";
+    }
+
+    fn request(content_type: &str, uri: &str, body: impl Into<Body>) -> Request {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(body.into())
+            .unwrap()
+    }
+
+    /// `(field name, Some((filename, content type)) for file parts, data)`
+    type Part<'a> = (&'a str, Option<(&'a str, &'a str)>, &'a [u8]);
+
+    fn multipart_body(boundary: &str, parts: &[Part]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (name, file, data) in parts {
+            body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            match file {
+                Some((filename, content_type)) => body.extend_from_slice(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                        name, filename, content_type
+                    )
+                    .as_bytes(),
+                ),
+                None => body.extend_from_slice(
+                    format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).as_bytes(),
+                ),
+            }
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+        body
+    }
+
+    #[tokio::test]
+    async fn raw_image_body_with_query_fields() {
+        let req = request("image/png", "/api/rotate?degrees=90&filters=clahe&filters=sharpen", &b"hello"[..]);
+        let ImageInput(dto) = ImageInput::<Dto>::from_request(req, &()).await.ok().unwrap();
+
+        assert_eq!(dto.image_base64, "aGVsbG8=");
+        assert_eq!(dto.mime_type, "image/png");
+        assert_eq!(dto.degrees, 90);
+        assert_eq!(dto.filters.unwrap(), vec!["clahe", "sharpen"]);
+    }
+
+    #[tokio::test]
+    async fn multipart_file_and_text_fields() {
+        let body = multipart_body("XYZ", &[
+            ("file", Some(("scan.jpg", "image/jpeg")), b"hello"),
+            ("degrees", None, b"180"),
+        ]);
+        let req = request("multipart/form-data; boundary=XYZ", "/api/rotate", body);
+        let ImageInput(dto) = ImageInput::<Dto>::from_request(req, &()).await.ok().unwrap();
+
+        assert_eq!(dto.image_base64, "aGVsbG8=");
+        assert_eq!(dto.mime_type, "image/jpeg");
+        assert_eq!(dto.degrees, 180);
+    }
+
+    #[tokio::test]
+    async fn multipart_named_files_fill_matching_fields() {
+        let body = multipart_body("XYZ", &[
+            ("original", Some(("a.png", "application/octet-stream")), b"a"),
+            ("restored", Some(("b.png", "application/octet-stream")), b"b"),
+        ]);
+        let req = request("multipart/form-data; boundary=XYZ", "/api/verify/restoration", body);
+        let ImageInput(dto) = ImageInput::<PairDto>::from_request(req, &()).await.ok().unwrap();
+
+        assert_eq!(dto.original_base64, "YQ==");
+        assert_eq!(dto.restored_base64, "Yg==");
+        assert_eq!(dto.mime_type, "image/png");
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct LocalDto {
+        mime_type: String,
+        degrees: i32,
+    }
+
+    impl ImagePayload for LocalDto {}
+
+    #[tokio::test]
+    async fn image_upload_keeps_uploads_binary() {
+        let req = request("image/png", "/api/rotate?degrees=90", &b"hello"[..]);
+        let upload = ImageUpload::<LocalDto>::from_request(req, &()).await.ok().unwrap();
+        assert!(matches!(upload.image, UploadedImage::Bytes(ref bytes) if &bytes[..] == b"hello"));
+        assert_eq!((upload.request.mime_type.as_str(), upload.request.degrees), ("image/png", 90));
+
+        let body = r#"{"image_base64": "aGVsbG8=", "mime_type": "image/jpeg", "degrees": 180}"#;
+        let req = request("application/json", "/api/rotate", body);
+        let upload = ImageUpload::<LocalDto>::from_request(req, &()).await.ok().unwrap();
+        assert!(matches!(upload.image, UploadedImage::Base64(ref data) if data == "aGVsbG8="));
+        assert_eq!(upload.request.degrees, 180);
+
+        let req = request("application/json", "/api/rotate", r#"{"mime_type": "image/jpeg", "degrees": 0}"#);
+        let response = ImageUpload::<LocalDto>::from_request(req, &()).await.err().unwrap().into_response();
+        assert_eq!(response.status(), 422);
+    }
+
+    #[tokio::test]
+    async fn json_body_still_accepted() {
+        let body = r#"{"image_base64": "aGVsbG8=", "mime_type": "image/jpeg", "degrees": 270}"#;
+        let req = request("application/json", "/api/rotate", body);
+        let ImageInput(dto) = ImageInput::<Dto>::from_request(req, &()).await.ok().unwrap();
+        assert_eq!(dto.degrees, 270);
+    }
+
+    #[tokio::test]
+    async fn missing_fields_are_rejected_with_validation_error() {
+        let req = request("image/png", "/api/rotate", &b"hello"[..]);
+        let response = ImageInput::<Dto>::from_request(req, &()).await.err().unwrap().into_response();
+        assert_eq!(response.status(), 422);
+    }
+
+    async fn format_of(uri: &str, accept: &str) -> ResponseFormat {
+        let req = Request::builder().uri(uri).header(header::ACCEPT, accept).body(Body::empty()).unwrap();
+        let (mut parts, _) = req.into_parts();
+        ResponseFormat::from_request_parts(&mut parts, &()).await.ok().unwrap()
+    }
+
+    #[tokio::test]
+    async fn response_format_from_query_or_accept() {
+        assert_eq!(format_of("/api/rotate", "application/json").await, ResponseFormat::Json);
+        assert_eq!(format_of("/api/rotate", "image/png").await, ResponseFormat::Binary);
+        assert_eq!(format_of("/api/crop", "multipart/mixed").await, ResponseFormat::Multipart);
+        assert_eq!(format_of("/api/rotate?format=binary", "application/json").await, ResponseFormat::Binary);
+    }
+
+    #[test]
+    fn bundle_filenames_cannot_break_out_of_the_header() {
+        assert_eq!(filename_params("scan_1.png"), "filename=\"scan_1.png\"");
+        assert_eq!(
+            filename_params("a\"b\r\nX-Evil: 1.png"),
+            "filename=\"a_b__X-Evil: 1.png\"; filename*=UTF-8''a%22b%0D%0AX-Evil%3A%201.png"
+        );
+        assert_eq!(filename_params("zdjęcie.png"), "filename=\"zdj_cie.png\"; filename*=UTF-8''zdj%C4%99cie.png");
+    }
+
+    #[tokio::test]
+    async fn binary_response_returns_image_bytes() {
+        let response = ResponseFormat::Binary.image(&"ignored", "aGVsbG8=", "image/png").ok().unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"hello");
+    }
+}
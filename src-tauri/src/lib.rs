@@ -1,10 +1,9 @@
-﻿mod ai;
-mod commands;
-mod models;
-mod state;
+﻿mod commands;
 
-use state::AppState;
 use std::sync::Arc;
+use tauri::Manager;
+use tissaia_core::health;
+use tissaia_core::{AppState, HistoryStore, PresetStore, SharedState};
 use tokio::sync::Mutex;
 
 
@@ -21,8 +20,6 @@ pub fn run() {
     println!("[Tissaia] ANTHROPIC_API_KEY present: {}", std::env::var("ANTHROPIC_API_KEY").is_ok());
     println!("[Tissaia] OPENAI_API_KEY present: {}", std::env::var("OPENAI_API_KEY").is_ok());
 
-    let app_state = Arc::new(Mutex::new(AppState::new()));
-
     tauri::Builder::default()
         .plugin(
             tauri_plugin_log::Builder::default()
@@ -35,12 +32,37 @@ pub fn run() {
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_os::init())
-        .manage(app_state)
+        .setup(|app| {
+            // History lives in the app data dir unless TISSAIA_HISTORY_DIR is set
+            let history = match std::env::var("TISSAIA_HISTORY_DIR") {
+                Ok(_) => HistoryStore::from_env(),
+                Err(_) => HistoryStore::open(app.path().app_data_dir()?.join("history"))?,
+            };
+            // Presets likewise, unless TISSAIA_PRESETS_FILE is set
+            let presets = match std::env::var("TISSAIA_PRESETS_FILE") {
+                Ok(_) => PresetStore::from_env(),
+                Err(_) => PresetStore::open(app.path().app_data_dir()?.join("presets.json"))?,
+            };
+            let state: SharedState = Arc::new(Mutex::new(AppState::with_history(history).with_presets(presets)));
+            // Background provider health probes (TISSAIA_HEALTH_INTERVAL_SECS, 0 = off)
+            if let Some(every) = health::monitor_interval_from_env() {
+                tauri::async_runtime::spawn(health::run_monitor(state.clone(), every));
+            }
+            app.manage(state);
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::health_check,
             commands::get_ollama_models,
             commands::restore_image,
+            commands::get_presets,
+            commands::save_preset,
+            commands::delete_preset,
+            commands::get_prompts,
             commands::get_history,
+            commands::get_history_entry,
+            commands::delete_history_entry,
+            commands::get_history_blob,
             commands::clear_history,
             commands::get_providers_status,
             commands::set_api_key,
@@ -61,6 +83,10 @@ pub fn run() {
             // Enhanced detection + outpainting
             commands::detect_photos_with_retry,
             commands::outpaint_photo,
+            // End-to-end pipeline
+            commands::run_pipeline,
+            // Folder batch
+            commands::process_folder,
         ])
         .run(tauri::generate_context!())
         .expect("error while running Tissaia AI");
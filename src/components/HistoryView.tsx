@@ -16,6 +16,21 @@ import { type HistoryEntry, useClearHistory, useHistory } from '../hooks/useApi'
 import { useSettingsStore } from '../store/useSettingsStore';
 import Skeleton from './ui/Skeleton';
 
+const OPERATION_LABELS: Partial<Record<HistoryEntry['operation'], string>> = {
+  restoration: 'Restauracja',
+  photoseparation: 'Separacja',
+  analysis: 'Analiza',
+  verification: 'Weryfikacja',
+  detection: 'Detekcja',
+  crop: 'Kadrowanie',
+  outpaint: 'Outpainting',
+  rotate: 'Obrót',
+  upscale: 'Powiększenie',
+  filters: 'Filtry',
+  metadata: 'Metadane',
+  save: 'Zapis',
+};
+
 export default function HistoryView() {
   const { t } = useTranslation();
   const { data: history, isLoading } = useHistory();
@@ -108,12 +123,11 @@ export default function HistoryView() {
                     {/* Info */}
                     <div className="flex-1 min-w-0">
                       <h3 className={`font-semibold capitalize ${theme.title}`}>
-                        {entry.operation === 'photoseparation'
-                          ? 'Separacja'
-                          : entry.operation === 'analysis'
-                            ? 'Analiza'
-                            : 'Restauracja'}
+                        {OPERATION_LABELS[entry.operation] ?? entry.operation}
                       </h3>
+                      {!entry.success && entry.error_message && (
+                        <p className="mt-1 text-sm text-red-500 truncate">{entry.error_message}</p>
+                      )}
 
                       <div className={`flex items-center gap-4 mt-1 text-sm ${theme.textMuted}`}>
                         <span className="flex items-center gap-1">
@@ -62,6 +62,7 @@ export { useVerifyCrop, useVerifyDetection, useVerifyRestoration } from './useVe
 export type { FileBase64Result } from './utils';
 // Utilities (v4.0 — HTTP client functions replace Tauri invoke)
 export {
+  ApiError,
   apiDelete,
   apiGet,
   apiPost,
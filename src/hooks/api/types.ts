@@ -21,13 +21,88 @@ export interface RestorationResult {
   improvements: string[];
   provider_used: string;
   processing_time_ms: number;
+  /** Providers tried by the failover chain, the successful one last. */
+  attempts?: ProviderAttempt[];
+  /** Plan from a text-only provider, executed by the local engine. */
+  plan?: RestorationPlan;
+  /** Options the restoration ran with. */
+  options?: RestorationOptions;
+  /** Prompt template used, e.g. 'restore_plan.v1'. */
+  prompt_version?: string;
+}
+
+export interface StepOption {
+  enabled: boolean;
+  /** 0.0 (lightest touch) to 1.0 (strongest, default). */
+  strength?: number;
+}
+
+/** Omitted steps run at full strength. */
+export interface RestorationOptions {
+  geometry?: StepOption;
+  flash?: StepOption;
+  denoise?: StepOption;
+  faces?: StepOption;
+  colorize?: StepOption;
+  upscale?: StepOption;
+  studio_finish?: StepOption;
+}
+
+export interface RestorationPreset {
+  name: string;
+  description?: string;
+  options: RestorationOptions;
+  /** Shipped with the app; cannot be changed or deleted. */
+  builtin?: boolean;
+}
+
+export type RestorationStep =
+  | 'dust_removal'
+  | 'denoise'
+  | 'bilateral'
+  | 'white_balance'
+  | 'fade_correction'
+  | 'levels'
+  | 'clahe'
+  | 'sharpen';
+
+export interface PlanStep {
+  step: RestorationStep;
+  /** 0.0 (off) to 1.0 (strongest). */
+  strength: number;
+}
+
+export interface RestorationPlan {
+  improvements: string[];
+  processing_steps: string[];
+  operations: PlanStep[];
+}
+
+export interface ProviderAttempt {
+  provider: string;
+  success: boolean;
+  error: string | null;
+  retryable: boolean;
+  duration_ms: number;
 }
 
 // ============================================
 // HISTORY TYPES
 // ============================================
 
-export type OperationType = 'restoration' | 'photoseparation' | 'verification' | 'analysis';
+export type OperationType =
+  | 'restoration'
+  | 'photoseparation'
+  | 'verification'
+  | 'analysis'
+  | 'detection'
+  | 'crop'
+  | 'outpaint'
+  | 'rotate'
+  | 'upscale'
+  | 'filters'
+  | 'metadata'
+  | 'save';
 
 export interface HistoryEntry {
   id: string;
@@ -38,6 +113,20 @@ export interface HistoryEntry {
   provider: string;
   success: boolean;
   error_message: string | null;
+  processing_time_ms?: number;
+  /** Full images, fetch via /api/history/blobs/{hash} */
+  inputs?: BlobRef[];
+  outputs?: BlobRef[];
+  result?: Record<string, unknown>;
+  verification?: VerificationResult;
+  attempts?: ProviderAttempt[];
+  settings?: AppSettings;
+}
+
+export interface BlobRef {
+  hash: string;
+  mime_type: string;
+  size: number;
 }
 
 // ============================================
@@ -50,10 +139,40 @@ export interface ProviderStatus {
   available: boolean;
   priority: number;
   last_error: string | null;
+  capabilities?: BackendCapabilities;
+  health?: ProviderHealth;
+}
+
+export type CircuitState = 'closed' | 'open' | 'half_open';
+
+/** Latency percentiles over the last 50 successful calls and probes. */
+export interface LatencyStats {
+  samples: number;
+  p50_ms: number | null;
+  p90_ms: number | null;
+  p99_ms: number | null;
+}
+
+export interface ProviderHealth {
+  circuit: CircuitState;
+  consecutive_failures: number;
+  /** When an open circuit turns half-open. */
+  open_until: string | null;
+  last_checked: string | null;
+  last_success: string | null;
+  latency: LatencyStats;
+}
+
+export interface BackendCapabilities {
+  restore: boolean;
+  image_output: boolean;
+  detection: boolean;
+  verification: boolean;
+  requires_api_key: boolean;
 }
 
 export interface HealthResponse {
-  status: string;
+  status: 'healthy' | 'degraded';
   version: string;
   providers: ProviderStatus[];
   uptime_seconds: number;
@@ -90,6 +209,41 @@ export interface AppSettings {
   output_quality: number;
   preferred_provider: string | null;
   verification_enabled: boolean;
+  /** Snap detected boxes to the photo edges in the scan (default true). */
+  edge_snapping?: boolean;
+  /**
+   * Endpoint config per provider. On save, entries may be partial and are
+   * merged field by field; omitted providers are left unchanged. `base_url`
+   * is read-only (providers file / env only); `timeout_secs` is capped at 1800.
+   */
+  providers?: Record<string, ProviderConfig>;
+}
+
+export interface OperationConfig {
+  model: string;
+  max_tokens: number;
+  base_url?: string;
+  timeout_secs?: number;
+}
+
+export interface RequestLimits {
+  max_retries: number;
+  backoff_base_ms: number;
+  backoff_max_ms: number;
+  max_concurrent: number;
+  /** null = no rate limit */
+  requests_per_minute: number | null;
+  burst: number;
+}
+
+export interface ProviderConfig {
+  base_url: string;
+  timeout_secs: number;
+  restore?: OperationConfig;
+  detect?: OperationConfig;
+  outpaint?: OperationConfig;
+  verify?: OperationConfig;
+  limits?: RequestLimits;
 }
 
 // ============================================
@@ -116,6 +270,30 @@ export interface BoundingBox {
   contour: Point2D[];
   /** Whether this photo needs generative outpainting to fill non-rectangular edges. */
   needs_outpaint: boolean;
+  /** Edges were snapped to the photo boundary; cropped without padding or trim. */
+  edges_snapped?: boolean;
+}
+
+/** Edge snapping result for one side of a box. */
+export interface EdgeAdjustment {
+  snapped: boolean;
+  /** Movement in 0-1000 units; positive = outward (box grew). */
+  shift: number;
+  /** Mean colour step across the edge (0-255). */
+  contrast: number;
+}
+
+/** What edge snapping changed on one detected box. */
+export interface BoxAdjustment {
+  index: number;
+  applied: boolean;
+  left: EdgeAdjustment;
+  top: EdgeAdjustment;
+  right: EdgeAdjustment;
+  bottom: EdgeAdjustment;
+  /** Measured skew in degrees (clockwise). */
+  skew: number | null;
+  note: string | null;
 }
 
 export interface DetectionResult {
@@ -126,6 +304,11 @@ export interface DetectionResult {
   provider_used: string;
   scan_width: number;
   scan_height: number;
+  attempts?: ProviderAttempt[];
+  /** Per-box edge snapping report. */
+  adjustments?: BoxAdjustment[];
+  /** Prompt template used; absent for the local detector. */
+  prompt_version?: string;
 }
 
 export interface CroppedPhoto {
@@ -136,6 +319,8 @@ export interface CroppedPhoto {
   width: number;
   height: number;
   source_box: BoundingBox;
+  /** Clockwise rotation undone on the crop in degrees: quarter turns plus deskew. */
+  applied_rotation?: number;
 }
 
 export interface CropResult {
@@ -178,4 +363,147 @@ export interface VerificationResult {
   model_used: string;
   /** Bounding boxes for photos the verifier detected as missing from the original detection. */
   missing_boxes: BoundingBox[];
+  attempts?: ProviderAttempt[];
+  /** Prompt template used, e.g. 'verify_detection.v1'. */
+  prompt_version?: string;
+}
+
+export interface PromptInfo {
+  name: string;
+  versions: number[];
+  active: number;
+  variables: string[];
+}
+
+// ============================================
+// STAGE PROGRESS TYPES
+// ============================================
+
+export type PipelineStage =
+  | 'exif_fix'
+  | 'detect'
+  | 'verify'
+  | 'merge'
+  | 'crop'
+  | 'outpaint'
+  | 'verify_crop'
+  | 'restore'
+  | 'verify_restoration'
+  | 'filters'
+  | 'convert'
+  | 'batch';
+export type StageStatus = 'pending' | 'running' | 'completed' | 'failed' | 'skipped';
+
+/**
+ * Stage transition of a multi-step operation. Streamed as SSE `stage` events
+ * by `POST /api/detect/retry/stream`; emitted as `detection-stage` /
+ * `pipeline-stage` in Tauri.
+ */
+export interface StageEvent {
+  stage: PipelineStage;
+  status: StageStatus;
+  message?: string;
+  /** detect: DetectionResult, verify: VerificationResult, merge: { merged_boxes, photo_count } */
+  payload?: unknown;
+}
+
+// ============================================
+// END-TO-END PIPELINE TYPES (POST /api/pipeline)
+// ============================================
+
+export interface PipelineOptions {
+  /** Verify detection, crops and restorations (default true) */
+  verify?: boolean;
+  /** Outpaint photos with needs_outpaint (default true) */
+  outpaint?: boolean;
+  /** Restore every photo (default true) */
+  restore?: boolean;
+  /** Named restoration preset, e.g. 'archival-faithful' */
+  preset?: string;
+  /** Restoration options; override preset when both are given */
+  restoration?: RestorationOptions;
+  /** Local filters applied last, e.g. ['clahe', 'sharpen'] */
+  filters?: string[];
+  output_format?: 'image/jpeg' | 'image/png' | 'image/webp';
+}
+
+export interface StageReport {
+  stage: PipelineStage;
+  status: StageStatus;
+  message?: string;
+  duration_ms: number;
+}
+
+export interface PipelinePhoto {
+  index: number;
+  filename: string;
+  image_base64: string;
+  mime_type: string;
+  source_box: BoundingBox;
+  restored: boolean;
+  provider: string | null;
+  improvements: string[];
+  crop_verification: VerificationResult | null;
+  restoration_verification: VerificationResult | null;
+  stages: StageReport[];
+}
+
+export interface PipelineResult {
+  id: string;
+  original_filename: string;
+  detection: DetectionResult;
+  detection_verification: VerificationResult | null;
+  stages: StageReport[];
+  photos: PipelinePhoto[];
+  processing_time_ms: number;
+}
+
+// ============================================
+// FOLDER BATCH TYPES (Tauri process_folder)
+// ============================================
+
+export interface BatchRequest {
+  input_dir: string;
+  output_dir: string;
+  recursive?: boolean;
+  /** Scans processed at once (default 2, max 8) */
+  concurrency?: number;
+  /** Reprocess scans the manifest lists as completed */
+  force?: boolean;
+  options?: PipelineOptions;
+}
+
+export type ScanStatus = 'completed' | 'partial' | 'failed';
+
+/** Payload of each `batch-progress` event; one row of manifest.json */
+export interface ManifestEntry {
+  source: string;
+  status: ScanStatus;
+  size: number;
+  modified: number | null;
+  photos: {
+    index: number;
+    output: string;
+    restored: boolean;
+    provider: string | null;
+    succeeded: boolean;
+  }[];
+  error?: string;
+  /** Options the scan was processed with; null in older manifests. */
+  options: PipelineOptions | null;
+  processing_time_ms: number;
+  processed_at: string;
+}
+
+export interface BatchSummary {
+  input_dir: string;
+  output_dir: string;
+  total: number;
+  processed: number;
+  skipped: number;
+  partial: number;
+  failed: number;
+  photos_written: number;
+  manifest: string;
+  processing_time_ms: number;
 }
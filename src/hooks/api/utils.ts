@@ -21,6 +21,26 @@ const DEFAULT_TIMEOUT_MS = 120_000;
 
 interface ApiErrorBody {
   error?: string;
+  code?: string;
+  provider?: string;
+  retry_after_secs?: number;
+}
+
+/**
+ * Error thrown for non-2xx responses. `code` is the backend's stable error
+ * code (e.g. `rate_limited`, `no_provider`, `decode_error`).
+ */
+export class ApiError extends Error {
+  constructor(
+    message: string,
+    readonly status: number,
+    readonly code?: string,
+    readonly provider?: string,
+    readonly retryAfterSecs?: number,
+  ) {
+    super(message);
+    this.name = 'ApiError';
+  }
 }
 
 /**
@@ -28,9 +48,9 @@ interface ApiErrorBody {
  */
 async function handleResponse<T>(response: Response, path: string): Promise<T> {
   if (!response.ok) {
-    const body = await response.json().catch(() => ({ error: response.statusText }));
-    const message = (body as ApiErrorBody).error || `HTTP ${response.status} on ${path}`;
-    throw new Error(message);
+    const body = (await response.json().catch(() => ({ error: response.statusText }))) as ApiErrorBody;
+    const message = body.error || `HTTP ${response.status} on ${path}`;
+    throw new ApiError(message, response.status, body.code, body.provider, body.retry_after_secs);
   }
   return (await response.json()) as T;
 }